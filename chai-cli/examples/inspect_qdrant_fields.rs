@@ -1,5 +1,5 @@
 use anyhow::Result;
-use chai_core::{DbConfig, embeddings, turso};
+use chai_core::{DbConfig, SearchFilters, TeaRepository, TursoRepository, embeddings};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -7,7 +7,7 @@ async fn main() -> Result<()> {
 
     // Initialize database
     let db_config = DbConfig::from_env();
-    turso::init_database(&db_config).await?;
+    let db = TursoRepository::init(&db_config).await?;
 
     // Create embedding for search query
     let embeddings_config = embeddings::EmbeddingsConfig::from_env()?;
@@ -16,7 +16,14 @@ async fn main() -> Result<()> {
         .create_embedding("облепиха".to_string())
         .await?;
 
-    let results = turso::search_teas(&query_embedding, 3, &turso::SearchFilters::default()).await?;
+    let results = db
+        .search_teas(
+            &query_embedding,
+            &db_config.embedding_model,
+            3,
+            &SearchFilters::new(),
+        )
+        .await?;
 
     for (i, result) in results.iter().enumerate() {
         let tea = &result.tea;
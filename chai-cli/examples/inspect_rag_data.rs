@@ -1,5 +1,5 @@
 use anyhow::Result;
-use chai_core::{DbConfig, embeddings, turso};
+use chai_core::{DbConfig, SearchFilters, TeaRepository, TursoRepository, embeddings};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -7,7 +7,7 @@ async fn main() -> Result<()> {
 
     // Initialize database
     let db_config = DbConfig::from_env();
-    turso::init_database(&db_config).await?;
+    let db = TursoRepository::init(&db_config).await?;
 
     // Create embeddings client
     let embeddings_config = embeddings::EmbeddingsConfig::from_env()?;
@@ -27,8 +27,14 @@ async fn main() -> Result<()> {
         let query_embedding = embeddings_client
             .create_embedding(query.to_string())
             .await?;
-        let results =
-            turso::search_teas(&query_embedding, 5, &turso::SearchFilters::default()).await?;
+        let results = db
+            .search_teas(
+                &query_embedding,
+                &db_config.embedding_model,
+                5,
+                &SearchFilters::new(),
+            )
+            .await?;
 
         println!("Найдено чаёв: {}\n", results.len());
 
@@ -0,0 +1,95 @@
+//! Refresh golden files of the parser regression suite (chai-core/tests/fixtures)
+//!
+//! Usage:
+//!   # Reload HTML of all fixtures from html_cache and regenerate expected JSON
+//!   cargo run -p chai-cli --example refresh_fixtures
+//!
+//!   # Only regenerate expected JSON from the HTML already in the fixtures dir
+//!   cargo run -p chai-cli --example refresh_fixtures -- --keep-html
+//!
+//!   # Add or replace fixtures from html_cache (only the named ones are exported)
+//!   cargo run -p chai-cli --example refresh_fixtures -- oblepiha=https://beliyles.com/tproduct/...
+
+use anyhow::{Context, Result};
+use chai_core::{DbConfig, Tea, TursoRepository, cache, scraper};
+use std::path::{Path, PathBuf};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../chai-core/tests/fixtures")
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    let mut keep_html = false;
+    let mut fixtures: Vec<(String, String)> = Vec::new();
+
+    for arg in std::env::args().skip(1) {
+        if arg == "--keep-html" {
+            keep_html = true;
+        } else if let Some((name, url)) = arg.split_once('=') {
+            fixtures.push((name.to_string(), url.to_string()));
+        } else {
+            anyhow::bail!(
+                "Unknown argument: {} (expected --keep-html or name=url)",
+                arg
+            );
+        }
+    }
+
+    // Without names, all existing fixtures (URL is stored in the expected JSON)
+    let dir = fixtures_dir();
+    if fixtures.is_empty() {
+        for entry in std::fs::read_dir(&dir).context("Failed to read fixtures dir")? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let tea: Tea = serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .with_context(|| format!("Invalid fixture {}", path.display()))?;
+            fixtures.push((name, tea.url));
+        }
+    }
+    fixtures.sort();
+
+    let mut db = None;
+
+    for (name, url) in &fixtures {
+        let html_path = dir.join(format!("{}.html", name));
+        let json_path = dir.join(format!("{}.json", name));
+
+        let html = if keep_html && html_path.exists() {
+            std::fs::read_to_string(&html_path)?
+        } else {
+            if db.is_none() {
+                db = Some(TursoRepository::init(&DbConfig::from_env()).await?);
+            }
+            let entry = cache::get(db.as_ref().unwrap(), url)
+                .await?
+                .with_context(|| format!("{} is not in html_cache (run `chai cache`)", url))?;
+            std::fs::write(&html_path, &entry.html)?;
+            entry.html
+        };
+
+        let tea = scraper::parse_tea_from_html(url, &html)
+            .with_context(|| format!("Failed to parse fixture {}", name))?;
+        std::fs::write(&json_path, serde_json::to_string_pretty(&tea)? + "\n")?;
+
+        println!(
+            "✓ {} ({})",
+            name,
+            tea.name.as_deref().unwrap_or("Без названия")
+        );
+    }
+
+    println!(
+        "\nRefreshed {} fixtures in {}",
+        fixtures.len(),
+        dir.display()
+    );
+    println!("Review the diff before committing: git diff chai-core/tests/fixtures");
+
+    Ok(())
+}
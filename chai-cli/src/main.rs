@@ -1,8 +1,23 @@
 use anyhow::{Context, Result};
-use chai_core::{DbConfig, Tea, cache, scraper, tea_utils, turso};
-use clap::{Parser, Subcommand};
-use reqwest::Client;
-use std::path::PathBuf;
+use chai_core::crawler::{CrawlSummary, Crawler, CrawlerConfig, Validators};
+use chai_core::embeddings::{EmbeddingsClient, EmbeddingsConfig};
+use chai_core::images::{self, ImagesConfig};
+use chai_core::migrations::{self, MigrationState};
+use chai_core::quantization::VectorStorage;
+use chai_core::samples::{SampleLink, link_samples};
+use chai_core::scraper::{
+    self, PageChange, ParseError, ParseReport, ParseSummary, ShopScraper, SitemapEntry,
+};
+use chai_core::turso::{HybridConfig, SearchMode, TursoRepository};
+use chai_core::{
+    DbConfig, FacetCount, FilterOptions, SearchFilters, SearchResult, Tea, TeaRepository,
+    TeaStatus, backup, cache, tea_utils, vector_index,
+};
+use clap::{Args, Parser, Subcommand};
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 
 #[derive(Parser)]
@@ -13,6 +28,108 @@ struct Cli {
     command: Commands,
 }
 
+/// Crawler options shared by commands that fetch pages
+#[derive(Args)]
+struct CrawlArgs {
+    /// Maximum number of pages fetched at the same time
+    #[arg(long)]
+    concurrency: Option<usize>,
+
+    /// Maximum requests per second per host
+    #[arg(long)]
+    rate: Option<f64>,
+}
+
+impl CrawlArgs {
+    /// Build crawler from environment config with CLI overrides
+    fn build(&self, db: &TursoRepository) -> Result<Crawler> {
+        let mut config = CrawlerConfig::from_env();
+        if let Some(concurrency) = self.concurrency {
+            config.concurrency = concurrency;
+        }
+        if let Some(rate) = self.rate {
+            config.requests_per_second = rate;
+        }
+        info!(
+            "Crawler: {} concurrent requests, {} req/s per host",
+            config.concurrency, config.requests_per_second
+        );
+        Crawler::new(config, Arc::new(db.clone()))
+    }
+}
+
+/// Search filters
+#[derive(Args)]
+struct FilterArgs {
+    /// Only items in stock
+    #[arg(long, conflicts_with = "with_sample")]
+    only_available: bool,
+
+    /// Items in stock or with a sample in stock
+    #[arg(long)]
+    with_sample: bool,
+
+    /// Exclude samples
+    #[arg(long)]
+    no_samples: bool,
+
+    /// Exclude sets
+    #[arg(long)]
+    no_sets: bool,
+
+    /// Filter by series (repeatable, any of them matches)
+    #[arg(long)]
+    series: Vec<String>,
+
+    /// Filter by shop ID (repeatable, any of them matches)
+    #[arg(long = "shop")]
+    shops: Vec<String>,
+
+    /// Filter by characteristic, e.g. --attr "Регион=Карелия" (repeatable)
+    #[arg(long = "attr", value_parser = parse_characteristic)]
+    characteristics: Vec<(String, String)>,
+
+    /// Minimum price
+    #[arg(long)]
+    min_price: Option<f64>,
+
+    /// Maximum price
+    #[arg(long)]
+    max_price: Option<f64>,
+
+    /// Composition must contain the ingredient (repeatable)
+    #[arg(long = "with")]
+    with_ingredients: Vec<String>,
+
+    /// Composition must not contain the ingredient (repeatable)
+    #[arg(long = "without")]
+    without_ingredients: Vec<String>,
+
+    /// Also find teas that are gone from the website
+    #[arg(long)]
+    include_discontinued: bool,
+}
+
+impl FilterArgs {
+    fn build(self) -> SearchFilters {
+        SearchFilters::from(&FilterOptions {
+            in_stock: self.only_available,
+            in_stock_or_sample: self.with_sample,
+            exclude_samples: self.no_samples,
+            exclude_sets: self.no_sets,
+            series: self.series,
+            shops: self.shops,
+            characteristics: self.characteristics,
+            min_price: self.min_price,
+            max_price: self.max_price,
+            with_ingredients: self.with_ingredients,
+            without_ingredients: self.without_ingredients,
+            include_discontinued: self.include_discontinued,
+            ..Default::default()
+        })
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Scrape tea data from website
@@ -28,6 +145,13 @@ enum Commands {
         /// Only save items in stock
         #[arg(long)]
         only_available: bool,
+
+        /// Shops to scrape (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
+
+        #[command(flatten)]
+        crawl: CrawlArgs,
     },
 
     /// Sync teas from website to database (incremental update)
@@ -43,13 +167,70 @@ enum Commands {
         /// Use cached HTML instead of fetching from website
         #[arg(long)]
         from_cache: bool,
+
+        /// Shops to sync (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
+
+        /// Don't download images and generate thumbnails
+        #[arg(long)]
+        skip_images: bool,
+
+        /// Also embed teas with this model, e.g. while rolling out a new one (repeatable)
+        #[arg(long = "embed-model")]
+        embed_models: Vec<String>,
+
+        /// Abort if more than this percent of a shop's active teas are gone from the website
+        #[arg(long, value_name = "PERCENT", default_value_t = tea_utils::DEFAULT_MAX_REMOVED_PERCENT)]
+        max_removed: f64,
+
+        #[command(flatten)]
+        crawl: CrawlArgs,
+    },
+
+    /// Embed stored teas with a model (teas without an up-to-date embedding of it)
+    Embed {
+        /// Embedding model (default: EMBEDDING_MODEL)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Limit number of teas
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Re-embed teas that already have an up-to-date embedding
+        #[arg(long)]
+        force: bool,
+
+        /// Shops to embed (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
     },
 
     /// Cache HTML pages to database
+    #[command(args_conflicts_with_subcommands = true)]
     Cache {
+        #[command(subcommand)]
+        action: Option<CacheAction>,
+
         /// Limit number of pages (for testing)
         #[arg(short, long)]
         limit: Option<usize>,
+
+        /// Shops to cache (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
+
+        /// Revalidate already cached pages (ETag / Last-Modified)
+        #[arg(long)]
+        refresh: bool,
+
+        /// Fetch only new pages and pages with a newer sitemap <lastmod>
+        #[arg(long, conflicts_with = "refresh")]
+        changed_only: bool,
+
+        #[command(flatten)]
+        crawl: CrawlArgs,
     },
 
     /// Migrate JSON cache to database
@@ -62,6 +243,33 @@ enum Commands {
     /// Show cache statistics
     CacheStats,
 
+    /// Compress cache entries stored before compression support
+    CompressCache,
+
+    /// List cached snapshots of a page
+    CacheHistory {
+        /// Page URL
+        url: String,
+    },
+
+    /// Show changes of a page between two cached snapshots
+    CacheDiff {
+        /// Page URL
+        url: String,
+
+        /// Older snapshot (0 = current, 1 = previous, ...)
+        #[arg(long, default_value = "1")]
+        from: usize,
+
+        /// Newer snapshot
+        #[arg(long, default_value = "0")]
+        to: usize,
+
+        /// Compare parsed products instead of HTML
+        #[arg(long)]
+        parsed: bool,
+    },
+
     /// Search teas by description
     Search {
         /// Search query
@@ -71,13 +279,28 @@ enum Commands {
         #[arg(short, long, default_value = "10")]
         limit: usize,
 
-        /// Only items in stock
+        /// Search mode: vector, keyword or hybrid (default: SEARCH_MODE or hybrid)
         #[arg(long)]
-        only_available: bool,
+        mode: Option<SearchMode>,
+
+        /// Weight of the vector ranking in hybrid mode
+        #[arg(long)]
+        vector_weight: Option<f32>,
 
-        /// Filter by series
+        /// Weight of the keyword ranking in hybrid mode
         #[arg(long)]
-        series: Option<String>,
+        keyword_weight: Option<f32>,
+
+        /// Embedding model to search with (default: EMBEDDING_MODEL)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Cheapest per 100 g first among the relevant teas, instead of the most relevant
+        #[arg(long)]
+        best_value: bool,
+
+        #[command(flatten)]
+        filters: FilterArgs,
     },
 
     /// Get tea by URL without vector search
@@ -86,8 +309,196 @@ enum Commands {
         url: String,
     },
 
+    /// Set the status of a tea: hide it from search or bring it back
+    SetStatus {
+        /// Tea URL
+        url: String,
+
+        /// active, discontinued or hidden
+        status: TeaStatus,
+    },
+
     /// Show database statistics
-    Stats,
+    Stats {
+        /// Also count teas per series, ingredient, price, state and characteristic
+        #[arg(long)]
+        facets: bool,
+
+        /// Values shown per facet
+        #[arg(long, default_value = "10", requires = "facets")]
+        facet_limit: usize,
+
+        /// Filters of the teas counted in facets
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+
+    /// Compare approximate vector search (ANN, binary) with exact search: latency and recall
+    BenchSearch {
+        /// Number of sampled tea embeddings used as queries
+        #[arg(long, default_value = "50")]
+        queries: usize,
+
+        /// Results per query
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+
+        /// Clusters scanned per query, repeat to compare (default: VECTOR_INDEX_PROBES or 4)
+        #[arg(long = "probes")]
+        probes: Vec<usize>,
+
+        /// Embedding model to benchmark (default: EMBEDDING_MODEL)
+        #[arg(long)]
+        model: Option<String>,
+    },
+
+    /// Database schema management and backups
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+}
+
+/// Database schema and backup commands
+#[derive(Subcommand)]
+enum DbAction {
+    /// Apply pending schema migrations
+    Migrate,
+
+    /// Show applied and pending schema migrations
+    Status,
+
+    /// Rebuild the keyword search index and typed tea columns from stored teas
+    Reindex,
+
+    /// Build the approximate nearest-neighbour index of tea embeddings
+    VectorIndex {
+        /// Number of clusters (default: about the square root of the tea count)
+        #[arg(long)]
+        clusters: Option<usize>,
+
+        /// Drop the index, vector search scans all teas
+        #[arg(long, conflicts_with = "clusters")]
+        drop: bool,
+
+        /// Embedding model to index (default: EMBEDDING_MODEL)
+        #[arg(long)]
+        model: Option<String>,
+    },
+
+    /// Delete all embeddings of a model (e.g. after switching to a new one)
+    DropModel {
+        /// Embedding model
+        model: String,
+    },
+
+    /// Convert stored embeddings to another precision and fill their sign bits
+    Quantize {
+        /// f32, f16 or int8 (default: VECTOR_STORAGE or f32)
+        #[arg(long)]
+        storage: Option<VectorStorage>,
+    },
+
+    /// Write a consistent backup of users, cache and catalog
+    Backup {
+        /// Backup file (e.g. chai.jsonl.zst)
+        path: PathBuf,
+
+        /// Compress with zstd (always for a .zst file name)
+        #[arg(long)]
+        compress: bool,
+    },
+
+    /// Replace the database with a backup after checking its integrity
+    ///
+    /// Stop chai-web first, the database file is replaced.
+    Restore {
+        /// Backup file, compressed or not
+        path: PathBuf,
+
+        /// Replace an existing database
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Write a timestamped backup into a directory, removing the oldest ones
+    Snapshot {
+        /// Snapshot directory (default: backups/ next to the database)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        /// Number of snapshots to keep
+        #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u32).range(1..))]
+        keep: u32,
+
+        /// Compress with zstd
+        #[arg(long)]
+        compress: bool,
+    },
+}
+
+/// Cache maintenance commands
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Revalidate cached pages fetched long ago
+    Refresh {
+        /// Refresh pages older than this, e.g. 12h, 7d (default: CACHE_MAX_AGE or 7d)
+        #[arg(long, value_parser = cache::parse_age)]
+        older_than: Option<Duration>,
+
+        /// Limit number of pages
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Shops to refresh (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
+
+        #[command(flatten)]
+        crawl: CrawlArgs,
+    },
+
+    /// Delete pages that left the sitemap and enforce the size limit
+    Prune {
+        /// Stored cache size limit, e.g. 500M (default: CACHE_MAX_SIZE or no limit)
+        #[arg(long, value_parser = cache::parse_size)]
+        max_size: Option<usize>,
+
+        /// Don't fetch sitemaps, only enforce the size limit
+        #[arg(long)]
+        skip_sitemap: bool,
+
+        /// Shops to prune (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
+
+        #[command(flatten)]
+        crawl: CrawlArgs,
+    },
+
+    /// Re-parse cached pages and list the ones that no longer parse
+    Verify {
+        /// Shops to verify (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
+    },
+
+    /// Export cached pages with fetch times and headers to an archive
+    Export {
+        /// Output archive path
+        #[arg(short, long, default_value = "cache.jsonl.zst")]
+        output: PathBuf,
+
+        /// Shops to export (all shops if not specified)
+        #[arg(long = "shop")]
+        shops: Vec<String>,
+    },
+
+    /// Import cached pages from an archive
+    Import {
+        /// Archive path
+        input: PathBuf,
+    },
 }
 
 #[tokio::main]
@@ -101,114 +512,257 @@ async fn main() -> Result<()> {
     // Load .env
     dotenvy::dotenv().ok();
 
-    // Initialize database
-    let db_config = DbConfig::from_env();
-    turso::init_database(&db_config).await?;
-
     let cli = Cli::parse();
 
+    // Initialize database (schema commands manage migrations themselves)
+    let db_config = DbConfig::from_env();
+    if let Commands::Db { action } = cli.command {
+        // Restore replaces the database file, so it must not be open
+        if let DbAction::Restore { path, force } = action {
+            return restore_command(&path, force, &db_config).await;
+        }
+        let db = TursoRepository::open(&db_config).await?;
+        return db_command(&db, action, &db_config).await;
+    }
+    let db = TursoRepository::init(&db_config).await?;
+
     match cli.command {
         Commands::Scrape {
             output,
             limit,
             only_available,
+            shops,
+            crawl,
         } => {
-            scrape_command(output, limit, only_available).await?;
+            scrape_command(&db, output, limit, only_available, shops, crawl).await?;
         }
         Commands::Sync {
             limit,
             force,
             from_cache,
+            shops,
+            skip_images,
+            embed_models,
+            max_removed,
+            crawl,
+        } => {
+            let mut models = vec![db_config.embedding_model.clone()];
+            for model in embed_models {
+                if !models.contains(&model) {
+                    models.push(model);
+                }
+            }
+            let options = SyncOptions {
+                limit,
+                force,
+                from_cache,
+                skip_images,
+                max_removed,
+            };
+            sync_command(&db, options, shops, models, crawl).await?;
+        }
+        Commands::Embed {
+            model,
+            limit,
+            force,
+            shops,
+        } => {
+            let model = model.unwrap_or_else(|| db_config.embedding_model.clone());
+            let shops = scraper::resolve_scrapers(&shops)?;
+            let embeddings_config = EmbeddingsConfig::from_env()?;
+            let embedded =
+                embed_teas(&db, &embeddings_config, &model, &shops, limit, force).await?;
+            info!("Done! Embedded {} teas with {}", embedded, model);
+        }
+        Commands::Cache {
+            action: Some(action),
+            ..
         } => {
-            sync_command(limit, force, from_cache).await?;
+            cache_action_command(&db, action).await?;
         }
-        Commands::Cache { limit } => {
-            cache_command(limit).await?;
+        Commands::Cache {
+            action: None,
+            limit,
+            shops,
+            refresh,
+            changed_only,
+            crawl,
+        } => {
+            let mode = if changed_only {
+                CacheMode::ChangedOnly
+            } else if refresh {
+                CacheMode::Refresh
+            } else {
+                CacheMode::Missing
+            };
+            cache_command(&db, limit, shops, mode, crawl).await?;
         }
         Commands::MigrateCache { input } => {
-            migrate_cache_command(input).await?;
+            migrate_cache_command(&db, input).await?;
         }
         Commands::CacheStats => {
-            cache_stats_command().await?;
+            cache_stats_command(&db).await?;
+        }
+        Commands::CompressCache => {
+            compress_cache_command(&db).await?;
+        }
+        Commands::CacheHistory { url } => {
+            cache_history_command(&db, url).await?;
+        }
+        Commands::CacheDiff {
+            url,
+            from,
+            to,
+            parsed,
+        } => {
+            cache_diff_command(&db, url, from, to, parsed).await?;
         }
         Commands::Search {
             query,
             limit,
-            only_available,
-            series,
+            mode,
+            vector_weight,
+            keyword_weight,
+            model,
+            best_value,
+            filters,
         } => {
-            search_command(query, limit, only_available, series).await?;
+            let mode = match mode {
+                Some(mode) => mode,
+                None => SearchMode::from_env()?,
+            };
+            let mut hybrid = HybridConfig::from_env();
+            if let Some(weight) = vector_weight {
+                hybrid.vector_weight = weight;
+            }
+            if let Some(weight) = keyword_weight {
+                hybrid.keyword_weight = weight;
+            }
+            let model = model.unwrap_or_else(|| db_config.embedding_model.clone());
+            let mut filters = filters.build();
+            if best_value {
+                filters = filters.best_value();
+            }
+            let results = search_command(&db, query, limit, mode, &model, &hybrid, filters).await?;
+            print_search_results(&results, mode);
         }
         Commands::Get { url } => {
-            get_command(url).await?;
+            get_command(&db, url).await?;
+        }
+        Commands::SetStatus { url, status } => {
+            if !db.set_tea_status(&url, status).await? {
+                anyhow::bail!("Tea with URL {} not found", url);
+            }
+            info!("{} is {} now", url, status);
         }
-        Commands::Stats => {
-            stats_command().await?;
+        Commands::Stats {
+            facets,
+            facet_limit,
+            filters,
+        } => {
+            stats_command(&db, &db_config.embedding_model).await?;
+            if facets {
+                facets_command(&db, &filters.build(), facet_limit).await?;
+            }
+        }
+        Commands::BenchSearch {
+            queries,
+            limit,
+            probes,
+            model,
+        } => {
+            let probes = if probes.is_empty() {
+                vec![db_config.vector_probes]
+            } else {
+                probes
+            };
+            let model = model.unwrap_or_else(|| db_config.embedding_model.clone());
+            bench_search_command(&db, &model, queries, limit, probes).await?;
         }
+        Commands::Db { .. } => unreachable!("handled before database initialization"),
     }
 
     Ok(())
 }
 
-async fn scrape_command(output: PathBuf, limit: Option<usize>, only_available: bool) -> Result<()> {
+async fn scrape_command(
+    db: &TursoRepository,
+    output: PathBuf,
+    limit: Option<usize>,
+    only_available: bool,
+    shops: Vec<String>,
+    crawl: CrawlArgs,
+) -> Result<()> {
     info!("Starting tea scraping");
     if only_available {
         info!("Filter: only items in stock");
     }
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .build()?;
+    let shops = scraper::resolve_scrapers(&shops)?;
+    let crawler = crawl.build(db)?;
 
     // Get URL list
-    let urls = scraper::get_tea_urls(&client).await?;
+    let mut urls = fetch_shop_urls(&crawler, &shops).await?;
 
     // Apply limit if specified
-    let urls_to_scrape = if let Some(limit) = limit {
-        &urls[..limit.min(urls.len())]
-    } else {
-        &urls
-    };
+    if let Some(limit) = limit {
+        urls.truncate(limit);
+    }
 
-    info!("Will process {} teas", urls_to_scrape.len());
+    info!("Will process {} teas", urls.len());
 
     // Parse each tea
     let mut teas = Vec::new();
-    let total = urls_to_scrape.len();
+    let total = urls.len();
+    let mut summary = CrawlSummary::default();
+    let mut pages = crawler.crawl(urls);
+    let mut i = 0;
+
+    while let Some((url, result)) = pages.next().await {
+        i += 1;
+        summary.record(&result);
+        if let Err(e) = &result
+            && e.disallowed
+        {
+            warn!("[{}/{}] - Skipped {}", i, total, e);
+            continue;
+        }
+
+        let parsed = result
+            .map_err(anyhow::Error::from)
+            .and_then(|page| scraper::parse_tea_from_html(&url, &page.body));
 
-    for (i, url) in urls_to_scrape.iter().enumerate() {
-        match scraper::scrape_tea(&client, url).await {
+        match parsed {
             Ok(tea) => {
                 // Filter by availability if flag is set
                 if only_available && !tea.in_stock {
                     if let Some(name) = &tea.name {
-                        info!("[{}/{}] - {} (out of stock)", i + 1, total, name);
+                        info!("[{}/{}] - {} (out of stock)", i, total, name);
                     }
                     continue;
                 }
 
                 if let Some(name) = &tea.name {
-                    info!("[{}/{}] + {}", i + 1, total, name);
+                    info!("[{}/{}] + {}", i, total, name);
                 } else {
-                    warn!("[{}/{}] ! No name: {}", i + 1, total, url);
+                    warn!("[{}/{}] ! No name: {}", i, total, url);
                 }
                 teas.push(tea);
             }
             Err(e) => {
-                error!("[{}/{}] x {}", i + 1, total, e);
+                error!("[{}/{}] x {}", i, total, e);
             }
         }
 
-        // Small delay to not overload the server
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-
         // Intermediate save every 50 teas
-        if (i + 1) % 50 == 0 {
+        if i % 50 == 0 {
             save_teas(&teas, &output)?;
             info!("Intermediate save: {} teas", teas.len());
         }
     }
 
+    summary.log();
+
     // Final save
     save_teas(&teas, &output)?;
 
@@ -226,6 +780,32 @@ async fn scrape_command(output: PathBuf, limit: Option<usize>, only_available: b
     Ok(())
 }
 
+/// Collect product URLs from sitemaps of all selected shops
+async fn fetch_shop_urls(crawler: &Crawler, shops: &[&dyn ShopScraper]) -> Result<Vec<String>> {
+    let mut urls = Vec::new();
+    for shop in shops {
+        urls.extend(scraper::get_tea_urls(crawler, *shop).await?);
+    }
+    Ok(urls)
+}
+
+/// Collect sitemap entries of all selected shops
+async fn fetch_shop_entries(
+    crawler: &Crawler,
+    shops: &[&dyn ShopScraper],
+) -> Result<Vec<SitemapEntry>> {
+    let mut entries = Vec::new();
+    for shop in shops {
+        entries.extend(scraper::get_tea_entries(crawler, *shop).await?);
+    }
+    Ok(entries)
+}
+
+/// Check if URL belongs to one of the selected shops
+fn is_selected_shop(url: &str, shops: &[&dyn ShopScraper]) -> bool {
+    scraper::scraper_for_url(url).is_some_and(|shop| shops.iter().any(|s| s.id() == shop.id()))
+}
+
 fn save_teas(teas: &[Tea], path: &PathBuf) -> Result<()> {
     let json = serde_json::to_string_pretty(teas).context("Failed to serialize teas to JSON")?;
     std::fs::write(path, json)
@@ -233,94 +813,267 @@ fn save_teas(teas: &[Tea], path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-async fn cache_command(limit: Option<usize>) -> Result<()> {
+/// Which pages the cache command fetches
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CacheMode {
+    /// Only pages that are not cached yet
+    Missing,
+    /// All pages, revalidating cached ones with conditional requests
+    Refresh,
+    /// New pages and pages changed according to sitemap <lastmod>
+    ChangedOnly,
+}
+
+async fn cache_command(
+    db: &TursoRepository,
+    limit: Option<usize>,
+    shops: Vec<String>,
+    mode: CacheMode,
+    crawl: CrawlArgs,
+) -> Result<()> {
     info!("Caching HTML pages to database");
+    match mode {
+        CacheMode::Missing => {}
+        CacheMode::Refresh => info!("Mode: revalidate cached pages"),
+        CacheMode::ChangedOnly => info!("Mode: only new and changed pages (sitemap lastmod)"),
+    }
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .build()?;
+    let shops = scraper::resolve_scrapers(&shops)?;
+    let crawler = crawl.build(db)?;
 
-    // Get URL list
-    let urls = scraper::get_tea_urls(&client).await?;
+    // Get page list
+    let mut entries = fetch_shop_entries(&crawler, &shops).await?;
 
     // Apply limit if specified
-    let urls_to_cache: Vec<String> = if let Some(limit) = limit {
-        urls[..limit.min(urls.len())].to_vec()
-    } else {
-        urls
-    };
-
-    let total = urls_to_cache.len();
-    info!("Will cache {} pages", total);
+    if let Some(limit) = limit {
+        entries.truncate(limit);
+    }
 
-    let mut cached_count = 0;
-    let mut error_count = 0;
+    info!("Will cache {} pages", entries.len());
+
+    // Decide what to fetch: cached pages are skipped, revalidated (refresh mode)
+    // or compared with sitemap lastmod (changed-only mode)
+    let mut skipped_count = 0;
+    let mut no_lastmod_count = 0;
+    let mut cached_urls = std::collections::HashSet::new();
+    let mut requests = Vec::new();
+    for entry in entries {
+        let url = entry.url.clone();
+        if mode == CacheMode::Missing {
+            if cache::contains(db, &url).await? {
+                skipped_count += 1;
+            } else {
+                requests.push((url, Validators::default()));
+            }
+            continue;
+        }
 
-    for (i, url) in urls_to_cache.iter().enumerate() {
-        // Check if already cached
-        if cache::contains(url).await? {
-            info!("[{}/{}] = {} (already cached)", i + 1, total, url);
-            cached_count += 1;
+        let Some(cached) = cache::get(db, &url).await? else {
+            requests.push((url, Validators::default()));
             continue;
+        };
+
+        if mode == CacheMode::ChangedOnly {
+            match entry.change_since(Some(cached.fetched_at)) {
+                PageChange::Unchanged => {
+                    skipped_count += 1;
+                    continue;
+                }
+                PageChange::Unknown => no_lastmod_count += 1,
+                PageChange::New | PageChange::Changed => {}
+            }
         }
 
-        match client.get(url).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    match response.text().await {
-                        Ok(html) => {
-                            cache::set(url, &html).await?;
-                            cached_count += 1;
-                            info!("[{}/{}] + {}", i + 1, total, url);
-                        }
-                        Err(e) => {
-                            error_count += 1;
-                            error!("[{}/{}] x Read error: {}", i + 1, total, e);
-                        }
-                    }
+        requests.push((url.clone(), Validators::from(&cached)));
+        cached_urls.insert(url);
+    }
+
+    let total = requests.len();
+    match mode {
+        CacheMode::Missing => {
+            info!("{} pages already cached, fetching {}", skipped_count, total);
+        }
+        CacheMode::Refresh => info!(
+            "Revalidating {} cached pages, fetching {} new",
+            cached_urls.len(),
+            total - cached_urls.len()
+        ),
+        CacheMode::ChangedOnly => info!(
+            "Sitemap: {} new, {} changed, {} unchanged, {} cached without lastmod (revalidating)",
+            total - cached_urls.len(),
+            cached_urls.len() - no_lastmod_count,
+            skipped_count,
+            no_lastmod_count
+        ),
+    }
+
+    let mut summary = CrawlSummary::default();
+    let mut pages = crawler.crawl_conditional(requests);
+    let mut i = 0;
+    let (mut new_count, mut refreshed_count, mut unchanged_count) = (0, 0, 0);
+
+    while let Some((url, result)) = pages.next().await {
+        i += 1;
+        summary.record(&result);
+
+        match result {
+            Ok(page) if page.is_not_modified() => {
+                cache::touch(db, &url, &page.cache_meta()).await?;
+                unchanged_count += 1;
+                info!("[{}/{}] = {}", i, total, url);
+            }
+            Ok(page) => {
+                cache::set_with_meta(db, &url, &page.body, &page.cache_meta()).await?;
+                if cached_urls.contains(&url) {
+                    refreshed_count += 1;
+                    info!("[{}/{}] ~ {}", i, total, url);
                 } else {
-                    error_count += 1;
-                    error!("[{}/{}] x HTTP {}", i + 1, total, response.status());
+                    new_count += 1;
+                    info!("[{}/{}] + {}", i, total, url);
                 }
             }
+            Err(e) if e.disallowed => {
+                warn!("[{}/{}] - Skipped {}", i, total, e);
+            }
             Err(e) => {
-                error_count += 1;
-                error!("[{}/{}] x Request error: {}", i + 1, total, e);
+                error!("[{}/{}] x {}", i, total, e);
             }
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
-
         // Progress every 100 pages
-        if (i + 1) % 100 == 0 {
-            info!("Progress: {}/{} pages cached", cached_count, i + 1);
+        if i % 100 == 0 {
+            info!("Progress: {}/{} pages fetched", i, total);
         }
     }
 
-    info!(
-        "Done! Cached {} pages, {} errors",
-        cached_count, error_count
-    );
+    summary.log();
+    match mode {
+        CacheMode::ChangedOnly => info!(
+            "Done! {} new, {} changed, {} unchanged ({} by lastmod, {} by 304), {} errors",
+            new_count,
+            refreshed_count,
+            skipped_count + unchanged_count,
+            skipped_count,
+            unchanged_count,
+            summary.failed.len()
+        ),
+        _ => info!(
+            "Done! {} new, {} re-downloaded, {} unchanged (304), {} already cached, {} errors",
+            new_count,
+            refreshed_count,
+            unchanged_count,
+            skipped_count,
+            summary.failed.len()
+        ),
+    }
+
+    Ok(())
+}
+
+async fn cache_action_command(db: &TursoRepository, action: CacheAction) -> Result<()> {
+    let retention = cache::RetentionConfig::from_env()?;
+
+    match action {
+        CacheAction::Refresh {
+            older_than,
+            limit,
+            shops,
+            crawl,
+        } => {
+            let max_age = older_than.unwrap_or(retention.max_age);
+            let shops = scraper::resolve_scrapers(&shops)?;
+            let crawler = crawl.build(db)?;
+
+            let mut urls: Vec<String> = cache::stale_urls(db, max_age)
+                .await?
+                .into_iter()
+                .filter(|url| is_selected_shop(url, &shops))
+                .collect();
+            if let Some(limit) = limit {
+                urls.truncate(limit);
+            }
+
+            info!("Refreshing {} pages older than {:?}", urls.len(), max_age);
+            cache::refresh(db, &crawler, urls).await?.log();
+        }
+        CacheAction::Prune {
+            max_size,
+            skip_sitemap,
+            shops,
+            crawl,
+        } => {
+            if !skip_sitemap {
+                let shops = scraper::resolve_scrapers(&shops)?;
+                let crawler = crawl.build(db)?;
+                let removed = cache::prune_removed(db, &crawler, &shops).await?;
+                for url in &removed {
+                    info!("  - {}", url);
+                }
+                info!("Removed {} pages that left the sitemap", removed.len());
+            }
+
+            if let Some(max_size) = max_size.or(retention.max_size_bytes) {
+                let summary = cache::enforce_size_cap(db, max_size).await?;
+                info!(
+                    "Size cap {} KB: removed {} old snapshots and {} pages, freed {} KB, {} KB left",
+                    max_size / 1024,
+                    summary.snapshots,
+                    summary.entries,
+                    summary.freed_bytes / 1024,
+                    summary.size_bytes / 1024
+                );
+            }
+        }
+        CacheAction::Verify { shops } => {
+            let shops = scraper::resolve_scrapers(&shops)?;
+            cache::verify(db, &shops).await?.log(WORST_OFFENDERS);
+        }
+        CacheAction::Export { output, shops } => {
+            let shops = scraper::resolve_scrapers(&shops)?;
+            info!("Exporting cache to {}", output.display());
+            let count = cache::export(db, &output, &shops).await?;
+            info!("Done! Exported {} entries", count);
+        }
+        CacheAction::Import { input } => {
+            info!("Importing cache from {}", input.display());
+            let summary = cache::import(db, &input).await?;
+            info!(
+                "Done! Imported {} entries, skipped {} already cached (same or newer)",
+                summary.imported, summary.skipped
+            );
+        }
+    }
 
     Ok(())
 }
 
-async fn migrate_cache_command(input: PathBuf) -> Result<()> {
+async fn migrate_cache_command(db: &TursoRepository, input: PathBuf) -> Result<()> {
     info!("Migrating JSON cache to database from {}", input.display());
 
-    let count = cache::migrate_from_json(input.to_str().unwrap()).await?;
+    let count = cache::migrate_from_json(db, input.to_str().unwrap()).await?;
 
     info!("Done! Migrated {} entries to database", count);
 
     Ok(())
 }
 
-async fn cache_stats_command() -> Result<()> {
-    let stats = cache::stats().await?;
+async fn cache_stats_command(db: &TursoRepository) -> Result<()> {
+    let stats = cache::stats(db).await?;
 
     println!("\nCache Statistics:");
     println!("  Entries: {}", stats.entry_count);
-    println!("  Total size: {} KB", stats.total_size_bytes / 1024);
+    println!("  Snapshots: {}", stats.snapshot_count);
+    println!(
+        "  Total size: {} KB ({} KB uncompressed)",
+        stats.total_size_bytes / 1024,
+        stats.uncompressed_size_bytes / 1024
+    );
+    if stats.legacy_count > 0 {
+        println!(
+            "  Uncompressed entries: {} (run `chai compress-cache`)",
+            stats.legacy_count
+        );
+    }
 
     if let Some(oldest) = stats.oldest_entry {
         let dt = chrono_lite(oldest);
@@ -335,7 +1088,93 @@ async fn cache_stats_command() -> Result<()> {
     Ok(())
 }
 
-fn chrono_lite(timestamp: i64) -> String {
+async fn compress_cache_command(db: &TursoRepository) -> Result<()> {
+    info!("Compressing cache entries");
+
+    let count = cache::compress_legacy(db).await?;
+
+    info!("Done! Compressed {} entries", count);
+
+    Ok(())
+}
+
+async fn cache_history_command(db: &TursoRepository, url: String) -> Result<()> {
+    let snapshots = cache::snapshots(db, &url).await?;
+    if snapshots.is_empty() {
+        if cache::contains(db, &url).await? {
+            println!("No snapshots of {} (run `chai compress-cache`)", url);
+        } else {
+            println!("Not cached: {}", url);
+        }
+        return Ok(());
+    }
+
+    println!("\nSnapshots of {}:", url);
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        println!(
+            "  [{}] {}  {}  {} KB ({} KB compressed)",
+            i,
+            chrono_lite(snapshot.fetched_at),
+            &snapshot.content_hash[..12],
+            snapshot.size_bytes / 1024,
+            snapshot.compressed_bytes / 1024
+        );
+    }
+
+    Ok(())
+}
+
+async fn cache_diff_command(
+    db: &TursoRepository,
+    url: String,
+    from: usize,
+    to: usize,
+    parsed: bool,
+) -> Result<()> {
+    let load = |index: usize| {
+        let url = url.clone();
+        async move {
+            cache::snapshot(db, &url, index).await?.with_context(|| {
+                format!(
+                    "No snapshot [{}] of {} (see `chai cache-history`)",
+                    index, url
+                )
+            })
+        }
+    };
+    let (old, old_html) = load(from).await?;
+    let (new, new_html) = load(to).await?;
+
+    let (old_text, new_text) = if parsed {
+        // Re-run the current parser against both snapshots
+        let parse = |html: &str| -> Result<String> {
+            let tea = scraper::parse_tea_from_html(&url, html)?;
+            Ok(serde_json::to_string_pretty(&tea)? + "\n")
+        };
+        (parse(&old_html)?, parse(&new_html)?)
+    } else {
+        (old_html, new_html)
+    };
+
+    if old.content_hash == new.content_hash || old_text == new_text {
+        println!("No changes between [{}] and [{}]", from, to);
+        return Ok(());
+    }
+
+    let old_header = format!("[{}] {}", from, chrono_lite(old.fetched_at));
+    let new_header = format!("[{}] {}", to, chrono_lite(new.fetched_at));
+    print!(
+        "{}",
+        similar::TextDiff::from_lines(&old_text, &new_text)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_header, &new_header)
+    );
+
+    Ok(())
+}
+
+fn chrono_lite(timestamp: i64) -> String {
     // Simple timestamp formatting without chrono dependency
     let secs_per_day = 86400;
     let secs_per_hour = 3600;
@@ -359,58 +1198,89 @@ fn chrono_lite(timestamp: i64) -> String {
     )
 }
 
-async fn sync_command(limit: Option<usize>, force: bool, from_cache: bool) -> Result<()> {
+/// Flags of `chai sync`
+struct SyncOptions {
+    limit: Option<usize>,
+    force: bool,
+    from_cache: bool,
+    skip_images: bool,
+    /// Percent of a shop's active teas that may be discontinued
+    max_removed: f64,
+}
+
+async fn sync_command(
+    db: &TursoRepository,
+    options: SyncOptions,
+    shops: Vec<String>,
+    models: Vec<String>,
+    crawl: CrawlArgs,
+) -> Result<()> {
+    let SyncOptions {
+        limit,
+        force,
+        from_cache,
+        skip_images,
+        max_removed,
+    } = options;
     info!("Syncing teas from website to database");
 
-    // Create clients
-    let http_client = Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .build()?;
+    let shops = scraper::resolve_scrapers(&shops)?;
 
-    let embeddings_config = chai_core::embeddings::EmbeddingsConfig::from_env()?;
-    info!("Embeddings model: {}", embeddings_config.model);
+    // Create clients
+    let crawler = crawl.build(db)?;
 
-    let embeddings_client = chai_core::embeddings::EmbeddingsClient::new(embeddings_config)?;
+    let embeddings_config = EmbeddingsConfig::from_env()?;
+    info!("Embedding models: {}", models.join(", "));
 
     // Get URL list from cache or website
-    let urls = if from_cache {
+    let mut urls: Vec<String> = if from_cache {
         info!("Loading URLs from cache");
-        cache::list_urls().await?
+        cache::list_urls(db)
+            .await?
+            .into_iter()
+            .filter(|url| scraper::is_product_url(url) && is_selected_shop(url, &shops))
+            .collect()
     } else {
         info!("Fetching URLs from sitemap");
-        scraper::get_tea_urls(&http_client).await?
+        fetch_shop_urls(&crawler, &shops).await?
     };
 
-    let urls_to_process = if let Some(limit) = limit {
-        &urls[..limit.min(urls.len())]
-    } else {
-        &urls
-    };
+    // A partial URL list says nothing about removed products
+    let complete = limit.is_none_or(|limit| urls.len() <= limit);
+    if let Some(limit) = limit {
+        urls.truncate(limit);
+    }
 
-    info!("Will process {} teas", urls_to_process.len());
+    info!("Will process {} teas", urls.len());
 
-    // Get list of all URLs from database for checking deleted items
-    let existing_urls = if !force {
-        turso::get_all_tea_urls().await?
+    // Active teas are checked for products gone from the website
+    // (only for the shops being synced - other shops are left untouched)
+    let existing_urls: Vec<String> = if !force && complete {
+        db.get_tea_urls(TeaStatus::Active)
+            .await?
+            .into_iter()
+            .filter(|url| is_selected_shop(url, &shops))
+            .collect()
     } else {
         Vec::new()
     };
+    let listed_urls: std::collections::HashSet<String> = urls.iter().cloned().collect();
 
     // Statistics
     let mut stats = SyncStats::default();
-    let total = urls_to_process.len();
+    let total = urls.len();
 
     // STEP 1: Parse all products
-    info!("Step 1/3: Parsing all products...");
-    let mut all_teas: std::collections::HashMap<String, Tea> = std::collections::HashMap::new();
-    let mut samples: Vec<String> = Vec::new();
-    let mut main_products: Vec<String> = Vec::new();
-
-    for (i, url) in urls_to_process.iter().enumerate() {
-        let scrape_result = if from_cache {
-            // Use cache
-            match cache::get(url).await? {
-                Some(entry) => scraper::parse_tea_from_html(url, &entry.html),
+    info!("Step 1/4: Parsing all products...");
+    let mut products = ParsedProducts::default();
+
+    if from_cache {
+        for (i, url) in urls.iter().enumerate() {
+            match cache::get(db, url).await? {
+                Some(entry) => {
+                    let result = scraper::parse_tea_with_report(url, &entry.html);
+                    products.add((i + 1, total), url, result.map_err(Into::into), &mut stats);
+                }
                 None => {
                     warn!(
                         "[{}/{}] ! URL not in cache, skipping: {}",
@@ -419,289 +1289,346 @@ async fn sync_command(limit: Option<usize>, force: bool, from_cache: bool) -> Re
                         url
                     );
                     stats.errors += 1;
-                    continue;
                 }
             }
-        } else {
-            // Fetch from website
-            scraper::scrape_tea(&http_client, url).await
-        };
-
-        match scrape_result {
-            Ok(tea) => {
-                // Save tea to map for later processing
-                all_teas.insert(url.clone(), tea.clone());
-
-                // Classify product
-                if tea.is_sample {
-                    if scraper::is_sample_set(url, &tea.name) {
-                        main_products.push(url.clone()); // Sets are treated as main products
-                    } else {
-                        samples.push(url.clone());
-                    }
-                } else {
-                    main_products.push(url.clone());
-                }
-
-                if let Some(name) = &tea.name {
-                    let tea_type = if tea.is_sample {
-                        if scraper::is_sample_set(url, &tea.name) {
-                            "set"
-                        } else {
-                            "sample"
-                        }
-                    } else {
-                        "product"
-                    };
-                    info!("[{}/{}] + {} ({})", i + 1, total, name, tea_type);
-                }
-            }
-            Err(e) => {
-                stats.errors += 1;
-                error!("[{}/{}] x {}", i + 1, total, e);
-            }
         }
+    } else {
+        // Fetch from website
+        let mut summary = CrawlSummary::default();
+        let mut pages = crawler.crawl(urls);
+        let mut i = 0;
+
+        while let Some((url, result)) = pages.next().await {
+            i += 1;
+            summary.record(&result);
+            if let Err(e) = &result
+                && e.disallowed
+            {
+                warn!("[{}/{}] - Skipped {}", i, total, e);
+                continue;
+            }
 
-        // Delay only when fetching from website (not for cache)
-        if !from_cache {
-            tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+            let result = result
+                .map_err(anyhow::Error::from)
+                .and_then(|page| Ok(scraper::parse_tea_with_report(&url, &page.body)?));
+            products.add((i, total), &url, result, &mut stats);
         }
+
+        summary.log();
     }
 
+    let ParsedProducts {
+        mut all_teas,
+        samples,
+        main_products,
+        diagnostics,
+    } = products;
+
+    diagnostics.log(WORST_OFFENDERS);
+
     info!(
         "Parsing done: {} main products, {} samples\n",
         main_products.len(),
         samples.len()
     );
 
-    // STEP 2: Link samples to main products (by name)
-    info!("Step 2/3: Linking samples to main products...");
-    let mut linked_count = 0;
-    let mut not_linked_count = 0;
-
-    for sample_url in &samples {
-        let sample_tea = match all_teas.get(sample_url) {
-            Some(tea) => tea,
-            None => continue,
-        };
-
-        // Get sample name and remove prefix
-        let sample_name = match &sample_tea.name {
-            Some(name) => name,
-            None => {
-                not_linked_count += 1;
-                continue;
-            }
-        };
-
-        // Normalize name: remove "пробник ", "Copy: пробник ", "Copy: " at start
-        let sample_lower = sample_name.trim().to_lowercase();
-        let normalized_sample_name = sample_lower
-            .trim_start_matches("copy: ")
-            .trim_start_matches("пробник ")
-            .trim();
-
-        // Find main product with matching name
-        let mut found = false;
-        let mut best_match: Option<(&str, usize)> = None;
-
-        for main_url in &main_products {
-            if let Some(main_tea) = all_teas.get(main_url)
-                && let Some(main_name) = &main_tea.name
-            {
-                let normalized_main_name = main_name.trim().to_lowercase();
-
-                // Exact match is best
-                if normalized_main_name == normalized_sample_name {
-                    best_match = Some((main_url, usize::MAX));
-                    break;
-                }
-
-                // For prefix matches, prefer longer matches
-                let sample_len = normalized_sample_name.chars().count();
-                let main_len = normalized_main_name.chars().count();
-                let min_len = sample_len.min(main_len);
-                let max_len = sample_len.max(main_len);
-
-                // Require at least 80% overlap
-                if min_len * 100 / max_len >= 80
-                    && (normalized_main_name.starts_with(normalized_sample_name)
-                        || normalized_sample_name.starts_with(&normalized_main_name))
-                {
-                    let match_len = min_len;
-                    if best_match.is_none_or(|(_, len)| match_len > len) {
-                        best_match = Some((main_url, match_len));
-                    }
-                }
-            }
-        }
-
-        if let Some((main_url, _)) = best_match
-            && let Some(main_tea_mut) = all_teas.get_mut(main_url)
-        {
-            main_tea_mut.sample_url = Some(sample_url.clone());
-            linked_count += 1;
-            found = true;
-        }
+    // STEP 2: Link samples to main products
+    info!("Step 2/4: Linking samples to main products...");
+    let links = {
+        let sample_teas: Vec<&Tea> = samples.iter().filter_map(|url| all_teas.get(url)).collect();
+        let main_teas: Vec<&Tea> = main_products
+            .iter()
+            .filter_map(|url| all_teas.get(url))
+            .collect();
+        link_samples(&sample_teas, &main_teas)
+    };
+    links.log();
 
-        if !found {
-            not_linked_count += 1;
+    for link in &links.links {
+        if let Some(main_tea) = all_teas.get_mut(&link.main_url) {
+            main_tea.sample_url = Some(link.sample_url.clone());
         }
     }
 
-    info!(
-        "Linking done: {} linked, {} not found\n",
-        linked_count, not_linked_count
-    );
-
-    // STEP 3: Vectorize and save only main products
-    info!("Step 3/3: Vectorizing and saving to database...");
-
-    #[derive(Clone, Copy)]
-    enum UpdateType {
-        Add,
-        Update,
+    // Teas gone from the website: no longer listed, or not a main product anymore.
+    // Listed pages that failed to load or parse keep their status.
+    let current_urls: std::collections::HashSet<&str> =
+        main_products.iter().map(String::as_str).collect();
+    let removed_urls: Vec<String> = existing_urls
+        .iter()
+        .filter(|url| {
+            !current_urls.contains(url.as_str())
+                && (!listed_urls.contains(*url) || all_teas.contains_key(*url))
+        })
+        .cloned()
+        .collect();
+    for shop in &shops {
+        let active = existing_urls
+            .iter()
+            .filter(|url| is_selected_shop(url, &[*shop]))
+            .count();
+        let removed = removed_urls
+            .iter()
+            .filter(|url| is_selected_shop(url, &[*shop]))
+            .count();
+        tea_utils::check_removed(shop.id(), active, removed, max_removed)?;
     }
 
-    const BATCH_SIZE: usize = 50;
-    let mut batch_items: Vec<(Tea, String, UpdateType)> = Vec::new();
-    let mut batch_texts: Vec<String> = Vec::new();
+    // STEP 3: Save only main products and vectorize them
+    info!("Step 3/4: Saving to database and vectorizing...");
 
-    for (i, url) in main_products.iter().enumerate() {
+    for url in &main_products {
         if let Some(tea) = all_teas.get(url) {
             let content_hash = tea_utils::compute_tea_hash(tea)?;
 
             // Check if update is needed
-            let update_info = if force {
-                Some(UpdateType::Update)
-            } else {
-                match turso::get_tea_with_hash(url).await? {
-                    Some((_, existing_hash)) => {
-                        if existing_hash != content_hash {
-                            Some(UpdateType::Update)
-                        } else {
-                            stats.skipped += 1;
-                            None
-                        }
-                    }
-                    None => Some(UpdateType::Add),
+            let existing_hash = db.get_tea_with_hash(url).await?.map(|(_, hash)| hash);
+            match existing_hash {
+                Some(hash) if hash == content_hash && !force => {
+                    stats.skipped += 1;
+                    continue;
                 }
-            };
-
-            if let Some(update_type) = update_info {
-                let text = tea_utils::tea_to_text(tea);
-                batch_items.push((tea.clone(), content_hash, update_type));
-                batch_texts.push(text);
+                Some(_) => stats.updated += 1,
+                None => stats.added += 1,
             }
 
-            // Vectorize and save batch
-            if (batch_items.len() >= BATCH_SIZE || i == main_products.len() - 1)
-                && !batch_items.is_empty()
-            {
-                info!("Vectorizing batch of {} teas...", batch_items.len());
-
-                let embeddings = embeddings_client
-                    .create_embeddings(batch_texts.clone())
-                    .await
-                    .context("Failed to create embeddings")?;
-
-                // Validate embedding count
-                if embeddings.len() != batch_items.len() {
-                    warn!(
-                        "Embedding count mismatch: expected {}, got {}",
-                        batch_items.len(),
-                        embeddings.len()
-                    );
-                }
-
-                for ((tea, hash, update_type), embedding) in
-                    batch_items.iter().zip(embeddings.iter())
-                {
-                    turso::upsert_tea(tea, Some(embedding.clone()), hash).await?;
+            db.upsert_tea(tea, &content_hash).await?;
+        }
+    }
+    db.mark_teas_seen(&main_products).await?;
 
-                    match update_type {
-                        UpdateType::Add => stats.added += 1,
-                        UpdateType::Update => stats.updated += 1,
-                    }
-                }
+    // Embeddings of changed teas are outdated now, teas of other shops are left untouched
+    for model in &models {
+        stats.embedded += embed_teas(db, &embeddings_config, model, &shops, None, force).await?;
+    }
 
-                info!("Batch of {} teas processed", batch_items.len());
+    // Keep teas that are no longer on the website as discontinued
+    if complete {
+        stats.discontinued = db.discontinue_teas(&removed_urls).await?;
+    } else {
+        info!("Not checking for removed teas: --limit syncs only part of the catalog");
+    }
 
-                batch_items.clear();
-                batch_texts.clear();
-            }
-        }
+    // Store sample links (teas are saved, so links can reference them)
+    for shop in &shops {
+        let shop_links: Vec<SampleLink> = links
+            .links
+            .iter()
+            .filter(|link| {
+                all_teas
+                    .get(&link.main_url)
+                    .is_some_and(|tea| tea.shop == shop.id())
+            })
+            .cloned()
+            .collect();
+        db.replace_sample_links(shop.id(), &shop_links).await?;
     }
 
-    // Delete teas that are no longer on the website
-    if !force {
-        let current_urls: std::collections::HashSet<_> =
-            main_products.iter().map(|s| s.as_str()).collect();
-        for existing_url in existing_urls {
-            if !current_urls.contains(existing_url.as_str()) {
-                turso::delete_tea_by_url(&existing_url).await?;
-                stats.deleted += 1;
-            }
-        }
+    // STEP 4: Mirror gallery images of main products
+    if skip_images {
+        info!("Step 4/4: Skipping images (--skip-images)\n");
+    } else {
+        info!("Step 4/4: Mirroring images...");
+        let images_config = ImagesConfig::from_env();
+        let image_urls: Vec<String> = main_products
+            .iter()
+            .filter_map(|url| all_teas.get(url))
+            .flat_map(|tea| tea.images.iter().cloned())
+            .collect();
+
+        let summary = images::mirror_images(db, &crawler, &images_config, image_urls).await;
+        summary.log();
     }
 
     // Print statistics
     info!("Sync completed!");
     info!("Statistics:");
     info!("  Main products: {}", main_products.len());
-    info!("  Samples linked: {}", linked_count);
+    info!("  Samples linked: {}", links.links.len());
     info!("  Added: {}", stats.added);
     info!("  Updated: {}", stats.updated);
     info!("  Skipped: {}", stats.skipped);
-    info!("  Deleted: {}", stats.deleted);
+    info!("  Discontinued: {}", stats.discontinued);
+    info!("  Embedded: {}", stats.embedded);
     info!("  Errors: {}", stats.errors);
 
     Ok(())
 }
 
+/// Teas embedded per embeddings API request
+const EMBED_BATCH_SIZE: usize = 50;
+
+/// Embed stored teas of the selected shops without an up-to-date embedding of the model
+///
+/// With `force`, teas with an up-to-date embedding are embedded again.
+/// Returns the number of embedded teas.
+async fn embed_teas(
+    db: &TursoRepository,
+    embeddings_config: &EmbeddingsConfig,
+    model: &str,
+    shops: &[&dyn ShopScraper],
+    limit: Option<usize>,
+    force: bool,
+) -> Result<usize> {
+    let embeddings_client = EmbeddingsClient::new(EmbeddingsConfig {
+        model: model.to_string(),
+        ..embeddings_config.clone()
+    })?;
+
+    let mut teas: Vec<(Tea, String)> = db
+        .teas_needing_embedding(model, force)
+        .await?
+        .into_iter()
+        .filter(|(tea, _)| is_selected_shop(&tea.url, shops))
+        .collect();
+    if let Some(limit) = limit {
+        teas.truncate(limit);
+    }
+
+    info!("{} teas to embed with {}", teas.len(), model);
+
+    let mut embedded = 0;
+    for batch in teas.chunks(EMBED_BATCH_SIZE) {
+        let texts = batch
+            .iter()
+            .map(|(tea, _)| tea_utils::tea_to_text(tea))
+            .collect();
+        let embeddings = embeddings_client
+            .create_embeddings(texts)
+            .await
+            .context("Failed to create embeddings")?;
+
+        // Validate embedding count
+        if embeddings.len() != batch.len() {
+            warn!(
+                "Embedding count mismatch: expected {}, got {}",
+                batch.len(),
+                embeddings.len()
+            );
+        }
+
+        for ((tea, hash), embedding) in batch.iter().zip(&embeddings) {
+            db.update_tea_embedding(&tea.url, model, hash, embedding)
+                .await?;
+            embedded += 1;
+        }
+
+        info!("Embedded {}/{} teas with {}", embedded, teas.len(), model);
+    }
+
+    Ok(embedded)
+}
+
+/// Number of pages with the most parse issues shown after sync step 1
+const WORST_OFFENDERS: usize = 5;
+
+/// Products parsed in sync step 1
+#[derive(Default)]
+struct ParsedProducts {
+    all_teas: std::collections::HashMap<String, Tea>,
+    samples: Vec<String>,
+    main_products: Vec<String>,
+    diagnostics: ParseSummary,
+}
+
+impl ParsedProducts {
+    /// Store a parse result, collect its diagnostics and log progress
+    fn add(
+        &mut self,
+        progress: (usize, usize),
+        url: &str,
+        result: Result<(Tea, ParseReport)>,
+        stats: &mut SyncStats,
+    ) {
+        let (i, total) = progress;
+
+        match result {
+            Ok((tea, report)) => {
+                self.diagnostics.record(report);
+
+                // Classify product
+                if tea.is_sample && !tea.is_set {
+                    self.samples.push(url.to_string());
+                } else {
+                    self.main_products.push(url.to_string()); // Sets are treated as main products
+                }
+
+                if let Some(name) = &tea.name {
+                    let tea_type = if tea.is_sample {
+                        if tea.is_set { "set" } else { "sample" }
+                    } else {
+                        "product"
+                    };
+                    info!("[{}/{}] + {} ({})", i, total, name, tea_type);
+                }
+
+                // Save tea to map for later processing
+                self.all_teas.insert(url.to_string(), tea);
+            }
+            Err(e) => {
+                if let Some(parse_error) = e.downcast_ref::<ParseError>() {
+                    self.diagnostics.record_error(parse_error);
+                }
+                stats.errors += 1;
+                error!("[{}/{}] x {}", i, total, e);
+            }
+        }
+    }
+}
+
 #[derive(Default)]
 struct SyncStats {
     added: usize,
     updated: usize,
     skipped: usize,
-    deleted: usize,
+    discontinued: usize,
+    embedded: usize,
     errors: usize,
 }
 
 async fn search_command(
+    db: &TursoRepository,
     query: String,
     limit: usize,
-    only_available: bool,
-    series: Option<String>,
-) -> Result<()> {
-    info!("Search: \"{}\"", query);
-    if let Some(ref s) = series {
-        info!("Filter by series: {}", s);
+    mode: SearchMode,
+    model: &str,
+    hybrid: &HybridConfig,
+    filters: SearchFilters,
+) -> Result<Vec<SearchResult>> {
+    info!("Search: \"{}\" ({:?})", query, mode);
+    for filter in filters.conditions() {
+        info!("Filter: {:?}", filter);
     }
 
-    // Create embedding for query
-    let embeddings_config = chai_core::embeddings::EmbeddingsConfig::from_env()?;
-    let embeddings_client = chai_core::embeddings::EmbeddingsClient::new(embeddings_config)?;
-
-    info!("Creating embedding for query...");
-    let query_embedding = embeddings_client.create_embedding(query.clone()).await?;
-
-    // Create filters
-    let filters = turso::SearchFilters {
-        exclude_samples: false,
-        exclude_sets: false,
-        only_in_stock: only_available,
-        series,
+    let results = if mode == SearchMode::Keyword {
+        db.search_teas_keyword(&query, limit, &filters).await?
+    } else {
+        // Create embedding for query
+        let embeddings_client = EmbeddingsClient::new(EmbeddingsConfig {
+            model: model.to_string(),
+            ..EmbeddingsConfig::from_env()?
+        })?;
+
+        info!("Creating embedding for query with {}...", model);
+        let query_embedding = embeddings_client.create_embedding(query.clone()).await?;
+
+        info!("Searching similar teas...");
+        if mode == SearchMode::Hybrid {
+            db.search_teas_hybrid(&query, &query_embedding, model, limit, &filters, hybrid)
+                .await?
+        } else {
+            db.search_teas(&query_embedding, model, limit, &filters)
+                .await?
+        }
     };
 
-    // Execute search
-    info!("Searching similar teas...");
-    let results = turso::search_teas(&query_embedding, limit, &filters).await?;
+    Ok(results)
+}
 
-    // Print results
+fn print_search_results(results: &[SearchResult], mode: SearchMode) {
     if results.is_empty() {
         warn!("No results found");
-        return Ok(());
+        return;
     }
 
     info!("Found {} results:\n", results.len());
@@ -728,19 +1655,35 @@ async fn search_command(
             relevance
         );
 
+        if mode != SearchMode::Vector {
+            let signal =
+                |score: Option<f32>| score.map_or("-".to_string(), |s| format!("{:.3}", s));
+            println!(
+                "   Scores: vector {} | keyword {}",
+                signal(result.vector_score),
+                signal(result.keyword_score)
+            );
+        }
+
         if let Some(price) = &tea.price {
             let stock = if tea.in_stock {
                 "In stock"
             } else {
                 "Out of stock"
             };
-            println!("   Price: {} | {}", price, stock);
+            println!("   Price: {} | {}", price_text(tea, price), stock);
         }
 
         if let Some(series) = &tea.series {
             println!("   Series: {}", series);
         }
 
+        println!("   Shop: {}", shop_display_name(&tea.shop));
+
+        if result.status != TeaStatus::Active {
+            println!("   Status: {}", result.status);
+        }
+
         if !tea.composition.is_empty() {
             let ingredients: Vec<_> = tea.composition.iter().take(5).collect();
             let more = if tea.composition.len() > 5 {
@@ -762,14 +1705,36 @@ async fn search_command(
         println!("   URL: {}", tea.url);
         println!();
     }
+}
 
-    Ok(())
+/// Parsed price with the best price per 100 g, the raw text if it doesn't parse
+fn price_text(tea: &Tea, raw: &str) -> String {
+    let Some(price) = tea.price_amount() else {
+        return raw.to_string();
+    };
+    match tea.price_per_100g() {
+        Some(per_100g) => format!("{} ({} per 100 g)", price, per_100g),
+        None => price.to_string(),
+    }
+}
+
+/// Parse `name=value` characteristic filter
+fn parse_characteristic(arg: &str) -> std::result::Result<(String, String), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=value, got '{}'", arg))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// Shop display name by ID (falls back to the ID for unknown shops)
+fn shop_display_name(shop_id: &str) -> &str {
+    scraper::scraper_by_id(shop_id).map_or(shop_id, |shop| shop.display_name())
 }
 
-async fn get_command(url: String) -> Result<()> {
+async fn get_command(db: &TursoRepository, url: String) -> Result<()> {
     info!("Getting tea by URL: {}", url);
 
-    let tea_option = turso::get_tea_with_hash(&url).await?;
+    let tea_option = db.get_tea_with_hash(&url).await?;
 
     match tea_option {
         Some((tea, content_hash)) => {
@@ -777,9 +1742,35 @@ async fn get_command(url: String) -> Result<()> {
 
             println!("Name: {}", tea.name.as_deref().unwrap_or("No name"));
             println!("URL: {}", tea.url);
+            println!("Shop: {}", shop_display_name(&tea.shop));
+
+            if let Some(lifecycle) = db.get_tea_lifecycle(&tea.url).await? {
+                let seen = |at: Option<i64>| at.map_or("-".to_string(), chrono_lite);
+                println!(
+                    "Status: {} (first seen {}, last seen {})",
+                    lifecycle.status,
+                    seen(lifecycle.first_seen),
+                    seen(lifecycle.last_seen)
+                );
+                if let Some(at) = lifecycle.discontinued_at {
+                    println!("Discontinued: {}", chrono_lite(at));
+                }
+            }
 
-            if let Some(sample_url) = &tea.sample_url {
-                println!("Sample URL: {}", sample_url);
+            match db.get_sample_link(&tea.url).await? {
+                Some((sample_url, sample_in_stock)) => {
+                    let stock = if sample_in_stock {
+                        "in stock"
+                    } else {
+                        "out of stock"
+                    };
+                    println!("Sample URL: {} ({})", sample_url, stock);
+                }
+                None => {
+                    if let Some(sample_url) = &tea.sample_url {
+                        println!("Sample URL: {}", sample_url);
+                    }
+                }
             }
 
             if let Some(price) = &tea.price {
@@ -788,13 +1779,26 @@ async fn get_command(url: String) -> Result<()> {
                 } else {
                     "Out of stock"
                 };
-                println!("Price: {} | {}", price, stock);
+                println!("Price: {} | {}", price_text(&tea, price), stock);
             }
 
             if let Some(series) = &tea.series {
                 println!("Series: {}", series);
             }
 
+            if let Some(origin) = &tea.origin {
+                println!("Origin: {}", origin);
+            }
+            if let Some(year) = tea.harvest_year {
+                println!("Harvest year: {}", year);
+            }
+            if let Some(leaf_type) = &tea.leaf_type {
+                println!("Leaf type: {}", leaf_type);
+            }
+            if let Some(fermentation) = &tea.fermentation {
+                println!("Fermentation: {}", fermentation);
+            }
+
             if !tea.composition.is_empty() {
                 println!("Composition: {}", tea.composition.join(", "));
             }
@@ -809,10 +1813,28 @@ async fn get_command(url: String) -> Result<()> {
 
             if !tea.price_variants.is_empty() {
                 println!("Price variants:");
+                let cheapest = tea.cheapest_in_stock_variant();
                 for variant in &tea.price_variants {
+                    let price = variant
+                        .amount()
+                        .map_or_else(|| variant.price.clone(), |amount| amount.to_string());
+                    let per_100g = variant
+                        .price_per_100g()
+                        .map(|per_100g| format!(", {} per 100 g", per_100g))
+                        .unwrap_or_default();
+                    let stock = match variant.stock() {
+                        Some(stock) => format!(" ({} left)", stock),
+                        None if !variant.quantity.is_empty() => format!(" ({})", variant.quantity),
+                        None => String::new(),
+                    };
+                    let marker = if cheapest.is_some_and(|c| std::ptr::eq(c, variant)) {
+                        ", cheapest in stock"
+                    } else {
+                        ""
+                    };
                     println!(
-                        "   {} - {} ({})",
-                        variant.packaging, variant.price, variant.quantity
+                        "   {} - {}{}{}{}",
+                        variant.packaging, price, per_100g, stock, marker
                     );
                 }
             }
@@ -825,6 +1847,13 @@ async fn get_command(url: String) -> Result<()> {
                 println!("Tags: {}", tea.search_tags.join(", "));
             }
 
+            if !tea.characteristics.is_empty() {
+                println!("Characteristics:");
+                for (name, value) in &tea.characteristics {
+                    println!("   {}: {}", name, value);
+                }
+            }
+
             println!("Is sample: {}", tea.is_sample);
             println!("Content hash: {}", content_hash);
 
@@ -837,22 +1866,341 @@ async fn get_command(url: String) -> Result<()> {
     }
 }
 
-async fn stats_command() -> Result<()> {
+async fn db_command(db: &TursoRepository, action: DbAction, db_config: &DbConfig) -> Result<()> {
+    let conn = db.connection()?;
+
+    match action {
+        DbAction::Migrate => {
+            let applied = db.migrate(db_config).await?;
+            if applied.is_empty() {
+                info!(
+                    "Schema is up to date (version {})",
+                    migrations::latest_version()
+                );
+            } else {
+                info!(
+                    "Done! Applied {} migrations, schema version {}",
+                    applied.len(),
+                    migrations::latest_version()
+                );
+            }
+        }
+        DbAction::Status => {
+            let status = migrations::status(&conn).await?;
+
+            println!("\nSchema of {}:", db_config.path);
+            println!(
+                "  Version: {} (this binary: {})",
+                status.current_version(),
+                migrations::latest_version()
+            );
+            for migration in &status.migrations {
+                let state = match migration.state {
+                    MigrationState::Applied(at) => format!("applied {}", chrono_lite(at)),
+                    MigrationState::Modified(at) => {
+                        format!("applied {}, CHANGED since", chrono_lite(at))
+                    }
+                    MigrationState::Pending => "pending".to_string(),
+                    MigrationState::Unknown(at) => {
+                        format!("applied {} by a newer version", chrono_lite(at))
+                    }
+                };
+                println!(
+                    "  {:>4} {:<24} {}",
+                    migration.version, migration.name, state
+                );
+            }
+
+            if let Some(size) = status.vector_size
+                && size != db_config.vector_size
+            {
+                println!(
+                    "  ! teas.embedding has {} dimensions, VECTOR_SIZE is {}",
+                    size, db_config.vector_size
+                );
+            }
+            for rebuild in &status.rebuilds {
+                println!(
+                    "  ! Rebuild {} didn't finish, run `db migrate`",
+                    rebuild.as_str()
+                );
+            }
+            if let Err(e) = status.check() {
+                println!("  ! {}", e);
+            }
+        }
+        DbAction::Reindex => {
+            ensure_migrated(db).await?;
+
+            let indexed = db.rebuild_keyword_index().await?;
+            let filled = db.rebuild_tea_columns().await?;
+            info!(
+                "Done! Indexed keywords of {} teas, filled typed columns of {}",
+                indexed, filled
+            );
+        }
+        DbAction::VectorIndex {
+            clusters,
+            drop,
+            model,
+        } => {
+            ensure_migrated(db).await?;
+            let model = model.unwrap_or_else(|| db_config.embedding_model.clone());
+
+            if drop {
+                db.drop_vector_index(&model).await?;
+                info!(
+                    "Done! Vector index of {} dropped, search scans all teas",
+                    model
+                );
+                return Ok(());
+            }
+
+            info!("Building vector index of {}", model);
+            let stats = db.build_vector_index(&model, clusters).await?;
+            info!(
+                "Done! {} teas in {} clusters",
+                stats.indexed, stats.clusters
+            );
+        }
+        DbAction::DropModel { model } => {
+            ensure_migrated(db).await?;
+
+            if model == db_config.embedding_model {
+                warn!(
+                    "{} is EMBEDDING_MODEL, vector search won't find teas",
+                    model
+                );
+            }
+            let deleted = db.delete_embedding_model(&model).await?;
+            info!("Done! Deleted {} embeddings of {}", deleted, model);
+        }
+        DbAction::Quantize { storage } => {
+            ensure_migrated(db).await?;
+
+            let storage = storage.unwrap_or(db_config.vector_storage);
+            if storage != db_config.vector_storage {
+                warn!(
+                    "VECTOR_STORAGE is {:?}, new embeddings won't be stored as {:?}",
+                    db_config.vector_storage, storage
+                );
+            }
+
+            let before = db.embedding_storage_size().await?;
+            let converted = db.convert_embeddings(storage).await?;
+            let after = db.embedding_storage_size().await?;
+            info!(
+                "Done! Converted {} embeddings to {:?}, {} KB -> {} KB",
+                converted,
+                storage,
+                before / 1024,
+                after / 1024
+            );
+        }
+        DbAction::Backup { path, compress } => {
+            ensure_migrated(db).await?;
+
+            let compress = compress || path.extension().is_some_and(|ext| ext == "zst");
+            let summary = backup::backup(db, &path, compress).await?;
+            info!(
+                "Done! Backed up {} rows of {} tables to {} ({} KB)",
+                summary.rows(),
+                summary.tables.len(),
+                path.display(),
+                file_size(&path) / 1024
+            );
+        }
+        DbAction::Restore { .. } => unreachable!("handled before opening the database"),
+        DbAction::Snapshot {
+            dir,
+            keep,
+            compress,
+        } => {
+            ensure_migrated(db).await?;
+
+            let dir = dir.unwrap_or_else(|| {
+                Path::new(&db_config.path)
+                    .parent()
+                    .unwrap_or(Path::new("."))
+                    .join("backups")
+            });
+            let snapshot = backup::snapshot(db, &dir, keep as usize, compress).await?;
+            for old in &snapshot.pruned {
+                info!("Removed old snapshot {}", old.display());
+            }
+            info!(
+                "Done! Snapshot of {} rows written to {} ({} KB)",
+                snapshot.summary.rows(),
+                snapshot.path.display(),
+                file_size(&snapshot.path) / 1024
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Size of a file in bytes (0 if it can't be read)
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+async fn restore_command(path: &Path, force: bool, db_config: &DbConfig) -> Result<()> {
+    if Path::new(&db_config.path).exists() && !force {
+        anyhow::bail!(
+            "Database {} exists, pass --force to replace it (stop chai-web first)",
+            db_config.path
+        );
+    }
+
+    info!("Restoring {} into {}", path.display(), db_config.path);
+    let summary = backup::restore(path, db_config).await?;
+
+    println!(
+        "\nRestored backup of {} (schema version {}):",
+        chrono_lite(summary.created_at),
+        summary.schema_version
+    );
+    for table in &summary.tables {
+        println!("  {:<24} {:>8} rows", table.name, table.rows);
+    }
+    info!(
+        "Done! Restored and verified {} rows, schema version {}",
+        summary.rows(),
+        migrations::latest_version()
+    );
+
+    Ok(())
+}
+
+/// Fail if the schema isn't at the version of this binary
+async fn ensure_migrated(db: &TursoRepository) -> Result<()> {
+    let status = migrations::status(&db.connection()?).await?;
+    status.check()?;
+    if status.pending().next().is_some() {
+        anyhow::bail!("Schema has pending migrations, run `db migrate` first");
+    }
+    Ok(())
+}
+
+async fn bench_search_command(
+    db: &TursoRepository,
+    model: &str,
+    queries: usize,
+    limit: usize,
+    probes: Vec<usize>,
+) -> Result<()> {
+    let index = db.vector_index_stats(model).await?;
+    let embeddings = db.sample_tea_embeddings(model, queries).await?;
+    if embeddings.is_empty() {
+        anyhow::bail!("No tea embeddings of {}, run `embed` first", model);
+    }
+    info!(
+        "Benchmarking {} on {} queries, top {}, index of {} clusters ({} teas unindexed)",
+        model,
+        embeddings.len(),
+        limit,
+        index.clusters,
+        index.unindexed
+    );
+    if index.clusters == 0 {
+        warn!(
+            "Vector index of {} is not built, run `db vector-index` to benchmark ANN search",
+            model
+        );
+    }
+
+    let filters = SearchFilters::new();
+    let mut exact_times = Vec::new();
+    let mut exact_results = Vec::new();
+    for embedding in &embeddings {
+        let start = Instant::now();
+        let results = db
+            .search_teas_exact(embedding, model, limit, &filters)
+            .await?;
+        exact_times.push(start.elapsed());
+        exact_results.push(result_urls(&results));
+    }
+
+    println!("\n=== Vector Search Benchmark ===\n");
+    println!(
+        "  {:<10} {:>10} {:>10} {:>10}",
+        "search", "avg", "p95", "recall"
+    );
+    print_bench_row("exact", &mut exact_times, 1.0);
+
+    let mut times = Vec::new();
+    let mut recall = 0.0;
+    for (embedding, exact) in embeddings.iter().zip(&exact_results) {
+        let start = Instant::now();
+        let results = db
+            .search_teas_binary(embedding, model, limit, &filters)
+            .await?;
+        times.push(start.elapsed());
+        recall += vector_index::recall(exact, &result_urls(&results));
+    }
+    print_bench_row("binary", &mut times, recall / embeddings.len() as f64);
+
+    for probes in probes.into_iter().filter(|_| index.clusters > 0) {
+        let mut times = Vec::new();
+        let mut recall = 0.0;
+        for (embedding, exact) in embeddings.iter().zip(&exact_results) {
+            let start = Instant::now();
+            let results = db
+                .search_teas_ann(embedding, model, limit, &filters, probes)
+                .await?;
+            times.push(start.elapsed());
+            recall += vector_index::recall(exact, &result_urls(&results));
+        }
+        recall /= embeddings.len() as f64;
+        print_bench_row(&format!("ann/{}", probes), &mut times, recall);
+    }
+
+    println!();
+    Ok(())
+}
+
+fn result_urls(results: &[SearchResult]) -> Vec<String> {
+    results.iter().map(|r| r.tea.url.clone()).collect()
+}
+
+fn print_bench_row(name: &str, times: &mut [Duration], recall: f64) {
+    times.sort();
+    let avg = times.iter().sum::<Duration>() / times.len().max(1) as u32;
+    let p95 = times
+        .get((times.len() * 95 / 100).min(times.len().saturating_sub(1)))
+        .copied()
+        .unwrap_or_default();
+    println!(
+        "  {:<10} {:>8.2}ms {:>8.2}ms {:>9.1}%",
+        name,
+        avg.as_secs_f64() * 1000.0,
+        p95.as_secs_f64() * 1000.0,
+        recall * 100.0
+    );
+}
+
+async fn stats_command(db: &TursoRepository, default_model: &str) -> Result<()> {
     info!("Getting statistics");
 
-    let stats = turso::get_stats().await?;
+    let stats = db.get_stats().await?;
 
     println!("\n=== Tea Database Statistics ===\n");
 
     println!("General:");
-    println!("  Total teas: {}", stats.total_teas);
+    println!("  Active teas: {}", stats.total_teas);
     println!("  In stock: {}", stats.in_stock);
     println!("  Out of stock: {}", stats.out_of_stock);
+    println!("  Discontinued: {}", stats.discontinued);
+    println!("  Hidden: {}", stats.hidden);
 
     if stats.total_teas > 0 {
         let in_stock_percent = (stats.in_stock as f32 / stats.total_teas as f32 * 100.0).round();
         println!("  In stock %: {}%", in_stock_percent);
     }
+    if let Some((min, max)) = stats.price_range {
+        println!("  Prices: {} - {}", min, max);
+    }
 
     println!("\nSeries:");
     println!("  Total series: {}", stats.series_count);
@@ -864,8 +2212,30 @@ async fn stats_command() -> Result<()> {
         }
     }
 
+    println!("\nShops:");
+    for (shop, count) in &stats.shop_counts {
+        println!("  {}: {}", shop_display_name(shop), count);
+    }
+
+    println!("\nEmbedding models:");
+    let models = db.embedding_models().await?;
+    if !models.iter().any(|m| m.model == default_model) {
+        println!("  {} (EMBEDDING_MODEL): no embeddings", default_model);
+    }
+    for model in &models {
+        let marker = if model.model == default_model {
+            " (EMBEDDING_MODEL)"
+        } else {
+            ""
+        };
+        println!(
+            "  {}{}: {} teas, {} dimensions, {} stale",
+            model.model, marker, model.teas, model.dims, model.stale
+        );
+    }
+
     // Cache stats
-    if let Ok(cache_stats) = cache::stats().await {
+    if let Ok(cache_stats) = cache::stats(db).await {
         println!("\nCache:");
         println!("  Entries: {}", cache_stats.entry_count);
         println!("  Size: {} KB", cache_stats.total_size_bytes / 1024);
@@ -875,3 +2245,52 @@ async fn stats_command() -> Result<()> {
 
     Ok(())
 }
+
+async fn facets_command(db: &TursoRepository, filters: &SearchFilters, limit: usize) -> Result<()> {
+    let facets = db.get_facets(filters).await?;
+
+    println!("=== Facets ===\n");
+
+    println!("Teas: {}", facets.total);
+    println!("  In stock: {}", facets.in_stock);
+    println!("  Out of stock: {}", facets.out_of_stock);
+    println!("  Sample in stock: {}", facets.sample_in_stock);
+    println!("  Samples: {}", facets.samples);
+    println!("  Sets: {}", facets.sets);
+
+    println!("\nSeries:");
+    print_facet_counts(&facets.series, limit, "  ");
+
+    println!("\nIngredients:");
+    print_facet_counts(&facets.ingredients, limit, "  ");
+
+    println!("\nPrices:");
+    for bucket in &facets.price_buckets {
+        let range = match (bucket.min, bucket.max) {
+            (None, Some(max)) => format!("under {}", max),
+            (Some(min), Some(max)) => format!("{} - {}", min, max),
+            (Some(min), None) => format!("{} and more", min),
+            (None, None) => "any".to_string(),
+        };
+        println!("  {}: {}", range, bucket.count);
+    }
+
+    println!("\nCharacteristics:");
+    for (name, values) in &facets.characteristics {
+        println!("  {}:", name);
+        print_facet_counts(values, limit, "    ");
+    }
+
+    println!();
+
+    Ok(())
+}
+
+fn print_facet_counts(counts: &[FacetCount], limit: usize, indent: &str) {
+    for count in counts.iter().take(limit) {
+        println!("{}{} ({})", indent, count.value, count.count);
+    }
+    if counts.len() > limit {
+        println!("{}... {} more", indent, counts.len() - limit);
+    }
+}
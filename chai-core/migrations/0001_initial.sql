@@ -0,0 +1,83 @@
+-- Schema as created by init_database before versioned migrations.
+-- Placeholders: {vector_size}, {default_shop}
+
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_users_email ON users(email);
+
+-- Current content of cached pages; html is empty for compressed entries,
+-- their content is the html_snapshots row with the same content_hash
+CREATE TABLE IF NOT EXISTS html_cache (
+    url TEXT PRIMARY KEY,
+    html TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    etag TEXT,
+    last_modified TEXT,
+    status INTEGER,
+    content_hash TEXT
+);
+
+-- Compressed page contents, one row per distinct content of a URL.
+-- fetched_at is when the page switched to this content.
+CREATE TABLE IF NOT EXISTS html_snapshots (
+    url TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    html_zstd BLOB NOT NULL,
+    size INTEGER NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    PRIMARY KEY (url, content_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_html_snapshots_url ON html_snapshots(url, fetched_at);
+
+-- Tea data as JSON, embedding as F32_BLOB
+CREATE TABLE IF NOT EXISTS teas (
+    id TEXT PRIMARY KEY,
+    url TEXT NOT NULL UNIQUE,
+    tea_data TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    embedding F32_BLOB({vector_size}),
+    shop TEXT NOT NULL DEFAULT '{default_shop}',
+    in_stock INTEGER NOT NULL DEFAULT 0,
+    is_sample INTEGER NOT NULL DEFAULT 0,
+    is_set INTEGER NOT NULL DEFAULT 0,
+    series TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_teas_url ON teas(url);
+CREATE INDEX IF NOT EXISTS idx_teas_in_stock ON teas(in_stock);
+CREATE INDEX IF NOT EXISTS idx_teas_series ON teas(series);
+CREATE INDEX IF NOT EXISTS idx_teas_shop ON teas(shop);
+
+-- Product characteristics (one row per name/value) for attribute filters
+CREATE TABLE IF NOT EXISTS tea_characteristics (
+    tea_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (tea_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tea_characteristics_name_value ON tea_characteristics(name, value);
+
+-- Samples linked to main products (one per product), joined in search
+CREATE TABLE IF NOT EXISTS tea_samples (
+    tea_id TEXT PRIMARY KEY,
+    sample_url TEXT NOT NULL,
+    sample_in_stock INTEGER NOT NULL DEFAULT 0,
+    match_method TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- Mirrored images: remote URL -> content hash of the local thumbnails
+CREATE TABLE IF NOT EXISTS mirrored_images (
+    url TEXT PRIMARY KEY,
+    hash TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Keyword index for hybrid search: stemmed terms of name, composition,
+-- search tags, series and description with field-weighted frequencies.
+-- Filled by upsert_tea; teas stored before this migration are indexed right after it.
+
+CREATE TABLE IF NOT EXISTS tea_terms (
+    tea_id TEXT NOT NULL,
+    term TEXT NOT NULL,
+    weight REAL NOT NULL,
+    PRIMARY KEY (tea_id, term)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tea_terms_term ON tea_terms(term);
@@ -0,0 +1,16 @@
+-- IVF vector index: k-means centroids of tea embeddings and the cluster of each tea.
+-- Vector search scans the clusters nearest to the query instead of the whole table.
+-- Built by `db vector-index`; teas embedded later are assigned to the nearest centroid.
+-- Placeholders: {vector_size}
+
+CREATE TABLE IF NOT EXISTS vector_centroids (
+    cluster INTEGER PRIMARY KEY,
+    centroid F32_BLOB({vector_size}) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tea_vector_clusters (
+    tea_id TEXT PRIMARY KEY,
+    cluster INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_tea_vector_clusters_cluster ON tea_vector_clusters(cluster);
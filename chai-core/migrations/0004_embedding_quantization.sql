@@ -0,0 +1,8 @@
+-- Quantized embeddings and sign bits.
+-- With VECTOR_STORAGE=f16/int8 the embedding is stored in embedding_quantized
+-- instead of teas.embedding. Sign bits of every embedding are the coarse first
+-- pass of binary vector search. Existing rows are converted after this migration.
+
+ALTER TABLE teas ADD COLUMN embedding_quantized BLOB;
+
+ALTER TABLE teas ADD COLUMN embedding_bits BLOB;
@@ -0,0 +1,62 @@
+-- Embeddings of several models side by side, one row per tea and model.
+-- content_hash is the teas.content_hash the embedding was made from, so
+-- embeddings of a model can be refreshed while another one serves search.
+-- Existing embeddings and the IVF index were made by EMBEDDING_MODEL and move here.
+-- Placeholders: {vector_size}, {embedding_model}
+
+CREATE TABLE IF NOT EXISTS tea_embeddings (
+    tea_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    dims INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    vector BLOB,
+    vector_quantized BLOB,
+    vector_bits BLOB,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (tea_id, model)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tea_embeddings_model ON tea_embeddings(model, dims);
+
+INSERT INTO tea_embeddings (tea_id, model, dims, content_hash, vector, vector_quantized, vector_bits, updated_at)
+SELECT id, '{embedding_model}', {vector_size}, content_hash, embedding, embedding_quantized, embedding_bits, updated_at
+FROM teas
+WHERE embedding IS NOT NULL OR embedding_quantized IS NOT NULL;
+
+ALTER TABLE teas DROP COLUMN embedding;
+
+ALTER TABLE teas DROP COLUMN embedding_quantized;
+
+ALTER TABLE teas DROP COLUMN embedding_bits;
+
+-- IVF index per model
+
+CREATE TABLE IF NOT EXISTS vector_centroids_by_model (
+    model TEXT NOT NULL,
+    cluster INTEGER NOT NULL,
+    centroid BLOB NOT NULL,
+    PRIMARY KEY (model, cluster)
+);
+
+INSERT INTO vector_centroids_by_model (model, cluster, centroid)
+SELECT '{embedding_model}', cluster, centroid FROM vector_centroids;
+
+DROP TABLE vector_centroids;
+
+ALTER TABLE vector_centroids_by_model RENAME TO vector_centroids;
+
+CREATE TABLE IF NOT EXISTS tea_vector_clusters_by_model (
+    tea_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    cluster INTEGER NOT NULL,
+    PRIMARY KEY (tea_id, model)
+);
+
+INSERT INTO tea_vector_clusters_by_model (tea_id, model, cluster)
+SELECT tea_id, '{embedding_model}', cluster FROM tea_vector_clusters;
+
+DROP TABLE tea_vector_clusters;
+
+ALTER TABLE tea_vector_clusters_by_model RENAME TO tea_vector_clusters;
+
+CREATE INDEX IF NOT EXISTS idx_tea_vector_clusters_model ON tea_vector_clusters(model, cluster);
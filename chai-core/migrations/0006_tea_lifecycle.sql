@@ -0,0 +1,16 @@
+-- Lifecycle of stored teas. Products that disappear from the shop are kept as
+-- 'discontinued' instead of being deleted, 'hidden' teas are taken out of
+-- search by hand. first_seen/last_seen are when the product was first and
+-- last found on the website, discontinued_at is when it disappeared.
+
+ALTER TABLE teas ADD COLUMN status TEXT NOT NULL DEFAULT 'active';
+
+ALTER TABLE teas ADD COLUMN first_seen INTEGER;
+
+ALTER TABLE teas ADD COLUMN last_seen INTEGER;
+
+ALTER TABLE teas ADD COLUMN discontinued_at INTEGER;
+
+UPDATE teas SET first_seen = created_at, last_seen = updated_at;
+
+CREATE INDEX IF NOT EXISTS idx_teas_status ON teas(status);
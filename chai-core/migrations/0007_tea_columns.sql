@@ -0,0 +1,23 @@
+-- Typed columns for frequently queried Tea fields, so filters and statistics
+-- don't parse tea_data. tea_data stays the full record; these are derived from
+-- it on every upsert. Existing rows are filled after this migration.
+
+ALTER TABLE teas ADD COLUMN name TEXT;
+
+-- Lowest price of the product and its variants
+ALTER TABLE teas ADD COLUMN min_price REAL;
+
+ALTER TABLE teas ADD COLUMN weight_grams REAL;
+
+CREATE INDEX IF NOT EXISTS idx_teas_name ON teas(name);
+CREATE INDEX IF NOT EXISTS idx_teas_min_price ON teas(min_price);
+
+-- Composition (one row per ingredient, in lower case) for ingredient filters
+CREATE TABLE IF NOT EXISTS tea_ingredients (
+    tea_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    ingredient TEXT NOT NULL,
+    PRIMARY KEY (tea_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tea_ingredients_ingredient ON tea_ingredients(ingredient);
@@ -6,6 +6,7 @@
 //! - Password hashing with Argon2
 //! - JWT token validation
 
+use crate::repository::UserRepository;
 use crate::turso;
 use anyhow::{Context, Result};
 use argon2::{
@@ -58,16 +59,39 @@ pub struct Claims {
 pub struct AuthConfig {
     /// JWT secret key
     pub jwt_secret: String,
+    /// Emails of users allowed to use admin endpoints (lowercase)
+    pub admin_emails: Vec<String>,
 }
 
 impl AuthConfig {
     /// Load config from environment variables
+    ///
+    /// `ADMIN_EMAILS` is a comma-separated list, no admins when unset.
     pub fn from_env() -> Result<Self> {
         let jwt_secret =
             std::env::var("JWT_SECRET").context("JWT_SECRET environment variable not set")?;
+        let admin_emails = parse_emails(&std::env::var("ADMIN_EMAILS").unwrap_or_default());
 
-        Ok(Self { jwt_secret })
+        Ok(Self {
+            jwt_secret,
+            admin_emails,
+        })
     }
+
+    /// Check if a user may use admin endpoints
+    #[must_use]
+    pub fn is_admin(&self, email: &str) -> bool {
+        let email = email.trim().to_lowercase();
+        self.admin_emails.contains(&email)
+    }
+}
+
+/// Parse a comma-separated email list
+fn parse_emails(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty())
+        .collect()
 }
 
 /// Hash a password using Argon2
@@ -126,7 +150,7 @@ pub fn validate_token(token: &str, secret: &str) -> Result<Claims> {
 }
 
 /// Register a new user
-pub async fn register(email: &str, password: &str) -> Result<User> {
+pub async fn register(users: &dyn UserRepository, email: &str, password: &str) -> Result<User> {
     // Validate email format (basic check)
     if !email.contains('@') || email.len() < 5 {
         anyhow::bail!("Invalid email format");
@@ -138,7 +162,7 @@ pub async fn register(email: &str, password: &str) -> Result<User> {
     }
 
     // Check if email already exists
-    if turso::get_user_by_email(email).await?.is_some() {
+    if users.get_user_by_email(email).await?.is_some() {
         anyhow::bail!("Email already registered");
     }
 
@@ -146,15 +170,21 @@ pub async fn register(email: &str, password: &str) -> Result<User> {
     let password_hash = hash_password(password)?;
 
     // Create user
-    let user = turso::create_user(email, &password_hash).await?;
+    let user = users.create_user(email, &password_hash).await?;
 
     tracing::info!("New user registered: {}", email);
     Ok(user)
 }
 
 /// Login a user and return a JWT token
-pub async fn login(email: &str, password: &str, jwt_secret: &str) -> Result<(User, String)> {
-    let user = turso::get_user_by_email(email)
+pub async fn login(
+    users: &dyn UserRepository,
+    email: &str,
+    password: &str,
+    jwt_secret: &str,
+) -> Result<(User, String)> {
+    let user = users
+        .get_user_by_email(email)
         .await?
         .ok_or_else(|| anyhow::anyhow!("Invalid email or password"))?;
 
@@ -171,13 +201,13 @@ pub async fn login(email: &str, password: &str, jwt_secret: &str) -> Result<(Use
 }
 
 /// Get user by ID
-pub async fn get_user_by_id(user_id: i64) -> Result<Option<User>> {
-    turso::get_user_by_id(user_id).await
+pub async fn get_user_by_id(users: &dyn UserRepository, user_id: i64) -> Result<Option<User>> {
+    users.get_user_by_id(user_id).await
 }
 
 /// Get user by email
-pub async fn get_user_by_email(email: &str) -> Result<Option<User>> {
-    turso::get_user_by_email(email).await
+pub async fn get_user_by_email(users: &dyn UserRepository, email: &str) -> Result<Option<User>> {
+    users.get_user_by_email(email).await
 }
 
 #[cfg(test)]
@@ -221,4 +251,21 @@ mod tests {
         let token = generate_token(&user, "secret1").unwrap();
         assert!(validate_token(&token, "secret2").is_err());
     }
+
+    #[test]
+    fn test_admin_emails() {
+        let config = AuthConfig {
+            jwt_secret: "secret".to_string(),
+            admin_emails: parse_emails(" Admin@Example.com, ,ops@example.com"),
+        };
+
+        assert_eq!(
+            config.admin_emails,
+            ["admin@example.com", "ops@example.com"]
+        );
+        assert!(config.is_admin("admin@example.com"));
+        assert!(config.is_admin("ADMIN@example.com"));
+        assert!(!config.is_admin("test@example.com"));
+        assert!(parse_emails("").is_empty());
+    }
 }
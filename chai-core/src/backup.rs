@@ -0,0 +1,827 @@
+//! Online database backups (JSON Lines, optionally zstd-compressed)
+//!
+//! A backup is a logical dump read in one transaction, so it is consistent
+//! while the server keeps writing. The first line is a [`BackupHeader`], then
+//! every table follows as a [`BackupRecord::Table`] with its `CREATE` statement,
+//! one [`BackupRecord::Row`] per row and a [`BackupRecord::TableEnd`] with the
+//! row count and SHA-256 of the row lines. Indexes come after the data, and
+//! [`BackupRecord::End`] marks a complete backup.
+//!
+//! Restore builds a new database next to the target, checks the counts and
+//! checksums and `PRAGMA integrity_check`, applies pending migrations and only
+//! then replaces the target. SQL lives in [`crate::turso`].
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::http;
+use crate::migrations;
+use crate::turso::{DbConfig, TursoRepository};
+
+/// Format name in the backup header
+const BACKUP_FORMAT: &str = "chai-backup";
+
+/// Current backup version
+const BACKUP_VERSION: u32 = 1;
+
+/// zstd level of backups (fast, snapshots are taken often)
+const BACKUP_ZSTD_LEVEL: i32 = 3;
+
+/// First bytes of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// File name prefix of snapshots
+const SNAPSHOT_PREFIX: &str = "chai-";
+
+/// First line of a backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupHeader {
+    pub format: String,
+    pub version: u32,
+    /// UNIX timestamp of the backup
+    pub created_at: i64,
+    /// Schema version of the backed up database
+    pub schema_version: u32,
+}
+
+/// Column value, as stored by SQLite
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BackupValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    /// Hex-encoded as `{"blob": "..."}` to tell it from text
+    Blob {
+        #[serde(with = "hex_bytes")]
+        blob: Vec<u8>,
+    },
+}
+
+/// Line of a backup after the header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupRecord {
+    /// Start of a table
+    Table {
+        name: String,
+        sql: String,
+        columns: Vec<String>,
+    },
+    /// Row of the current table, values in column order
+    Row(Vec<BackupValue>),
+    /// End of the current table
+    TableEnd {
+        name: String,
+        rows: u64,
+        sha256: String,
+    },
+    /// Index, created after the data
+    Index { name: String, sql: String },
+    /// End of a complete backup
+    End { tables: usize },
+}
+
+/// Backed up table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSummary {
+    pub name: String,
+    pub rows: u64,
+    /// SHA-256 of the row lines
+    pub sha256: String,
+}
+
+/// Summary of a written or restored backup
+#[derive(Debug, Clone)]
+pub struct BackupSummary {
+    pub created_at: i64,
+    pub schema_version: u32,
+    pub tables: Vec<TableSummary>,
+}
+
+impl BackupSummary {
+    /// Rows in all tables
+    #[must_use]
+    pub fn rows(&self) -> u64 {
+        self.tables.iter().map(|t| t.rows).sum()
+    }
+}
+
+/// Table being written or read
+struct TableState {
+    name: String,
+    rows: u64,
+    hasher: Sha256,
+}
+
+impl TableState {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            rows: 0,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn add_row(&mut self, line: &[u8]) {
+        self.rows += 1;
+        self.hasher.update(line);
+        self.hasher.update(b"\n");
+    }
+
+    fn finish(self) -> TableSummary {
+        TableSummary {
+            name: self.name,
+            rows: self.rows,
+            sha256: format!("{:x}", self.hasher.finalize()),
+        }
+    }
+}
+
+/// Streaming backup writer
+pub struct BackupWriter<W: Write> {
+    out: W,
+    table: Option<TableState>,
+    tables: Vec<TableSummary>,
+}
+
+impl<W: Write> BackupWriter<W> {
+    /// Start a backup, writing its header
+    pub fn new(mut out: W, created_at: i64, schema_version: u32) -> Result<Self> {
+        let header = BackupHeader {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            created_at,
+            schema_version,
+        };
+        serde_json::to_writer(&mut out, &header).context("Failed to write backup header")?;
+        out.write_all(b"\n")?;
+        Ok(Self {
+            out,
+            table: None,
+            tables: Vec::new(),
+        })
+    }
+
+    fn write_record(&mut self, record: &BackupRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.out, record).context("Failed to write backup")?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Start a table, finishing the previous one
+    pub fn begin_table(&mut self, name: &str, sql: &str, columns: &[String]) -> Result<()> {
+        self.end_table()?;
+        self.write_record(&BackupRecord::Table {
+            name: name.to_string(),
+            sql: sql.to_string(),
+            columns: columns.to_vec(),
+        })?;
+        self.table = Some(TableState::new(name));
+        Ok(())
+    }
+
+    pub fn write_row(&mut self, values: Vec<BackupValue>) -> Result<()> {
+        let table = self
+            .table
+            .as_mut()
+            .context("Backup row written outside of a table")?;
+        let line = serde_json::to_vec(&BackupRecord::Row(values))
+            .with_context(|| format!("Failed to serialize row of {}", table.name))?;
+        table.add_row(&line);
+        self.out.write_all(&line)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn end_table(&mut self) -> Result<()> {
+        if let Some(table) = self.table.take() {
+            let summary = table.finish();
+            self.write_record(&BackupRecord::TableEnd {
+                name: summary.name.clone(),
+                rows: summary.rows,
+                sha256: summary.sha256.clone(),
+            })?;
+            self.tables.push(summary);
+        }
+        Ok(())
+    }
+
+    pub fn write_index(&mut self, name: &str, sql: &str) -> Result<()> {
+        self.end_table()?;
+        self.write_record(&BackupRecord::Index {
+            name: name.to_string(),
+            sql: sql.to_string(),
+        })
+    }
+
+    /// Mark the backup complete, flush and return the writer with the tables
+    pub fn finish(mut self) -> Result<(W, Vec<TableSummary>)> {
+        self.end_table()?;
+        let tables = self.tables.len();
+        self.write_record(&BackupRecord::End { tables })?;
+        self.out.flush().context("Failed to flush backup")?;
+        Ok((self.out, self.tables))
+    }
+}
+
+/// Streaming backup reader
+///
+/// Checks row counts and checksums of every table while reading, and fails
+/// on a backup that ends before [`BackupRecord::End`].
+pub struct BackupReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+    line: usize,
+    table: Option<TableState>,
+    tables: Vec<TableSummary>,
+    done: bool,
+    pub header: BackupHeader,
+}
+
+impl<R: BufRead> BackupReader<R> {
+    /// Open a backup, checking its header
+    pub fn new(input: R) -> Result<Self> {
+        let mut lines = input.lines();
+        let first = lines
+            .next()
+            .context("Backup is empty")?
+            .context("Failed to read backup header")?;
+        let header: BackupHeader =
+            serde_json::from_str(&first).context("Not a chai database backup")?;
+
+        if header.format != BACKUP_FORMAT {
+            anyhow::bail!("Not a chai database backup (format '{}')", header.format);
+        }
+        if header.version > BACKUP_VERSION {
+            anyhow::bail!(
+                "Backup version {} is newer than supported {}",
+                header.version,
+                BACKUP_VERSION
+            );
+        }
+
+        Ok(Self {
+            lines,
+            line: 1,
+            table: None,
+            tables: Vec::new(),
+            done: false,
+            header,
+        })
+    }
+
+    /// Tables read so far
+    #[must_use]
+    pub fn tables(&self) -> &[TableSummary] {
+        &self.tables
+    }
+
+    fn check(&mut self, line: &str, record: &BackupRecord) -> Result<()> {
+        match record {
+            BackupRecord::Table { name, .. } => {
+                if let Some(table) = &self.table {
+                    anyhow::bail!("Table {} has no end", table.name);
+                }
+                self.table = Some(TableState::new(name));
+            }
+            BackupRecord::Row(_) => self
+                .table
+                .as_mut()
+                .context("Row outside of a table")?
+                .add_row(line.as_bytes()),
+            BackupRecord::TableEnd { name, rows, sha256 } => {
+                let table = self
+                    .table
+                    .take()
+                    .filter(|table| table.name == *name)
+                    .with_context(|| format!("Unexpected end of table {}", name))?
+                    .finish();
+                if table.rows != *rows {
+                    anyhow::bail!(
+                        "Table {} has {} rows, the backup lists {}",
+                        name,
+                        table.rows,
+                        rows
+                    );
+                }
+                if table.sha256 != *sha256 {
+                    anyhow::bail!("Checksum mismatch in table {}", name);
+                }
+                self.tables.push(table);
+            }
+            BackupRecord::Index { .. } => {
+                if let Some(table) = &self.table {
+                    anyhow::bail!("Table {} has no end", table.name);
+                }
+            }
+            BackupRecord::End { tables } => {
+                if *tables != self.tables.len() {
+                    anyhow::bail!(
+                        "Backup lists {} tables, found {}",
+                        tables,
+                        self.tables.len()
+                    );
+                }
+                self.done = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for BackupReader<R> {
+    type Item = Result<BackupRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e).context("Failed to read backup")),
+                None => {
+                    self.done = true;
+                    return Some(Err(anyhow::anyhow!("Backup is truncated")));
+                }
+            };
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let line_number = self.line;
+            let result = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid backup record on line {}", line_number))
+                .and_then(|record| {
+                    self.check(&line, &record)
+                        .with_context(|| format!("Invalid backup on line {}", line_number))?;
+                    Ok(record)
+                });
+            if result.is_err() {
+                self.done = true;
+            }
+            return Some(result);
+        }
+    }
+}
+
+/// Hex encoding of blobs
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if !hex.len().is_multiple_of(2) {
+            return Err(D::Error::custom("odd length of hex blob"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// Open a backup file, compressed or not
+pub fn open(path: &Path) -> Result<BackupReader<Box<dyn BufRead + Send>>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut input = BufReader::new(file);
+    let compressed = input
+        .fill_buf()
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .starts_with(&ZSTD_MAGIC);
+
+    let input: Box<dyn BufRead + Send> = if compressed {
+        let decoder = zstd::Decoder::with_buffer(input).context("Failed to start zstd stream")?;
+        Box::new(BufReader::new(decoder))
+    } else {
+        Box::new(input)
+    };
+    BackupReader::new(input).with_context(|| format!("Failed to read backup {}", path.display()))
+}
+
+fn now() -> Result<i64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("System time error")?
+        .as_secs() as i64)
+}
+
+/// Back up the database into a file, zstd-compressed if `compress`
+///
+/// The backup is written next to `path` and renamed when complete,
+/// so a failed backup never leaves a partial file under its name.
+pub async fn backup(db: &TursoRepository, path: &Path, compress: bool) -> Result<BackupSummary> {
+    let partial = PathBuf::from(format!("{}.partial", path.display()));
+    let result = write_file(db, &partial, compress).await;
+    match result {
+        Ok(summary) => {
+            std::fs::rename(&partial, path)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            Ok(summary)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+async fn write_file(db: &TursoRepository, path: &Path, compress: bool) -> Result<BackupSummary> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let out = BufWriter::new(file);
+    let created_at = now()?;
+
+    let summary = if compress {
+        let encoder =
+            zstd::Encoder::new(out, BACKUP_ZSTD_LEVEL).context("Failed to start zstd stream")?;
+        let (encoder, summary) = db.write_backup(encoder, created_at).await?;
+        encoder
+            .finish()
+            .context("Failed to finish zstd stream")?
+            .flush()?;
+        summary
+    } else {
+        db.write_backup(out, created_at).await?.1
+    };
+
+    Ok(summary)
+}
+
+/// SQLite files of a database (the main file and its write-ahead log)
+fn database_files(path: &str) -> [PathBuf; 3] {
+    [
+        PathBuf::from(path),
+        PathBuf::from(format!("{}-wal", path)),
+        PathBuf::from(format!("{}-shm", path)),
+    ]
+}
+
+fn remove_database(path: &str) -> Result<()> {
+    for file in database_files(path) {
+        match std::fs::remove_file(&file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to remove {}", file.display()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Fail if another process (chai-web) has the database open
+///
+/// Replacing the file under a running server would go unnoticed: it keeps
+/// writing to the old one.
+async fn ensure_not_in_use(config: &DbConfig) -> Result<()> {
+    if !Path::new(&config.path).exists() {
+        return Ok(());
+    }
+    match TursoRepository::open(config).await {
+        Err(e) if format!("{:#}", e).contains("locked by another process") => {
+            anyhow::bail!(
+                "Database {} is in use, stop chai-web before restoring",
+                config.path
+            )
+        }
+        // A damaged database can't be opened, but it is replaced anyway
+        _ => Ok(()),
+    }
+}
+
+/// Restore a backup into the database at `config.path`, replacing it
+///
+/// The database must not be open. The target is only replaced after the
+/// restored copy passed all checks and was migrated to this binary's schema.
+pub async fn restore(path: &Path, config: &DbConfig) -> Result<BackupSummary> {
+    let reader = open(path)?;
+    if reader.header.schema_version > migrations::latest_version() {
+        anyhow::bail!(
+            "Backup schema version {} is newer than {} supported by this binary, upgrade chai",
+            reader.header.schema_version,
+            migrations::latest_version()
+        );
+    }
+
+    ensure_not_in_use(config).await?;
+
+    let restore_config = DbConfig {
+        path: format!("{}.restore", config.path),
+        ..config.clone()
+    };
+    remove_database(&restore_config.path)?;
+
+    let result = async {
+        let db = TursoRepository::open(&restore_config).await?;
+        let summary = db.restore_backup(reader).await?;
+        db.migrate(&restore_config).await?;
+        db.checkpoint().await?;
+        Ok::<_, anyhow::Error>(summary)
+    }
+    .await;
+
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            remove_database(&restore_config.path)?;
+            return Err(e);
+        }
+    };
+
+    // The old log must not be replayed onto the restored file
+    remove_database(&config.path)?;
+    let [restored, ..] = database_files(&restore_config.path);
+    std::fs::rename(&restored, &config.path)
+        .with_context(|| format!("Failed to move restored database to {}", config.path))?;
+    remove_database(&restore_config.path)?;
+
+    Ok(summary)
+}
+
+/// Snapshot written by [`snapshot`]
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub summary: BackupSummary,
+    /// Older snapshots removed to keep the requested number
+    pub pruned: Vec<PathBuf>,
+}
+
+/// Write a timestamped backup into `dir` and keep only the newest `keep` snapshots
+pub async fn snapshot(
+    db: &TursoRepository,
+    dir: &Path,
+    keep: usize,
+    compress: bool,
+) -> Result<Snapshot> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let path = dir.join(file_name(now()?, compress));
+    let summary = backup(db, &path, compress).await?;
+
+    let mut snapshots = list_snapshots(dir)?;
+    let excess = snapshots.len().saturating_sub(keep.max(1));
+    let pruned: Vec<PathBuf> = snapshots.drain(..excess).collect();
+    for old in &pruned {
+        std::fs::remove_file(old).with_context(|| format!("Failed to remove {}", old.display()))?;
+    }
+
+    Ok(Snapshot {
+        path,
+        summary,
+        pruned,
+    })
+}
+
+/// Name of a backup taken at `created_at`, e.g. `chai-20261017-163456.jsonl.zst`
+#[must_use]
+pub fn file_name(created_at: i64, compress: bool) -> String {
+    let extension = if compress { "jsonl.zst" } else { "jsonl" };
+    format!("{}{}.{}", SNAPSHOT_PREFIX, utc_stamp(created_at), extension)
+}
+
+/// Snapshots in a directory, oldest first
+pub fn list_snapshots(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_snapshot_name)
+        })
+        .collect();
+    // Timestamps in names have a fixed width
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+fn is_snapshot_name(name: &str) -> bool {
+    name.strip_prefix(SNAPSHOT_PREFIX)
+        .and_then(|rest| {
+            rest.strip_suffix(".jsonl.zst")
+                .or_else(|| rest.strip_suffix(".jsonl"))
+        })
+        .is_some_and(|stamp| {
+            stamp.len() == 15
+                && stamp
+                    .char_indices()
+                    .all(|(i, c)| if i == 8 { c == '-' } else { c.is_ascii_digit() })
+        })
+}
+
+/// `YYYYMMDD-HHMMSS` in UTC
+fn utc_stamp(timestamp: i64) -> String {
+    let (year, month, day) = http::civil_from_days(timestamp.div_euclid(86400));
+    let secs = timestamp.rem_euclid(86400);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_backup() -> Vec<u8> {
+        let mut writer = BackupWriter::new(Vec::new(), 1_800_000_000, 5).unwrap();
+        writer
+            .begin_table(
+                "users",
+                "CREATE TABLE users (id INTEGER, email TEXT, score REAL, avatar BLOB)",
+                &["id".into(), "email".into(), "score".into(), "avatar".into()],
+            )
+            .unwrap();
+        writer
+            .write_row(vec![
+                BackupValue::Integer(1),
+                BackupValue::Text("чай@example.com".into()),
+                BackupValue::Real(2.0),
+                BackupValue::Blob {
+                    blob: vec![0, 0xab, 0xff],
+                },
+            ])
+            .unwrap();
+        writer
+            .write_row(vec![
+                BackupValue::Integer(2),
+                BackupValue::Text("{\"blob\": 1}".into()),
+                BackupValue::Null,
+                BackupValue::Null,
+            ])
+            .unwrap();
+        writer
+            .begin_table("empty", "CREATE TABLE empty (x)", &["x".into()])
+            .unwrap();
+        writer
+            .write_index(
+                "idx_users_email",
+                "CREATE INDEX idx_users_email ON users (email)",
+            )
+            .unwrap();
+        let (out, tables) = writer.finish().unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!((tables[0].rows, tables[1].rows), (2, 0));
+        out
+    }
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let backup = sample_backup();
+
+        let mut reader = BackupReader::new(backup.as_slice()).unwrap();
+        assert_eq!(reader.header.schema_version, 5);
+        let records: Vec<BackupRecord> = reader.by_ref().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 8);
+        assert_eq!(
+            records[1],
+            BackupRecord::Row(vec![
+                BackupValue::Integer(1),
+                BackupValue::Text("чай@example.com".into()),
+                BackupValue::Real(2.0),
+                BackupValue::Blob {
+                    blob: vec![0, 0xab, 0xff]
+                },
+            ])
+        );
+        assert_eq!(
+            records[2],
+            BackupRecord::Row(vec![
+                BackupValue::Integer(2),
+                BackupValue::Text("{\"blob\": 1}".into()),
+                BackupValue::Null,
+                BackupValue::Null,
+            ])
+        );
+        assert_eq!(reader.tables().len(), 2);
+
+        assert!(BackupReader::new(b"{\"url\": \"x\"}\n".as_slice()).is_err());
+        let newer = br#"{"format":"chai-backup","version":99,"created_at":0,"schema_version":1}"#;
+        assert!(BackupReader::new(newer.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_backup_integrity() {
+        let backup = String::from_utf8(sample_backup()).unwrap();
+        let read_all = |backup: &str| -> Result<usize> {
+            let reader = BackupReader::new(backup.as_bytes())?;
+            reader
+                .collect::<Result<Vec<_>>>()
+                .map(|records| records.len())
+        };
+        assert_eq!(read_all(&backup).unwrap(), 8);
+
+        // Changed value
+        let tampered = backup.replace("чай@example.com", "tea@example.com");
+        let err = read_all(&tampered).unwrap_err();
+        assert!(format!("{:#}", err).contains("Checksum mismatch"));
+
+        // Dropped row
+        let lines: Vec<&str> = backup.lines().collect();
+        let dropped = [&lines[..2], &lines[3..]].concat().join("\n");
+        let err = read_all(&dropped).unwrap_err();
+        assert!(format!("{:#}", err).contains("has 1 rows"));
+
+        // Cut before the end marker
+        let truncated = lines[..lines.len() - 1].join("\n");
+        let err = read_all(&truncated).unwrap_err();
+        assert!(err.to_string().contains("truncated"));
+    }
+
+    #[test]
+    fn test_database_roundtrip() {
+        use crate::models::Tea;
+        use crate::repository::{CacheRepository, TeaRepository, UserRepository};
+        use crate::turso::CacheMeta;
+
+        let dir = std::env::temp_dir().join(format!("chai-backup-test-{}", std::process::id()));
+        let config = |name: &str| DbConfig {
+            path: dir.join(name).to_string_lossy().into_owned(),
+            ..DbConfig::from_env()
+        };
+        let (source, target) = (config("source.db"), config("target.db"));
+        let path = dir.join("backup.jsonl.zst");
+
+        futures::executor::block_on(async {
+            let db = TursoRepository::init(&source).await.unwrap();
+            db.create_user("a@example.com", "hash").await.unwrap();
+            let tea = Tea {
+                name: Some("Иван-чай с мятой".to_string()),
+                price: Some("450".to_string()),
+                composition: vec!["Иван-чай".to_string(), "Мята".to_string()],
+                ..Tea::new("https://a/1")
+            };
+            db.upsert_tea(&tea, "hash").await.unwrap();
+            db.update_tea_embedding(&tea.url, "model", "hash", &[0.6, 0.8])
+                .await
+                .unwrap();
+            db.cache_set_at(&tea.url, "<html>чай</html>", 1, &CacheMeta::default())
+                .await
+                .unwrap();
+
+            let written = backup(&db, &path, true).await.unwrap();
+            assert!(written.rows() > 0);
+            let restored = restore(&path, &target).await.unwrap();
+            assert_eq!(restored.tables, written.tables);
+
+            // A backup of the restored database has the same rows
+            let db = TursoRepository::init(&target).await.unwrap();
+            let again = backup(&db, &dir.join("again.jsonl"), false).await.unwrap();
+            assert_eq!(again.schema_version, written.schema_version);
+            assert_eq!(again.tables, written.tables);
+
+            assert_eq!(db.count_teas().await.unwrap(), 1);
+            assert!(
+                db.get_user_by_email("a@example.com")
+                    .await
+                    .unwrap()
+                    .is_some()
+            );
+            assert_eq!(
+                db.cache_get(&tea.url)
+                    .await
+                    .unwrap()
+                    .map(|entry| entry.html),
+                Some("<html>чай</html>".to_string())
+            );
+        });
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_snapshot_names() {
+        assert_eq!(utc_stamp(0), "19700101-000000");
+        assert_eq!(utc_stamp(951_782_400), "20000229-000000");
+        assert_eq!(utc_stamp(1_792_254_896), "20261017-163456");
+
+        assert_eq!(
+            file_name(1_792_254_896, false),
+            "chai-20261017-163456.jsonl"
+        );
+        assert!(is_snapshot_name(&file_name(1_792_254_896, true)));
+        assert!(is_snapshot_name("chai-20261017-161456.jsonl"));
+        assert!(!is_snapshot_name("chai-20261017-161456.jsonl.zst.partial"));
+        assert!(!is_snapshot_name("chai-latest.jsonl"));
+        assert!(!is_snapshot_name("chai.db"));
+    }
+}
@@ -0,0 +1,279 @@
+//! Portable cache archives (zstd-compressed JSON Lines)
+//!
+//! The first line is an [`ArchiveHeader`], every following line is one
+//! [`ArchiveRecord`] with the page, its fetch time and response headers.
+//! Archives are written and read as streams, so the cache never has to fit in memory.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::{CacheEntry, CacheMeta};
+use crate::repository::CacheRepository;
+use crate::scraper::ShopScraper;
+
+/// Format name in the archive header
+const ARCHIVE_FORMAT: &str = "chai-cache";
+
+/// Current archive version
+const ARCHIVE_VERSION: u32 = 1;
+
+/// zstd level of archives (written once, read many times)
+const ARCHIVE_ZSTD_LEVEL: i32 = 12;
+
+/// First line of an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveHeader {
+    pub format: String,
+    pub version: u32,
+    /// UNIX timestamp of the export
+    pub exported_at: i64,
+}
+
+/// Cached page in an archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveRecord {
+    pub url: String,
+    pub fetched_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub html: String,
+}
+
+impl From<CacheEntry> for ArchiveRecord {
+    fn from(entry: CacheEntry) -> Self {
+        Self {
+            url: entry.url,
+            fetched_at: entry.fetched_at,
+            etag: entry.etag,
+            last_modified: entry.last_modified,
+            status: entry.status,
+            html: entry.html,
+        }
+    }
+}
+
+/// Streaming archive writer
+pub struct ArchiveWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Start an archive, writing its header
+    pub fn new(mut out: W, exported_at: i64) -> Result<Self> {
+        let header = ArchiveHeader {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at,
+        };
+        serde_json::to_writer(&mut out, &header).context("Failed to write archive header")?;
+        out.write_all(b"\n")?;
+        Ok(Self { out })
+    }
+
+    pub fn write(&mut self, record: &ArchiveRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.out, record)
+            .with_context(|| format!("Failed to write {} to archive", record.url))?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Flush and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.out.flush().context("Failed to flush archive")?;
+        Ok(self.out)
+    }
+}
+
+/// Streaming archive reader
+pub struct ArchiveReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+    line: usize,
+    pub header: ArchiveHeader,
+}
+
+impl<R: BufRead> ArchiveReader<R> {
+    /// Open an archive, checking its header
+    pub fn new(input: R) -> Result<Self> {
+        let mut lines = input.lines();
+        let first = lines
+            .next()
+            .context("Archive is empty")?
+            .context("Failed to read archive header")?;
+        let header: ArchiveHeader =
+            serde_json::from_str(&first).context("Not a chai cache archive")?;
+
+        if header.format != ARCHIVE_FORMAT {
+            anyhow::bail!("Not a chai cache archive (format '{}')", header.format);
+        }
+        if header.version > ARCHIVE_VERSION {
+            anyhow::bail!(
+                "Archive version {} is newer than supported {}",
+                header.version,
+                ARCHIVE_VERSION
+            );
+        }
+
+        Ok(Self {
+            lines,
+            line: 1,
+            header,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for ArchiveReader<R> {
+    type Item = Result<ArchiveRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e).context("Failed to read archive")),
+            };
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(
+                serde_json::from_str(&line)
+                    .with_context(|| format!("Invalid archive record on line {}", self.line)),
+            );
+        }
+    }
+}
+
+/// Summary of an archive import
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    /// Entries stored from the archive
+    pub imported: usize,
+    /// Entries skipped because the local copy is the same age or newer
+    pub skipped: usize,
+}
+
+/// Export cached pages of the shops into a `.jsonl.zst` archive
+///
+/// Returns the number of exported entries.
+pub async fn export(
+    store: &dyn CacheRepository,
+    path: &Path,
+    shops: &[&dyn ShopScraper],
+) -> Result<usize> {
+    let mut urls: Vec<String> = super::list_urls(store)
+        .await?
+        .into_iter()
+        .filter(|url| shops.iter().any(|shop| shop.handles_url(url)))
+        .collect();
+    urls.sort();
+
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let encoder = zstd::Encoder::new(BufWriter::new(file), ARCHIVE_ZSTD_LEVEL)
+        .context("Failed to start zstd stream")?;
+    let exported_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("System time error")?
+        .as_secs() as i64;
+    let mut writer = ArchiveWriter::new(encoder, exported_at)?;
+
+    let mut count = 0;
+    for url in urls {
+        if let Some(entry) = super::get(store, &url).await? {
+            writer.write(&entry.into())?;
+            count += 1;
+        }
+    }
+
+    writer
+        .finish()?
+        .finish()
+        .context("Failed to finish zstd stream")?
+        .flush()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(count)
+}
+
+/// Import a `.jsonl.zst` archive, keeping fetch times and headers
+///
+/// Local entries fetched at the same time or later than the archived ones are kept.
+pub async fn import(store: &dyn CacheRepository, path: &Path) -> Result<ImportSummary> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let decoder = zstd::Decoder::new(file).context("Failed to start zstd stream")?;
+    let reader = ArchiveReader::new(BufReader::new(decoder))?;
+
+    let mut summary = ImportSummary::default();
+    for record in reader {
+        let record = record?;
+
+        if let Some(local) = super::get(store, &record.url).await?
+            && local.fetched_at >= record.fetched_at
+        {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let meta = CacheMeta {
+            etag: record.etag,
+            last_modified: record.last_modified,
+            status: record.status,
+        };
+        store
+            .cache_set_at(&record.url, &record.html, record.fetched_at, &meta)
+            .await?;
+        summary.imported += 1;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let records = vec![
+            ArchiveRecord {
+                url: "https://beliyles.com/tproduct/1-ivan-chai".to_string(),
+                fetched_at: 1_700_000_000,
+                etag: Some("\"abc\"".to_string()),
+                last_modified: None,
+                status: Some(200),
+                html: "<html>Иван-чай\n</html>".to_string(),
+            },
+            ArchiveRecord {
+                url: "https://beliyles.com/robots.txt".to_string(),
+                fetched_at: 1_600_000_000,
+                etag: None,
+                last_modified: None,
+                status: None,
+                html: "User-agent: *".to_string(),
+            },
+        ];
+
+        let encoder = zstd::Encoder::new(Vec::new(), 3).unwrap();
+        let mut writer = ArchiveWriter::new(encoder, 1_800_000_000).unwrap();
+        for record in &records {
+            writer.write(record).unwrap();
+        }
+        let archive = writer.finish().unwrap().finish().unwrap();
+
+        let decoder = zstd::Decoder::new(archive.as_slice()).unwrap();
+        let reader = ArchiveReader::new(BufReader::new(decoder)).unwrap();
+        assert_eq!(reader.header.exported_at, 1_800_000_000);
+        let read: Vec<ArchiveRecord> = reader.map(Result::unwrap).collect();
+        assert_eq!(read, records);
+
+        assert!(ArchiveReader::new(b"{\"url\": \"x\"}\n".as_slice()).is_err());
+        let newer = br#"{"format":"chai-cache","version":99,"exported_at":0}"#;
+        assert!(ArchiveReader::new(newer.as_slice()).is_err());
+    }
+}
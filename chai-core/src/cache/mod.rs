@@ -0,0 +1,190 @@
+//! HTML cache module for storing scraped pages
+//!
+//! This module provides:
+//! - Store HTML content for URLs (zstd-compressed)
+//! - Retrieve cached HTML
+//! - Keep the last snapshots of every URL to see when a page changed
+//! - Check cache freshness
+//! - Response validators (`ETag`, `Last-Modified`) for conditional refresh
+//! - Migrate from JSON cache file
+//! - Export/import of portable archives (`.jsonl.zst`)
+//! - Retention: refreshing stale pages, pruning, size cap and re-parse checks
+//!
+//! Functions take the [`CacheRepository`] that stores the pages.
+
+mod archive;
+mod retention;
+
+pub use archive::{
+    ArchiveHeader, ArchiveReader, ArchiveRecord, ArchiveWriter, ImportSummary, export, import,
+};
+pub use retention::{
+    Eviction, EvictionSummary, RefreshSummary, RetentionConfig, VerifyReport, enforce_size_cap,
+    parse_age, parse_size, plan_eviction, prune_removed, refresh, refresh_stale,
+    removed_from_sitemap, stale_urls, verify,
+};
+
+use crate::repository::CacheRepository;
+use crate::turso;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::info;
+
+/// Cached HTML entry (re-export from turso)
+pub use turso::CacheEntry;
+
+/// Response metadata stored with an entry (re-export from turso)
+pub use turso::CacheMeta;
+
+/// Snapshot of a cached page (re-export from turso)
+pub use turso::CacheSnapshot;
+
+/// Stored snapshot or legacy entry with its size (re-export from turso)
+pub use turso::CacheItem;
+
+/// Cache statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub entry_count: usize,
+    pub total_size_bytes: usize,
+    pub uncompressed_size_bytes: usize,
+    pub snapshot_count: usize,
+    pub legacy_count: usize,
+    pub oldest_entry: Option<i64>,
+    pub newest_entry: Option<i64>,
+}
+
+impl From<turso::CacheStats> for CacheStats {
+    fn from(stats: turso::CacheStats) -> Self {
+        Self {
+            entry_count: stats.entry_count,
+            total_size_bytes: stats.total_size_bytes,
+            uncompressed_size_bytes: stats.uncompressed_size_bytes,
+            snapshot_count: stats.snapshot_count,
+            legacy_count: stats.legacy_count,
+            oldest_entry: stats.oldest_entry,
+            newest_entry: stats.newest_entry,
+        }
+    }
+}
+
+/// Get cached HTML for a URL
+pub async fn get(store: &dyn CacheRepository, url: &str) -> Result<Option<CacheEntry>> {
+    store.cache_get(url).await
+}
+
+/// Store HTML in cache
+pub async fn set(store: &dyn CacheRepository, url: &str, html: &str) -> Result<()> {
+    store.cache_set(url, html).await
+}
+
+/// Store HTML in cache with response metadata
+pub async fn set_with_meta(
+    store: &dyn CacheRepository,
+    url: &str,
+    html: &str,
+    meta: &CacheMeta,
+) -> Result<()> {
+    store.cache_set_with_meta(url, html, meta).await
+}
+
+/// Bump `fetched_at` of an unchanged entry (`304 Not Modified`)
+///
+/// Returns false if the URL is not cached.
+pub async fn touch(store: &dyn CacheRepository, url: &str, meta: &CacheMeta) -> Result<bool> {
+    store.cache_touch(url, meta).await
+}
+
+/// Store multiple entries in cache (batch operation)
+pub async fn set_many(store: &dyn CacheRepository, entries: &[(String, String)]) -> Result<usize> {
+    let mut count = 0;
+    for (url, html) in entries {
+        store.cache_set(url, html).await?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Get snapshots of a URL, newest first (the first one is the current content)
+pub async fn snapshots(store: &dyn CacheRepository, url: &str) -> Result<Vec<CacheSnapshot>> {
+    store.cache_snapshots(url).await
+}
+
+/// Get a snapshot with its HTML by position (0 = current, 1 = previous, ...)
+pub async fn snapshot(
+    store: &dyn CacheRepository,
+    url: &str,
+    index: usize,
+) -> Result<Option<(CacheSnapshot, String)>> {
+    let Some(snapshot) = store.cache_snapshots(url).await?.into_iter().nth(index) else {
+        return Ok(None);
+    };
+
+    let html = store
+        .cache_snapshot_html(url, &snapshot.content_hash)
+        .await?
+        .with_context(|| format!("Snapshot {} of {} disappeared", index, url))?;
+    Ok(Some((snapshot, html)))
+}
+
+/// Delete a cached URL with all its snapshots
+///
+/// Returns false if the URL is not cached.
+pub async fn remove(store: &dyn CacheRepository, url: &str) -> Result<bool> {
+    store.cache_delete(url).await
+}
+
+/// Compress entries stored before compression support
+pub async fn compress_legacy(store: &dyn CacheRepository) -> Result<usize> {
+    store.cache_compress_legacy().await
+}
+
+/// Get all cached URLs
+pub async fn list_urls(store: &dyn CacheRepository) -> Result<Vec<String>> {
+    store.cache_list_urls().await
+}
+
+/// Get cache statistics
+pub async fn stats(store: &dyn CacheRepository) -> Result<CacheStats> {
+    store.cache_stats().await.map(CacheStats::from)
+}
+
+/// Migrate from JSON cache file
+pub async fn migrate_from_json(store: &dyn CacheRepository, json_path: &str) -> Result<usize> {
+    let content = std::fs::read_to_string(json_path).context("Failed to read JSON cache file")?;
+    let cache_map: HashMap<String, String> =
+        serde_json::from_str(&content).context("Failed to parse JSON cache file")?;
+
+    let count = cache_map.len();
+    for (url, html) in cache_map {
+        store.cache_set(&url, &html).await?;
+    }
+
+    info!("Migrated {} entries from JSON cache", count);
+    Ok(count)
+}
+
+/// Get all cached entries as a HashMap (for compatibility with existing code)
+pub async fn get_all(store: &dyn CacheRepository) -> Result<HashMap<String, String>> {
+    let urls = store.cache_list_urls().await?;
+    let mut map = HashMap::new();
+
+    for url in urls {
+        if let Some(entry) = store.cache_get(&url).await? {
+            map.insert(entry.url, entry.html);
+        }
+    }
+
+    Ok(map)
+}
+
+/// Check if URL is cached
+pub async fn contains(store: &dyn CacheRepository, url: &str) -> Result<bool> {
+    store.cache_contains(url).await
+}
+
+/// Clear all cache entries
+pub async fn clear(store: &dyn CacheRepository) -> Result<usize> {
+    store.cache_clear().await
+}
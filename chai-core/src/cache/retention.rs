@@ -0,0 +1,495 @@
+//! Cache retention: refreshing stale pages, pruning and verification
+//!
+//! Every policy is a plain async function, so both the CLI and
+//! a scheduled task in the web server can run them.
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::CacheItem;
+use crate::crawler::{CrawlSummary, Crawler, Validators};
+use crate::repository::CacheRepository;
+use crate::scraper::{self, ParseSummary, ShopScraper};
+use crate::turso;
+
+/// Default age after which cached pages are refreshed
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Retention configuration
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    /// Pages fetched longer ago are refreshed
+    pub max_age: Duration,
+    /// Stored cache size limit in bytes (no limit if None)
+    pub max_size_bytes: Option<usize>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age: DEFAULT_MAX_AGE,
+            max_size_bytes: None,
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// Load config from environment variables
+    ///
+    /// Environment variables:
+    /// - `CACHE_MAX_AGE`: Refresh pages older than this, e.g. "12h", "7d" (default: 7d)
+    /// - `CACHE_MAX_SIZE`: Stored cache size limit, e.g. "500M", "2G" (default: no limit)
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Ok(age) = std::env::var("CACHE_MAX_AGE") {
+            config.max_age = parse_age(&age).context("Invalid CACHE_MAX_AGE")?;
+        }
+        if let Ok(size) = std::env::var("CACHE_MAX_SIZE") {
+            config.max_size_bytes = Some(parse_size(&size).context("Invalid CACHE_MAX_SIZE")?);
+        }
+        Ok(config)
+    }
+}
+
+/// Parse an age like "90s", "30m", "12h", "7d" or "2w"
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().with_context(|| {
+        format!(
+            "Expected a number with a unit (s, m, h, d, w), got '{}'",
+            value
+        )
+    })?;
+
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        "w" => 7 * 24 * 3600,
+        _ => anyhow::bail!("Unknown age unit '{}' (expected s, m, h, d or w)", unit),
+    };
+    Ok(Duration::from_secs(number * unit_secs))
+}
+
+/// Parse a size like "800K", "500M" or "2G" (binary units, plain number = bytes)
+pub fn parse_size(value: &str) -> Result<usize> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: usize = number
+        .parse()
+        .with_context(|| format!("Expected a size like 500M, got '{}'", value))?;
+
+    let multiplier = match unit
+        .trim_end_matches(['B', 'b'])
+        .to_ascii_uppercase()
+        .as_str()
+    {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => anyhow::bail!("Unknown size unit '{}' (expected K, M or G)", unit),
+    };
+    Ok(number * multiplier)
+}
+
+fn now_secs() -> Result<i64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("System time error")?
+        .as_secs() as i64)
+}
+
+/// Cached URLs fetched longer than `max_age` ago, oldest first
+pub async fn stale_urls(store: &dyn CacheRepository, max_age: Duration) -> Result<Vec<String>> {
+    store
+        .cache_urls_fetched_before(now_secs()? - max_age.as_secs() as i64)
+        .await
+}
+
+/// Summary of a refresh run
+#[derive(Debug, Clone, Default)]
+pub struct RefreshSummary {
+    /// Pages re-downloaded with new content
+    pub changed: usize,
+    /// Pages confirmed unchanged (`304` or the same content)
+    pub unchanged: usize,
+    pub crawl: CrawlSummary,
+}
+
+impl RefreshSummary {
+    /// Log the crawl summary and change counts
+    pub fn log(&self) {
+        self.crawl.log();
+        info!(
+            "Refresh done: {} changed, {} unchanged, {} errors",
+            self.changed,
+            self.unchanged,
+            self.crawl.failed.len()
+        );
+    }
+}
+
+/// Revalidate cached pages with conditional requests
+pub async fn refresh(
+    store: &dyn CacheRepository,
+    crawler: &Crawler,
+    urls: Vec<String>,
+) -> Result<RefreshSummary> {
+    let mut requests = Vec::with_capacity(urls.len());
+    for url in urls {
+        let validators = super::get(store, &url)
+            .await?
+            .map(|entry| Validators::from(&entry))
+            .unwrap_or_default();
+        requests.push((url, validators));
+    }
+
+    let total = requests.len();
+    let mut summary = RefreshSummary::default();
+    let mut pages = crawler.crawl_conditional(requests);
+    let mut i = 0;
+
+    while let Some((url, result)) = pages.next().await {
+        i += 1;
+        summary.crawl.record(&result);
+
+        let page = match result {
+            Ok(page) => page,
+            Err(e) => {
+                warn!("[{}/{}] x {}", i, total, e);
+                continue;
+            }
+        };
+
+        if page.is_not_modified() {
+            super::touch(store, &url, &page.cache_meta()).await?;
+            summary.unchanged += 1;
+            continue;
+        }
+
+        let previous = store.cache_snapshots(&url).await?;
+        super::set_with_meta(store, &url, &page.body, &page.cache_meta()).await?;
+        if previous
+            .first()
+            .is_some_and(|s| s.content_hash == turso::html_hash(&page.body))
+        {
+            summary.unchanged += 1;
+        } else {
+            summary.changed += 1;
+            info!("[{}/{}] ~ {}", i, total, url);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Revalidate cached pages fetched longer than `max_age` ago
+pub async fn refresh_stale(
+    store: &dyn CacheRepository,
+    crawler: &Crawler,
+    max_age: Duration,
+) -> Result<RefreshSummary> {
+    let urls = stale_urls(store, max_age).await?;
+    info!(
+        "Refreshing {} cached pages older than {:?}",
+        urls.len(),
+        max_age
+    );
+    refresh(store, crawler, urls).await
+}
+
+/// Cached product pages of the shops that are no longer in their sitemaps
+///
+/// Fails if a shop's sitemap has no products, so a broken sitemap
+/// doesn't wipe the cache.
+pub async fn removed_from_sitemap(
+    store: &dyn CacheRepository,
+    crawler: &Crawler,
+    shops: &[&dyn ShopScraper],
+) -> Result<Vec<String>> {
+    let mut removed = Vec::new();
+    let cached = super::list_urls(store).await?;
+
+    for shop in shops {
+        let listed: HashSet<String> = scraper::get_tea_urls(crawler, *shop)
+            .await?
+            .into_iter()
+            .collect();
+        if listed.is_empty() {
+            anyhow::bail!(
+                "{} sitemap has no products, refusing to prune",
+                shop.display_name()
+            );
+        }
+
+        removed.extend(
+            cached
+                .iter()
+                .filter(|url| shop.handles_url(url) && shop.is_product_url(url))
+                .filter(|url| !listed.contains(*url))
+                .cloned(),
+        );
+    }
+
+    removed.sort();
+    Ok(removed)
+}
+
+/// Delete pages that left the sitemaps of the shops
+///
+/// Returns the deleted URLs.
+pub async fn prune_removed(
+    store: &dyn CacheRepository,
+    crawler: &Crawler,
+    shops: &[&dyn ShopScraper],
+) -> Result<Vec<String>> {
+    let removed = removed_from_sitemap(store, crawler, shops).await?;
+    for url in &removed {
+        store.cache_delete(url).await?;
+    }
+    Ok(removed)
+}
+
+/// Item removed to fit the size limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Eviction {
+    /// Older snapshot of a URL
+    Snapshot { url: String, content_hash: String },
+    /// Whole cached URL
+    Entry { url: String },
+}
+
+/// Summary of a size cap run
+#[derive(Debug, Clone, Default)]
+pub struct EvictionSummary {
+    pub snapshots: usize,
+    pub entries: usize,
+    pub freed_bytes: usize,
+    /// Stored size after eviction
+    pub size_bytes: usize,
+}
+
+/// Choose items to delete so the cache fits into `max_bytes`
+///
+/// Older snapshots go first (oldest first), then whole entries that
+/// were fetched longest ago.
+#[must_use]
+pub fn plan_eviction(items: &[CacheItem], max_bytes: usize) -> (Vec<Eviction>, usize) {
+    let mut size: usize = items.iter().map(|item| item.size_bytes).sum();
+    let mut evictions = Vec::new();
+    if size <= max_bytes {
+        return (evictions, size);
+    }
+
+    let mut history: Vec<&CacheItem> = items.iter().filter(|item| !item.current).collect();
+    history.sort_by_key(|item| item.fetched_at);
+    for item in history {
+        if size <= max_bytes {
+            return (evictions, size);
+        }
+        if let Some(hash) = &item.content_hash {
+            size -= item.size_bytes;
+            evictions.push(Eviction::Snapshot {
+                url: item.url.clone(),
+                content_hash: hash.clone(),
+            });
+        }
+    }
+
+    let mut current: Vec<&CacheItem> = items.iter().filter(|item| item.current).collect();
+    current.sort_by(|a, b| a.fetched_at.cmp(&b.fetched_at).then(a.url.cmp(&b.url)));
+    for item in current {
+        if size <= max_bytes {
+            break;
+        }
+        size -= item.size_bytes;
+        evictions.push(Eviction::Entry {
+            url: item.url.clone(),
+        });
+    }
+
+    (evictions, size)
+}
+
+/// Delete older snapshots, then the least recently fetched pages,
+/// until the stored cache size fits into `max_bytes`
+pub async fn enforce_size_cap(
+    store: &dyn CacheRepository,
+    max_bytes: usize,
+) -> Result<EvictionSummary> {
+    let items = store.cache_items().await?;
+    let total: usize = items.iter().map(|item| item.size_bytes).sum();
+    let (evictions, size) = plan_eviction(&items, max_bytes);
+
+    let mut summary = EvictionSummary {
+        freed_bytes: total - size,
+        size_bytes: size,
+        ..EvictionSummary::default()
+    };
+    for eviction in evictions {
+        match eviction {
+            Eviction::Snapshot { url, content_hash } => {
+                store.cache_delete_snapshot(&url, &content_hash).await?;
+                summary.snapshots += 1;
+            }
+            Eviction::Entry { url } => {
+                store.cache_delete(&url).await?;
+                summary.entries += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Result of re-parsing cached pages
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Pages checked
+    pub checked: usize,
+    /// Pages that no longer parse: (URL, reason)
+    pub failed: Vec<(String, String)>,
+    /// Field diagnostics of the pages that parsed
+    pub diagnostics: ParseSummary,
+}
+
+impl VerifyReport {
+    /// Log the failures and field diagnostics
+    pub fn log(&self, worst_limit: usize) {
+        self.diagnostics.log(worst_limit);
+        info!(
+            "Verified {} cached pages: {} parse, {} don't",
+            self.checked,
+            self.checked - self.failed.len(),
+            self.failed.len()
+        );
+        for (url, reason) in &self.failed {
+            warn!("  x {}: {}", url, reason);
+        }
+    }
+}
+
+/// Re-parse cached product pages of the shops with the current parser
+pub async fn verify(
+    store: &dyn CacheRepository,
+    shops: &[&dyn ShopScraper],
+) -> Result<VerifyReport> {
+    let mut urls: Vec<String> = super::list_urls(store)
+        .await?
+        .into_iter()
+        .filter(|url| {
+            shops
+                .iter()
+                .any(|s| s.handles_url(url) && s.is_product_url(url))
+        })
+        .collect();
+    urls.sort();
+
+    let mut report = VerifyReport::default();
+    for url in urls {
+        report.checked += 1;
+
+        let html = match super::get(store, &url).await {
+            Ok(Some(entry)) => entry.html,
+            Ok(None) => continue,
+            Err(e) => {
+                report.failed.push((url, format!("{:#}", e)));
+                continue;
+            }
+        };
+
+        match scraper::parse_tea_with_report(&url, &html) {
+            Ok((_, parse_report)) => report.diagnostics.record(parse_report),
+            Err(e) => {
+                report.diagnostics.record_error(&e);
+                report.failed.push((url, e.to_string()));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(url: &str, hash: &str, size: usize, fetched_at: i64, current: bool) -> CacheItem {
+        CacheItem {
+            url: url.to_string(),
+            content_hash: Some(hash.to_string()),
+            size_bytes: size,
+            fetched_at,
+            current,
+        }
+    }
+
+    #[test]
+    fn test_parse_age_and_size() {
+        assert_eq!(parse_age("7d").unwrap(), Duration::from_secs(7 * 86400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86400));
+        assert!(parse_age("7").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("7y").is_err());
+
+        assert_eq!(parse_size("500M").unwrap(), 500 << 20);
+        assert_eq!(parse_size("2GB").unwrap(), 2 << 30);
+        assert_eq!(parse_size("800k").unwrap(), 800 << 10);
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert!(parse_size("5T").is_err());
+    }
+
+    #[test]
+    fn test_plan_eviction() {
+        let items = [
+            item("a", "a1", 100, 50, true),
+            item("a", "a0", 100, 10, false),
+            item("b", "b1", 100, 20, true),
+            item("b", "b0", 100, 5, false),
+            item("c", "c1", 100, 30, true),
+        ];
+
+        // Fits - nothing to do
+        assert_eq!(plan_eviction(&items, 500), (vec![], 500));
+
+        // History goes first, oldest snapshot first
+        let (evictions, size) = plan_eviction(&items, 400);
+        assert_eq!(size, 400);
+        assert_eq!(
+            evictions,
+            vec![Eviction::Snapshot {
+                url: "b".to_string(),
+                content_hash: "b0".to_string()
+            }]
+        );
+
+        // Then the least recently fetched pages
+        let (evictions, size) = plan_eviction(&items, 150);
+        assert_eq!(size, 100);
+        assert_eq!(evictions.len(), 4);
+        assert_eq!(
+            evictions[2..],
+            [
+                Eviction::Entry {
+                    url: "b".to_string()
+                },
+                Eviction::Entry {
+                    url: "c".to_string()
+                }
+            ]
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::{Context, Result};
 
+use crate::turso::{HybridConfig, SearchMode};
+
 /// Default embedding model used when EMBEDDING_MODEL env var is not set
 pub const DEFAULT_EMBEDDING_MODEL: &str = "qwen/qwen3-embedding-8b";
 
@@ -12,6 +14,10 @@ pub struct Config {
     pub openrouter_api_key: String,
     pub embedding_model: String,
     pub vector_size: usize,
+    /// Retrieval mode of the AI pipeline
+    pub search_mode: SearchMode,
+    /// Rank fusion settings for hybrid search
+    pub hybrid: HybridConfig,
 }
 
 impl Config {
@@ -34,6 +40,8 @@ impl Config {
             openrouter_api_key,
             embedding_model,
             vector_size,
+            search_mode: SearchMode::from_env()?,
+            hybrid: HybridConfig::from_env(),
         })
     }
 }
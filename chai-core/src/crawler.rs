@@ -0,0 +1,820 @@
+//! Polite concurrent crawler for shop pages
+//!
+//! This module provides:
+//! - Bounded concurrency for page fetches
+//! - Per-host token bucket rate limiting
+//! - Retries with exponential backoff and jitter for 5xx/429/timeouts
+//! - `Retry-After` support (pauses the whole host)
+//! - robots.txt compliance (`Disallow`, `Crawl-delay`), cached in `html_cache`
+//! - Conditional requests (`If-None-Match` / `If-Modified-Since`)
+//! - Summary of pages that still failed after all retries
+
+use crate::cache::{self, CacheEntry, CacheMeta};
+use crate::http::{parse_http_date, unix_now};
+use crate::repository::CacheRepository;
+use crate::robots::RobotsTxt;
+use anyhow::{Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use reqwest::{Client, StatusCode, Url, header};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+/// Browser-like user agent used for shop requests
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
+
+/// Agent name matched against `User-agent` lines of robots.txt
+pub const DEFAULT_ROBOTS_AGENT: &str = "chai";
+
+/// How long a cached robots.txt is trusted (RFC 9309 recommends 24 hours)
+const ROBOTS_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// Default number of pages fetched at the same time
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Default sustained request rate per host
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 3.0;
+
+/// Default number of requests allowed in a burst per host
+const DEFAULT_BURST: u32 = 3;
+
+/// Default number of retries after the first attempt
+const DEFAULT_MAX_RETRIES: u32 = 4;
+
+/// Default request timeout in seconds
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// First retry delay (doubled on every attempt)
+const BASE_BACKOFF_MS: u64 = 500;
+
+/// Upper bound for the retry delay
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// Upper bound for honoring `Retry-After` (don't stall the crawl for hours)
+const MAX_RETRY_AFTER_SECS: u64 = 300;
+
+/// Crawler configuration
+#[derive(Debug, Clone)]
+pub struct CrawlerConfig {
+    /// Maximum number of in-flight requests
+    pub concurrency: usize,
+    /// Sustained request rate per host
+    pub requests_per_second: f64,
+    /// Bucket capacity per host
+    pub burst: u32,
+    /// Retries after the first attempt for transient errors
+    pub max_retries: u32,
+    /// Request timeout
+    pub timeout: Duration,
+    /// User agent sent with every request
+    pub user_agent: String,
+    /// Agent name used to pick the robots.txt group
+    pub robots_agent: String,
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            requests_per_second: DEFAULT_REQUESTS_PER_SECOND,
+            burst: DEFAULT_BURST,
+            max_retries: DEFAULT_MAX_RETRIES,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            robots_agent: DEFAULT_ROBOTS_AGENT.to_string(),
+        }
+    }
+}
+
+impl CrawlerConfig {
+    /// Load config from environment variables
+    ///
+    /// Environment variables:
+    /// - `CRAWL_CONCURRENCY`: Maximum in-flight requests (default: 4)
+    /// - `CRAWL_RATE`: Requests per second per host (default: 3)
+    /// - `CRAWL_MAX_RETRIES`: Retries for transient errors (default: 4)
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let concurrency = std::env::var("CRAWL_CONCURRENCY")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.concurrency);
+        let requests_per_second = std::env::var("CRAWL_RATE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.requests_per_second);
+        let max_retries = std::env::var("CRAWL_MAX_RETRIES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.max_retries);
+
+        Self {
+            concurrency,
+            requests_per_second,
+            max_retries,
+            ..defaults
+        }
+    }
+}
+
+/// Successfully fetched page (`FetchedPage<Vec<u8>>` for binary files)
+#[derive(Debug, Clone)]
+pub struct FetchedPage<B = String> {
+    pub url: String,
+    pub status: u16,
+    /// Page body (empty for `304 Not Modified`)
+    pub body: B,
+    /// `ETag` response header
+    pub etag: Option<String>,
+    /// `Last-Modified` response header
+    pub last_modified: Option<String>,
+    /// Number of attempts it took (1 = no retries)
+    pub attempts: u32,
+}
+
+impl<B> FetchedPage<B> {
+    /// Server confirmed that the cached copy is still valid
+    #[must_use]
+    pub fn is_not_modified(&self) -> bool {
+        self.status == StatusCode::NOT_MODIFIED.as_u16()
+    }
+
+    /// Response metadata to store in the cache
+    #[must_use]
+    pub fn cache_meta(&self) -> CacheMeta {
+        CacheMeta {
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+            status: Some(self.status),
+        }
+    }
+}
+
+/// Validators of a cached copy, sent with conditional requests
+#[derive(Debug, Clone, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// No validators - the request is unconditional
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+impl From<&CacheEntry> for Validators {
+    fn from(entry: &CacheEntry) -> Self {
+        Self {
+            etag: entry.etag.clone(),
+            last_modified: entry.last_modified.clone(),
+        }
+    }
+}
+
+/// Successful response of a single attempt
+struct Response<B> {
+    status: u16,
+    body: B,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Response body type: text for pages, raw bytes for images
+trait ResponseBody: Default + Sized {
+    async fn read(response: reqwest::Response) -> reqwest::Result<Self>;
+}
+
+impl ResponseBody for String {
+    async fn read(response: reqwest::Response) -> reqwest::Result<Self> {
+        response.text().await
+    }
+}
+
+impl ResponseBody for Vec<u8> {
+    async fn read(response: reqwest::Response) -> reqwest::Result<Self> {
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Page that could not be fetched
+#[derive(Debug, Clone)]
+pub struct FetchError {
+    pub url: String,
+    /// HTTP status of the last attempt (None for transport errors)
+    pub status: Option<u16>,
+    pub message: String,
+    pub attempts: u32,
+    /// Not fetched because robots.txt disallows it
+    pub disallowed: bool,
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.disallowed {
+            return write!(f, "{}: {}", self.url, self.message);
+        }
+        write!(
+            f,
+            "{}: {} (after {} attempt{})",
+            self.url,
+            self.message,
+            self.attempts,
+            if self.attempts == 1 { "" } else { "s" }
+        )
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Result of a single page fetch in a crawl
+pub type FetchResult = std::result::Result<FetchedPage, FetchError>;
+
+/// Summary of a crawl
+#[derive(Debug, Clone, Default)]
+pub struct CrawlSummary {
+    /// Pages fetched successfully
+    pub succeeded: usize,
+    /// Successful pages that needed at least one retry
+    pub retried: usize,
+    /// Pages that still failed after all retries
+    pub failed: Vec<FetchError>,
+    /// Pages skipped because of robots.txt
+    pub skipped: Vec<FetchError>,
+}
+
+impl CrawlSummary {
+    /// Record the outcome of a fetch
+    pub fn record(&mut self, result: &FetchResult) {
+        match result {
+            Ok(page) => {
+                self.succeeded += 1;
+                if page.attempts > 1 {
+                    self.retried += 1;
+                }
+            }
+            Err(e) if e.disallowed => self.skipped.push(e.clone()),
+            Err(e) => self.failed.push(e.clone()),
+        }
+    }
+
+    /// Log the summary, listing every failed and skipped page
+    pub fn log(&self) {
+        info!(
+            "Crawl finished: {} fetched ({} after retries), {} failed, {} skipped by robots.txt",
+            self.succeeded,
+            self.retried,
+            self.failed.len(),
+            self.skipped.len()
+        );
+
+        for failure in &self.failed {
+            warn!("  x {}", failure);
+        }
+        for skipped in &self.skipped {
+            info!("  - {}", skipped);
+        }
+    }
+}
+
+/// Outcome of a single attempt
+struct AttemptError {
+    status: Option<u16>,
+    message: String,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+/// Token bucket for a single host
+struct HostLimiter {
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+    /// Set by `Retry-After` - no requests to the host until then
+    paused_until: Option<Instant>,
+}
+
+impl HostLimiter {
+    fn new(rate: f64, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            state: Mutex::new(BucketState {
+                rate: rate.max(0.01),
+                capacity,
+                tokens: capacity,
+                updated: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Wait until a request to the host is allowed
+    async fn acquire(&self) {
+        while let Some(wait) = self.try_acquire(Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take a token if available, otherwise return how long to wait
+    fn try_acquire(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().expect("Host limiter lock poisoned");
+
+        if let Some(until) = state.paused_until {
+            if until > now {
+                return Some(until - now);
+            }
+            state.paused_until = None;
+        }
+
+        let elapsed = now.saturating_duration_since(state.updated).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * state.rate).min(state.capacity);
+        state.updated = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - state.tokens) / state.rate))
+        }
+    }
+
+    /// Enforce a minimum interval between requests (robots.txt `Crawl-delay`)
+    fn slow_down(&self, interval: Duration) {
+        if interval.is_zero() {
+            return;
+        }
+        let mut state = self.state.lock().expect("Host limiter lock poisoned");
+        state.rate = state.rate.min(1.0 / interval.as_secs_f64());
+        state.capacity = 1.0;
+        state.tokens = state.tokens.min(1.0);
+    }
+
+    /// Pause all requests to the host
+    fn pause_for(&self, duration: Duration) {
+        let mut state = self.state.lock().expect("Host limiter lock poisoned");
+        let until = Instant::now() + duration;
+        if state.paused_until.is_none_or(|current| current < until) {
+            state.paused_until = Some(until);
+        }
+    }
+}
+
+/// Polite concurrent crawler
+pub struct Crawler {
+    client: Client,
+    config: CrawlerConfig,
+    limiters: Mutex<HashMap<String, Arc<HostLimiter>>>,
+    /// robots.txt per origin, loaded once on first request
+    robots: Mutex<HashMap<String, Arc<OnceCell<Arc<RobotsTxt>>>>>,
+    /// Where robots.txt is cached
+    cache: Arc<dyn CacheRepository>,
+}
+
+impl Crawler {
+    /// Create a new crawler caching robots.txt in `cache`
+    pub fn new(config: CrawlerConfig, cache: Arc<dyn CacheRepository>) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(&config.user_agent)
+            .timeout(config.timeout)
+            .build()
+            .context("Failed to create crawler HTTP client")?;
+
+        Ok(Self {
+            client,
+            config,
+            limiters: Mutex::new(HashMap::new()),
+            robots: Mutex::new(HashMap::new()),
+            cache,
+        })
+    }
+
+    /// Crawler configuration
+    pub fn config(&self) -> &CrawlerConfig {
+        &self.config
+    }
+
+    /// Fetch a single page, retrying transient errors
+    ///
+    /// URLs disallowed by robots.txt are not requested and return
+    /// an error with `disallowed` set.
+    pub async fn fetch(&self, url: &str) -> FetchResult {
+        self.fetch_conditional(url, &Validators::default()).await
+    }
+
+    /// Fetch a page, asking the server to reply `304 Not Modified`
+    /// if it still matches the validators of our cached copy
+    pub async fn fetch_conditional(&self, url: &str, validators: &Validators) -> FetchResult {
+        self.fetch_allowed(url, validators).await
+    }
+
+    /// Fetch a binary file (image), with the same robots.txt, rate limit
+    /// and retry handling as pages
+    pub async fn fetch_bytes(
+        &self,
+        url: &str,
+    ) -> std::result::Result<FetchedPage<Vec<u8>>, FetchError> {
+        self.fetch_allowed(url, &Validators::default()).await
+    }
+
+    /// Fetch after checking robots.txt
+    async fn fetch_allowed<B: ResponseBody>(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> std::result::Result<FetchedPage<B>, FetchError> {
+        if let Err(reason) = self.check_robots(url).await {
+            return Err(FetchError {
+                url: url.to_string(),
+                status: None,
+                message: format!("Disallowed by robots.txt ({})", reason),
+                attempts: 0,
+                disallowed: true,
+            });
+        }
+
+        self.fetch_with_retries(url, validators).await
+    }
+
+    /// Check URL against robots.txt of its site, returning the reason if disallowed
+    pub async fn check_robots(&self, url: &str) -> std::result::Result<(), String> {
+        let Ok(parsed) = Url::parse(url) else {
+            // Invalid URLs fail on fetch with a proper error
+            return Ok(());
+        };
+
+        let mut path = parsed.path().to_string();
+        if let Some(query) = parsed.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+
+        self.robots_for(&parsed).await.check(&path)
+    }
+
+    /// Get robots.txt rules for the URL's origin (loaded once per crawler)
+    async fn robots_for(&self, url: &Url) -> Arc<RobotsTxt> {
+        let origin = url.origin().ascii_serialization();
+        let cell = {
+            let mut robots = self.robots.lock().expect("Robots map lock poisoned");
+            robots.entry(origin.clone()).or_default().clone()
+        };
+
+        cell.get_or_init(|| async {
+            let robots = self.load_robots(&origin).await;
+            if let Some(delay) = robots.crawl_delay() {
+                info!(
+                    "robots.txt of {} requests Crawl-delay {:.1}s",
+                    origin,
+                    delay.as_secs_f32()
+                );
+                self.limiter_for(url.as_str()).slow_down(delay);
+            }
+            Arc::new(robots)
+        })
+        .await
+        .clone()
+    }
+
+    /// Load robots.txt from cache or the site
+    ///
+    /// Missing robots.txt (4xx) allows everything and is cached as an empty body.
+    /// Unreachable robots.txt (5xx, network errors) disallows everything
+    /// and is not cached, so the next run tries again.
+    async fn load_robots(&self, origin: &str) -> RobotsTxt {
+        let robots_url = format!("{}/robots.txt", origin);
+        let agent = &self.config.robots_agent;
+
+        let cached = match cache::get(self.cache.as_ref(), &robots_url).await {
+            Ok(Some(entry)) if unix_now() - entry.fetched_at < ROBOTS_TTL_SECS => {
+                return RobotsTxt::parse(&entry.html, agent);
+            }
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Failed to read cached robots.txt: {:#}", e);
+                None
+            }
+        };
+
+        let validators = cached.as_ref().map(Validators::from).unwrap_or_default();
+        let (body, meta) = match self.fetch_with_retries(&robots_url, &validators).await {
+            Ok(page) if page.is_not_modified() => {
+                if let Err(e) =
+                    cache::touch(self.cache.as_ref(), &robots_url, &page.cache_meta()).await
+                {
+                    warn!("Failed to refresh cached robots.txt: {:#}", e);
+                }
+                let body = cached.map(|entry| entry.html).unwrap_or_default();
+                return RobotsTxt::parse(&body, agent);
+            }
+            Ok(page) => {
+                let meta = page.cache_meta();
+                (page.body, meta)
+            }
+            Err(e) if e.status.is_some_and(|s| (400..500).contains(&s)) => {
+                info!("No robots.txt at {} ({}), allowing all", origin, e.message);
+                let meta = CacheMeta {
+                    status: e.status,
+                    ..CacheMeta::default()
+                };
+                (String::new(), meta)
+            }
+            Err(e) => {
+                warn!("{}, assuming full disallow for {}", e, origin);
+                return RobotsTxt::disallow_all();
+            }
+        };
+
+        if let Err(e) = cache::set_with_meta(self.cache.as_ref(), &robots_url, &body, &meta).await {
+            warn!("Failed to cache robots.txt: {:#}", e);
+        }
+
+        RobotsTxt::parse(&body, agent)
+    }
+
+    /// Fetch without robots.txt check
+    async fn fetch_with_retries<B: ResponseBody>(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> std::result::Result<FetchedPage<B>, FetchError> {
+        let limiter = self.limiter_for(url);
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            limiter.acquire().await;
+
+            let error = match self.try_fetch(url, validators).await {
+                Ok(response) => {
+                    return Ok(FetchedPage {
+                        url: url.to_string(),
+                        status: response.status,
+                        body: response.body,
+                        etag: response.etag,
+                        last_modified: response.last_modified,
+                        attempts,
+                    });
+                }
+                Err(e) => e,
+            };
+
+            if !error.retryable || attempts > self.config.max_retries {
+                return Err(FetchError {
+                    url: url.to_string(),
+                    status: error.status,
+                    message: error.message,
+                    attempts,
+                    disallowed: false,
+                });
+            }
+
+            let delay = match error.retry_after {
+                Some(retry_after) => {
+                    limiter.pause_for(retry_after);
+                    retry_after
+                }
+                None => backoff_delay(attempts),
+            };
+
+            warn!(
+                "Retrying {} in {:.1}s (attempt {}/{}): {}",
+                url,
+                delay.as_secs_f32(),
+                attempts,
+                self.config.max_retries + 1,
+                error.message
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Fetch many pages concurrently
+    ///
+    /// Results are yielded as soon as pages complete (not in input order).
+    pub fn crawl(&self, urls: Vec<String>) -> BoxStream<'_, (String, FetchResult)> {
+        let requests = urls
+            .into_iter()
+            .map(|url| (url, Validators::default()))
+            .collect();
+        self.crawl_conditional(requests)
+    }
+
+    /// Fetch many pages concurrently with conditional requests
+    ///
+    /// Pages that didn't change come back with status 304 and an empty body.
+    pub fn crawl_conditional(
+        &self,
+        requests: Vec<(String, Validators)>,
+    ) -> BoxStream<'_, (String, FetchResult)> {
+        stream::iter(requests)
+            .map(move |(url, validators)| async move {
+                let result = self.fetch_conditional(&url, &validators).await;
+                (url, result)
+            })
+            .buffer_unordered(self.config.concurrency.max(1))
+            .boxed()
+    }
+
+    /// Single request without retries
+    async fn try_fetch<B: ResponseBody>(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> std::result::Result<Response<B>, AttemptError> {
+        let mut request = self.client.get(url);
+        if let Some(etag) = &validators.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let response = request.send().await.map_err(|e| AttemptError {
+            status: None,
+            message: format!("Request error: {}", e),
+            retryable: !e.is_builder(),
+            retry_after: None,
+        })?;
+
+        let status = response.status();
+        let header_value = |name: header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let etag = header_value(header::ETAG);
+        let last_modified = header_value(header::LAST_MODIFIED);
+
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Response {
+                status: status.as_u16(),
+                body: B::default(),
+                etag,
+                last_modified,
+            });
+        }
+
+        if !status.is_success() {
+            let retry_after = response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| parse_retry_after(v, unix_now()));
+
+            return Err(AttemptError {
+                status: Some(status.as_u16()),
+                message: format!("HTTP {}", status),
+                retryable: is_retryable_status(status),
+                retry_after,
+            });
+        }
+
+        let body = B::read(response).await.map_err(|e| AttemptError {
+            status: Some(status.as_u16()),
+            message: format!("Read error: {}", e),
+            retryable: true,
+            retry_after: None,
+        })?;
+
+        Ok(Response {
+            status: status.as_u16(),
+            body,
+            etag,
+            last_modified,
+        })
+    }
+
+    fn limiter_for(&self, url: &str) -> Arc<HostLimiter> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+
+        let mut limiters = self.limiters.lock().expect("Limiter map lock poisoned");
+        limiters
+            .entry(host)
+            .or_insert_with(|| {
+                Arc::new(HostLimiter::new(
+                    self.config.requests_per_second,
+                    self.config.burst,
+                ))
+            })
+            .clone()
+    }
+}
+
+/// Check if an HTTP status is worth retrying
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+/// Exponential backoff with jitter for the given attempt (1-based)
+fn backoff_delay(attempt: u32) -> Duration {
+    let exp = BASE_BACKOFF_MS.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    let capped = exp.min(MAX_BACKOFF_SECS * 1000);
+    // "Equal jitter": half fixed, half random
+    let half = capped / 2;
+    Duration::from_millis(half + rand::random_range(0..=half))
+}
+
+/// Parse `Retry-After` header (seconds or HTTP date)
+fn parse_retry_after(value: &str, now: i64) -> Option<Duration> {
+    let secs = match value.trim().parse::<u64>() {
+        Ok(secs) => secs,
+        Err(_) => (parse_http_date(value)? - now).max(0) as u64,
+    };
+    Some(Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120", 0), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", 784_111_767),
+            Some(Duration::from_secs(10))
+        );
+        // Capped and never negative
+        assert_eq!(
+            parse_retry_after("999999", 0),
+            Some(Duration::from_secs(MAX_RETRY_AFTER_SECS))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", 784_111_800),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", 0), None);
+    }
+
+    #[test]
+    fn test_backoff_delay_bounds() {
+        for attempt in 1..10 {
+            let full = (BASE_BACKOFF_MS << (attempt - 1)).min(MAX_BACKOFF_SECS * 1000);
+            let delay = backoff_delay(attempt).as_millis() as u64;
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "attempt {attempt}: {delay}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn test_host_limiter_burst_then_wait() {
+        let limiter = HostLimiter::new(2.0, 2);
+        let now = Instant::now();
+
+        assert_eq!(limiter.try_acquire(now), None);
+        assert_eq!(limiter.try_acquire(now), None);
+        // Bucket is empty: next token in 1/rate seconds
+        let wait = limiter.try_acquire(now).unwrap();
+        assert!((wait.as_secs_f64() - 0.5).abs() < 0.01);
+
+        // Refilled after waiting
+        assert_eq!(limiter.try_acquire(now + Duration::from_millis(500)), None);
+    }
+
+    #[test]
+    fn test_host_limiter_slow_down() {
+        let limiter = HostLimiter::new(10.0, 5);
+        limiter.slow_down(Duration::from_secs(2));
+        let now = Instant::now();
+
+        // Burst is reduced to a single request
+        assert_eq!(limiter.try_acquire(now), None);
+        let wait = limiter.try_acquire(now).unwrap();
+        assert!((wait.as_secs_f64() - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_host_limiter_pause() {
+        let limiter = HostLimiter::new(10.0, 5);
+        limiter.pause_for(Duration::from_secs(30));
+        let wait = limiter.try_acquire(Instant::now()).unwrap();
+        assert!(wait > Duration::from_secs(29));
+    }
+}
@@ -0,0 +1,221 @@
+//! Catalog facets: tea counts per filter option
+//!
+//! [`TeaRepository::get_facets`](crate::repository::TeaRepository::get_facets)
+//! counts the teas passing any [`SearchFilters`](crate::filters::SearchFilters)
+//! per series, ingredient, price bucket, characteristic and stock/sample/set
+//! state, so a UI can show "Ягодные (42)" next to each option.
+//!
+//! The database counts with `GROUP BY`, [`FacetCounter`] counts teas one by one
+//! for storage without SQL. Both share the price buckets and the sort order.
+
+use std::collections::HashMap;
+
+use crate::models::{CatalogFacets, FacetCount, PriceBucket, Tea};
+use crate::tea_utils;
+
+/// Bounds between price buckets, a bucket goes from its bound up to the next one
+pub const PRICE_BUCKET_BOUNDS: [f64; 4] = [300.0, 500.0, 1000.0, 2000.0];
+
+/// Index of the price bucket of a price
+#[must_use]
+pub fn price_bucket(price: f64) -> usize {
+    PRICE_BUCKET_BOUNDS
+        .iter()
+        .take_while(|bound| price >= **bound)
+        .count()
+}
+
+/// SQL expression computing [`price_bucket`] of a price column
+#[must_use]
+pub fn price_bucket_sql(column: &str) -> String {
+    let cases: Vec<String> = PRICE_BUCKET_BOUNDS
+        .iter()
+        .enumerate()
+        .map(|(i, bound)| format!("WHEN {} < {:?} THEN {}", column, bound, i))
+        .collect();
+    format!(
+        "CASE {} ELSE {} END",
+        cases.join(" "),
+        PRICE_BUCKET_BOUNDS.len()
+    )
+}
+
+/// All price buckets in ascending order with the counts per bucket index
+#[must_use]
+pub fn price_buckets(counts: &HashMap<usize, usize>) -> Vec<PriceBucket> {
+    (0..=PRICE_BUCKET_BOUNDS.len())
+        .map(|i| PriceBucket {
+            min: i.checked_sub(1).map(|prev| PRICE_BUCKET_BOUNDS[prev]),
+            max: PRICE_BUCKET_BOUNDS.get(i).copied(),
+            count: counts.get(&i).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
+/// Facet values sorted by count (highest first), then by value
+#[must_use]
+pub fn sorted_counts(counts: impl IntoIterator<Item = (String, usize)>) -> Vec<FacetCount> {
+    let mut counts: Vec<FacetCount> = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts
+}
+
+/// Counts facets of teas one by one
+#[derive(Debug, Default)]
+pub struct FacetCounter {
+    facets: CatalogFacets,
+    series: HashMap<String, usize>,
+    ingredients: HashMap<String, usize>,
+    prices: HashMap<usize, usize>,
+    characteristics: HashMap<String, HashMap<String, usize>>,
+}
+
+impl FacetCounter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a tea, `sample_in_stock` is the stock of its linked sample
+    pub fn add(&mut self, tea: &Tea, sample_in_stock: bool) {
+        let facets = &mut self.facets;
+        facets.total += 1;
+        if tea.in_stock {
+            facets.in_stock += 1;
+        } else {
+            facets.out_of_stock += 1;
+        }
+        facets.sample_in_stock += usize::from(sample_in_stock);
+        facets.samples += usize::from(tea.is_sample);
+        facets.sets += usize::from(tea.is_set);
+
+        if let Some(series) = tea.series.as_ref().filter(|s| !s.is_empty()) {
+            *self.series.entry(series.clone()).or_default() += 1;
+        }
+        for ingredient in tea_utils::ingredients(tea) {
+            *self.ingredients.entry(ingredient).or_default() += 1;
+        }
+        if let Some(price) = tea_utils::min_price(tea) {
+            *self.prices.entry(price_bucket(price)).or_default() += 1;
+        }
+        for (name, value) in &tea.characteristics {
+            *self
+                .characteristics
+                .entry(name.clone())
+                .or_default()
+                .entry(value.clone())
+                .or_default() += 1;
+        }
+    }
+
+    #[must_use]
+    pub fn finish(self) -> CatalogFacets {
+        CatalogFacets {
+            series: sorted_counts(self.series),
+            ingredients: sorted_counts(self.ingredients),
+            price_buckets: price_buckets(&self.prices),
+            characteristics: self
+                .characteristics
+                .into_iter()
+                .map(|(name, values)| (name, sorted_counts(values)))
+                .collect(),
+            ..self.facets
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_buckets() {
+        assert_eq!(price_bucket(0.0), 0);
+        assert_eq!(price_bucket(299.99), 0);
+        assert_eq!(price_bucket(300.0), 1);
+        assert_eq!(price_bucket(1999.0), 3);
+        assert_eq!(price_bucket(5000.0), 4);
+        assert_eq!(
+            price_bucket_sql("teas.min_price"),
+            "CASE WHEN teas.min_price < 300.0 THEN 0 WHEN teas.min_price < 500.0 THEN 1 \
+             WHEN teas.min_price < 1000.0 THEN 2 WHEN teas.min_price < 2000.0 THEN 3 ELSE 4 END"
+        );
+
+        let buckets = price_buckets(&HashMap::from([(1, 2)]));
+        assert_eq!(buckets.len(), PRICE_BUCKET_BOUNDS.len() + 1);
+        assert_eq!(
+            buckets[0],
+            PriceBucket {
+                min: None,
+                max: Some(300.0),
+                count: 0
+            }
+        );
+        assert_eq!(
+            buckets[1],
+            PriceBucket {
+                min: Some(300.0),
+                max: Some(500.0),
+                count: 2
+            }
+        );
+        assert_eq!(buckets[4].max, None);
+    }
+
+    #[test]
+    fn test_counter() {
+        let berry = Tea {
+            series: Some("Ягодные".to_string()),
+            price: Some("450".to_string()),
+            composition: vec!["Иван-чай".to_string(), "Малина".to_string()],
+            characteristics: [("Регион".to_string(), "Карелия".to_string())].into(),
+            in_stock: true,
+            ..Default::default()
+        };
+        let sample = Tea {
+            series: Some("Ягодные".to_string()),
+            composition: vec!["иван-чай".to_string()],
+            is_sample: true,
+            ..Default::default()
+        };
+        let herbal = Tea {
+            series: Some("Травяные".to_string()),
+            price: Some("2500".to_string()),
+            ..Default::default()
+        };
+
+        let mut counter = FacetCounter::new();
+        counter.add(&berry, true);
+        counter.add(&sample, false);
+        counter.add(&herbal, false);
+        let facets = counter.finish();
+
+        assert_eq!(facets.total, 3);
+        assert_eq!(facets.in_stock, 1);
+        assert_eq!(facets.out_of_stock, 2);
+        assert_eq!(facets.sample_in_stock, 1);
+        assert_eq!(facets.samples, 1);
+        assert_eq!(facets.sets, 0);
+
+        let values = |counts: &[FacetCount]| -> Vec<(String, usize)> {
+            counts.iter().map(|c| (c.value.clone(), c.count)).collect()
+        };
+        assert_eq!(
+            values(&facets.series),
+            vec![("Ягодные".to_string(), 2), ("Травяные".to_string(), 1)]
+        );
+        assert_eq!(
+            values(&facets.ingredients),
+            vec![("иван-чай".to_string(), 2), ("малина".to_string(), 1)]
+        );
+        let prices: Vec<usize> = facets.price_buckets.iter().map(|b| b.count).collect();
+        assert_eq!(prices, vec![0, 1, 0, 0, 1]);
+        assert_eq!(
+            values(&facets.characteristics["Регион"]),
+            vec![("Карелия".to_string(), 1)]
+        );
+    }
+}
@@ -0,0 +1,706 @@
+//! Composable search filters
+//!
+//! [`SearchFilters`] is a list of [`Filter`] conditions that all must match.
+//! Conditions can be grouped with [`Filter::All`], [`Filter::Any`] and
+//! [`Filter::Not`]. Filters compile to a SQL condition over the `teas` table
+//! with `?` placeholders and the values to bind, so user input never becomes
+//! part of the SQL text. [`SearchFilters::matches`] evaluates the same
+//! condition on a [`Tea`] for storage without SQL. [`FilterOptions`] (sent by
+//! the web client) converts into filters with `From`.
+//!
+//! Only active teas match unless discontinued ones are included with
+//! [`SearchFilters::include_discontinued`]; hidden teas never match.
+//!
+//! Results come most relevant first. With [`SearchFilters::best_value`] the
+//! searches rank [`BEST_VALUE_CANDIDATES`] relevant teas and return the ones
+//! with the lowest price per 100 g, see [`SearchFilters::order_results`].
+//!
+//! ```ignore
+//! let filters = SearchFilters::new()
+//!     .exclude_samples()
+//!     .in_stock_or_sample_in_stock()
+//!     .series(["Ягодные", "Травяные"])
+//!     .price_range(None, Some(500.0))
+//!     .without_ingredients(["мята"]);
+//! ```
+
+use turso::Value;
+
+use crate::models::{FilterOptions, SearchResult, Tea, TeaStatus};
+use crate::tea_utils;
+
+/// Relevant teas compared by price when ordering by best value
+pub const BEST_VALUE_CANDIDATES: usize = 50;
+
+/// Single condition on a stored tea
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Product is in stock
+    InStock,
+    /// Linked sample of the product is in stock
+    SampleInStock,
+    /// Product is a sample
+    Sample,
+    /// Product is a set
+    Set,
+    /// Series is one of the values
+    Series(Vec<String>),
+    /// Shop ID is one of the values
+    Shop(Vec<String>),
+    /// Characteristic has exactly this value
+    Characteristic { name: String, value: String },
+    /// Lowest price of the tea and its variants within bounds (inclusive),
+    /// teas without a price never match
+    Price { min: Option<f64>, max: Option<f64> },
+    /// Composition has an ingredient containing the text (in lower case)
+    Ingredient(String),
+    /// All conditions match (true if empty)
+    All(Vec<Filter>),
+    /// Any condition matches (false if empty)
+    Any(Vec<Filter>),
+    /// Condition doesn't match
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// Ingredient condition, the text is matched in lower case
+    #[must_use]
+    pub fn ingredient(text: &str) -> Self {
+        Self::Ingredient(text.trim().to_lowercase())
+    }
+
+    /// Negated condition
+    #[must_use]
+    pub fn negate(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+
+    /// SQL condition, pushing bound values in placeholder order
+    fn to_sql(&self, params: &mut Vec<Value>) -> String {
+        match self {
+            Self::InStock => "teas.in_stock = 1".to_string(),
+            Self::SampleInStock => {
+                "teas.id IN (SELECT tea_id FROM tea_samples WHERE sample_in_stock = 1)".to_string()
+            }
+            Self::Sample => "teas.is_sample = 1".to_string(),
+            Self::Set => "teas.is_set = 1".to_string(),
+            Self::Series(values) => in_list("teas.series", values, params),
+            Self::Shop(values) => in_list("teas.shop", values, params),
+            Self::Characteristic { name, value } => {
+                params.push(Value::Text(name.clone()));
+                params.push(Value::Text(value.clone()));
+                "teas.id IN (SELECT tea_id FROM tea_characteristics WHERE name = ? AND value = ?)"
+                    .to_string()
+            }
+            Self::Price { min, max } => {
+                let mut bounds = Vec::new();
+                if let Some(min) = min {
+                    params.push(Value::Real(*min));
+                    bounds.push("teas.min_price >= ?");
+                }
+                if let Some(max) = max {
+                    params.push(Value::Real(*max));
+                    bounds.push("teas.min_price <= ?");
+                }
+                if bounds.is_empty() {
+                    "teas.min_price IS NOT NULL".to_string()
+                } else {
+                    format!("({})", bounds.join(" AND "))
+                }
+            }
+            Self::Ingredient(text) => {
+                params.push(Value::Text(text.clone()));
+                "EXISTS (SELECT 1 FROM tea_ingredients WHERE tea_id = teas.id AND instr(ingredient, ?) > 0)"
+                    .to_string()
+            }
+            Self::All(filters) => join(filters, " AND ", "1", params),
+            Self::Any(filters) => join(filters, " OR ", "0", params),
+            Self::Not(filter) => format!("NOT ({})", filter.to_sql(params)),
+        }
+    }
+
+    /// Evaluate the condition like SQL does: None is `NULL` (a price condition
+    /// on a tea without a price), which stays unknown under `NOT`
+    fn eval(&self, tea: &Tea, sample_in_stock: bool) -> Option<bool> {
+        match self {
+            Self::InStock => Some(tea.in_stock),
+            Self::SampleInStock => Some(sample_in_stock),
+            Self::Sample => Some(tea.is_sample),
+            Self::Set => Some(tea.is_set),
+            Self::Series(values) => {
+                let series = tea.series.as_deref().unwrap_or("");
+                Some(values.iter().any(|v| v == series))
+            }
+            Self::Shop(values) => Some(values.contains(&tea.shop)),
+            Self::Characteristic { name, value } => {
+                Some(tea.characteristics.get(name) == Some(value))
+            }
+            Self::Price { min, max } => {
+                let price = tea_utils::min_price(tea)?;
+                Some(min.is_none_or(|min| price >= min) && max.is_none_or(|max| price <= max))
+            }
+            Self::Ingredient(text) => {
+                Some(tea_utils::ingredients(tea).iter().any(|v| v.contains(text)))
+            }
+            // Like SQL, false wins over NULL in AND, true wins over NULL in OR
+            Self::All(filters) => filters
+                .iter()
+                .try_fold(Some(true), |all, f| match f.eval(tea, sample_in_stock) {
+                    Some(false) => Err(()),
+                    Some(true) => Ok(all),
+                    None => Ok(None),
+                })
+                .unwrap_or(Some(false)),
+            Self::Any(filters) => filters
+                .iter()
+                .try_fold(Some(false), |any, f| match f.eval(tea, sample_in_stock) {
+                    Some(true) => Err(()),
+                    Some(false) => Ok(any),
+                    None => Ok(None),
+                })
+                .unwrap_or(Some(true)),
+            Self::Not(filter) => filter.eval(tea, sample_in_stock).map(|m| !m),
+        }
+    }
+}
+
+/// `column IN (?, ...)`, false for an empty list
+fn in_list(column: &str, values: &[String], params: &mut Vec<Value>) -> String {
+    if values.is_empty() {
+        return "0".to_string();
+    }
+
+    params.extend(values.iter().cloned().map(Value::Text));
+    let placeholders = vec!["?"; values.len()].join(", ");
+    format!("{} IN ({})", column, placeholders)
+}
+
+fn join(filters: &[Filter], separator: &str, empty: &str, params: &mut Vec<Value>) -> String {
+    if filters.is_empty() {
+        return empty.to_string();
+    }
+
+    let parts: Vec<String> = filters.iter().map(|f| f.to_sql(params)).collect();
+    format!("({})", parts.join(separator))
+}
+
+/// Search filters: conditions that all must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilters {
+    conditions: Vec<Filter>,
+    /// Discontinued teas match too
+    include_discontinued: bool,
+    /// Order results by the best price per 100 g instead of relevance
+    best_value: bool,
+}
+
+impl SearchFilters {
+    /// Filters matching every tea
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match discontinued teas too (hidden teas never match)
+    #[must_use]
+    pub fn include_discontinued(mut self) -> Self {
+        self.include_discontinued = true;
+        self
+    }
+
+    /// Add an arbitrary condition
+    #[must_use]
+    pub fn matching(mut self, filter: Filter) -> Self {
+        self.conditions.push(filter);
+        self
+    }
+
+    /// Add a group where any of the conditions must match
+    #[must_use]
+    pub fn any_of(self, filters: Vec<Filter>) -> Self {
+        self.matching(Filter::Any(filters))
+    }
+
+    #[must_use]
+    pub fn exclude_samples(self) -> Self {
+        self.matching(Filter::Sample.negate())
+    }
+
+    #[must_use]
+    pub fn exclude_sets(self) -> Self {
+        self.matching(Filter::Set.negate())
+    }
+
+    #[must_use]
+    pub fn only_in_stock(self) -> Self {
+        self.matching(Filter::InStock)
+    }
+
+    /// Product or its linked sample is in stock
+    #[must_use]
+    pub fn in_stock_or_sample_in_stock(self) -> Self {
+        self.any_of(vec![Filter::InStock, Filter::SampleInStock])
+    }
+
+    /// Series is one of the values (no-op for an empty list)
+    #[must_use]
+    pub fn series<I, S>(self, series: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let series: Vec<String> = series.into_iter().map(Into::into).collect();
+        if series.is_empty() {
+            return self;
+        }
+        self.matching(Filter::Series(series))
+    }
+
+    /// Shop ID is one of the values (no-op for an empty list)
+    #[must_use]
+    pub fn shops<I, S>(self, shops: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let shops: Vec<String> = shops.into_iter().map(Into::into).collect();
+        if shops.is_empty() {
+            return self;
+        }
+        self.matching(Filter::Shop(shops))
+    }
+
+    /// Characteristic has exactly this value
+    #[must_use]
+    pub fn characteristic(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.matching(Filter::Characteristic {
+            name: name.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Price within bounds (no-op if both are None)
+    #[must_use]
+    pub fn price_range(self, min: Option<f64>, max: Option<f64>) -> Self {
+        if min.is_none() && max.is_none() {
+            return self;
+        }
+        self.matching(Filter::Price { min, max })
+    }
+
+    /// Composition has all of the ingredients
+    #[must_use]
+    pub fn with_ingredients<I, S>(self, ingredients: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        ingredients.into_iter().fold(self, |filters, ingredient| {
+            filters.matching(Filter::ingredient(ingredient.as_ref()))
+        })
+    }
+
+    /// Composition has none of the ingredients
+    #[must_use]
+    pub fn without_ingredients<I, S>(self, ingredients: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        ingredients.into_iter().fold(self, |filters, ingredient| {
+            filters.matching(Filter::ingredient(ingredient.as_ref()).negate())
+        })
+    }
+
+    /// Order results by the lowest price per 100 g among the relevant teas
+    #[must_use]
+    pub fn best_value(mut self) -> Self {
+        self.best_value = true;
+        self
+    }
+
+    /// Conditions that all must match
+    #[must_use]
+    pub fn conditions(&self) -> &[Filter] {
+        &self.conditions
+    }
+
+    /// No conditions (the status of teas is still checked)
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// Statuses of teas that can match
+    #[must_use]
+    pub fn statuses(&self) -> Vec<TeaStatus> {
+        if self.include_discontinued {
+            vec![TeaStatus::Active, TeaStatus::Discontinued]
+        } else {
+            vec![TeaStatus::Active]
+        }
+    }
+
+    /// SQL condition over `teas` with values to bind in placeholder order
+    #[must_use]
+    pub fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        let statuses: Vec<String> = self
+            .statuses()
+            .iter()
+            .map(|status| status.as_str().to_string())
+            .collect();
+        let status_sql = in_list("teas.status", &statuses, &mut params);
+        let sql = join(&self.conditions, " AND ", "1", &mut params);
+        (format!("({} AND {})", status_sql, sql), params)
+    }
+
+    /// Whether a tea passes the filters, same as the SQL condition
+    ///
+    /// `sample_in_stock` is the stock of the sample linked to the tea,
+    /// `status` is its state in the catalog.
+    #[must_use]
+    pub fn matches(&self, tea: &Tea, sample_in_stock: bool, status: TeaStatus) -> bool {
+        self.statuses().contains(&status)
+            && Filter::All(self.conditions.clone()).eval(tea, sample_in_stock) == Some(true)
+    }
+
+    /// Number of teas to rank by relevance for `limit` results
+    #[must_use]
+    pub fn candidates(&self, limit: usize) -> usize {
+        if self.best_value {
+            limit.max(BEST_VALUE_CANDIDATES)
+        } else {
+            limit
+        }
+    }
+
+    /// Put results ranked by relevance in the requested order and keep `limit` of them
+    ///
+    /// By best value, teas without a price per 100 g go last
+    /// and equal prices keep their relevance order.
+    #[must_use]
+    pub fn order_results(&self, mut results: Vec<SearchResult>, limit: usize) -> Vec<SearchResult> {
+        if self.best_value {
+            results.sort_by_cached_key(|r| {
+                let price = r.tea.price_per_100g();
+                (price.is_none(), price)
+            });
+        }
+        results.truncate(limit);
+        results
+    }
+}
+
+impl From<&FilterOptions> for SearchFilters {
+    fn from(options: &FilterOptions) -> Self {
+        let mut filters = SearchFilters::new()
+            .series(options.series.iter().cloned())
+            .shops(options.shops.iter().cloned())
+            .price_range(options.min_price, options.max_price)
+            .with_ingredients(&options.with_ingredients)
+            .without_ingredients(&options.without_ingredients);
+
+        if options.in_stock {
+            filters = filters.only_in_stock();
+        }
+        if options.in_stock_or_sample {
+            filters = filters.in_stock_or_sample_in_stock();
+        }
+        if options.exclude_samples {
+            filters = filters.exclude_samples();
+        }
+        if options.exclude_sets {
+            filters = filters.exclude_sets();
+        }
+        for (name, value) in &options.characteristics {
+            filters = filters.characteristic(name.clone(), value.clone());
+        }
+        if options.include_discontinued {
+            filters = filters.include_discontinued();
+        }
+        if options.best_value {
+            filters = filters.best_value();
+        }
+
+        filters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PriceVariant;
+
+    const ACTIVE: TeaStatus = TeaStatus::Active;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_empty_filters() {
+        let (sql, params) = SearchFilters::new().to_sql();
+        assert_eq!(sql, "(teas.status IN (?) AND 1)");
+        assert_eq!(params, vec![text("active")]);
+
+        // Empty lists are ignored instead of matching nothing
+        let filters = SearchFilters::new()
+            .series(Vec::<String>::new())
+            .shops(Vec::<String>::new())
+            .price_range(None, None)
+            .with_ingredients(Vec::<String>::new());
+        assert!(filters.is_empty());
+    }
+
+    #[test]
+    fn test_values_are_bound() {
+        let (sql, params) = SearchFilters::new()
+            .series(["Ягодные", "O'Brien"])
+            .characteristic("Регион", "'; DROP TABLE teas; --")
+            .to_sql();
+
+        assert_eq!(
+            sql,
+            "(teas.status IN (?) AND (teas.series IN (?, ?) AND teas.id IN (SELECT tea_id FROM tea_characteristics WHERE name = ? AND value = ?)))"
+        );
+        assert!(!sql.contains('\''));
+        assert_eq!(
+            params,
+            vec![
+                text("active"),
+                text("Ягодные"),
+                text("O'Brien"),
+                text("Регион"),
+                text("'; DROP TABLE teas; --"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        let (sql, params) = SearchFilters::new()
+            .exclude_samples()
+            .in_stock_or_sample_in_stock()
+            .price_range(Some(100.0), Some(500.0))
+            .without_ingredients([" Мята "])
+            .to_sql();
+
+        assert!(sql.starts_with(
+            "(teas.status IN (?) AND (NOT (teas.is_sample = 1) AND (teas.in_stock = 1 OR teas.id IN"
+        ));
+        assert!(sql.contains(" >= ? AND "));
+        assert!(sql.ends_with("AND NOT (EXISTS (SELECT 1 FROM tea_ingredients WHERE tea_id = teas.id AND instr(ingredient, ?) > 0))))"));
+        assert_eq!(
+            params,
+            vec![
+                text("active"),
+                Value::Real(100.0),
+                Value::Real(500.0),
+                text("мята")
+            ]
+        );
+
+        let (sql, params) = SearchFilters::new()
+            .any_of(vec![])
+            .matching(Filter::All(vec![]))
+            .to_sql();
+        assert_eq!(sql, "(teas.status IN (?) AND (0 AND 1))");
+        assert_eq!(params, vec![text("active")]);
+    }
+
+    #[test]
+    fn test_params_follow_placeholders() {
+        let (sql, params) = SearchFilters::new()
+            .any_of(vec![
+                Filter::Series(vec!["Травяные".to_string()]),
+                Filter::All(vec![
+                    Filter::Shop(vec!["beliyles".to_string()]),
+                    Filter::ingredient("Чабрец"),
+                ]),
+            ])
+            .price_range(None, Some(300.0))
+            .to_sql();
+
+        assert_eq!(sql.matches('?').count(), params.len());
+        assert_eq!(
+            params,
+            vec![
+                text("active"),
+                text("Травяные"),
+                text("beliyles"),
+                text("чабрец"),
+                Value::Real(300.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let tea = Tea {
+            shop: "beliyles".to_string(),
+            series: Some("Травяные".to_string()),
+            price: Some("450.0000".to_string()),
+            composition: vec!["Иван-чай".to_string(), "мята перечная".to_string()],
+            in_stock: false,
+            ..Default::default()
+        };
+
+        assert!(SearchFilters::new().matches(&tea, false, ACTIVE));
+        assert!(
+            SearchFilters::new()
+                .series(["Травяные"])
+                .shops(["beliyles"])
+                .price_range(Some(400.0), Some(450.0))
+                .with_ingredients(["Мята"])
+                .matches(&tea, false, ACTIVE)
+        );
+        assert!(
+            !SearchFilters::new()
+                .without_ingredients(["мята"])
+                .matches(&tea, false, ACTIVE)
+        );
+        assert!(
+            !SearchFilters::new()
+                .only_in_stock()
+                .matches(&tea, false, ACTIVE)
+        );
+        assert!(
+            SearchFilters::new()
+                .in_stock_or_sample_in_stock()
+                .matches(&tea, true, ACTIVE)
+        );
+        assert!(
+            !SearchFilters::new()
+                .characteristic("Регион", "Алтай")
+                .matches(&tea, false, ACTIVE)
+        );
+
+        // No price is unknown, negation doesn't make it match
+        let no_price = Tea { price: None, ..tea };
+        let price = Filter::Price {
+            min: None,
+            max: Some(500.0),
+        };
+        assert!(
+            !SearchFilters::new()
+                .matching(price.clone())
+                .matches(&no_price, false, ACTIVE)
+        );
+        assert!(
+            !SearchFilters::new()
+                .matching(price.negate())
+                .matches(&no_price, false, ACTIVE)
+        );
+        assert!(
+            SearchFilters::new()
+                .any_of(vec![
+                    Filter::Sample.negate(),
+                    Filter::Price {
+                        min: None,
+                        max: None
+                    }
+                ])
+                .matches(&no_price, false, ACTIVE)
+        );
+
+        // Discontinued teas only match when included, hidden ones never
+        let discontinued = TeaStatus::Discontinued;
+        assert!(!SearchFilters::new().matches(&no_price, false, discontinued));
+        let included = SearchFilters::new().include_discontinued();
+        assert!(included.matches(&no_price, false, discontinued));
+        assert!(!included.matches(&no_price, false, TeaStatus::Hidden));
+        let (sql, params) = included.to_sql();
+        assert_eq!(sql, "(teas.status IN (?, ?) AND 1)");
+        assert_eq!(params, vec![text("active"), text("discontinued")]);
+
+        // The lowest of the variant prices counts
+        let variants = Tea {
+            price_variants: vec![PriceVariant {
+                price: "120".to_string(),
+                ..Default::default()
+            }],
+            ..no_price
+        };
+        assert!(
+            SearchFilters::new()
+                .price_range(None, Some(150.0))
+                .with_ingredients(["иван-чай"])
+                .matches(&variants, false, ACTIVE)
+        );
+    }
+
+    #[test]
+    fn test_from_options() {
+        assert_eq!(
+            SearchFilters::from(&FilterOptions::default()),
+            SearchFilters::new()
+        );
+
+        let options = FilterOptions {
+            in_stock_or_sample: true,
+            exclude_samples: true,
+            series: vec!["Ягодные".to_string()],
+            characteristics: vec![("Регион".to_string(), "Карелия".to_string())],
+            max_price: Some(500.0),
+            without_ingredients: vec!["Мята".to_string()],
+            include_discontinued: true,
+            best_value: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            SearchFilters::from(&options),
+            SearchFilters::new()
+                .series(["Ягодные"])
+                .price_range(None, Some(500.0))
+                .without_ingredients(["мята"])
+                .in_stock_or_sample_in_stock()
+                .exclude_samples()
+                .characteristic("Регион", "Карелия")
+                .include_discontinued()
+                .best_value()
+        );
+    }
+
+    #[test]
+    fn test_order_results() {
+        let result = |url: &str, price: Option<&str>, weight: &str| SearchResult {
+            tea: Tea {
+                price: price.map(str::to_string),
+                weight: Some(weight.to_string()),
+                in_stock: true,
+                ..Tea::new(url)
+            },
+            score: 0.0,
+            vector_score: None,
+            keyword_score: None,
+            sample_in_stock: false,
+            status: ACTIVE,
+        };
+        let urls = |results: &[SearchResult]| -> Vec<String> {
+            results.iter().map(|r| r.tea.url.clone()).collect()
+        };
+        // By relevance: 900 ₽ per 100 g, no price, 300 ₽ per 100 g, 450 ₽ per 100 g
+        let ranked = || {
+            vec![
+                result("a", Some("450"), "50 г"),
+                result("b", None, "100 г"),
+                result("c", Some("300"), "100 г"),
+                result("d", Some("900"), "200 г"),
+            ]
+        };
+
+        let filters = SearchFilters::new();
+        assert_eq!(filters.candidates(5), 5);
+        assert_eq!(urls(&filters.order_results(ranked(), 3)), ["a", "b", "c"]);
+
+        let filters = SearchFilters::new().best_value();
+        assert_eq!(filters.candidates(5), BEST_VALUE_CANDIDATES);
+        assert_eq!(filters.candidates(80), 80);
+        assert_eq!(urls(&filters.order_results(ranked(), 3)), ["c", "d", "a"]);
+        assert_eq!(
+            urls(&filters.order_results(ranked(), 10)),
+            ["c", "d", "a", "b"]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Local mirror of tea images
+//!
+//! This module provides:
+//! - Downloading gallery images through the [`Crawler`] (robots.txt, rate limits, retries)
+//! - Deduplication by content hash (the same photo used by several products is stored once)
+//! - WebP thumbnails in several sizes, stored as `<dir>/<size>/<hash>.webp`
+//! - URLs of the thumbnails served by chai-web under `/img/...`
+//!
+//! Thumbnail files are named by content hash and never change,
+//! so they can be cached by browsers forever.
+
+use crate::crawler::Crawler;
+use crate::repository::TeaRepository;
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use image::{DynamicImage, ImageFormat};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// URL prefix the thumbnails are served under
+pub const IMAGES_URL_PREFIX: &str = "/img";
+
+/// Default directory for thumbnails
+const DEFAULT_IMAGES_DIR: &str = "data/images";
+
+/// Thumbnail size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailSize {
+    /// Tea card in search results
+    Small,
+    /// Product modal
+    Large,
+}
+
+impl ThumbnailSize {
+    /// All sizes generated for every image
+    pub const ALL: [Self; 2] = [Self::Small, Self::Large];
+
+    /// Directory and URL segment
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Small => "sm",
+            Self::Large => "lg",
+        }
+    }
+
+    /// Maximum width and height in pixels
+    #[must_use]
+    pub fn max_side(&self) -> u32 {
+        match self {
+            Self::Small => 320,
+            Self::Large => 960,
+        }
+    }
+}
+
+/// Image mirror configuration
+#[derive(Debug, Clone)]
+pub struct ImagesConfig {
+    /// Directory with thumbnails
+    pub dir: PathBuf,
+}
+
+impl ImagesConfig {
+    /// Load config from environment variables
+    ///
+    /// Environment variables:
+    /// - `IMAGES_DIR`: Directory for thumbnails (default: "data/images")
+    pub fn from_env() -> Self {
+        let dir = std::env::var("IMAGES_DIR").unwrap_or_else(|_| DEFAULT_IMAGES_DIR.to_string());
+        Self { dir: dir.into() }
+    }
+}
+
+/// Hex-encoded SHA256 of image bytes
+#[must_use]
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Path of a thumbnail on disk
+#[must_use]
+pub fn thumbnail_path(dir: &Path, hash: &str, size: ThumbnailSize) -> PathBuf {
+    dir.join(size.as_str()).join(format!("{}.webp", hash))
+}
+
+/// URL a thumbnail is served under
+#[must_use]
+pub fn thumbnail_url(hash: &str, size: ThumbnailSize) -> String {
+    format!("{}/{}/{}.webp", IMAGES_URL_PREFIX, size.as_str(), hash)
+}
+
+/// Decode an image and encode WebP thumbnails of all sizes
+///
+/// Images smaller than a thumbnail size are not upscaled.
+/// The `image` crate only has a lossless WebP encoder, which is fine at these sizes.
+pub fn render_thumbnails(bytes: &[u8]) -> Result<Vec<(ThumbnailSize, Vec<u8>)>> {
+    let image = image::load_from_memory(bytes).context("Failed to decode image")?;
+
+    ThumbnailSize::ALL
+        .iter()
+        .map(|&size| {
+            let side = size.max_side();
+            let resized = if image.width() > side || image.height() > side {
+                image.thumbnail(side, side)
+            } else {
+                image.clone()
+            };
+
+            // WebP encoder only accepts 8-bit RGB(A)
+            let resized = if resized.color().has_alpha() {
+                DynamicImage::from(resized.to_rgba8())
+            } else {
+                DynamicImage::from(resized.to_rgb8())
+            };
+
+            let mut webp = Cursor::new(Vec::new());
+            resized
+                .write_to(&mut webp, ImageFormat::WebP)
+                .context("Failed to encode WebP thumbnail")?;
+            Ok((size, webp.into_inner()))
+        })
+        .collect()
+}
+
+/// Check if all thumbnails of an image exist on disk
+fn has_thumbnails(dir: &Path, hash: &str) -> bool {
+    ThumbnailSize::ALL
+        .iter()
+        .all(|&size| thumbnail_path(dir, hash, size).exists())
+}
+
+/// Write thumbnails via a temp file + rename, so chai-web never serves a partial file
+fn save_thumbnails(dir: &Path, hash: &str, thumbnails: &[(ThumbnailSize, Vec<u8>)]) -> Result<()> {
+    for (size, data) in thumbnails {
+        let path = thumbnail_path(dir, hash, *size);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let tmp = path.with_extension(format!("{:08x}.tmp", rand::random::<u32>()));
+        std::fs::write(&tmp, data).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to move thumbnail to {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Outcome of mirroring a single image
+enum Mirrored {
+    /// Mirrored in a previous run
+    Known,
+    /// Downloaded and converted
+    Downloaded,
+    /// Downloaded, but the same content was already mirrored from another URL
+    Duplicate,
+}
+
+/// Summary of an image mirroring run
+#[derive(Debug, Clone, Default)]
+pub struct MirrorSummary {
+    /// Images mirrored in previous runs
+    pub known: usize,
+    /// New images downloaded and converted
+    pub downloaded: usize,
+    /// Downloaded images with content already on disk
+    pub duplicates: usize,
+    /// Images that could not be downloaded or decoded (dead links)
+    pub failed: Vec<String>,
+}
+
+impl MirrorSummary {
+    /// Log the summary, listing every failed image
+    pub fn log(&self) {
+        info!(
+            "Images: {} new, {} duplicates, {} already mirrored, {} failed",
+            self.downloaded,
+            self.duplicates,
+            self.known,
+            self.failed.len()
+        );
+
+        for failure in &self.failed {
+            warn!("  x {}", failure);
+        }
+    }
+}
+
+/// Mirror remote images into the thumbnails directory
+///
+/// Images mirrored in previous runs are not downloaded again
+/// (shop CDNs put a new URL on a new image).
+pub async fn mirror_images(
+    teas: &dyn TeaRepository,
+    crawler: &Crawler,
+    config: &ImagesConfig,
+    mut urls: Vec<String>,
+) -> MirrorSummary {
+    urls.sort();
+    urls.dedup();
+
+    let mut results = stream::iter(urls)
+        .map(|url| async move {
+            let result = mirror_image(teas, crawler, config, &url).await;
+            (url, result)
+        })
+        .buffer_unordered(crawler.config().concurrency.max(1));
+
+    let mut summary = MirrorSummary::default();
+    while let Some((url, result)) = results.next().await {
+        match result {
+            Ok(Mirrored::Known) => summary.known += 1,
+            Ok(Mirrored::Downloaded) => summary.downloaded += 1,
+            Ok(Mirrored::Duplicate) => summary.duplicates += 1,
+            Err(e) => summary.failed.push(format!("{}: {:#}", url, e)),
+        }
+    }
+
+    summary
+}
+
+/// Download a single image and store its thumbnails
+async fn mirror_image(
+    teas: &dyn TeaRepository,
+    crawler: &Crawler,
+    config: &ImagesConfig,
+    url: &str,
+) -> Result<Mirrored> {
+    if let Some(hash) = teas.get_image_hash(url).await?
+        && has_thumbnails(&config.dir, &hash)
+    {
+        return Ok(Mirrored::Known);
+    }
+
+    let page = crawler.fetch_bytes(url).await?;
+    let hash = content_hash(&page.body);
+
+    let outcome = if has_thumbnails(&config.dir, &hash) {
+        Mirrored::Duplicate
+    } else {
+        // Decoding and resizing is CPU-bound
+        let bytes = page.body;
+        let thumbnails = tokio::task::spawn_blocking(move || render_thumbnails(&bytes))
+            .await
+            .context("Thumbnail task failed")??;
+        save_thumbnails(&config.dir, &hash, &thumbnails)?;
+        Mirrored::Downloaded
+    };
+
+    teas.set_image_hash(url, &hash).await?;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_locations() {
+        let hash = content_hash(b"image");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash(b"image"));
+
+        assert_eq!(
+            thumbnail_path(Path::new("data/images"), &hash, ThumbnailSize::Small),
+            PathBuf::from(format!("data/images/sm/{}.webp", hash))
+        );
+        assert_eq!(
+            thumbnail_url(&hash, ThumbnailSize::Large),
+            format!("/img/lg/{}.webp", hash)
+        );
+    }
+
+    #[test]
+    fn test_render_thumbnails() {
+        let encode = |width, height| {
+            let mut png = Cursor::new(Vec::new());
+            DynamicImage::from(image::RgbImage::new(width, height))
+                .write_to(&mut png, ImageFormat::Png)
+                .unwrap();
+            png.into_inner()
+        };
+        let sizes = |bytes: &[u8]| -> Vec<(u32, u32)> {
+            render_thumbnails(bytes)
+                .unwrap()
+                .iter()
+                .map(|(_, webp)| {
+                    let thumb = image::load_from_memory(webp).unwrap();
+                    (thumb.width(), thumb.height())
+                })
+                .collect()
+        };
+
+        // Aspect ratio is kept
+        assert_eq!(sizes(&encode(2000, 1000)), vec![(320, 160), (960, 480)]);
+        // Small images are not upscaled
+        assert_eq!(sizes(&encode(400, 200)), vec![(320, 160), (400, 200)]);
+
+        assert!(render_thumbnails(b"not an image").is_err());
+    }
+}
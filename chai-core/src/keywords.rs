@@ -0,0 +1,344 @@
+//! Keyword search primitives
+//!
+//! - Tokenization with Russian stemming (Snowball algorithm) and stop words
+//! - Field-weighted terms of a tea for the `tea_terms` index
+//! - BM25 scoring
+//! - Weighted reciprocal rank fusion of several rankings
+//!
+//! Storage and queries live in [`crate::turso`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::models::Tea;
+
+/// Term weights per field: a match in the name counts more than in the description
+const NAME_WEIGHT: f64 = 3.0;
+const COMPOSITION_WEIGHT: f64 = 2.0;
+const TAGS_WEIGHT: f64 = 2.0;
+const SERIES_WEIGHT: f64 = 1.0;
+const DESCRIPTION_WEIGHT: f64 = 1.0;
+
+/// BM25 term frequency saturation
+const BM25_K1: f64 = 1.2;
+/// BM25 document length normalization
+const BM25_B: f64 = 0.75;
+
+/// Words that carry no meaning for search, separated by spaces
+const STOP_WORDS: &str = "а без бы в во все вы да для до его ее если есть же за и из или им их к как \
+    ко ли мне мы на не нет но о об он она они от по при с со так то только ты у уже хочу что чтобы \
+    это я and for of the with";
+
+fn is_stop_word(word: &str) -> bool {
+    STOP_WORDS.split_whitespace().any(|stop| stop == word)
+}
+
+/// Split text into normalized search terms
+///
+/// Lowercases, treats `ё` as `е`, splits on anything but letters and digits
+/// (`иван-чай` is two terms), drops stop words and stems Russian words.
+#[must_use]
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .replace('ё', "е")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !is_stop_word(word))
+        .map(|word| {
+            if word.chars().all(is_cyrillic) {
+                stem_russian(word)
+            } else {
+                word.to_string()
+            }
+        })
+        .filter(|term| term.chars().count() > 1 || term.chars().all(|c| c.is_ascii_digit()))
+        .collect()
+}
+
+fn is_cyrillic(c: char) -> bool {
+    matches!(c, 'а'..='я' | 'ё')
+}
+
+/// Terms of a tea with field-weighted frequencies
+#[must_use]
+pub fn document_terms(tea: &Tea) -> BTreeMap<String, f64> {
+    let mut terms = BTreeMap::new();
+    let mut add = |text: &str, weight: f64| {
+        for term in tokenize(text) {
+            *terms.entry(term).or_insert(0.0) += weight;
+        }
+    };
+
+    if let Some(name) = &tea.name {
+        add(name, NAME_WEIGHT);
+    }
+    for ingredient in &tea.composition {
+        add(ingredient, COMPOSITION_WEIGHT);
+    }
+    for tag in &tea.search_tags {
+        add(tag, TAGS_WEIGHT);
+    }
+    if let Some(series) = &tea.series {
+        add(series, SERIES_WEIGHT);
+    }
+    if let Some(description) = &tea.description {
+        add(description, DESCRIPTION_WEIGHT);
+    }
+
+    terms
+}
+
+/// BM25 score of a term in a document
+///
+/// `tf` is the weighted term frequency, `df` the number of documents with the term.
+#[must_use]
+pub fn bm25(tf: f64, df: usize, docs: usize, doc_len: f64, avg_len: f64) -> f64 {
+    let df = df as f64;
+    let idf = ((docs as f64 - df + 0.5) / (df + 0.5) + 1.0).ln();
+    let norm = if avg_len > 0.0 {
+        doc_len / avg_len
+    } else {
+        1.0
+    };
+    idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * norm))
+}
+
+/// Fuse rankings with weighted reciprocal rank fusion
+///
+/// Each ranking is a weight and IDs ordered best first. A document gets
+/// `weight / (k + rank)` from every ranking it appears in. Scores are
+/// normalized so a document ranked first everywhere scores 1.0.
+/// Returns IDs with fused scores, best first.
+#[must_use]
+pub fn reciprocal_rank_fusion(rankings: &[(f32, Vec<String>)], k: f32) -> Vec<(String, f32)> {
+    let mut scores: HashMap<&str, f32> = HashMap::new();
+    // Order of first appearance breaks ties deterministically
+    let mut order: Vec<&str> = Vec::new();
+
+    for (weight, ids) in rankings {
+        for (rank, id) in ids.iter().enumerate() {
+            let score = scores.entry(id).or_insert_with(|| {
+                order.push(id);
+                0.0
+            });
+            *score += weight / (k + rank as f32 + 1.0);
+        }
+    }
+
+    let max: f32 = rankings.iter().map(|(weight, _)| weight / (k + 1.0)).sum();
+    let mut fused: Vec<(String, f32)> = order
+        .into_iter()
+        .map(|id| {
+            let score = scores[id];
+            (id.to_string(), if max > 0.0 { score / max } else { 0.0 })
+        })
+        .collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+    fused
+}
+
+// ============================================================================
+// Russian stemmer (Snowball)
+// ============================================================================
+
+const PERFECTIVE_GERUND_1: &[&str] = &["в", "вши", "вшись"];
+const PERFECTIVE_GERUND_2: &[&str] = &["ив", "ивши", "ившись", "ыв", "ывши", "ывшись"];
+const ADJECTIVE: &[&str] = &[
+    "ее", "ие", "ые", "ое", "ими", "ыми", "ей", "ий", "ый", "ой", "ем", "им", "ым", "ом", "его",
+    "ого", "ему", "ому", "их", "ых", "ую", "юю", "ая", "яя", "ою", "ею",
+];
+const PARTICIPLE_1: &[&str] = &["ем", "нн", "вш", "ющ", "щ"];
+const PARTICIPLE_2: &[&str] = &["ивш", "ывш", "ующ"];
+const REFLEXIVE: &[&str] = &["ся", "сь"];
+const VERB_1: &[&str] = &[
+    "ла", "на", "ете", "йте", "ли", "й", "л", "ем", "н", "ло", "но", "ет", "ют", "ны", "ть", "ешь",
+    "нно",
+];
+const VERB_2: &[&str] = &[
+    "ила", "ыла", "ена", "ейте", "уйте", "ите", "или", "ыли", "ей", "уй", "ил", "ыл", "им", "ым",
+    "ен", "ило", "ыло", "ено", "ят", "ует", "уют", "ит", "ыт", "ены", "ить", "ыть", "ишь", "ую",
+    "ю",
+];
+const NOUN: &[&str] = &[
+    "а", "ев", "ов", "ие", "ье", "е", "иями", "ями", "ами", "еи", "ии", "и", "ией", "ей", "ой",
+    "ий", "й", "иям", "ям", "ием", "ем", "ам", "ом", "о", "у", "ах", "иях", "ях", "ы", "ь", "ию",
+    "ью", "ю", "ия", "ья", "я",
+];
+const SUPERLATIVE: &[&str] = &["ейше", "ейш"];
+const DERIVATIONAL: &[&str] = &["ость", "ост"];
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'а' | 'е' | 'и' | 'о' | 'у' | 'ы' | 'э' | 'ю' | 'я')
+}
+
+/// Stem a lowercase Russian word
+#[must_use]
+pub fn stem_russian(word: &str) -> String {
+    let mut word: Vec<char> = word
+        .chars()
+        .map(|c| if c == 'ё' { 'е' } else { c })
+        .collect();
+
+    // RV: after the first vowel; R2: R1 of R1, where R1 is after the first
+    // non-vowel that follows a vowel
+    let Some(first_vowel) = word.iter().position(|&c| is_vowel(c)) else {
+        return word.into_iter().collect();
+    };
+    let rv = first_vowel + 1;
+    let r1 = region_after(&word, 0);
+    let r2 = region_after(&word, r1);
+
+    // Step 1
+    if !strip(&mut word, rv, PERFECTIVE_GERUND_1, PERFECTIVE_GERUND_2) {
+        strip(&mut word, rv, &[], REFLEXIVE);
+        if strip(&mut word, rv, &[], ADJECTIVE) {
+            strip(&mut word, rv, PARTICIPLE_1, PARTICIPLE_2);
+        } else if !strip(&mut word, rv, VERB_1, VERB_2) {
+            strip(&mut word, rv, &[], NOUN);
+        }
+    }
+
+    // Step 2
+    strip(&mut word, rv, &[], &["и"]);
+
+    // Step 3
+    strip(&mut word, r2, &[], DERIVATIONAL);
+
+    // Step 4
+    if !strip(&mut word, rv, &[], &["ь"]) {
+        strip(&mut word, rv, &[], SUPERLATIVE);
+        if word.len() > rv + 1 && word.ends_with(&['н', 'н']) {
+            word.pop();
+        }
+    }
+
+    word.into_iter().collect()
+}
+
+/// Start of the region after the first non-vowel following a vowel
+fn region_after(word: &[char], start: usize) -> usize {
+    (start + 1..word.len())
+        .find(|&i| !is_vowel(word[i]) && is_vowel(word[i - 1]))
+        .map_or(word.len(), |i| i + 1)
+}
+
+/// Remove the longest ending that lies in the region starting at `region`
+///
+/// Endings of `after_a` must follow `а` or `я` (which are kept).
+/// Returns true if an ending was removed.
+fn strip(word: &mut Vec<char>, region: usize, after_a: &[&str], endings: &[&str]) -> bool {
+    let candidates = after_a
+        .iter()
+        .map(|e| (e, true))
+        .chain(endings.iter().map(|e| (e, false)));
+
+    let mut best: Option<usize> = None;
+    for (ending, needs_a) in candidates {
+        let ending: Vec<char> = ending.chars().collect();
+        if word.len() < region + ending.len() || !word.ends_with(&ending) {
+            continue;
+        }
+        let start = word.len() - ending.len();
+        if needs_a && (start <= region || !matches!(word[start - 1], 'а' | 'я')) {
+            continue;
+        }
+        if best.is_none_or(|len| ending.len() > len) {
+            best = Some(ending.len());
+        }
+    }
+
+    match best {
+        Some(len) => {
+            word.truncate(word.len() - len);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem_russian() {
+        for (word, stem) in [
+            ("облепиха", "облепих"),
+            ("облепихой", "облепих"),
+            ("облепихи", "облепих"),
+            ("чабрец", "чабрец"),
+            ("чабрецом", "чабрец"),
+            ("ягодные", "ягодн"),
+            ("ягодный", "ягодн"),
+            ("мята", "мят"),
+            ("мятой", "мят"),
+            ("согревающий", "согрева"),
+            ("сладость", "сладост"),
+            ("ёлка", "елк"),
+            ("чай", "ча"),
+            ("чаи", "ча"),
+        ] {
+            assert_eq!(stem_russian(word), stem, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Иван-чай с чабрецом и Earl Grey 2024"),
+            vec!["ива", "ча", "чабрец", "earl", "grey", "2024"]
+        );
+        assert_eq!(
+            tokenize("Иван-чай с чабрецом"),
+            tokenize("иван чай, чабрец")
+        );
+        assert!(tokenize("и в с, не - да").is_empty());
+    }
+
+    #[test]
+    fn test_document_terms() {
+        let mut tea = Tea::new("https://example.com/a");
+        tea.name = Some("Иван-чай с облепихой".to_string());
+        tea.composition = vec!["иван-чай".to_string(), "облепиха".to_string()];
+        tea.description = Some("Ягоды облепихи".to_string());
+
+        let terms = document_terms(&tea);
+        assert_eq!(
+            terms["облепих"],
+            NAME_WEIGHT + COMPOSITION_WEIGHT + DESCRIPTION_WEIGHT
+        );
+        assert_eq!(terms["ива"], NAME_WEIGHT + COMPOSITION_WEIGHT);
+        assert!(!terms.contains_key("с"));
+    }
+
+    #[test]
+    fn test_bm25() {
+        // Rare terms score higher than common ones
+        assert!(bm25(1.0, 1, 100, 10.0, 10.0) > bm25(1.0, 50, 100, 10.0, 10.0));
+        // More occurrences score higher, with saturation
+        let once = bm25(1.0, 5, 100, 10.0, 10.0);
+        let twice = bm25(2.0, 5, 100, 10.0, 10.0);
+        assert!(twice > once && twice < 2.0 * once);
+        // Shorter documents score higher
+        assert!(bm25(1.0, 5, 100, 5.0, 10.0) > bm25(1.0, 5, 100, 20.0, 10.0));
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let ids = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let fused = reciprocal_rank_fusion(
+            &[(1.0, ids(&["a", "b", "c"])), (1.0, ids(&["b", "d"]))],
+            60.0,
+        );
+        let order: Vec<&str> = fused.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["b", "a", "d", "c"]);
+        assert!(fused[0].1 < 1.0 && fused[0].1 > fused[1].1);
+
+        // First in every ranking scores 1.0
+        let fused = reciprocal_rank_fusion(&[(1.0, ids(&["a"])), (2.0, ids(&["a"]))], 60.0);
+        assert!((fused[0].1 - 1.0).abs() < 1e-6);
+
+        // Weights decide between rankings
+        let fused = reciprocal_rank_fusion(&[(0.2, ids(&["a"])), (1.0, ids(&["b"]))], 60.0);
+        assert_eq!(fused[0].0, "b");
+    }
+}
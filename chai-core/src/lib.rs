@@ -7,26 +7,51 @@ pub mod ai;
 #[cfg(feature = "server")]
 pub mod auth;
 #[cfg(feature = "server")]
+pub mod backup;
+#[cfg(feature = "server")]
 pub mod cache;
 #[cfg(feature = "server")]
 pub mod config;
 #[cfg(feature = "server")]
+pub mod crawler;
+#[cfg(feature = "server")]
 pub mod embeddings;
 #[cfg(feature = "server")]
+pub mod facets;
+#[cfg(feature = "server")]
+pub mod filters;
+#[cfg(feature = "server")]
 pub mod http;
 #[cfg(feature = "server")]
+pub mod images;
+#[cfg(feature = "server")]
+pub mod keywords;
+#[cfg(feature = "server")]
+pub mod migrations;
+#[cfg(feature = "server")]
 pub mod openrouter;
 #[cfg(feature = "server")]
+pub mod quantization;
+#[cfg(feature = "server")]
+pub mod repository;
+#[cfg(feature = "server")]
+pub mod robots;
+#[cfg(feature = "server")]
+pub mod samples;
+#[cfg(feature = "server")]
 pub mod scraper;
 #[cfg(feature = "server")]
 pub mod tea_utils;
 #[cfg(feature = "server")]
 pub mod turso;
+#[cfg(feature = "server")]
+pub mod vector_index;
 
 // Re-export commonly used types
 pub use models::{
-    AIResponse, LLMResponse, PriceVariant, SearchResult, Tea, TeaCard, generate_point_id,
-    generate_tea_id,
+    AIResponse, CatalogFacets, Currency, FacetCount, FilterOptions, LLMResponse, Money,
+    PriceBucket, PriceVariant, SearchResult, Tea, TeaCard, TeaLifecycle, TeaStatus,
+    generate_point_id, generate_tea_id, parse_grams,
 };
 
 #[cfg(feature = "server")]
@@ -36,4 +61,8 @@ pub use cache::CacheStats;
 #[cfg(feature = "server")]
 pub use config::Config;
 #[cfg(feature = "server")]
-pub use turso::{CacheStats as TursoCacheStats, DatabaseStats, DbConfig, SearchFilters};
+pub use filters::{Filter, SearchFilters};
+#[cfg(feature = "server")]
+pub use repository::{CacheRepository, MemoryRepository, TeaRepository, UserRepository};
+#[cfg(feature = "server")]
+pub use turso::{CacheStats as TursoCacheStats, DatabaseStats, DbConfig, TursoRepository};
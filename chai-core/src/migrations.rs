@@ -0,0 +1,585 @@
+//! Versioned schema migrations
+//!
+//! Migrations are SQL files in `chai-core/migrations`, embedded into the binary
+//! and applied in order. Every applied migration is recorded in `schema_migrations`
+//! with a checksum, so an edited migration is detected instead of silently skipped.
+//!
+//! SQL may use placeholders filled from [`DbConfig`]:
+//! - `{vector_size}`: embedding dimension
+//! - `{default_shop}`: shop of rows created before multi-shop support
+//! - `{embedding_model}`: model of embeddings stored before multi-model support
+//!   (inside a string literal, quotes are escaped)
+//!
+//! Checksums are computed before the placeholders are filled,
+//! so a different `VECTOR_SIZE` doesn't look like an edited migration.
+//!
+//! Some migrations need data derived in Rust afterwards (a [`Rebuild`]). The
+//! step is recorded in `pending_rebuilds` in the migration's transaction and
+//! removed with [`finish_rebuild`] once it succeeded, so a step that failed
+//! runs again on the next [`TursoRepository::migrate`](crate::turso::TursoRepository::migrate).
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use tracing::info;
+use turso::Connection;
+
+use crate::models::DEFAULT_SHOP;
+use crate::turso::DbConfig;
+
+/// Embedded migration
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    sql: &'static str,
+    /// Data derived after the migration is applied
+    pub rebuild: Option<Rebuild>,
+}
+
+/// Data derived in Rust after a migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rebuild {
+    /// Index keywords of existing teas into `tea_terms`
+    KeywordIndex,
+    /// Convert existing embeddings to `VECTOR_STORAGE`
+    EmbeddingStorage,
+    /// Fill typed tea columns and `tea_ingredients` from `tea_data`
+    TeaColumns,
+}
+
+impl Rebuild {
+    /// Name stored in `pending_rebuilds`
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rebuild::KeywordIndex => "keyword_index",
+            Rebuild::EmbeddingStorage => "embedding_storage",
+            Rebuild::TeaColumns => "tea_columns",
+        }
+    }
+}
+
+impl Migration {
+    /// Hex-encoded SHA256 of the SQL template
+    #[must_use]
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.sql.as_bytes()))
+    }
+
+    /// SQL statements with placeholders filled in
+    fn statements(&self, config: &DbConfig) -> Vec<String> {
+        let sql: String = self
+            .sql
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("--"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .replace("{vector_size}", &config.vector_size.to_string())
+            .replace("{default_shop}", DEFAULT_SHOP)
+            .replace(
+                "{embedding_model}",
+                &config.embedding_model.replace('\'', "''"),
+            );
+
+        sql.split(';')
+            .map(str::trim)
+            .filter(|statement| !statement.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// All migrations, ordered by version
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../migrations/0001_initial.sql"),
+        rebuild: None,
+    },
+    Migration {
+        version: 2,
+        name: "keyword_index",
+        sql: include_str!("../migrations/0002_keyword_index.sql"),
+        rebuild: Some(Rebuild::KeywordIndex),
+    },
+    Migration {
+        version: 3,
+        name: "vector_index",
+        sql: include_str!("../migrations/0003_vector_index.sql"),
+        rebuild: None,
+    },
+    Migration {
+        version: 4,
+        name: "embedding_quantization",
+        sql: include_str!("../migrations/0004_embedding_quantization.sql"),
+        rebuild: Some(Rebuild::EmbeddingStorage),
+    },
+    Migration {
+        version: 5,
+        name: "tea_embeddings",
+        sql: include_str!("../migrations/0005_tea_embeddings.sql"),
+        rebuild: None,
+    },
+    Migration {
+        version: 6,
+        name: "tea_lifecycle",
+        sql: include_str!("../migrations/0006_tea_lifecycle.sql"),
+        rebuild: None,
+    },
+    Migration {
+        version: 7,
+        name: "tea_columns",
+        sql: include_str!("../migrations/0007_tea_columns.sql"),
+        rebuild: Some(Rebuild::TeaColumns),
+    },
+];
+
+/// Latest schema version known to this binary
+#[must_use]
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// State of a migration in the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    /// Applied at the given UNIX timestamp
+    Applied(i64),
+    /// Applied, but the embedded SQL has changed since
+    Modified(i64),
+    /// Not applied yet
+    Pending,
+    /// Applied by a newer binary
+    Unknown(i64),
+}
+
+/// Migration with its state
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: u32,
+    pub name: String,
+    pub state: MigrationState,
+}
+
+/// Schema state of a database
+#[derive(Debug, Clone, Default)]
+pub struct SchemaStatus {
+    /// Known and unknown migrations, ordered by version
+    pub migrations: Vec<MigrationStatus>,
+    /// Declared dimension of `teas.embedding` (None before the first migration
+    /// and after embeddings moved to `tea_embeddings`)
+    pub vector_size: Option<usize>,
+    /// Rebuilds of applied migrations that haven't succeeded yet
+    pub rebuilds: Vec<Rebuild>,
+}
+
+impl SchemaStatus {
+    /// Highest applied version (0 for an empty database)
+    #[must_use]
+    pub fn current_version(&self) -> u32 {
+        self.migrations
+            .iter()
+            .filter(|m| m.state != MigrationState::Pending)
+            .map(|m| m.version)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Migrations waiting to be applied
+    pub fn pending(&self) -> impl Iterator<Item = &MigrationStatus> {
+        self.migrations
+            .iter()
+            .filter(|m| m.state == MigrationState::Pending)
+    }
+
+    /// Database was migrated by a newer binary
+    #[must_use]
+    pub fn is_newer(&self) -> bool {
+        self.current_version() > latest_version()
+    }
+
+    /// Check that this binary can work with the database
+    pub fn check(&self) -> Result<()> {
+        if self.is_newer() {
+            anyhow::bail!(
+                "Database schema version {} is newer than {} supported by this binary, upgrade chai",
+                self.current_version(),
+                latest_version()
+            );
+        }
+
+        if let Some(modified) = self
+            .migrations
+            .iter()
+            .find(|m| matches!(m.state, MigrationState::Modified(_)))
+        {
+            anyhow::bail!(
+                "Migration {} ({}) was changed after it was applied, add a new migration instead",
+                modified.version,
+                modified.name
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Create the migrations tables if they're missing
+async fn ensure_migrations_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )
+        "#,
+        (),
+    )
+    .await
+    .context("Failed to create schema_migrations table")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pending_rebuilds (name TEXT PRIMARY KEY)",
+        (),
+    )
+    .await
+    .context("Failed to create pending_rebuilds table")?;
+
+    Ok(())
+}
+
+/// Rebuilds recorded by applied migrations and not finished yet, in migration order
+pub async fn pending_rebuilds(conn: &Connection) -> Result<Vec<Rebuild>> {
+    ensure_migrations_table(conn).await?;
+
+    let mut rows = conn
+        .query("SELECT name FROM pending_rebuilds", ())
+        .await
+        .context("Failed to query pending_rebuilds")?;
+    let mut names = Vec::new();
+    while let Some(row) = rows.next().await? {
+        names.push(row.get::<String>(0)?);
+    }
+
+    Ok(MIGRATIONS
+        .iter()
+        .filter_map(|m| m.rebuild)
+        .filter(|rebuild| names.iter().any(|name| name == rebuild.as_str()))
+        .collect())
+}
+
+/// Mark a rebuild as done
+pub async fn finish_rebuild(conn: &Connection, rebuild: Rebuild) -> Result<()> {
+    conn.execute(
+        "DELETE FROM pending_rebuilds WHERE name = ?",
+        [rebuild.as_str()],
+    )
+    .await
+    .with_context(|| format!("Failed to finish rebuild {}", rebuild.as_str()))?;
+    Ok(())
+}
+
+/// Read the schema state of a database
+pub async fn status(conn: &Connection) -> Result<SchemaStatus> {
+    ensure_migrations_table(conn).await?;
+
+    let mut rows = conn
+        .query(
+            "SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version",
+            (),
+        )
+        .await
+        .context("Failed to query schema_migrations")?;
+
+    let mut applied = Vec::new();
+    while let Some(row) = rows.next().await? {
+        applied.push((
+            row.get::<i64>(0)? as u32,
+            row.get::<String>(1)?,
+            row.get::<String>(2)?,
+            row.get::<i64>(3)?,
+        ));
+    }
+
+    let mut migrations: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let state = match applied.iter().find(|(v, ..)| *v == migration.version) {
+                Some((_, _, checksum, at)) if *checksum == migration.checksum() => {
+                    MigrationState::Applied(*at)
+                }
+                Some((_, _, _, at)) => MigrationState::Modified(*at),
+                None => MigrationState::Pending,
+            };
+            MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state,
+            }
+        })
+        .collect();
+
+    migrations.extend(
+        applied
+            .into_iter()
+            .filter(|(v, ..)| MIGRATIONS.iter().all(|m| m.version != *v))
+            .map(|(version, name, _, at)| MigrationStatus {
+                version,
+                name,
+                state: MigrationState::Unknown(at),
+            }),
+    );
+    migrations.sort_by_key(|m| m.version);
+
+    Ok(SchemaStatus {
+        migrations,
+        vector_size: embedding_size(conn).await?,
+        rebuilds: pending_rebuilds(conn).await?,
+    })
+}
+
+/// Declared dimension of `teas.embedding`
+async fn embedding_size(conn: &Connection) -> Result<Option<usize>> {
+    let mut rows = conn
+        .query("PRAGMA table_info(teas)", ())
+        .await
+        .context("Failed to inspect teas table")?;
+
+    while let Some(row) = rows.next().await? {
+        if row.get::<String>(1)? == "embedding" {
+            let column_type = row.get::<String>(2)?;
+            return Ok(column_type
+                .trim_start_matches("F32_BLOB(")
+                .trim_end_matches(')')
+                .parse()
+                .ok());
+        }
+    }
+
+    Ok(None)
+}
+
+/// Apply pending migrations
+///
+/// Fails without changes if the database is newer than this binary
+/// or an applied migration was edited. Returns the applied versions.
+pub async fn migrate(conn: &Connection, config: &DbConfig) -> Result<Vec<u32>> {
+    let status = status(conn).await?;
+    status.check()?;
+
+    if let Some(size) = status.vector_size
+        && size != config.vector_size
+    {
+        anyhow::bail!(
+            "teas.embedding has {} dimensions, but VECTOR_SIZE is {}",
+            size,
+            config.vector_size
+        );
+    }
+
+    let pending: Vec<u32> = status.pending().map(|m| m.version).collect();
+    if pending.first() == Some(&1) {
+        upgrade_legacy_schema(conn).await?;
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| pending.contains(&m.version)) {
+        apply(conn, migration, config).await.with_context(|| {
+            format!(
+                "Migration {} ({}) failed",
+                migration.version, migration.name
+            )
+        })?;
+        info!(
+            "Applied migration {} ({})",
+            migration.version, migration.name
+        );
+    }
+
+    Ok(pending)
+}
+
+/// Columns added by the old `init_database` before versioned migrations
+const LEGACY_COLUMNS: &[(&str, &str, &str)] = &[
+    ("html_cache", "etag", "TEXT"),
+    ("html_cache", "last_modified", "TEXT"),
+    ("html_cache", "status", "INTEGER"),
+    ("html_cache", "content_hash", "TEXT"),
+    ("teas", "shop", "TEXT NOT NULL DEFAULT '{default_shop}'"),
+];
+
+/// Bring tables of a database created before migrations to the initial schema
+///
+/// The initial migration only creates missing tables, so columns added
+/// to existing tables over time are added here.
+async fn upgrade_legacy_schema(conn: &Connection) -> Result<()> {
+    for (table, column, definition) in LEGACY_COLUMNS {
+        let mut rows = conn
+            .query(&format!("PRAGMA table_info({})", table), ())
+            .await
+            .with_context(|| format!("Failed to inspect {} table", table))?;
+
+        let mut columns = Vec::new();
+        while let Some(row) = rows.next().await? {
+            columns.push(row.get::<String>(1)?);
+        }
+        // Missing table is created by the initial migration
+        if columns.is_empty() || columns.iter().any(|c| c == column) {
+            continue;
+        }
+
+        let definition = definition.replace("{default_shop}", DEFAULT_SHOP);
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            (),
+        )
+        .await
+        .with_context(|| format!("Failed to add {}.{} column", table, column))?;
+
+        info!("Added column {}.{}", table, column);
+    }
+
+    Ok(())
+}
+
+/// Apply a single migration in a transaction
+async fn apply(conn: &Connection, migration: &Migration, config: &DbConfig) -> Result<()> {
+    conn.execute("BEGIN", ()).await?;
+
+    let result = async {
+        for statement in migration.statements(config) {
+            conn.execute(&statement, ())
+                .await
+                .with_context(|| format!("Failed statement: {}", statement))?;
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, checksum, applied_at) VALUES (?, ?, ?, ?)",
+            (
+                i64::from(migration.version),
+                migration.name,
+                migration.checksum(),
+                now,
+            ),
+        )
+        .await
+        .context("Failed to record migration")?;
+
+        if let Some(rebuild) = migration.rebuild {
+            conn.execute(
+                "INSERT OR IGNORE INTO pending_rebuilds (name) VALUES (?)",
+                [rebuild.as_str()],
+            )
+            .await
+            .context("Failed to record pending rebuild")?;
+        }
+
+        Ok::<(), anyhow::Error>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            conn.execute("COMMIT", ()).await?;
+            Ok(())
+        }
+        Err(e) => {
+            conn.execute("ROLLBACK", ()).await.ok();
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DbConfig {
+        DbConfig {
+            path: String::new(),
+            vector_size: 1024,
+            cache_snapshots: 1,
+            vector_search: Default::default(),
+            vector_probes: 1,
+            vector_storage: Default::default(),
+            embedding_model: "vendor/model's-v1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_migrations_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
+        assert_eq!(latest_version(), MIGRATIONS.len() as u32);
+    }
+
+    #[test]
+    fn test_statements() {
+        let statements = MIGRATIONS[0].statements(&config());
+        assert!(
+            statements
+                .iter()
+                .all(|s| !s.contains("--") && !s.contains('{'))
+        );
+        assert!(
+            statements
+                .iter()
+                .any(|s| s.contains("embedding F32_BLOB(1024)"))
+        );
+
+        let statements = MIGRATIONS[4].statements(&config());
+        assert!(
+            statements
+                .iter()
+                .any(|s| s.contains("SELECT id, 'vendor/model''s-v1', 1024, content_hash"))
+        );
+
+        // Checksum depends only on the template
+        assert_eq!(MIGRATIONS[0].checksum(), MIGRATIONS[0].checksum());
+        assert_eq!(MIGRATIONS[0].checksum().len(), 64);
+    }
+
+    #[test]
+    fn test_schema_status() {
+        let migration = |version, state| MigrationStatus {
+            version,
+            name: format!("m{}", version),
+            state,
+        };
+
+        let empty = SchemaStatus {
+            migrations: vec![migration(1, MigrationState::Pending)],
+            vector_size: None,
+            rebuilds: Vec::new(),
+        };
+        assert_eq!(empty.current_version(), 0);
+        assert_eq!(empty.pending().count(), 1);
+        assert!(empty.check().is_ok());
+
+        let newer = SchemaStatus {
+            migrations: vec![
+                migration(1, MigrationState::Applied(1)),
+                migration(latest_version() + 1, MigrationState::Unknown(2)),
+            ],
+            vector_size: Some(1024),
+            rebuilds: Vec::new(),
+        };
+        assert!(newer.is_newer());
+        assert!(newer.check().is_err());
+
+        let modified = SchemaStatus {
+            migrations: vec![migration(1, MigrationState::Modified(1))],
+            vector_size: Some(1024),
+            rebuilds: Vec::new(),
+        };
+        assert!(!modified.is_newer());
+        assert!(modified.check().is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 /// Generates a short unique ID from URL (first 8 characters of UUID v5)
@@ -13,6 +14,13 @@ pub fn generate_tea_id(url: &str) -> String {
     uuid.to_string()[..8].to_string()
 }
 
+/// Shop ID assumed for teas stored before multi-shop support
+pub const DEFAULT_SHOP: &str = "beliyles";
+
+fn default_shop() -> String {
+    DEFAULT_SHOP.to_string()
+}
+
 /// Generates a full UUID v5 from URL for use as database primary key
 ///
 /// This ensures uniqueness for database storage. The short [`generate_tea_id`]
@@ -28,6 +36,9 @@ pub struct Tea {
     /// Уникальный ID (первые 8 символов UUID, генерируется из URL)
     pub id: String,
     pub url: String,
+    /// Магазин, из которого получен чай (ID скрапера)
+    #[serde(default = "default_shop")]
+    pub shop: String,
     pub name: Option<String>,
     pub price: Option<String>,
 
@@ -45,6 +56,23 @@ pub struct Tea {
     pub description: Option<String>,
     pub series: Option<String>,
 
+    // Характеристики
+    /// Все характеристики товара как есть (название -> значение)
+    #[serde(default)]
+    pub characteristics: BTreeMap<String, String>,
+    /// Регион происхождения
+    #[serde(default)]
+    pub origin: Option<String>,
+    /// Год урожая
+    #[serde(default)]
+    pub harvest_year: Option<u16>,
+    /// Тип листа (крупнолистовой, гранулированный, ...)
+    #[serde(default)]
+    pub leaf_type: Option<String>,
+    /// Степень ферментации
+    #[serde(default)]
+    pub fermentation: Option<String>,
+
     // Варианты и хранение
     #[serde(default)]
     pub volume_options: Vec<String>,
@@ -78,24 +106,316 @@ impl Tea {
         Self {
             id: generate_tea_id(url),
             url: url.to_string(),
+            shop: default_shop(),
             ..Default::default()
         }
     }
+
+    /// Цена товара (основная, из `price`)
+    #[must_use]
+    pub fn price_amount(&self) -> Option<Money> {
+        self.price.as_deref().and_then(Money::parse)
+    }
+
+    /// Самый дешёвый вариант упаковки в наличии
+    #[must_use]
+    pub fn cheapest_in_stock_variant(&self) -> Option<&PriceVariant> {
+        self.price_variants
+            .iter()
+            .filter(|variant| variant.in_stock())
+            .filter_map(|variant| Some((variant.amount()?, variant)))
+            .min_by_key(|(amount, _)| *amount)
+            .map(|(_, variant)| variant)
+    }
+
+    /// Лучшая цена за 100 г: по вариантам в наличии (по всем, если в наличии нет ни одного),
+    /// у чая без вариантов — из `price` и `weight`
+    #[must_use]
+    pub fn price_per_100g(&self) -> Option<Money> {
+        if self.price_variants.is_empty() {
+            let grams = parse_grams(self.weight.as_deref()?)?;
+            return self.price_amount()?.per_100g(grams);
+        }
+
+        let any_in_stock = self.price_variants.iter().any(PriceVariant::in_stock);
+        self.price_variants
+            .iter()
+            .filter(|variant| variant.in_stock() || !any_in_stock)
+            .filter_map(PriceVariant::price_per_100g)
+            .min()
+    }
 }
 
 /// Вариант цены/упаковки
+///
+/// Поля хранятся как на сайте, разобранные значения дают методы.
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct PriceVariant {
     pub packaging: String,
     pub price: String,
+    /// Остаток на складе
     pub quantity: String,
 }
 
-/// Результат векторного поиска
+impl PriceVariant {
+    /// Цена варианта
+    #[must_use]
+    pub fn amount(&self) -> Option<Money> {
+        Money::parse(&self.price)
+    }
+
+    /// Остаток на складе (None, если магазин его не указал)
+    #[must_use]
+    pub fn stock(&self) -> Option<u32> {
+        self.quantity.trim().parse().ok()
+    }
+
+    /// Есть на складе
+    #[must_use]
+    pub fn in_stock(&self) -> bool {
+        self.stock().is_some_and(|stock| stock > 0)
+    }
+
+    /// Вес упаковки в граммах ("Крафт-пакет 50 г")
+    #[must_use]
+    pub fn grams(&self) -> Option<f64> {
+        parse_grams(&self.packaging)
+    }
+
+    /// Цена за 100 г
+    #[must_use]
+    pub fn price_per_100g(&self) -> Option<Money> {
+        self.amount()?.per_100g(self.grams()?)
+    }
+}
+
+/// Валюта цены
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    /// Цены магазинов без указания валюты в рублях
+    #[default]
+    Rub,
+    Usd,
+    Eur,
+}
+
+impl Currency {
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Rub => "₽",
+            Self::Usd => "$",
+            Self::Eur => "€",
+        }
+    }
+
+    /// Валюта, упомянутая в тексте цены
+    fn detect(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        if text.contains('$') || text.contains("usd") {
+            Some(Self::Usd)
+        } else if text.contains('€') || text.contains("eur") {
+            Some(Self::Eur)
+        } else if text.contains('₽') || text.contains("руб") || text.contains("rub") {
+            Some(Self::Rub)
+        } else {
+            None
+        }
+    }
+}
+
+/// Денежная сумма без ошибок округления: целое число сотых долей валюты
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Money {
+    /// Сумма в копейках (центах)
+    pub minor: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    /// Разбирает число в начале текста цены ("450.0000", "1 200,50 ₽", "$12.5")
+    ///
+    /// Знаки после второго округляются, валюта без указания — рубли.
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Self> {
+        let number: String = text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .skip_while(|c| matches!(c, '$' | '€' | '₽'))
+            .map(|c| if c == ',' { '.' } else { c })
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+
+        let (units, fraction) = number.split_once('.').unwrap_or((&number, ""));
+        if units.is_empty() || fraction.contains('.') {
+            return None;
+        }
+        let units: i64 = units.parse().ok()?;
+        let digit = |i: usize| {
+            fraction
+                .as_bytes()
+                .get(i)
+                .map_or(0, |digit| i64::from(digit - b'0'))
+        };
+        let cents = digit(0) * 10 + digit(1) + i64::from(digit(2) >= 5);
+
+        Some(Self {
+            minor: units.checked_mul(100)?.checked_add(cents)?,
+            currency: Currency::detect(text).unwrap_or_default(),
+        })
+    }
+
+    /// Сумма в единицах валюты
+    #[must_use]
+    pub fn amount(&self) -> f64 {
+        self.minor as f64 / 100.0
+    }
+
+    /// Цена 100 г, если эта сумма стоит `grams` граммов
+    #[must_use]
+    pub fn per_100g(&self, grams: f64) -> Option<Self> {
+        (grams > 0.0).then(|| Self {
+            minor: (self.minor as f64 * 100.0 / grams).round() as i64,
+            currency: self.currency,
+        })
+    }
+}
+
+impl std::fmt::Display for Money {
+    /// "450 ₽", "1200,50 ₽"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (units, cents) = (self.minor / 100, self.minor % 100);
+        if cents == 0 {
+            write!(f, "{} {}", units, self.currency.symbol())
+        } else {
+            write!(f, "{},{:02} {}", units, cents, self.currency.symbol())
+        }
+    }
+}
+
+/// Вес в граммах из текста упаковки или веса ("Пакет 50 г", "0,5 кг", "100 g")
+///
+/// Берётся первое число, за которым идёт единица веса.
+#[must_use]
+pub fn parse_grams(text: &str) -> Option<f64> {
+    let text = text.to_lowercase();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) =
+            chars.next_if(|(_, c)| c.is_ascii_digit() || matches!(c, '.' | ','))
+        {
+            end = i + c.len_utf8();
+        }
+        let Ok(value) = text[start..end].replace(',', ".").parse::<f64>() else {
+            continue;
+        };
+
+        let unit: String = text[end..]
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_alphabetic())
+            .collect();
+        let scale = match unit.as_str() {
+            "g" | "gr" | "gram" | "grams" | "г" | "гр" | "грамм" | "грамма" | "граммов" => {
+                1.0
+            }
+            "kg" | "кг" | "килограмм" => 1000.0,
+            _ => continue,
+        };
+        return Some(value * scale);
+    }
+    None
+}
+
+/// Состояние чая в каталоге
+///
+/// Товары, пропавшие с сайта магазина, не удаляются, а становятся `Discontinued`:
+/// они не показываются в поиске по умолчанию, но находятся по ID. `Hidden` убирает
+/// чай из поиска вручную, синхронизация его не возвращает.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TeaStatus {
+    /// Есть на сайте магазина
+    #[default]
+    Active,
+    /// Пропал с сайта магазина
+    Discontinued,
+    /// Скрыт вручную
+    Hidden,
+}
+
+impl TeaStatus {
+    /// Значение колонки `teas.status`
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Discontinued => "discontinued",
+            Self::Hidden => "hidden",
+        }
+    }
+}
+
+impl std::fmt::Display for TeaStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TeaStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "active" => Ok(Self::Active),
+            "discontinued" => Ok(Self::Discontinued),
+            "hidden" => Ok(Self::Hidden),
+            _ => Err(format!(
+                "Unknown tea status '{}' (active, discontinued, hidden)",
+                s
+            )),
+        }
+    }
+}
+
+/// Жизненный цикл чая в каталоге (UNIX-время)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeaLifecycle {
+    pub status: TeaStatus,
+    /// Когда товар впервые найден на сайте
+    pub first_seen: Option<i64>,
+    /// Когда товар последний раз найден на сайте
+    pub last_seen: Option<i64>,
+    /// Когда товар пропал с сайта (None для активных)
+    pub discontinued_at: Option<i64>,
+}
+
+/// Результат поиска
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub tea: Tea,
+    /// Итоговая оценка: косинусное сходство, BM25 или нормированная оценка слияния рангов
     pub score: f32,
+    /// Косинусное сходство с запросом (None, если у чая нет эмбеддинга)
+    #[serde(default)]
+    pub vector_score: Option<f32>,
+    /// BM25 по ключевым словам (None, если слова запроса не найдены)
+    #[serde(default)]
+    pub keyword_score: Option<f32>,
+    /// Пробник этого чая в наличии (из связи `tea_samples`)
+    #[serde(default)]
+    pub sample_in_stock: bool,
+    /// Состояние в каталоге (снятые с продажи находятся только по запросу)
+    #[serde(default)]
+    pub status: TeaStatus,
 }
 
 /// Карточка чая для UI (упрощённая версия для фронтенда)
@@ -111,8 +431,23 @@ pub struct TeaCard {
     // Обогащённые данные из базы данных
     #[serde(default)]
     pub price: Option<String>,
+    /// Разобранная цена (`price` остаётся для отображения как есть)
+    #[serde(default)]
+    pub price_amount: Option<Money>,
+    /// Самый дешёвый вариант упаковки в наличии
+    #[serde(default)]
+    pub cheapest_variant: Option<PriceVariant>,
+    /// Лучшая цена за 100 г, для сортировки по выгодности
+    #[serde(default)]
+    pub price_per_100g: Option<Money>,
     #[serde(default)]
     pub image_url: Option<String>,
+    /// Локальная миниатюра для карточки (`/img/sm/...`), если изображение скачано
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    /// Локальная миниатюра для модального окна (`/img/lg/...`)
+    #[serde(default)]
+    pub thumbnail_large_url: Option<String>,
     #[serde(default)]
     pub in_stock: bool,
     #[serde(default)]
@@ -121,6 +456,12 @@ pub struct TeaCard {
     pub sample_url: Option<String>,
     #[serde(default)]
     pub sample_in_stock: bool,
+    /// ID магазина
+    #[serde(default)]
+    pub shop: String,
+    /// Название магазина для отображения
+    #[serde(default)]
+    pub shop_name: String,
 
     // Дополнительные поля для детальной карточки
     #[serde(default)]
@@ -153,3 +494,168 @@ pub struct AIResponse {
     pub answer: String,
     pub tea_cards: Vec<TeaCard>,
 }
+
+/// Фильтры поиска в сериализуемом виде (выбор пользователя в веб-клиенте и CLI)
+///
+/// На сервере превращаются в `SearchFilters`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterOptions {
+    /// Только в наличии
+    pub in_stock: bool,
+    /// В наличии сам товар или его пробник
+    pub in_stock_or_sample: bool,
+    pub exclude_samples: bool,
+    pub exclude_sets: bool,
+    /// Любая из серий
+    pub series: Vec<String>,
+    /// Любой из магазинов
+    pub shops: Vec<String>,
+    /// Характеристики (название, значение), все должны совпасть
+    pub characteristics: Vec<(String, String)>,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    /// Ингредиенты, которые должны быть в составе
+    pub with_ingredients: Vec<String>,
+    /// Ингредиенты, которых не должно быть в составе
+    pub without_ingredients: Vec<String>,
+    /// Показывать и снятые с продажи
+    pub include_discontinued: bool,
+    /// Сначала самые выгодные по цене за 100 г (в поиске)
+    pub best_value: bool,
+}
+
+/// Значение фасета и число чаёв с ним
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Ценовой диапазон фасета: от `min` включительно до `max` не включительно
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceBucket {
+    /// None для первого диапазона
+    pub min: Option<f64>,
+    /// None для последнего диапазона
+    pub max: Option<f64>,
+    pub count: usize,
+}
+
+/// Фасеты каталога: сколько подходящих под фильтры чаёв у каждого значения
+///
+/// Значения отсортированы по убыванию числа чаёв, чтобы интерфейс мог показать
+/// «Ягодные (42)» рядом с каждым вариантом.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CatalogFacets {
+    /// Всего подходящих чаёв
+    pub total: usize,
+    pub in_stock: usize,
+    pub out_of_stock: usize,
+    /// С пробником в наличии
+    pub sample_in_stock: usize,
+    /// Пробники
+    pub samples: usize,
+    /// Наборы
+    pub sets: usize,
+    pub series: Vec<FacetCount>,
+    /// Ингредиенты состава (в нижнем регистре)
+    pub ingredients: Vec<FacetCount>,
+    /// Все ценовые диапазоны по возрастанию, чаи без цены не учитываются
+    pub price_buckets: Vec<PriceBucket>,
+    /// Значения по названию характеристики
+    pub characteristics: BTreeMap<String, Vec<FacetCount>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rub(minor: i64) -> Money {
+        Money {
+            minor,
+            currency: Currency::Rub,
+        }
+    }
+
+    fn variant(packaging: &str, price: &str, quantity: &str) -> PriceVariant {
+        PriceVariant {
+            packaging: packaging.to_string(),
+            price: price.to_string(),
+            quantity: quantity.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_money() {
+        assert_eq!(Money::parse("450.0000"), Some(rub(45000)));
+        assert_eq!(Money::parse(" 1 200,50 ₽"), Some(rub(120050)));
+        assert_eq!(Money::parse("99.995"), Some(rub(10000)));
+        assert_eq!(Money::parse("300 руб."), Some(rub(30000)));
+        assert_eq!(
+            Money::parse("$12.5"),
+            Some(Money {
+                minor: 1250,
+                currency: Currency::Usd
+            })
+        );
+        assert_eq!(Money::parse("цена"), None);
+        assert_eq!(Money::parse(".5"), None);
+        assert_eq!(Money::parse("1.2.3"), None);
+        assert_eq!(Money::parse(""), None);
+
+        assert_eq!(rub(45000).to_string(), "450 ₽");
+        assert_eq!(rub(120050).to_string(), "1200,50 ₽");
+        assert_eq!(rub(45000).per_100g(50.0), Some(rub(90000)));
+        assert_eq!(rub(45000).per_100g(0.0), None);
+    }
+
+    #[test]
+    fn test_parse_grams() {
+        assert_eq!(parse_grams("Крафт-пакет 50 г"), Some(50.0));
+        assert_eq!(parse_grams("Жестяная банка 100г"), Some(100.0));
+        assert_eq!(parse_grams("Пакет 0,5 кг"), Some(500.0));
+        assert_eq!(parse_grams("100 g"), Some(100.0));
+        assert_eq!(parse_grams("2 шт по 25 граммов"), Some(25.0));
+        assert_eq!(parse_grams("Пакет"), None);
+        assert_eq!(parse_grams("3 шт"), None);
+    }
+
+    #[test]
+    fn test_tea_prices() {
+        let mut tea = Tea {
+            price: Some("450.0000".to_string()),
+            price_variants: vec![
+                variant("Жестяная банка 100 г", "890.0000", "0"),
+                variant("Крафт-пакет 50 г", "450.0000", "12"),
+                variant("Пакет 200 г", "1500", "3"),
+                variant("Пакет", "300", ""),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(tea.price_amount(), Some(rub(45000)));
+        assert_eq!(tea.price_variants[1].stock(), Some(12));
+        assert_eq!(tea.price_variants[3].stock(), None);
+        assert_eq!(
+            tea.cheapest_in_stock_variant(),
+            Some(&tea.price_variants[1])
+        );
+        // The 100 g tin is cheaper per gram, but out of stock
+        assert_eq!(tea.price_per_100g(), Some(rub(75000)));
+
+        for variant in &mut tea.price_variants {
+            variant.quantity = "0".to_string();
+        }
+        assert_eq!(tea.cheapest_in_stock_variant(), None);
+        assert_eq!(tea.price_per_100g(), Some(rub(75000)));
+        tea.price_variants[2].price = "2000".to_string();
+        assert_eq!(tea.price_per_100g(), Some(rub(89000)));
+
+        // Without variants the weight of the tea counts
+        tea.price_variants.clear();
+        assert_eq!(tea.price_per_100g(), None);
+        tea.weight = Some("50 г".to_string());
+        assert_eq!(tea.price_per_100g(), Some(rub(90000)));
+    }
+}
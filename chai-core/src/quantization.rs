@@ -0,0 +1,271 @@
+//! Embedding storage formats and quantization
+//!
+//! Turso only has f32/f64 vector functions (no `vector16`/`vector8`), so
+//! quantized embeddings are encoded here and compared in Rust:
+//! - `f32`: `tea_embeddings.vector` as an `F32_BLOB` (4 bytes per dimension)
+//! - `f16`: `tea_embeddings.vector_quantized`, half floats (2 bytes per dimension)
+//! - `int8`: `tea_embeddings.vector_quantized`, bytes scaled by the largest value (1 byte per dimension)
+//!
+//! Every embedding also has its sign bits in `tea_embeddings.vector_bits` (1 bit per
+//! dimension). Binary search compares them by Hamming distance to pick
+//! candidates, then rescores the candidates with f32 cosine similarity.
+
+use anyhow::Result;
+
+/// Binary search rescores `limit * RESCORE_FACTOR` candidates
+pub const RESCORE_FACTOR: usize = 10;
+
+/// Leading byte of an f16 `vector_quantized` blob
+const F16_TAG: u8 = 1;
+
+/// Leading byte of an int8 `vector_quantized` blob (followed by the f32 scale)
+const INT8_TAG: u8 = 2;
+
+/// How tea embeddings are stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VectorStorage {
+    /// Full precision in `tea_embeddings.vector`
+    #[default]
+    F32,
+    /// Half precision, half the size
+    F16,
+    /// 8-bit, a quarter of the size
+    Int8,
+}
+
+impl std::str::FromStr for VectorStorage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "f32" => Ok(Self::F32),
+            "f16" => Ok(Self::F16),
+            "int8" => Ok(Self::Int8),
+            _ => anyhow::bail!("Unknown vector storage '{}' (f32, f16, int8)", s),
+        }
+    }
+}
+
+/// Encode a vector as an `F32_BLOB` (little-endian f32 values)
+#[must_use]
+pub fn encode_f32_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// Decode an `F32_BLOB` column (little-endian f32 values)
+pub fn decode_f32_blob(data: &[u8]) -> Result<Vec<f32>> {
+    if !data.len().is_multiple_of(4) {
+        anyhow::bail!("Vector blob of {} bytes is not a f32 array", data.len());
+    }
+
+    Ok(data
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// Encode a vector for `vector_quantized` (None for f32, stored in `vector`)
+#[must_use]
+pub fn quantize(storage: VectorStorage, vector: &[f32]) -> Option<Vec<u8>> {
+    match storage {
+        VectorStorage::F32 => None,
+        VectorStorage::F16 => {
+            let mut data = vec![F16_TAG];
+            data.extend(vector.iter().flat_map(|&v| f32_to_f16(v).to_le_bytes()));
+            Some(data)
+        }
+        VectorStorage::Int8 => {
+            let max = vector.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+            let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+            let mut data = vec![INT8_TAG];
+            data.extend(scale.to_le_bytes());
+            data.extend(
+                vector
+                    .iter()
+                    .map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8 as u8),
+            );
+            Some(data)
+        }
+    }
+}
+
+/// Decode a `vector_quantized` blob
+pub fn dequantize(data: &[u8]) -> Result<Vec<f32>> {
+    match data.split_first() {
+        Some((&F16_TAG, values)) if values.len().is_multiple_of(2) => Ok(values
+            .chunks_exact(2)
+            .map(|b| f16_to_f32(u16::from_le_bytes([b[0], b[1]])))
+            .collect()),
+        Some((&INT8_TAG, values)) if values.len() >= 4 => {
+            let (scale, values) = values.split_at(4);
+            let scale = f32::from_le_bytes([scale[0], scale[1], scale[2], scale[3]]);
+            Ok(values.iter().map(|&v| v as i8 as f32 * scale).collect())
+        }
+        _ => anyhow::bail!("Unknown quantized vector of {} bytes", data.len()),
+    }
+}
+
+/// Storage format of a `vector_quantized` blob
+#[must_use]
+pub fn quantized_storage(data: &[u8]) -> Option<VectorStorage> {
+    match data.first() {
+        Some(&F16_TAG) => Some(VectorStorage::F16),
+        Some(&INT8_TAG) => Some(VectorStorage::Int8),
+        _ => None,
+    }
+}
+
+/// Sign bits of a vector (bit set for positive values), 8 dimensions per byte
+#[must_use]
+pub fn sign_bits(vector: &[f32]) -> Vec<u8> {
+    vector
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| **v > 0.0)
+                .fold(0u8, |byte, (i, _)| byte | 1 << i)
+        })
+        .collect()
+}
+
+/// Number of differing bits
+#[must_use]
+pub fn hamming(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+/// Cosine similarity (0 for a zero vector), same as `1 - vector_distance_cos`
+#[must_use]
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// IEEE 754 half float bits, rounding to nearest even
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and NaN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Subnormal half floats keep the implicit leading bit in the mantissa
+    let (exponent_bits, mantissa, shift) = if exponent > 0 {
+        ((exponent as u32) << 10, mantissa, 13)
+    } else if exponent >= -10 {
+        (0, mantissa | 0x80_0000, (14 - exponent) as u32)
+    } else {
+        return sign;
+    };
+
+    let half = exponent_bits | (mantissa >> shift);
+    let remainder = mantissa & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let round_up = remainder > halfway || (remainder == halfway && half & 1 == 1);
+
+    // A carry into the exponent is the correct rounding (up to infinity)
+    sign | (half + round_up as u32) as u16
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    let bits = match exponent {
+        0 => {
+            let value = mantissa as f32 / (1 << 24) as f32;
+            return if sign != 0 { -value } else { value };
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f32_blob() {
+        let blob = [0, 0, 128, 63, 0, 0, 0, 63, 0, 0, 0, 192];
+        assert_eq!(decode_f32_blob(&blob).unwrap(), vec![1.0, 0.5, -2.0]);
+        assert_eq!(encode_f32_blob(&[1.0, 0.5, -2.0]), blob);
+        assert!(decode_f32_blob(&blob[..5]).is_err());
+    }
+
+    #[test]
+    fn test_f16_conversion() {
+        for value in [0.0, -0.0, 1.0, -2.5, 0.333_251_95, 65504.0, 6.1035156e-5] {
+            assert_eq!(f16_to_f32(f32_to_f16(value)), value);
+        }
+        // Subnormal and out of range values
+        assert_eq!(f16_to_f32(f32_to_f16(5.9604645e-8)), 5.9604645e-8);
+        assert_eq!(f32_to_f16(1e-10), 0);
+        assert_eq!(f16_to_f32(f32_to_f16(1e6)), f32::INFINITY);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        // Ties round to even
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 / 2048.0), 0x3c02);
+    }
+
+    #[test]
+    fn test_quantize_roundtrip() {
+        let vector: Vec<f32> = (0..64).map(|i| ((i as f32) * 0.37).sin() * 0.05).collect();
+        assert_eq!(quantize(VectorStorage::F32, &vector), None);
+
+        for (storage, size, tolerance) in [
+            (VectorStorage::F16, 1 + 2 * 64, 1e-4),
+            (VectorStorage::Int8, 1 + 4 + 64, 1e-3),
+        ] {
+            let data = quantize(storage, &vector).unwrap();
+            assert_eq!(data.len(), size);
+            assert_eq!(quantized_storage(&data), Some(storage));
+
+            let restored = dequantize(&data).unwrap();
+            assert_eq!(restored.len(), vector.len());
+            for (a, b) in vector.iter().zip(&restored) {
+                assert!((a - b).abs() < tolerance, "{:?}: {} vs {}", storage, a, b);
+            }
+            assert!(cosine(&vector, &restored) > 0.999);
+        }
+
+        assert_eq!(
+            dequantize(&quantize(VectorStorage::Int8, &[0.0; 3]).unwrap()).unwrap(),
+            vec![0.0; 3]
+        );
+        assert!(dequantize(&[]).is_err());
+        assert!(dequantize(&[F16_TAG, 0]).is_err());
+    }
+
+    #[test]
+    fn test_sign_bits() {
+        let bits = sign_bits(&[1.0, -1.0, 0.5, 0.0, -0.2, 3.0, 1.0, -1.0, 2.0]);
+        assert_eq!(bits, vec![0b0110_0101, 0b1]);
+        assert_eq!(hamming(&bits, &bits), 0);
+        assert_eq!(hamming(&bits, &sign_bits(&[-1.0; 9])), 5);
+
+        assert!((cosine(&[1.0, 0.0], &[1.0, 1.0]) - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(cosine(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+}
@@ -0,0 +1,1070 @@
+//! In-memory repository
+//!
+//! Keeps everything in plain collections and searches by brute force:
+//! cosine similarity against every embedding of the model, BM25 over the
+//! terms of every tea. Filters are evaluated with [`SearchFilters::matches`],
+//! typed fields (prices, ingredients) are derived with [`crate::tea_utils`] like
+//! the columns of the database, facets are counted with [`FacetCounter`].
+//! Embeddings are kept in full precision whatever `VECTOR_STORAGE` says.
+//!
+//! Nothing is persisted, so it suits tests and quick experiments.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use super::{CacheRepository, TeaRepository, UserRepository};
+use crate::facets::FacetCounter;
+use crate::filters::SearchFilters;
+use crate::keywords;
+use crate::models::{CatalogFacets, SearchResult, Tea, TeaLifecycle, TeaStatus, generate_point_id};
+use crate::quantization;
+use crate::samples::SampleLink;
+use crate::tea_utils;
+use crate::turso::{
+    CacheEntry, CacheItem, CacheMeta, CacheSnapshot, CacheStats, DEFAULT_CACHE_SNAPSHOTS,
+    DatabaseStats, HybridConfig, User, compress_html, decompress_html, html_hash,
+};
+
+/// Repository that lives in memory
+pub struct MemoryRepository {
+    state: Mutex<State>,
+    /// Number of HTML snapshots kept per URL
+    cache_snapshots: usize,
+}
+
+#[derive(Default)]
+struct State {
+    users: Vec<User>,
+    /// Current cache entry per URL
+    cache: BTreeMap<String, CachedPage>,
+    /// Snapshots per URL, newest first
+    snapshots: HashMap<String, Vec<StoredSnapshot>>,
+    images: HashMap<String, String>,
+    /// Teas by point ID
+    teas: BTreeMap<String, StoredTea>,
+    /// Embeddings by point ID and model
+    embeddings: HashMap<(String, String), StoredEmbedding>,
+    /// Sample URL and its stock status by point ID of the main product
+    samples: HashMap<String, (String, bool)>,
+}
+
+struct CachedPage {
+    content_hash: String,
+    fetched_at: i64,
+    meta: CacheMeta,
+}
+
+struct StoredSnapshot {
+    content_hash: String,
+    html_zstd: Vec<u8>,
+    size: usize,
+    fetched_at: i64,
+}
+
+struct StoredTea {
+    tea: Tea,
+    content_hash: String,
+    terms: BTreeMap<String, f64>,
+    lifecycle: TeaLifecycle,
+}
+
+impl StoredTea {
+    fn is_active(&self) -> bool {
+        self.lifecycle.status == TeaStatus::Active
+    }
+
+    /// Found on the website at `now`
+    fn seen(&mut self, now: i64) {
+        let lifecycle = &mut self.lifecycle;
+        lifecycle.last_seen = Some(now);
+        if lifecycle.status == TeaStatus::Discontinued {
+            lifecycle.status = TeaStatus::Active;
+            lifecycle.discontinued_at = None;
+        }
+    }
+}
+
+struct StoredEmbedding {
+    content_hash: String,
+    vector: Vec<f32>,
+}
+
+impl Default for MemoryRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryRepository {
+    /// Empty repository keeping [`DEFAULT_CACHE_SNAPSHOTS`] snapshots per URL
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_cache_snapshots(DEFAULT_CACHE_SNAPSHOTS)
+    }
+
+    /// Empty repository keeping `snapshots` HTML snapshots per URL
+    #[must_use]
+    pub fn with_cache_snapshots(snapshots: usize) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            cache_snapshots: snapshots.max(1),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panic in another test thread doesn't make the data invalid
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn now() -> Result<i64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("System time error")?
+        .as_secs() as i64)
+}
+
+impl State {
+    fn sample_in_stock(&self, id: &str) -> bool {
+        self.samples.get(id).is_some_and(|(_, in_stock)| *in_stock)
+    }
+
+    /// Teas passing the filters, by point ID
+    fn filtered<'a>(
+        &'a self,
+        filters: &'a SearchFilters,
+    ) -> impl Iterator<Item = (&'a String, &'a StoredTea)> {
+        self.teas.iter().filter(|(id, stored)| {
+            filters.matches(
+                &stored.tea,
+                self.sample_in_stock(id),
+                stored.lifecycle.status,
+            )
+        })
+    }
+
+    fn search_result(&self, id: &str, query: Option<(&str, &[f32])>) -> Option<SearchResult> {
+        let stored = self.teas.get(id)?;
+        let vector_score = query.and_then(|(model, embedding)| {
+            self.embeddings
+                .get(&(id.to_string(), model.to_string()))
+                .filter(|e| e.vector.len() == embedding.len())
+                .map(|e| quantization::cosine(embedding, &e.vector))
+        });
+
+        Some(SearchResult {
+            tea: stored.tea.clone(),
+            score: 0.0,
+            vector_score,
+            keyword_score: None,
+            sample_in_stock: self.sample_in_stock(id),
+            status: stored.lifecycle.status,
+        })
+    }
+
+    /// Point IDs with cosine similarity, best first
+    fn vector_ranking(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Vec<(String, f32)> {
+        let mut scores: Vec<(String, f32)> = self
+            .filtered(filters)
+            .filter_map(|(id, _)| {
+                let embedding = self.embeddings.get(&(id.clone(), model.to_string()))?;
+                (embedding.vector.len() == query_embedding.len()).then(|| {
+                    (
+                        id.clone(),
+                        quantization::cosine(query_embedding, &embedding.vector),
+                    )
+                })
+            })
+            .collect();
+
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scores.truncate(limit);
+        scores
+    }
+
+    /// Point IDs with BM25 scores, best first
+    fn keyword_ranking(
+        &self,
+        query: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Vec<(String, f64)> {
+        let mut terms = keywords::tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        // Corpus statistics over teas with any terms
+        let docs = self.teas.values().filter(|t| !t.terms.is_empty()).count();
+        if terms.is_empty() || docs == 0 {
+            return Vec::new();
+        }
+        let total_len: f64 = self.teas.values().flat_map(|t| t.terms.values()).sum();
+        let avg_len = total_len / docs as f64;
+        let doc_freq: HashMap<&str, usize> = terms
+            .iter()
+            .map(|term| {
+                let df = self
+                    .teas
+                    .values()
+                    .filter(|t| t.terms.contains_key(term))
+                    .count();
+                (term.as_str(), df)
+            })
+            .collect();
+
+        let mut ranking: Vec<(String, f64)> = self
+            .filtered(filters)
+            .filter_map(|(id, stored)| {
+                let len: f64 = stored.terms.values().sum();
+                let score = terms
+                    .iter()
+                    .filter_map(|term| {
+                        let tf = *stored.terms.get(term)?;
+                        Some(keywords::bm25(
+                            tf,
+                            doc_freq[term.as_str()],
+                            docs,
+                            len,
+                            avg_len,
+                        ))
+                    })
+                    .fold(None, |sum: Option<f64>, s| Some(sum.unwrap_or(0.0) + s))?;
+                Some((id.clone(), score))
+            })
+            .collect();
+
+        ranking.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranking.truncate(limit);
+        ranking
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .find(|u| u.email == email)
+            .cloned())
+    }
+
+    async fn get_user_by_id(&self, user_id: i64) -> Result<Option<User>> {
+        Ok(self.state().users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    async fn create_user(&self, email: &str, password_hash: &str) -> Result<User> {
+        let mut state = self.state();
+        if state.users.iter().any(|u| u.email == email) {
+            anyhow::bail!("Failed to create user: email {} is taken", email);
+        }
+
+        let user = User {
+            id: state.users.len() as i64 + 1,
+            email: email.to_string(),
+            password_hash: password_hash.to_string(),
+            created_at: now()?,
+        };
+        state.users.push(user.clone());
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl CacheRepository for MemoryRepository {
+    async fn cache_get(&self, url: &str) -> Result<Option<CacheEntry>> {
+        let state = self.state();
+        let Some(page) = state.cache.get(url) else {
+            return Ok(None);
+        };
+        let snapshot = state
+            .snapshots
+            .get(url)
+            .and_then(|s| s.iter().find(|s| s.content_hash == page.content_hash))
+            .with_context(|| format!("Broken cache entry {}", url))?;
+
+        Ok(Some(CacheEntry {
+            url: url.to_string(),
+            html: decompress_html(&snapshot.html_zstd)?,
+            fetched_at: page.fetched_at,
+            etag: page.meta.etag.clone(),
+            last_modified: page.meta.last_modified.clone(),
+            status: page.meta.status,
+        }))
+    }
+
+    async fn cache_set_at(
+        &self,
+        url: &str,
+        html: &str,
+        fetched_at: i64,
+        meta: &CacheMeta,
+    ) -> Result<()> {
+        let hash = html_hash(html);
+        let html_zstd = compress_html(html)?;
+
+        let mut state = self.state();
+        let snapshots = state.snapshots.entry(url.to_string()).or_default();
+        if snapshots.first().is_none_or(|s| s.content_hash != hash) {
+            // A page that went back to an older content moves that snapshot to the top
+            snapshots.retain(|s| s.content_hash != hash);
+            snapshots.insert(
+                0,
+                StoredSnapshot {
+                    content_hash: hash.clone(),
+                    html_zstd,
+                    size: html.len(),
+                    fetched_at,
+                },
+            );
+            snapshots.truncate(self.cache_snapshots);
+        }
+
+        state.cache.insert(
+            url.to_string(),
+            CachedPage {
+                content_hash: hash,
+                fetched_at,
+                meta: meta.clone(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn cache_snapshots(&self, url: &str) -> Result<Vec<CacheSnapshot>> {
+        Ok(self
+            .state()
+            .snapshots
+            .get(url)
+            .into_iter()
+            .flatten()
+            .map(|s| CacheSnapshot {
+                url: url.to_string(),
+                content_hash: s.content_hash.clone(),
+                fetched_at: s.fetched_at,
+                size_bytes: s.size,
+                compressed_bytes: s.html_zstd.len(),
+            })
+            .collect())
+    }
+
+    async fn cache_snapshot_html(&self, url: &str, content_hash: &str) -> Result<Option<String>> {
+        self.state()
+            .snapshots
+            .get(url)
+            .and_then(|s| s.iter().find(|s| s.content_hash == content_hash))
+            .map(|s| decompress_html(&s.html_zstd))
+            .transpose()
+    }
+
+    async fn cache_compress_legacy(&self) -> Result<usize> {
+        // Everything is compressed when stored
+        Ok(0)
+    }
+
+    async fn cache_touch(&self, url: &str, meta: &CacheMeta) -> Result<bool> {
+        let now = now()?;
+        let mut state = self.state();
+        let Some(page) = state.cache.get_mut(url) else {
+            return Ok(false);
+        };
+
+        page.fetched_at = now;
+        if meta.etag.is_some() {
+            page.meta.etag = meta.etag.clone();
+        }
+        if meta.last_modified.is_some() {
+            page.meta.last_modified = meta.last_modified.clone();
+        }
+        Ok(true)
+    }
+
+    async fn cache_contains(&self, url: &str) -> Result<bool> {
+        Ok(self.state().cache.contains_key(url))
+    }
+
+    async fn cache_list_urls(&self) -> Result<Vec<String>> {
+        Ok(self.state().cache.keys().cloned().collect())
+    }
+
+    async fn cache_urls_fetched_before(&self, cutoff: i64) -> Result<Vec<String>> {
+        let state = self.state();
+        let mut pages: Vec<(&String, i64)> = state
+            .cache
+            .iter()
+            .filter(|(_, page)| page.fetched_at < cutoff)
+            .map(|(url, page)| (url, page.fetched_at))
+            .collect();
+        pages.sort_by_key(|(_, fetched_at)| *fetched_at);
+        Ok(pages.into_iter().map(|(url, _)| url.clone()).collect())
+    }
+
+    async fn cache_items(&self) -> Result<Vec<CacheItem>> {
+        let state = self.state();
+        let mut items = Vec::new();
+        for (url, snapshots) in &state.snapshots {
+            let page = state.cache.get(url);
+            for snapshot in snapshots {
+                let current = page.is_some_and(|p| p.content_hash == snapshot.content_hash);
+                items.push(CacheItem {
+                    url: url.clone(),
+                    content_hash: Some(snapshot.content_hash.clone()),
+                    size_bytes: snapshot.html_zstd.len(),
+                    fetched_at: match page {
+                        Some(page) if current => page.fetched_at,
+                        _ => snapshot.fetched_at,
+                    },
+                    current,
+                });
+            }
+        }
+        Ok(items)
+    }
+
+    async fn cache_delete(&self, url: &str) -> Result<bool> {
+        let mut state = self.state();
+        state.snapshots.remove(url);
+        Ok(state.cache.remove(url).is_some())
+    }
+
+    async fn cache_delete_snapshot(&self, url: &str, content_hash: &str) -> Result<()> {
+        let mut state = self.state();
+        let current = state.cache.get(url).map(|p| p.content_hash.clone());
+        if current.as_deref() == Some(content_hash) {
+            return Ok(());
+        }
+        if let Some(snapshots) = state.snapshots.get_mut(url) {
+            snapshots.retain(|s| s.content_hash != content_hash);
+        }
+        Ok(())
+    }
+
+    async fn cache_stats(&self) -> Result<CacheStats> {
+        let state = self.state();
+        let snapshots = state.snapshots.values().flatten();
+
+        Ok(CacheStats {
+            entry_count: state.cache.len(),
+            total_size_bytes: snapshots.clone().map(|s| s.html_zstd.len()).sum(),
+            uncompressed_size_bytes: snapshots.clone().map(|s| s.size).sum(),
+            snapshot_count: snapshots.count(),
+            legacy_count: 0,
+            oldest_entry: state.cache.values().map(|p| p.fetched_at).min(),
+            newest_entry: state.cache.values().map(|p| p.fetched_at).max(),
+        })
+    }
+
+    async fn cache_clear(&self) -> Result<usize> {
+        let mut state = self.state();
+        state.snapshots.clear();
+        let count = state.cache.len();
+        state.cache.clear();
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl TeaRepository for MemoryRepository {
+    async fn get_image_hash(&self, url: &str) -> Result<Option<String>> {
+        Ok(self.state().images.get(url).cloned())
+    }
+
+    async fn get_image_hashes(&self, urls: &[String]) -> Result<HashMap<String, String>> {
+        let state = self.state();
+        Ok(urls
+            .iter()
+            .filter_map(|url| Some((url.clone(), state.images.get(url)?.clone())))
+            .collect())
+    }
+
+    async fn set_image_hash(&self, url: &str, hash: &str) -> Result<()> {
+        self.state()
+            .images
+            .insert(url.to_string(), hash.to_string());
+        Ok(())
+    }
+
+    async fn upsert_tea(&self, tea: &Tea, content_hash: &str) -> Result<()> {
+        let now = now()?;
+        let mut state = self.state();
+        let id = generate_point_id(&tea.url);
+        let lifecycle = state.teas.get(&id).map_or(
+            TeaLifecycle {
+                status: TeaStatus::Active,
+                first_seen: Some(now),
+                last_seen: Some(now),
+                discontinued_at: None,
+            },
+            |stored| stored.lifecycle,
+        );
+
+        let mut stored = StoredTea {
+            tea: tea.clone(),
+            content_hash: content_hash.to_string(),
+            terms: keywords::document_terms(tea),
+            lifecycle,
+        };
+        stored.seen(now);
+        state.teas.insert(id, stored);
+        Ok(())
+    }
+
+    async fn update_tea_embedding(
+        &self,
+        url: &str,
+        model: &str,
+        content_hash: &str,
+        embedding: &[f32],
+    ) -> Result<()> {
+        self.state().embeddings.insert(
+            (generate_point_id(url), model.to_string()),
+            StoredEmbedding {
+                content_hash: content_hash.to_string(),
+                vector: embedding.to_vec(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn teas_needing_embedding(&self, model: &str, force: bool) -> Result<Vec<(Tea, String)>> {
+        let state = self.state();
+        let mut teas: Vec<(Tea, String)> = state
+            .teas
+            .iter()
+            .filter(|(id, stored)| {
+                force
+                    || state
+                        .embeddings
+                        .get(&(id.to_string(), model.to_string()))
+                        .is_none_or(|e| e.content_hash != stored.content_hash)
+            })
+            .map(|(_, stored)| (stored.tea.clone(), stored.content_hash.clone()))
+            .collect();
+        teas.sort_by(|a, b| a.0.url.cmp(&b.0.url));
+        Ok(teas)
+    }
+
+    async fn get_tea_by_url(&self, url: &str) -> Result<Option<Tea>> {
+        self.get_tea_by_id(&generate_point_id(url)).await
+    }
+
+    async fn get_tea_by_id(&self, id: &str) -> Result<Option<Tea>> {
+        Ok(self.state().teas.get(id).map(|stored| stored.tea.clone()))
+    }
+
+    async fn get_tea_with_hash(&self, url: &str) -> Result<Option<(Tea, String)>> {
+        Ok(self
+            .state()
+            .teas
+            .get(&generate_point_id(url))
+            .map(|stored| (stored.tea.clone(), stored.content_hash.clone())))
+    }
+
+    async fn delete_tea_by_url(&self, url: &str) -> Result<bool> {
+        let id = generate_point_id(url);
+        let mut state = self.state();
+        state.embeddings.retain(|(tea_id, _), _| *tea_id != id);
+        state.samples.remove(&id);
+        Ok(state.teas.remove(&id).is_some())
+    }
+
+    async fn get_tea_lifecycle(&self, url: &str) -> Result<Option<TeaLifecycle>> {
+        Ok(self
+            .state()
+            .teas
+            .get(&generate_point_id(url))
+            .map(|stored| stored.lifecycle))
+    }
+
+    async fn mark_teas_seen(&self, urls: &[String]) -> Result<usize> {
+        let now = now()?;
+        let mut state = self.state();
+        let mut seen = 0;
+        for url in urls {
+            if let Some(stored) = state.teas.get_mut(&generate_point_id(url)) {
+                stored.seen(now);
+                seen += 1;
+            }
+        }
+        Ok(seen)
+    }
+
+    async fn discontinue_teas(&self, urls: &[String]) -> Result<usize> {
+        let now = now()?;
+        let mut state = self.state();
+        let mut discontinued = 0;
+        for url in urls {
+            if let Some(stored) = state.teas.get_mut(&generate_point_id(url))
+                && stored.is_active()
+            {
+                stored.lifecycle.status = TeaStatus::Discontinued;
+                stored.lifecycle.discontinued_at = Some(now);
+                discontinued += 1;
+            }
+        }
+        Ok(discontinued)
+    }
+
+    async fn set_tea_status(&self, url: &str, status: TeaStatus) -> Result<bool> {
+        let now = now()?;
+        let mut state = self.state();
+        let Some(stored) = state.teas.get_mut(&generate_point_id(url)) else {
+            return Ok(false);
+        };
+
+        let lifecycle = &mut stored.lifecycle;
+        lifecycle.status = status;
+        match status {
+            TeaStatus::Active => lifecycle.discontinued_at = None,
+            TeaStatus::Discontinued => {
+                lifecycle.discontinued_at.get_or_insert(now);
+            }
+            TeaStatus::Hidden => {}
+        }
+        Ok(true)
+    }
+
+    async fn replace_sample_links(&self, shop: &str, links: &[SampleLink]) -> Result<()> {
+        let mut state = self.state();
+        let shop_ids: Vec<String> = state
+            .teas
+            .iter()
+            .filter(|(_, stored)| stored.tea.shop == shop)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in shop_ids {
+            state.samples.remove(&id);
+        }
+
+        for link in links {
+            state.samples.insert(
+                generate_point_id(&link.main_url),
+                (link.sample_url.clone(), link.sample_in_stock),
+            );
+        }
+        Ok(())
+    }
+
+    async fn get_sample_link(&self, url: &str) -> Result<Option<(String, bool)>> {
+        Ok(self.state().samples.get(&generate_point_id(url)).cloned())
+    }
+
+    async fn get_tea_urls(&self, status: TeaStatus) -> Result<Vec<String>> {
+        Ok(self
+            .state()
+            .teas
+            .values()
+            .filter(|stored| stored.lifecycle.status == status)
+            .map(|stored| stored.tea.url.clone())
+            .collect())
+    }
+
+    async fn search_teas(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        let state = self.state();
+        let results = state
+            .vector_ranking(query_embedding, model, filters.candidates(limit), filters)
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let mut result = state.search_result(&id, None)?;
+                result.score = score;
+                result.vector_score = Some(score);
+                Some(result)
+            })
+            .collect();
+        Ok(filters.order_results(results, limit))
+    }
+
+    async fn search_teas_keyword(
+        &self,
+        query: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        let state = self.state();
+        let results = state
+            .keyword_ranking(query, filters.candidates(limit), filters)
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let mut result = state.search_result(&id, None)?;
+                result.score = score as f32;
+                result.keyword_score = Some(score as f32);
+                Some(result)
+            })
+            .collect();
+        Ok(filters.order_results(results, limit))
+    }
+
+    async fn search_teas_hybrid(
+        &self,
+        query: &str,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        config: &HybridConfig,
+    ) -> Result<Vec<SearchResult>> {
+        let state = self.state();
+        let limit_candidates = filters.candidates(limit);
+        let candidates = config.candidates.max(limit_candidates);
+
+        let vector_ids: Vec<String> = state
+            .vector_ranking(query_embedding, model, candidates, filters)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        let keyword_scores = state.keyword_ranking(query, candidates, filters);
+        let keyword_ids: Vec<String> = keyword_scores.iter().map(|(id, _)| id.clone()).collect();
+        let keyword_scores: HashMap<String, f64> = keyword_scores.into_iter().collect();
+
+        let fused = keywords::reciprocal_rank_fusion(
+            &[
+                (config.vector_weight, vector_ids),
+                (config.keyword_weight, keyword_ids),
+            ],
+            config.rrf_k,
+        );
+
+        let results = fused
+            .into_iter()
+            .take(limit_candidates)
+            .filter_map(|(id, score)| {
+                let mut result = state.search_result(&id, Some((model, query_embedding)))?;
+                result.score = score;
+                result.keyword_score = keyword_scores.get(&id).map(|&s| s as f32);
+                Some(result)
+            })
+            .collect();
+        Ok(filters.order_results(results, limit))
+    }
+
+    async fn get_stats(&self) -> Result<DatabaseStats> {
+        let state = self.state();
+        let active: Vec<&StoredTea> = state.teas.values().filter(|t| t.is_active()).collect();
+        let count = |status: TeaStatus| {
+            state
+                .teas
+                .values()
+                .filter(|t| t.lifecycle.status == status)
+                .count()
+        };
+        let total = active.len();
+        let in_stock = active.iter().filter(|t| t.tea.in_stock).count();
+
+        let mut series_list: Vec<String> = active
+            .iter()
+            .filter_map(|t| t.tea.series.clone())
+            .filter(|s| !s.is_empty())
+            .collect();
+        series_list.sort();
+        series_list.dedup();
+
+        let prices: Vec<f64> = active
+            .iter()
+            .filter_map(|t| tea_utils::min_price(&t.tea))
+            .collect();
+        let price_range = prices
+            .iter()
+            .copied()
+            .min_by(f64::total_cmp)
+            .zip(prices.iter().copied().max_by(f64::total_cmp));
+
+        let mut shops: BTreeMap<String, usize> = BTreeMap::new();
+        for stored in &active {
+            *shops.entry(stored.tea.shop.clone()).or_default() += 1;
+        }
+
+        Ok(DatabaseStats {
+            total_teas: total,
+            in_stock,
+            out_of_stock: total - in_stock,
+            series_count: series_list.len(),
+            series_list,
+            shop_counts: shops.into_iter().collect(),
+            discontinued: count(TeaStatus::Discontinued),
+            hidden: count(TeaStatus::Hidden),
+            price_range,
+        })
+    }
+
+    async fn get_facets(&self, filters: &SearchFilters) -> Result<CatalogFacets> {
+        let state = self.state();
+        let mut counter = FacetCounter::new();
+        for (id, stored) in state.filtered(filters) {
+            counter.add(&stored.tea, state.sample_in_stock(id));
+        }
+        Ok(counter.finish())
+    }
+
+    async fn count_teas(&self) -> Result<usize> {
+        Ok(self.state().teas.values().filter(|t| t.is_active()).count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn tea(url: &str, name: &str, in_stock: bool) -> Tea {
+        Tea {
+            url: url.to_string(),
+            name: Some(name.to_string()),
+            in_stock,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_users() {
+        let repo = MemoryRepository::new();
+        block_on(async {
+            let user = repo.create_user("a@example.com", "hash").await.unwrap();
+            assert_eq!(user.id, 1);
+            assert!(repo.create_user("a@example.com", "other").await.is_err());
+
+            let found = repo.get_user_by_email("a@example.com").await.unwrap();
+            assert_eq!(found.map(|u| u.id), Some(1));
+            assert_eq!(
+                repo.get_user_by_id(1).await.unwrap().unwrap().email,
+                "a@example.com"
+            );
+            assert!(repo.get_user_by_id(2).await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_cache_snapshots() {
+        let repo = MemoryRepository::with_cache_snapshots(2);
+        let url = "https://example.com/";
+        block_on(async {
+            for (html, at) in [("v1", 1), ("v2", 2), ("v2", 3), ("v3", 4)] {
+                repo.cache_set_at(url, html, at, &CacheMeta::default())
+                    .await
+                    .unwrap();
+            }
+
+            let entry = repo.cache_get(url).await.unwrap().unwrap();
+            assert_eq!((entry.html.as_str(), entry.fetched_at), ("v3", 4));
+
+            // Unchanged content keeps its snapshot, the oldest beyond the limit is dropped
+            let snapshots = repo.cache_snapshots(url).await.unwrap();
+            let hashes: Vec<String> = snapshots.iter().map(|s| s.content_hash.clone()).collect();
+            assert_eq!(hashes, vec![html_hash("v3"), html_hash("v2")]);
+            assert_eq!(snapshots[1].fetched_at, 2);
+            assert_eq!(
+                repo.cache_snapshot_html(url, &hashes[1]).await.unwrap(),
+                Some("v2".to_string())
+            );
+
+            // The current snapshot can't be deleted on its own
+            repo.cache_delete_snapshot(url, &hashes[0]).await.unwrap();
+            repo.cache_delete_snapshot(url, &hashes[1]).await.unwrap();
+            assert_eq!(repo.cache_snapshots(url).await.unwrap().len(), 1);
+
+            assert_eq!(repo.cache_urls_fetched_before(5).await.unwrap(), vec![url]);
+            assert!(repo.cache_urls_fetched_before(4).await.unwrap().is_empty());
+            assert_eq!(repo.cache_stats().await.unwrap().entry_count, 1);
+
+            assert!(repo.cache_delete(url).await.unwrap());
+            assert!(!repo.cache_contains(url).await.unwrap());
+            assert!(repo.cache_items().await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_search() {
+        let repo = MemoryRepository::new();
+        let teas = [
+            (tea("https://a/1", "Иван-чай с мятой", true), [1.0, 0.0]),
+            (tea("https://a/2", "Иван-чай с малиной", false), [0.8, 0.6]),
+            (tea("https://a/3", "Травяной сбор", true), [0.0, 1.0]),
+        ];
+        block_on(async {
+            for (tea, embedding) in &teas {
+                repo.upsert_tea(tea, "hash").await.unwrap();
+                repo.update_tea_embedding(&tea.url, "model", "hash", embedding)
+                    .await
+                    .unwrap();
+            }
+            let urls = |results: Vec<SearchResult>| {
+                results.into_iter().map(|r| r.tea.url).collect::<Vec<_>>()
+            };
+            let all = SearchFilters::new();
+
+            let results = repo.search_teas(&[1.0, 0.1], "model", 2, &all).await;
+            assert_eq!(urls(results.unwrap()), vec!["https://a/1", "https://a/2"]);
+
+            // Other models and dimensions are not compared
+            assert!(
+                repo.search_teas(&[1.0, 0.1], "other", 2, &all)
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+            assert!(
+                repo.search_teas(&[1.0], "model", 2, &all)
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+
+            let in_stock = SearchFilters::new().only_in_stock();
+            let results = repo.search_teas(&[1.0, 0.1], "model", 2, &in_stock).await;
+            assert_eq!(urls(results.unwrap()), vec!["https://a/1", "https://a/3"]);
+
+            let results = repo.search_teas_keyword("малина", 10, &all).await.unwrap();
+            assert_eq!(urls(results), vec!["https://a/2"]);
+
+            let config = HybridConfig::default();
+            let results = repo
+                .search_teas_hybrid("малина", &[1.0, 0.0], "model", 3, &all, &config)
+                .await
+                .unwrap();
+            assert_eq!(results[0].tea.url, "https://a/2");
+            assert!(results[0].keyword_score.is_some() && results[0].vector_score.is_some());
+            assert!(results[1].keyword_score.is_none());
+
+            // New content makes the embedding stale
+            assert!(
+                repo.teas_needing_embedding("model", false)
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+            repo.upsert_tea(&teas[0].0, "changed").await.unwrap();
+            let stale = repo.teas_needing_embedding("model", false).await.unwrap();
+            assert_eq!(stale.len(), 1);
+            assert_eq!(
+                repo.teas_needing_embedding("other", false)
+                    .await
+                    .unwrap()
+                    .len(),
+                3
+            );
+
+            assert!(repo.delete_tea_by_url("https://a/1").await.unwrap());
+            assert_eq!(repo.count_teas().await.unwrap(), 2);
+            let stats = repo.get_stats().await.unwrap();
+            assert_eq!((stats.in_stock, stats.out_of_stock), (1, 1));
+        });
+    }
+
+    #[test]
+    fn test_best_value_search() {
+        let repo = MemoryRepository::new();
+        let priced = |url: &str, price: &str, embedding: [f32; 2]| {
+            let tea = Tea {
+                price: Some(price.to_string()),
+                weight: Some("100 г".to_string()),
+                ..tea(url, "Иван-чай", true)
+            };
+            (tea, embedding)
+        };
+        let teas = [
+            priced("https://a/1", "900", [1.0, 0.0]),
+            priced("https://a/2", "600", [0.8, 0.6]),
+            priced("https://a/3", "300", [0.0, 1.0]),
+        ];
+        block_on(async {
+            for (tea, embedding) in &teas {
+                repo.upsert_tea(tea, "hash").await.unwrap();
+                repo.update_tea_embedding(&tea.url, "model", "hash", embedding)
+                    .await
+                    .unwrap();
+            }
+            let urls = |results: Vec<SearchResult>| {
+                results.into_iter().map(|r| r.tea.url).collect::<Vec<_>>()
+            };
+            let best_value = SearchFilters::new().best_value();
+
+            // The least similar tea is the cheapest per 100 g, the limit applies after ordering
+            let results = repo.search_teas(&[1.0, 0.0], "model", 1, &best_value).await;
+            assert_eq!(urls(results.unwrap()), vec!["https://a/3"]);
+
+            let results = repo
+                .search_teas_keyword("иван-чай", 2, &best_value)
+                .await
+                .unwrap();
+            assert_eq!(urls(results), vec!["https://a/3", "https://a/2"]);
+
+            let config = HybridConfig::default();
+            let results = repo
+                .search_teas_hybrid("иван-чай", &[1.0, 0.0], "model", 2, &best_value, &config)
+                .await
+                .unwrap();
+            assert_eq!(urls(results), vec!["https://a/3", "https://a/2"]);
+        });
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let repo = MemoryRepository::new();
+        let urls = ["https://a/1", "https://a/2"].map(String::from);
+        block_on(async {
+            for url in &urls {
+                repo.upsert_tea(&tea(url, "Иван-чай", true), "hash")
+                    .await
+                    .unwrap();
+            }
+            let all = SearchFilters::new();
+            let repo = &repo;
+            let found = |filters: SearchFilters| async move {
+                let results = repo.search_teas_keyword("иван", 10, &filters).await;
+                results.unwrap().len()
+            };
+
+            // Gone teas are kept, out of search unless asked for
+            assert_eq!(repo.discontinue_teas(&urls[1..]).await.unwrap(), 1);
+            assert_eq!(repo.discontinue_teas(&urls[1..]).await.unwrap(), 0);
+            assert_eq!(found(all.clone()).await, 1);
+            assert_eq!(found(all.clone().include_discontinued()).await, 2);
+            assert!(repo.get_tea_by_url(&urls[1]).await.unwrap().is_some());
+            let lifecycle = repo.get_tea_lifecycle(&urls[1]).await.unwrap().unwrap();
+            assert_eq!(lifecycle.status, TeaStatus::Discontinued);
+            assert!(lifecycle.discontinued_at.is_some());
+            assert_eq!(repo.count_teas().await.unwrap(), 1);
+            assert_eq!(repo.get_stats().await.unwrap().discontinued, 1);
+            assert_eq!(
+                repo.get_tea_urls(TeaStatus::Active).await.unwrap(),
+                vec![urls[0].clone()]
+            );
+
+            // Coming back makes it active, hidden teas stay hidden
+            assert!(
+                repo.set_tea_status(&urls[0], TeaStatus::Hidden)
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(repo.mark_teas_seen(&urls).await.unwrap(), 2);
+            let lifecycle = repo.get_tea_lifecycle(&urls[1]).await.unwrap().unwrap();
+            assert_eq!(
+                (lifecycle.status, lifecycle.discontinued_at),
+                (TeaStatus::Active, None)
+            );
+            let hidden = repo.get_tea_lifecycle(&urls[0]).await.unwrap().unwrap();
+            assert_eq!(hidden.status, TeaStatus::Hidden);
+            assert_eq!(found(all.include_discontinued()).await, 1);
+            assert!(
+                !repo
+                    .set_tea_status("https://a/3", TeaStatus::Active)
+                    .await
+                    .unwrap()
+            );
+        });
+    }
+}
@@ -0,0 +1,242 @@
+//! Storage interfaces for teas, users and the HTML cache
+//!
+//! Code that reads or writes data takes one of these traits instead of a
+//! concrete database, so the same logic runs on:
+//! - [`TursoRepository`](crate::turso::TursoRepository): the database file used in production
+//! - [`MemoryRepository`]: plain collections with brute-force search, for tests and experiments
+//!
+//! Applications open one repository at startup and pass a reference down.
+//! Maintenance that only makes sense on the database file (vector index,
+//! embedding conversion, migrations) stays on `TursoRepository`.
+
+mod memory;
+
+pub use memory::MemoryRepository;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+use crate::filters::SearchFilters;
+use crate::models::{CatalogFacets, SearchResult, Tea, TeaLifecycle, TeaStatus};
+use crate::samples::SampleLink;
+use crate::turso::{
+    CacheEntry, CacheItem, CacheMeta, CacheSnapshot, CacheStats, DatabaseStats, HybridConfig, User,
+};
+
+/// User accounts
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    /// Get user by email
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>>;
+
+    /// Get user by ID
+    async fn get_user_by_id(&self, user_id: i64) -> Result<Option<User>>;
+
+    /// Create a new user
+    async fn create_user(&self, email: &str, password_hash: &str) -> Result<User>;
+}
+
+/// HTML cache with snapshot history
+#[async_trait]
+pub trait CacheRepository: Send + Sync {
+    /// Get cached HTML for a URL
+    async fn cache_get(&self, url: &str) -> Result<Option<CacheEntry>>;
+
+    /// Store HTML in cache with an explicit fetch time (imports and compression of old entries)
+    ///
+    /// A new snapshot is added only if the content changed, snapshots beyond
+    /// the configured number per URL are removed.
+    async fn cache_set_at(
+        &self,
+        url: &str,
+        html: &str,
+        fetched_at: i64,
+        meta: &CacheMeta,
+    ) -> Result<()>;
+
+    /// Store HTML in cache
+    async fn cache_set(&self, url: &str, html: &str) -> Result<()> {
+        self.cache_set_with_meta(url, html, &CacheMeta::default())
+            .await
+    }
+
+    /// Store HTML in cache together with response metadata
+    async fn cache_set_with_meta(&self, url: &str, html: &str, meta: &CacheMeta) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        self.cache_set_at(url, html, now, meta).await
+    }
+
+    /// Get snapshots of a cached URL, newest first
+    async fn cache_snapshots(&self, url: &str) -> Result<Vec<CacheSnapshot>>;
+
+    /// Get HTML of a snapshot by its content hash
+    async fn cache_snapshot_html(&self, url: &str, content_hash: &str) -> Result<Option<String>>;
+
+    /// Compress entries stored before snapshot support
+    ///
+    /// Keeps `fetched_at` and response metadata. Returns the number of entries compressed.
+    async fn cache_compress_legacy(&self) -> Result<usize>;
+
+    /// Mark cached entry as still fresh (after `304 Not Modified`)
+    ///
+    /// Bumps `fetched_at` and stores validators if the server sent new ones.
+    /// Returns false if the URL is not cached.
+    async fn cache_touch(&self, url: &str, meta: &CacheMeta) -> Result<bool>;
+
+    /// Check if URL is cached
+    async fn cache_contains(&self, url: &str) -> Result<bool>;
+
+    /// Get all cached URLs
+    async fn cache_list_urls(&self) -> Result<Vec<String>>;
+
+    /// Get URLs of cache entries fetched before a UNIX timestamp, oldest first
+    async fn cache_urls_fetched_before(&self, cutoff: i64) -> Result<Vec<String>>;
+
+    /// Get all stored cache items with their sizes
+    async fn cache_items(&self) -> Result<Vec<CacheItem>>;
+
+    /// Delete a cached URL with all its snapshots
+    ///
+    /// Returns false if the URL is not cached.
+    async fn cache_delete(&self, url: &str) -> Result<bool>;
+
+    /// Delete an older snapshot of a URL
+    async fn cache_delete_snapshot(&self, url: &str, content_hash: &str) -> Result<()>;
+
+    /// Get cache statistics
+    async fn cache_stats(&self) -> Result<CacheStats>;
+
+    /// Clear all cache entries and their snapshots
+    async fn cache_clear(&self) -> Result<usize>;
+}
+
+/// Teas, their embeddings, sample links and mirrored images
+#[async_trait]
+pub trait TeaRepository: Send + Sync {
+    /// Get content hash of a mirrored image by its remote URL
+    async fn get_image_hash(&self, url: &str) -> Result<Option<String>>;
+
+    /// Get content hashes of mirrored images by their remote URLs in one query
+    ///
+    /// Images that aren't mirrored are left out.
+    async fn get_image_hashes(&self, urls: &[String]) -> Result<HashMap<String, String>>;
+
+    /// Store content hash of a mirrored image
+    async fn set_image_hash(&self, url: &str, hash: &str) -> Result<()>;
+
+    /// Upsert a tea (insert or update)
+    ///
+    /// Embeddings are stored separately with [`Self::update_tea_embedding`]. Embeddings made
+    /// from older content are kept (and searched) until they are replaced.
+    /// The tea counts as seen on the website, see [`Self::mark_teas_seen`].
+    async fn upsert_tea(&self, tea: &Tea, content_hash: &str) -> Result<()>;
+
+    /// Store the embedding of a tea made by a model
+    ///
+    /// `content_hash` is the hash of the tea content the embedding was made from.
+    async fn update_tea_embedding(
+        &self,
+        url: &str,
+        model: &str,
+        content_hash: &str,
+        embedding: &[f32],
+    ) -> Result<()>;
+
+    /// Stored teas without an up-to-date embedding of a model, with their content hash
+    ///
+    /// With `force`, all teas are returned.
+    async fn teas_needing_embedding(&self, model: &str, force: bool) -> Result<Vec<(Tea, String)>>;
+
+    /// Get tea by URL, whatever its status
+    async fn get_tea_by_url(&self, url: &str) -> Result<Option<Tea>>;
+
+    /// Get tea by ID, whatever its status
+    async fn get_tea_by_id(&self, id: &str) -> Result<Option<Tea>>;
+
+    /// Get tea with content hash by URL
+    async fn get_tea_with_hash(&self, url: &str) -> Result<Option<(Tea, String)>>;
+
+    /// Delete tea by URL
+    ///
+    /// Products gone from the website are discontinued with [`Self::discontinue_teas`] instead.
+    async fn delete_tea_by_url(&self, url: &str) -> Result<bool>;
+
+    /// Get status and timestamps of a stored tea
+    async fn get_tea_lifecycle(&self, url: &str) -> Result<Option<TeaLifecycle>>;
+
+    /// Record that stored teas are on the website now
+    ///
+    /// Bumps `last_seen` and makes discontinued teas active again, hidden teas stay hidden.
+    /// Returns the number of stored teas among the URLs.
+    async fn mark_teas_seen(&self, urls: &[String]) -> Result<usize>;
+
+    /// Mark active teas as gone from the website
+    ///
+    /// Returns the number of teas discontinued, other statuses are left as they are.
+    async fn discontinue_teas(&self, urls: &[String]) -> Result<usize>;
+
+    /// Set the status of a tea by hand (hide it or bring it back)
+    ///
+    /// Returns false if the tea is not stored.
+    async fn set_tea_status(&self, url: &str, status: TeaStatus) -> Result<bool>;
+
+    /// Replace sample links of a shop's main products
+    ///
+    /// Links must point to teas already stored in the database.
+    async fn replace_sample_links(&self, shop: &str, links: &[SampleLink]) -> Result<()>;
+
+    /// Get sample URL and its stock status for a main product
+    async fn get_sample_link(&self, url: &str) -> Result<Option<(String, bool)>>;
+
+    /// Get URLs of teas with a status
+    async fn get_tea_urls(&self, status: TeaStatus) -> Result<Vec<String>>;
+
+    /// Search teas by vector similarity (cosine distance)
+    ///
+    /// `model` is the model that made the query embedding, only its embeddings are compared.
+    /// Turso uses the IVF index unless `VECTOR_SEARCH` is `exact` or `binary`,
+    /// see [`TursoRepository::search_teas_ann`](crate::turso::TursoRepository::search_teas_ann).
+    async fn search_teas(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>>;
+
+    /// Search teas by keywords (BM25 over name, composition, tags and description)
+    async fn search_teas_keyword(
+        &self,
+        query: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>>;
+
+    /// Search teas by keywords and vector similarity, fusing both rankings
+    ///
+    /// `score` of the results is the normalized fusion score,
+    /// `vector_score` and `keyword_score` report the individual signals.
+    async fn search_teas_hybrid(
+        &self,
+        query: &str,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        config: &HybridConfig,
+    ) -> Result<Vec<SearchResult>>;
+
+    /// Get database statistics (of active teas, with counts of the others)
+    async fn get_stats(&self) -> Result<DatabaseStats>;
+
+    /// Count the teas passing the filters per facet value, see [`crate::facets`]
+    async fn get_facets(&self, filters: &SearchFilters) -> Result<CatalogFacets>;
+
+    /// Count active teas
+    async fn count_teas(&self) -> Result<usize>;
+}
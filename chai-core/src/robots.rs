@@ -0,0 +1,278 @@
+//! robots.txt parsing and matching
+//!
+//! This module provides:
+//! - Parsing robots.txt into groups (RFC 9309)
+//! - Picking the group for our robots agent (falls back to `*`)
+//! - Allow/Disallow matching with `*` and `$` wildcards (longest match wins)
+//! - Non-standard `Crawl-delay` and `Sitemap` lines
+//!
+//! Fetching and caching is done by [`crate::crawler::Crawler`].
+
+use std::time::Duration;
+
+/// Maximum robots.txt size we parse (RFC 9309 requires at least 500 KiB)
+pub const MAX_ROBOTS_SIZE: usize = 500 * 1024;
+
+/// A single Allow/Disallow rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RobotsRule {
+    pub allow: bool,
+    pub pattern: String,
+}
+
+impl std::fmt::Display for RobotsRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let directive = if self.allow { "Allow" } else { "Disallow" };
+        write!(f, "{}: {}", directive, self.pattern)
+    }
+}
+
+/// Rules of robots.txt that apply to our agent
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<Duration>,
+    disallow_all: bool,
+    /// Sitemaps listed in the file (apply to all agents)
+    pub sitemaps: Vec<String>,
+}
+
+/// Group of rules while parsing
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsTxt {
+    /// Robots that allow everything (missing robots.txt, 4xx)
+    #[must_use]
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Robots that disallow everything (robots.txt unreachable, 5xx)
+    #[must_use]
+    pub fn disallow_all() -> Self {
+        Self {
+            disallow_all: true,
+            ..Self::default()
+        }
+    }
+
+    /// Parse robots.txt body and keep the rules for `agent`
+    ///
+    /// Groups naming the agent (case-insensitive) are merged; if there are none,
+    /// the `*` groups are used instead.
+    #[must_use]
+    pub fn parse(body: &str, agent: &str) -> Self {
+        let body = truncate_to_char_boundary(body, MAX_ROBOTS_SIZE);
+        let agent = agent.to_lowercase();
+
+        let mut groups: Vec<Group> = Vec::new();
+        let mut sitemaps = Vec::new();
+        // A user-agent line after rules starts a new group
+        let mut in_rules = true;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules || groups.is_empty() {
+                        groups.push(Group::default());
+                        in_rules = false;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // Empty Disallow means "allow everything" - no rule needed
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.rules.push(RobotsRule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let (Some(group), Ok(secs)) = (groups.last_mut(), value.parse::<f64>())
+                        && secs.is_finite()
+                        && secs >= 0.0
+                    {
+                        group.crawl_delay = Some(Duration::from_secs_f64(secs));
+                    }
+                }
+                "sitemap" if !value.is_empty() => sitemaps.push(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let matches_agent = |g: &&Group| g.agents.contains(&agent);
+        let selected: Vec<&Group> = if groups.iter().any(|g| matches_agent(&g)) {
+            groups.iter().filter(matches_agent).collect()
+        } else {
+            groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect()
+        };
+
+        Self {
+            rules: selected.iter().flat_map(|g| g.rules.clone()).collect(),
+            crawl_delay: selected.iter().find_map(|g| g.crawl_delay),
+            disallow_all: false,
+            sitemaps,
+        }
+    }
+
+    /// Crawl delay requested for our agent
+    #[must_use]
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// Check if a URL path (with query) may be fetched
+    #[must_use]
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.check(path).is_ok()
+    }
+
+    /// Check a URL path, returning the rule that disallows it
+    ///
+    /// The most specific (longest) matching rule wins; on a tie Allow wins.
+    /// `/robots.txt` itself is always allowed.
+    pub fn check(&self, path: &str) -> Result<(), String> {
+        if path == "/robots.txt" {
+            return Ok(());
+        }
+        if self.disallow_all {
+            return Err("robots.txt unavailable, assuming full disallow".to_string());
+        }
+
+        let best = self
+            .rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow));
+
+        match best {
+            Some(rule) if !rule.allow => Err(rule.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Match robots.txt pattern against a path (`*` - any sequence, `$` - end of path)
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return !anchored || rest.is_empty();
+    }
+
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+fn truncate_to_char_boundary(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+User-agent: Yandex
+Disallow: /
+Crawl-delay: 10
+
+User-agent: *
+Disallow: /members/
+Disallow: /*?utm_
+Allow: /members/public$
+Crawl-delay: 2.5
+Host: beliyles.com
+Sitemap: https://beliyles.com/sitemap.xml
+";
+
+    #[test]
+    fn test_parse_groups() {
+        let robots = RobotsTxt::parse(ROBOTS, "chai");
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_millis(2500)));
+        assert_eq!(robots.sitemaps, vec!["https://beliyles.com/sitemap.xml"]);
+        assert!(robots.is_allowed("/tproduct/123-ivan-chai"));
+        assert!(!robots.is_allowed("/members/list"));
+        assert!(robots.is_allowed("/members/public"));
+        assert!(!robots.is_allowed("/members/public/x"));
+        assert!(!robots.is_allowed("/tproduct/1?utm_source=x"));
+
+        // Named group takes precedence over `*`
+        let yandex = RobotsTxt::parse(ROBOTS, "yandex");
+        assert!(!yandex.is_allowed("/tproduct/1"));
+        assert!(yandex.is_allowed("/robots.txt"));
+        assert_eq!(yandex.crawl_delay(), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_check_reports_rule() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /tproduct/\n", "chai");
+        assert_eq!(
+            robots.check("/tproduct/1"),
+            Err("Disallow: /tproduct/".to_string())
+        );
+        assert!(RobotsTxt::allow_all().is_allowed("/anything"));
+        assert!(!RobotsTxt::disallow_all().is_allowed("/anything"));
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("/", "/a"));
+        assert!(pattern_matches("/*.xml$", "/sitemap.xml"));
+        assert!(!pattern_matches("/*.xml$", "/sitemap.xml?x=1"));
+        assert!(pattern_matches("/a*b*c", "/a-b-c-d"));
+        assert!(!pattern_matches("/a*b*c", "/a-c-b"));
+        assert!(pattern_matches("/exact$", "/exact"));
+        assert!(!pattern_matches("/exact$", "/exact/"));
+    }
+}
@@ -0,0 +1,517 @@
+//! Linking samples (пробники) to their main products
+//!
+//! Strategies are tried from the most to the least reliable, the first
+//! one that finds a candidate wins:
+//! 1. URL slug: shop's [`ShopScraper::main_product_url`] of the sample vs product URL,
+//!    compared without Tilda's numeric product ID
+//! 2. Normalized name: lowercase, `ё` -> `е`, punctuation and "пробник" removed
+//! 3. Transliterated sample name vs product URL slug
+//! 4. Edit distance between normalized names (at most 20% of the longer name)
+//!
+//! A sample matching several products equally well is not linked and is
+//! reported as ambiguous, so a wrong sample never shows up on a product card.
+//!
+//! [`ShopScraper::main_product_url`]: crate::scraper::ShopScraper::main_product_url
+
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::models::Tea;
+use crate::scraper;
+
+/// Words that mark a product as a sample and are ignored in names
+const SAMPLE_WORDS: &[&str] = &["пробник", "probnik", "copy"];
+
+/// Maximum edit distance as a share of the longer name (1/5 = 80% similarity)
+const MAX_DISTANCE_RATIO: usize = 5;
+
+/// How a sample was matched to its product
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchMethod {
+    Slug,
+    Name,
+    Translit,
+    EditDistance,
+}
+
+impl MatchMethod {
+    /// Name stored in `tea_samples.match_method`
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Slug => "slug",
+            Self::Name => "name",
+            Self::Translit => "translit",
+            Self::EditDistance => "edit_distance",
+        }
+    }
+}
+
+impl std::fmt::Display for MatchMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Sample linked to a main product
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleLink {
+    pub sample_url: String,
+    pub main_url: String,
+    pub sample_in_stock: bool,
+    pub method: MatchMethod,
+}
+
+/// Sample that matched several products equally well
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmbiguousSample {
+    pub sample_url: String,
+    pub method: MatchMethod,
+    pub candidates: Vec<String>,
+}
+
+/// Result of linking samples of a sync run
+#[derive(Debug, Clone, Default)]
+pub struct LinkReport {
+    /// One link per main product, sorted by sample URL
+    pub links: Vec<SampleLink>,
+    /// Samples not linked because several products matched
+    pub ambiguous: Vec<AmbiguousSample>,
+    /// Samples without a matching product
+    pub missing: Vec<String>,
+    /// Samples of a product that already has a better matching sample
+    pub duplicates: Vec<SampleLink>,
+}
+
+impl LinkReport {
+    /// Log link counts, listing ambiguous and missing samples
+    pub fn log(&self) {
+        let mut by_method: Vec<(MatchMethod, usize)> = Vec::new();
+        for link in &self.links {
+            match by_method
+                .iter_mut()
+                .find(|(method, _)| *method == link.method)
+            {
+                Some((_, count)) => *count += 1,
+                None => by_method.push((link.method, 1)),
+            }
+        }
+        by_method.sort();
+
+        info!(
+            "Linking done: {} linked, {} ambiguous, {} not found, {} duplicate samples",
+            self.links.len(),
+            self.ambiguous.len(),
+            self.missing.len(),
+            self.duplicates.len()
+        );
+        for (method, count) in by_method {
+            info!("  by {}: {}", method, count);
+        }
+
+        for sample in &self.ambiguous {
+            warn!(
+                "  ? {} (by {}): {}",
+                sample.sample_url,
+                sample.method,
+                sample.candidates.join(", ")
+            );
+        }
+        for url in &self.missing {
+            warn!("  x {}: no main product", url);
+        }
+        for link in &self.duplicates {
+            info!(
+                "  = {}: {} already has a sample",
+                link.sample_url, link.main_url
+            );
+        }
+    }
+}
+
+/// Main product prepared for matching
+struct Candidate<'a> {
+    tea: &'a Tea,
+    name: String,
+    slug: String,
+}
+
+/// Outcome of matching a single sample
+enum Match {
+    Found(String, MatchMethod),
+    Ambiguous(Vec<String>, MatchMethod),
+    NotFound,
+}
+
+/// Link samples to main products of the same shop
+#[must_use]
+pub fn link_samples(samples: &[&Tea], main_products: &[&Tea]) -> LinkReport {
+    let candidates: Vec<Candidate> = main_products
+        .iter()
+        .map(|tea| Candidate {
+            tea,
+            name: normalize_name(tea.name.as_deref().unwrap_or_default()),
+            slug: url_slug(&tea.url),
+        })
+        .collect();
+
+    let mut report = LinkReport::default();
+    let mut by_main: HashMap<String, SampleLink> = HashMap::new();
+
+    for sample in samples {
+        let shop_candidates: Vec<&Candidate> = candidates
+            .iter()
+            .filter(|c| c.tea.shop == sample.shop)
+            .collect();
+
+        let (main_url, method) = match match_sample(sample, &shop_candidates) {
+            Match::Found(main_url, method) => (main_url, method),
+            Match::Ambiguous(candidates, method) => {
+                report.ambiguous.push(AmbiguousSample {
+                    sample_url: sample.url.clone(),
+                    method,
+                    candidates,
+                });
+                continue;
+            }
+            Match::NotFound => {
+                report.missing.push(sample.url.clone());
+                continue;
+            }
+        };
+
+        let link = SampleLink {
+            sample_url: sample.url.clone(),
+            main_url,
+            sample_in_stock: sample.in_stock,
+            method,
+        };
+
+        // Keep the best sample per product: stronger match, then in stock
+        let rank = |l: &SampleLink| (l.method, !l.sample_in_stock, l.sample_url.clone());
+        match by_main.get(&link.main_url) {
+            Some(existing) if rank(existing) <= rank(&link) => report.duplicates.push(link),
+            _ => {
+                if let Some(replaced) = by_main.insert(link.main_url.clone(), link) {
+                    report.duplicates.push(replaced);
+                }
+            }
+        }
+    }
+
+    report.links = by_main.into_values().collect();
+    report.links.sort_by(|a, b| a.sample_url.cmp(&b.sample_url));
+    report
+        .duplicates
+        .sort_by(|a, b| a.sample_url.cmp(&b.sample_url));
+    report.missing.sort();
+    report
+}
+
+/// Try all strategies for a single sample
+fn match_sample(sample: &Tea, candidates: &[&Candidate]) -> Match {
+    let pick = |matches: Vec<&&Candidate>, method| match matches.as_slice() {
+        [] => None,
+        [single] => Some(Match::Found(single.tea.url.clone(), method)),
+        _ => Some(Match::Ambiguous(
+            matches.iter().map(|c| c.tea.url.clone()).collect(),
+            method,
+        )),
+    };
+
+    // 1. URL slug
+    if let Some(shop) = scraper::scraper_for_url(&sample.url) {
+        let slug = url_slug(&shop.main_product_url(&sample.url));
+        if !slug.is_empty() {
+            let matches = candidates.iter().filter(|c| c.slug == slug).collect();
+            if let Some(found) = pick(matches, MatchMethod::Slug) {
+                return found;
+            }
+        }
+    }
+
+    let name = normalize_name(sample.name.as_deref().unwrap_or_default());
+    if name.is_empty() {
+        return Match::NotFound;
+    }
+
+    // 2. Normalized name
+    let matches = candidates.iter().filter(|c| c.name == name).collect();
+    if let Some(found) = pick(matches, MatchMethod::Name) {
+        return found;
+    }
+
+    // 3. Transliterated name vs URL slug
+    let translit = slugify(&name);
+    let matches = candidates.iter().filter(|c| c.slug == translit).collect();
+    if let Some(found) = pick(matches, MatchMethod::Translit) {
+        return found;
+    }
+
+    // 4. Closest name by edit distance
+    let name_len = name.chars().count();
+    let mut best: Option<usize> = None;
+    let mut matches: Vec<&&Candidate> = Vec::new();
+    for candidate in candidates.iter().filter(|c| !c.name.is_empty()) {
+        let max_len = name_len.max(candidate.name.chars().count());
+        let limit = max_len / MAX_DISTANCE_RATIO;
+        if name_len.abs_diff(candidate.name.chars().count()) > limit {
+            continue;
+        }
+        let distance = edit_distance(&name, &candidate.name);
+        if distance > limit || best.is_some_and(|b| distance > b) {
+            continue;
+        }
+        if best != Some(distance) {
+            best = Some(distance);
+            matches.clear();
+        }
+        matches.push(candidate);
+    }
+
+    pick(matches, MatchMethod::EditDistance).unwrap_or(Match::NotFound)
+}
+
+/// Normalize a product name for comparison
+///
+/// "Copy: Пробник Иван-чай «Ёлка»" -> "иван чай елка"
+#[must_use]
+pub fn normalize_name(name: &str) -> String {
+    let lower = name.to_lowercase().replace('ё', "е");
+    let cleaned: String = lower
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+
+    cleaned
+        .split_whitespace()
+        .filter(|word| !SAMPLE_WORDS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Transliterate Cyrillic text the way Tilda builds product slugs
+///
+/// "иван чай с облепихой" -> "ivan-chai-s-oblepihoi"
+#[must_use]
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.to_lowercase().chars() {
+        let latin = match c {
+            'а' => "a",
+            'б' => "b",
+            'в' => "v",
+            'г' => "g",
+            'д' => "d",
+            'е' | 'ё' | 'э' => "e",
+            'ж' => "zh",
+            'з' => "z",
+            'и' | 'й' | 'ы' => "i",
+            'к' => "k",
+            'л' => "l",
+            'м' => "m",
+            'н' => "n",
+            'о' => "o",
+            'п' => "p",
+            'р' => "r",
+            'с' => "s",
+            'т' => "t",
+            'у' => "u",
+            'ф' => "f",
+            'х' => "h",
+            'ц' => "c",
+            'ч' => "ch",
+            'ш' => "sh",
+            'щ' => "sch",
+            'ъ' | 'ь' => "",
+            'ю' => "yu",
+            'я' => "ya",
+            c if c.is_ascii_alphanumeric() => {
+                slug.push(c);
+                continue;
+            }
+            _ => "-",
+        };
+        slug.push_str(latin);
+    }
+
+    slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Last path segment of a product URL without the numeric product ID
+///
+/// "https://beliyles.com/tproduct/123-ivan-chai" -> "ivan-chai"
+#[must_use]
+pub fn url_slug(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let segment = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    let slug = match segment.split_once('-') {
+        Some((id, rest)) if id.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => segment,
+    };
+    slug.to_lowercase()
+}
+
+/// Levenshtein distance over characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tea(url: &str, name: &str, in_stock: bool) -> Tea {
+        Tea {
+            name: Some(name.to_string()),
+            in_stock,
+            ..Tea::new(url)
+        }
+    }
+
+    #[test]
+    fn test_normalize_and_slugs() {
+        assert_eq!(
+            normalize_name("Copy: Пробник Иван-чай «Ёлка»"),
+            "иван чай елка"
+        );
+        assert_eq!(slugify("Иван-чай с облепихой"), "ivan-chai-s-oblepihoi");
+        assert_eq!(slugify("Набор «Ягодные» 3x10"), "nabor-yagodnie-3x10");
+        assert_eq!(
+            url_slug("https://beliyles.com/tproduct/123456-ivan-chai?x=1"),
+            "ivan-chai"
+        );
+        assert_eq!(url_slug("https://beliyles.com/tea/ivan-chai/"), "ivan-chai");
+        assert_eq!(edit_distance("чабрец", "чебрец"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_link_strategies() {
+        let mains = [
+            tea(
+                "https://beliyles.com/tproduct/1-ivan-chai-s-oblepihoi",
+                "Иван-чай с облепихой",
+                true,
+            ),
+            tea(
+                "https://beliyles.com/tproduct/2-chabrec",
+                "Иван-чай с чабрецом",
+                true,
+            ),
+            tea(
+                "https://beliyles.com/tproduct/3-kiprei-lesnoi",
+                "Лесной сбор",
+                true,
+            ),
+            tea(
+                "https://beliyles.com/tproduct/4-mint",
+                "Иван-чай с мятой",
+                true,
+            ),
+        ];
+        let samples = [
+            // Slug of the product URL
+            tea(
+                "https://beliyles.com/tproduct/11-probnik-ivan-chai-s-oblepihoi",
+                "Пробник облепиха",
+                false,
+            ),
+            // Same name, unrelated slug
+            tea(
+                "https://beliyles.com/tproduct/12-probnik-1",
+                "Пробник Иван-чай с чабрецом",
+                true,
+            ),
+            // Transliterated name equals the product slug
+            tea(
+                "https://beliyles.com/tproduct/13-probnik-2",
+                "Пробник Кипрей лесной",
+                true,
+            ),
+            // Typo in the name
+            tea(
+                "https://beliyles.com/tproduct/14-probnik-3",
+                "Пробник Иван-чай с мятою",
+                true,
+            ),
+            tea(
+                "https://beliyles.com/tproduct/15-probnik-4",
+                "Пробник Пуэр",
+                true,
+            ),
+        ];
+
+        let report = link_samples(
+            &samples.iter().collect::<Vec<_>>(),
+            &mains.iter().collect::<Vec<_>>(),
+        );
+
+        let links: Vec<(&str, MatchMethod)> = report
+            .links
+            .iter()
+            .map(|l| (l.main_url.as_str(), l.method))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (mains[0].url.as_str(), MatchMethod::Slug),
+                (mains[1].url.as_str(), MatchMethod::Name),
+                (mains[2].url.as_str(), MatchMethod::Translit),
+                (mains[3].url.as_str(), MatchMethod::EditDistance),
+            ]
+        );
+        assert!(!report.links[0].sample_in_stock);
+        assert_eq!(report.missing, vec![samples[4].url.clone()]);
+        assert!(report.ambiguous.is_empty());
+    }
+
+    #[test]
+    fn test_link_conflicts() {
+        let mains = [
+            tea("https://beliyles.com/tproduct/1-a", "Иван-чай", true),
+            tea("https://beliyles.com/tproduct/2-b", "Иван-чай", true),
+            tea("https://beliyles.com/tproduct/3-myata", "Мята", true),
+        ];
+        let samples = [
+            tea(
+                "https://beliyles.com/tproduct/11-x",
+                "Пробник Иван-чай",
+                true,
+            ),
+            tea("https://beliyles.com/tproduct/12-y", "Пробник Мята", false),
+            tea("https://beliyles.com/tproduct/13-z", "Мята (пробник)", true),
+        ];
+
+        let report = link_samples(
+            &samples.iter().collect::<Vec<_>>(),
+            &mains.iter().collect::<Vec<_>>(),
+        );
+
+        // Two products with the same name - don't guess
+        assert_eq!(report.ambiguous.len(), 1);
+        assert_eq!(report.ambiguous[0].candidates.len(), 2);
+
+        // Product keeps the sample that is in stock
+        assert_eq!(report.links.len(), 1);
+        assert_eq!(report.links[0].sample_url, samples[2].url);
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].sample_url, samples[1].url);
+    }
+}
@@ -0,0 +1,440 @@
+//! Scraper for beliyles.com (Tilda store)
+
+use regex::Regex;
+use scraper::{Html, Selector};
+use std::sync::LazyLock;
+
+use super::{
+    ParseError, ParseReport, ShopScraper, TeaField, apply_characteristics, extract_between,
+    strip_html,
+};
+use crate::models::{PriceVariant, Tea};
+
+// Pre-compiled regexes for better performance
+static PRODUCT_JSON_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"var product = (\{.+?\});").expect("Invalid PRODUCT_JSON_RE"));
+static VOLUME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+[-~≈]?\d*)").expect("Invalid VOLUME_RE"));
+
+// Pre-compiled CSS selectors for better performance
+static SCRIPT_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("script").expect("Invalid script selector"));
+
+const SITEMAP_URLS: &[&str] = &["https://beliyles.com/sitemap-store.xml"];
+
+/// Scraper for beliyles.com
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BeliylesScraper;
+
+impl ShopScraper for BeliylesScraper {
+    fn id(&self) -> &'static str {
+        "beliyles"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Белый лес"
+    }
+
+    fn host(&self) -> &'static str {
+        "beliyles.com"
+    }
+
+    fn sitemap_urls(&self) -> &'static [&'static str] {
+        SITEMAP_URLS
+    }
+
+    fn is_product_url(&self, url: &str) -> bool {
+        // Filter only products (exclude constructors and certificates)
+        // Include samples for later processing
+        url.contains("/tproduct/") && !url.contains("/constructor/") && !url.contains("/card/")
+    }
+
+    fn parse_document(&self, url: &str, document: &Html) -> Result<(Tea, ParseReport), ParseError> {
+        let mut tea = Tea::new(url);
+        tea.shop = self.id().to_string();
+        let mut report = ParseReport::new(url);
+
+        let product_script = document
+            .select(&SCRIPT_SELECTOR)
+            .map(|script| script.text().collect::<String>())
+            .find_map(|text| extract_product_json(&text));
+
+        let Some(json_str) = product_script else {
+            return Err(ParseError::NoProductScript {
+                url: url.to_string(),
+            });
+        };
+
+        let product_data = serde_json::from_str::<serde_json::Value>(&json_str).map_err(|e| {
+            ParseError::InvalidProductJson {
+                url: url.to_string(),
+                message: e.to_string(),
+            }
+        })?;
+        parse_product_json(&mut tea, &product_data, &mut report);
+
+        // Determine product type
+        let is_sample = self.is_sample(url, &tea.name);
+        tea.is_sample = is_sample;
+        tea.is_set = self.is_sample_set(url, &tea.name);
+
+        // Check: if no product data at all - skip
+        if tea.name.is_none() && tea.images.is_empty() {
+            return Err(ParseError::NoProductData {
+                url: url.to_string(),
+            });
+        }
+
+        // Check: if this is a discontinued sample (suffix "r" + no price)
+        if is_sample && let Some(name) = &tea.name {
+            let name_lower = name.to_lowercase();
+            let has_r_suffix = name_lower.ends_with(" r") || name_lower.contains(" r\"");
+            let no_price = tea.price.is_none() || tea.price.as_ref().is_none_or(|p| p.is_empty());
+
+            if has_r_suffix && no_price && !tea.in_stock {
+                return Err(ParseError::DiscontinuedSample {
+                    url: url.to_string(),
+                    name: name.clone(),
+                });
+            }
+        }
+
+        Ok((tea, report))
+    }
+
+    fn is_sample(&self, url: &str, _name: &Option<String>) -> bool {
+        url.contains("probnik") || url.contains("/probe/")
+    }
+
+    fn is_sample_set(&self, url: &str, name: &Option<String>) -> bool {
+        if url.contains("nabor") || url.contains("набор") {
+            return true;
+        }
+
+        if let Some(n) = name {
+            let n_lower = n.to_lowercase();
+            if n_lower.contains("набор") || n_lower.contains("nabor") {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn main_product_url(&self, sample_url: &str) -> String {
+        sample_url
+            .replace("probnik-", "")
+            .replace("/probe/", "/")
+            .replace("/rasprodazha/", "/")
+    }
+}
+
+/// Extract JSON from "var product = {...};" string
+fn extract_product_json(text: &str) -> Option<String> {
+    let caps = PRODUCT_JSON_RE.captures(text)?;
+    caps.get(1).map(|m| m.as_str().to_string())
+}
+
+/// Parse data from JSON product object
+fn parse_product_json(tea: &mut Tea, data: &serde_json::Value, report: &mut ParseReport) {
+    // Title
+    if let Some(title) = data["title"].as_str() {
+        tea.name = Some(title.to_string());
+        if title.trim().is_empty() {
+            report.suspicious(TeaField::Name, "product.title is empty");
+        }
+    } else {
+        report.missing(TeaField::Name, "product.title not found");
+    }
+
+    // Price
+    if let Some(price) = data["price"].as_str() {
+        tea.price = Some(price.to_string());
+    } else {
+        report.missing(TeaField::Price, "product.price not found");
+    }
+
+    // Images
+    if let Some(gallery) = data["gallery"].as_array() {
+        tea.images = gallery
+            .iter()
+            .filter_map(|img| img["img"].as_str().map(|s| s.to_string()))
+            .collect();
+
+        if tea.images.len() < gallery.len() {
+            report.suspicious(
+                TeaField::Images,
+                format!(
+                    "{} of {} gallery items have no 'img'",
+                    gallery.len() - tea.images.len(),
+                    gallery.len()
+                ),
+            );
+        }
+    }
+    if tea.images.is_empty() {
+        report.missing(TeaField::Images, "product.gallery is empty");
+    }
+
+    // Price variants/editions
+    if let Some(editions) = data["editions"].as_array() {
+        tea.price_variants = editions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, edition)| {
+                let mut field = |name: &str| {
+                    let value = edition[name].as_str();
+                    if value.is_none() {
+                        report.suspicious(
+                            TeaField::PriceVariants,
+                            format!("edition #{} has no '{}', skipped", i + 1, name),
+                        );
+                    }
+                    value
+                };
+
+                Some(PriceVariant {
+                    packaging: field("Упаковка")?.to_string(),
+                    price: field("price")?.to_string(),
+                    quantity: field("quantity")?.to_string(),
+                })
+            })
+            .collect();
+
+        for variant in &tea.price_variants {
+            if !variant.quantity.is_empty() && variant.quantity.parse::<i32>().is_err() {
+                report.suspicious(
+                    TeaField::Stock,
+                    format!("quantity '{}' is not a number", variant.quantity),
+                );
+            }
+        }
+
+        // Check stock (if at least one variant has quantity > 0)
+        tea.in_stock = tea
+            .price_variants
+            .iter()
+            .any(|v| v.quantity.parse::<i32>().unwrap_or(0) > 0);
+
+        // Extract volume options
+        tea.volume_options = tea
+            .price_variants
+            .iter()
+            .filter_map(|v| {
+                let volume = VOLUME_RE
+                    .captures(&v.packaging)
+                    .and_then(|cap| cap.get(1))
+                    .map(|m| m.as_str().to_string());
+                if volume.is_none() {
+                    report.suspicious(
+                        TeaField::VolumeOptions,
+                        format!("no volume in packaging '{}'", v.packaging),
+                    );
+                }
+                volume
+            })
+            .collect();
+    }
+
+    // If price_variants is empty, check main quantity field
+    if !tea.in_stock
+        && let Some(quantity_str) = data["quantity"].as_str()
+    {
+        tea.in_stock = quantity_str.parse::<i32>().unwrap_or(0) > 0;
+    }
+
+    // Full description text
+    if let Some(text) = data["text"].as_str() {
+        // Description (before "Состав:")
+        if let Some(desc_end) = text.find("Состав:") {
+            let desc = &text[..desc_end];
+            tea.description = Some(strip_html(desc));
+        } else {
+            report.missing(
+                TeaField::Description,
+                "marker 'Состав:' not found (description ends there)",
+            );
+        }
+
+        // Composition
+        if let Some(comp_match) = extract_between(text, "Состав:", "<br") {
+            tea.composition = comp_match
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            if tea.composition.is_empty() {
+                report.suspicious(TeaField::Composition, "empty list after 'Состав:'");
+            }
+        } else {
+            report.missing(
+                TeaField::Composition,
+                "marker 'Состав:' ... '<br' not found",
+            );
+        }
+
+        // Detailed composition
+        if let Some(detailed_comp) = extract_between(text, "Подробный состав:", "<br")
+        {
+            tea.full_composition = detailed_comp
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        } else {
+            report.missing(
+                TeaField::FullComposition,
+                "marker 'Подробный состав:' ... '<br' not found",
+            );
+        }
+
+        // Search tags
+        if let Some(tags) = extract_between(text, "Также для поиска:", "<br") {
+            tea.search_tags = tags
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        } else {
+            report.missing(
+                TeaField::SearchTags,
+                "marker 'Также для поиска:' ... '<br' not found",
+            );
+        }
+
+        // Storage info
+        if let Some(storage) = extract_between(text, "Хранить", "Дата изготовления")
+        {
+            tea.storage_info = Some(
+                storage
+                    .replace("<br />", " ")
+                    .replace("<br/>", " ")
+                    .trim()
+                    .to_string(),
+            );
+        } else {
+            report.missing(
+                TeaField::StorageInfo,
+                "marker 'Хранить' ... 'Дата изготовления' not found",
+            );
+        }
+    } else {
+        report.missing(TeaField::Description, "product.text not found");
+    }
+
+    // Package dimensions (from first variant if available)
+    if let Some(first_edition) = data["editions"].as_array().and_then(|arr| arr.first()) {
+        if let (Some(x), Some(y), Some(z)) = (
+            first_edition["pack_x"].as_i64(),
+            first_edition["pack_y"].as_i64(),
+            first_edition["pack_z"].as_i64(),
+        ) {
+            tea.dimensions = Some(format!("{}x{}x{} mm", x, y, z));
+        } else {
+            report.missing(
+                TeaField::Dimensions,
+                "edition pack_x/pack_y/pack_z not found",
+            );
+        }
+
+        if let Some(weight) = first_edition["pack_m"].as_i64() {
+            tea.weight = Some(format!("{} g", weight));
+        } else {
+            report.missing(TeaField::Weight, "edition pack_m not found");
+        }
+    } else {
+        report.missing(TeaField::PriceVariants, "product.editions not found");
+    }
+
+    // Characteristics (series, origin, ...)
+    if let Some(chars) = data["characteristics"].as_array() {
+        for char in chars {
+            let value = match &char["value"] {
+                serde_json::Value::String(s) => s.trim().to_string(),
+                serde_json::Value::Number(n) => n.to_string(),
+                _ => continue,
+            };
+            if let Some(title) = char["title"].as_str()
+                && !title.trim().is_empty()
+                && !value.is_empty()
+            {
+                tea.characteristics.insert(title.trim().to_string(), value);
+            }
+        }
+    }
+    apply_characteristics(tea, report);
+    if tea.series.is_none() {
+        report.missing(TeaField::Series, "characteristic 'Серия' not found");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_product_json() {
+        let text = r#"var product = {"title":"Test","price":"100"}; var other = 1;"#;
+        let result = extract_product_json(text);
+        assert_eq!(
+            result,
+            Some(r#"{"title":"Test","price":"100"}"#.to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let html = r#"<script>var product = {"title":"Иван-чай","price":"100","gallery":[{"img":"a.jpg"}],"text":"Вкусный<br />Состав: иван-чай<br />","editions":[{"Упаковка":"Пакет","price":"100","quantity":"x"},{"price":"200","quantity":"1"}]};</script>"#;
+        let document = Html::parse_document(html);
+
+        let (tea, report) = BeliylesScraper
+            .parse_document("https://beliyles.com/tproduct/1-ivan-chai", &document)
+            .unwrap();
+
+        assert_eq!(tea.composition, vec!["иван-чай"]);
+        assert!(!report.has_issue(TeaField::Name));
+        assert!(!report.has_issue(TeaField::Composition));
+        assert!(report.has_issue(TeaField::PriceVariants)); // second edition has no 'Упаковка'
+        assert!(report.has_issue(TeaField::Stock)); // quantity "x"
+        assert!(report.has_issue(TeaField::VolumeOptions)); // "Пакет" has no volume
+        assert!(report.has_issue(TeaField::Series));
+        assert!(report.has_issue(TeaField::Dimensions));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let url = "https://beliyles.com/tproduct/1-ivan-chai";
+
+        let document = Html::parse_document("<html><body>Nothing here</body></html>");
+        assert!(matches!(
+            BeliylesScraper.parse_document(url, &document),
+            Err(ParseError::NoProductScript { .. })
+        ));
+
+        let document = Html::parse_document(r#"<script>var product = {"title":};</script>"#);
+        assert!(matches!(
+            BeliylesScraper.parse_document(url, &document),
+            Err(ParseError::InvalidProductJson { .. })
+        ));
+
+        let document = Html::parse_document(r#"<script>var product = {"price":"1"};</script>"#);
+        assert!(matches!(
+            BeliylesScraper.parse_document(url, &document),
+            Err(ParseError::NoProductData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sample_classification() {
+        let shop = BeliylesScraper;
+        let url = "https://beliyles.com/tproduct/123-probnik-ivan-chai";
+
+        assert!(shop.is_sample(url, &None));
+        assert!(!shop.is_sample("https://beliyles.com/tproduct/123-ivan-chai", &None));
+        assert!(shop.is_sample_set(url, &Some("Набор пробников".to_string())));
+        assert_eq!(
+            shop.main_product_url(url),
+            "https://beliyles.com/tproduct/123-ivan-chai"
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Web scraping of tea shops
+//!
+//! Each supported shop implements [`ShopScraper`], which covers:
+//! - Product URL discovery (sitemaps + URL filtering, `<lastmod>` for incremental crawls)
+//! - Parsing a product page into a [`Tea`] with a [`ParseReport`] of missing/suspicious fields
+//! - Sample/set classification and sample -> main product mapping
+//!
+//! The free functions in this module dispatch to the right scraper
+//! based on the URL, so callers don't need to know which shop a page is from.
+
+mod beliyles;
+mod report;
+mod sitemap;
+
+pub use beliyles::BeliylesScraper;
+pub use report::{IssueKind, ParseError, ParseIssue, ParseReport, ParseSummary, TeaField};
+pub use sitemap::{PageChange, Sitemap, SitemapEntry, parse_lastmod, parse_sitemap};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use scraper::Html;
+use std::collections::{HashSet, VecDeque};
+use std::sync::LazyLock;
+use tracing::{info, warn};
+
+use crate::crawler::Crawler;
+use crate::models::Tea;
+
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").expect("Invalid TAG_RE"));
+static WHITESPACE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s+").expect("Invalid WHITESPACE_RE"));
+static YEAR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(19|20)\d{2}\b").expect("Invalid YEAR_RE"));
+
+/// Characteristic names mapped to `Tea::series`
+const SERIES_KEYS: &[&str] = &["серия"];
+/// Characteristic names mapped to `Tea::origin`
+const ORIGIN_KEYS: &[&str] = &[
+    "происхождение",
+    "регион",
+    "регион сбора",
+    "место сбора",
+    "страна",
+    "страна происхождения",
+];
+/// Characteristic names mapped to `Tea::harvest_year`
+const HARVEST_YEAR_KEYS: &[&str] = &["год урожая", "год сбора", "урожай", "сбор"];
+/// Characteristic names mapped to `Tea::leaf_type`
+const LEAF_TYPE_KEYS: &[&str] = &["тип листа", "вид листа", "лист", "листовой тип"];
+/// Characteristic names mapped to `Tea::fermentation`
+const FERMENTATION_KEYS: &[&str] = &["ферментация", "степень ферментации"];
+
+/// How deep sitemap index files are followed
+const MAX_SITEMAP_DEPTH: usize = 3;
+
+/// A tea shop that can be crawled and parsed
+pub trait ShopScraper: Send + Sync {
+    /// Stable shop identifier (stored in the `teas.shop` column)
+    fn id(&self) -> &'static str;
+
+    /// Human-readable shop name for UI and CLI output
+    fn display_name(&self) -> &'static str;
+
+    /// Host name of the shop, used to route URLs to their scraper
+    fn host(&self) -> &'static str;
+
+    /// Sitemaps listing the shop's product pages
+    fn sitemap_urls(&self) -> &'static [&'static str];
+
+    /// Check if a sitemap URL points to a product page we want to index
+    fn is_product_url(&self, url: &str) -> bool;
+
+    /// Parse a product page into a Tea with diagnostics of the extracted fields
+    fn parse_document(
+        &self,
+        url: &str,
+        document: &Html,
+    ) -> std::result::Result<(Tea, ParseReport), ParseError>;
+
+    /// Check if product is a sample (пробник)
+    fn is_sample(&self, url: &str, name: &Option<String>) -> bool;
+
+    /// Check if product is a sample set (набор)
+    fn is_sample_set(&self, url: &str, name: &Option<String>) -> bool;
+
+    /// Try to find main product URL from sample URL
+    fn main_product_url(&self, sample_url: &str) -> String;
+
+    /// Check if URL belongs to this shop
+    fn handles_url(&self, url: &str) -> bool {
+        url_host(url).is_some_and(|host| {
+            host == self.host() || host.strip_prefix("www.") == Some(self.host())
+        })
+    }
+}
+
+/// All supported shops
+static SCRAPERS: &[&dyn ShopScraper] = &[&BeliylesScraper];
+
+/// Get all registered shop scrapers
+#[must_use]
+pub fn all_scrapers() -> &'static [&'static dyn ShopScraper] {
+    SCRAPERS
+}
+
+/// Find scraper by shop ID
+#[must_use]
+pub fn scraper_by_id(id: &str) -> Option<&'static dyn ShopScraper> {
+    SCRAPERS.iter().copied().find(|s| s.id() == id)
+}
+
+/// Find scraper responsible for a URL
+#[must_use]
+pub fn scraper_for_url(url: &str) -> Option<&'static dyn ShopScraper> {
+    SCRAPERS.iter().copied().find(|s| s.handles_url(url))
+}
+
+/// Resolve shop IDs from the CLI/config into scrapers (all shops if empty)
+pub fn resolve_scrapers(ids: &[String]) -> Result<Vec<&'static dyn ShopScraper>> {
+    if ids.is_empty() {
+        return Ok(SCRAPERS.to_vec());
+    }
+
+    ids.iter()
+        .map(|id| {
+            scraper_by_id(id).ok_or_else(|| {
+                let known: Vec<_> = SCRAPERS.iter().map(|s| s.id()).collect();
+                anyhow::anyhow!("Unknown shop '{}' (known: {})", id, known.join(", "))
+            })
+        })
+        .collect()
+}
+
+/// Extract host part from URL
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?;
+    Some(host.split(':').next().unwrap_or(host))
+}
+
+/// Check if URL is a product page of any supported shop
+///
+/// Filters out other pages stored in the cache (e.g. robots.txt).
+#[must_use]
+pub fn is_product_url(url: &str) -> bool {
+    scraper_for_url(url).is_some_and(|shop| shop.is_product_url(url))
+}
+
+fn scraper_for_url_or_err(url: &str) -> std::result::Result<&'static dyn ShopScraper, ParseError> {
+    scraper_for_url(url).ok_or_else(|| ParseError::UnsupportedUrl {
+        url: url.to_string(),
+    })
+}
+
+/// Get list of all tea URLs of a shop from its sitemaps
+pub async fn get_tea_urls(crawler: &Crawler, shop: &dyn ShopScraper) -> Result<Vec<String>> {
+    let entries = get_tea_entries(crawler, shop).await?;
+    Ok(entries.into_iter().map(|entry| entry.url).collect())
+}
+
+/// Get all tea pages of a shop with their `<lastmod>`
+///
+/// Sitemap index files are followed (up to a few levels deep).
+pub async fn get_tea_entries(
+    crawler: &Crawler,
+    shop: &dyn ShopScraper,
+) -> Result<Vec<SitemapEntry>> {
+    info!("Fetching tea URLs from {} sitemap", shop.display_name());
+
+    let mut entries = Vec::new();
+    let mut seen_urls = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut queue: VecDeque<(String, usize)> = shop
+        .sitemap_urls()
+        .iter()
+        .map(|url| (url.to_string(), 0))
+        .collect();
+
+    while let Some((sitemap_url, depth)) = queue.pop_front() {
+        if !visited.insert(sitemap_url.clone()) {
+            continue;
+        }
+
+        let page = match crawler.fetch(&sitemap_url).await {
+            Ok(page) => page,
+            Err(e) if e.disallowed => {
+                warn!("Skipping sitemap {}", e);
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to fetch sitemap"),
+        };
+
+        let sitemap = parse_sitemap(&page.body);
+
+        if depth < MAX_SITEMAP_DEPTH {
+            queue.extend(sitemap.sitemaps.into_iter().map(|url| (url, depth + 1)));
+        } else if !sitemap.sitemaps.is_empty() {
+            warn!("Sitemap index nested too deep, ignoring: {}", sitemap_url);
+        }
+
+        entries.extend(
+            sitemap
+                .entries
+                .into_iter()
+                .filter(|entry| shop.is_product_url(&entry.url))
+                .filter(|entry| seen_urls.insert(entry.url.clone())),
+        );
+    }
+
+    let with_lastmod = entries.iter().filter(|e| e.lastmod.is_some()).count();
+    info!(
+        "Found {} teas ({} with lastmod)",
+        entries.len(),
+        with_lastmod
+    );
+    Ok(entries)
+}
+
+/// Parse tea from HTML string (for cache)
+pub fn parse_tea_from_html(url: &str, html: &str) -> Result<Tea> {
+    let (tea, _) = parse_tea_with_report(url, html)?;
+    Ok(tea)
+}
+
+/// Parse tea from HTML string, keeping field diagnostics
+pub fn parse_tea_with_report(
+    url: &str,
+    html: &str,
+) -> std::result::Result<(Tea, ParseReport), ParseError> {
+    let shop = scraper_for_url_or_err(url)?;
+    let document = Html::parse_document(html);
+    shop.parse_document(url, &document)
+}
+
+/// Scrape a single tea page (fetch from website)
+pub async fn scrape_tea(crawler: &Crawler, url: &str) -> Result<Tea> {
+    let shop = scraper_for_url_or_err(url)?;
+
+    let page = crawler
+        .fetch(url)
+        .await
+        .context("Failed to fetch tea page")?;
+
+    let document = Html::parse_document(&page.body);
+    let (tea, _) = shop.parse_document(url, &document)?;
+    Ok(tea)
+}
+
+/// Fill typed fields (series, origin, harvest year, ...) from `tea.characteristics`
+///
+/// Characteristic names are matched case-insensitively against known aliases.
+pub(crate) fn apply_characteristics(tea: &mut Tea, report: &mut ParseReport) {
+    let find = |keys: &[&str]| {
+        tea.characteristics
+            .iter()
+            .find(|(name, _)| keys.contains(&name.trim().to_lowercase().as_str()))
+            .map(|(_, value)| value.clone())
+    };
+
+    let series = find(SERIES_KEYS);
+    let origin = find(ORIGIN_KEYS);
+    let harvest = find(HARVEST_YEAR_KEYS);
+    let leaf_type = find(LEAF_TYPE_KEYS);
+    let fermentation = find(FERMENTATION_KEYS);
+
+    if series.is_some() {
+        tea.series = series;
+    }
+    if origin.is_some() {
+        tea.origin = origin;
+    }
+    if let Some(harvest) = harvest {
+        tea.harvest_year = YEAR_RE
+            .find(&harvest)
+            .and_then(|year| year.as_str().parse().ok());
+        if tea.harvest_year.is_none() {
+            report.suspicious(
+                TeaField::HarvestYear,
+                format!("no year in characteristic value '{}'", harvest),
+            );
+        }
+    }
+    if leaf_type.is_some() {
+        tea.leaf_type = leaf_type;
+    }
+    if fermentation.is_some() {
+        tea.fermentation = fermentation;
+    }
+}
+
+/// Strip HTML tags and normalize whitespace
+pub(crate) fn strip_html(text: &str) -> String {
+    let no_tags = TAG_RE.replace_all(text, " ");
+    let normalized = WHITESPACE_RE.replace_all(&no_tags, " ");
+    normalized.trim().to_string()
+}
+
+/// Extract text between two markers
+pub(crate) fn extract_between(text: &str, start_marker: &str, end_marker: &str) -> Option<String> {
+    let start = text.find(start_marker)? + start_marker.len();
+    let remaining = &text[start..];
+    let end = remaining.find(end_marker)?;
+    let raw = remaining[..end].trim();
+    Some(clean_html(raw))
+}
+
+/// Clean text from HTML tags and entities
+pub(crate) fn clean_html(text: &str) -> String {
+    let result = TAG_RE.replace_all(text, "");
+
+    let result = result
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'");
+
+    WHITESPACE_RE.replace_all(&result, " ").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scraper_for_url() {
+        let shop = scraper_for_url("https://beliyles.com/tproduct/123-ivan-chai").unwrap();
+        assert_eq!(shop.id(), "beliyles");
+
+        assert!(scraper_for_url("https://www.beliyles.com/tproduct/1").is_some());
+        assert!(scraper_for_url("https://example.com/tproduct/1").is_none());
+        assert!(scraper_for_url("https://notbeliyles.com/tproduct/1").is_none());
+
+        assert!(is_product_url(
+            "https://beliyles.com/tproduct/123-ivan-chai"
+        ));
+        assert!(!is_product_url("https://beliyles.com/robots.txt"));
+    }
+
+    #[test]
+    fn test_apply_characteristics() {
+        let mut tea = Tea::new("https://beliyles.com/tproduct/1");
+        for (name, value) in [
+            ("Серия", "Дикоросы"),
+            ("Регион сбора", "Карелия"),
+            ("Год урожая", "урожай 2024 года"),
+            ("Тип листа", "крупнолистовой"),
+            ("Ферментация", "сильная"),
+            ("Вкус", "медовый"),
+        ] {
+            tea.characteristics
+                .insert(name.to_string(), value.to_string());
+        }
+
+        let mut report = ParseReport::new(&tea.url);
+        apply_characteristics(&mut tea, &mut report);
+
+        assert!(report.is_clean());
+        assert_eq!(tea.series.as_deref(), Some("Дикоросы"));
+        assert_eq!(tea.origin.as_deref(), Some("Карелия"));
+        assert_eq!(tea.harvest_year, Some(2024));
+        assert_eq!(tea.leaf_type.as_deref(), Some("крупнолистовой"));
+        assert_eq!(tea.fermentation.as_deref(), Some("сильная"));
+
+        tea.characteristics
+            .insert("Год урожая".to_string(), "свежий".to_string());
+        apply_characteristics(&mut tea, &mut report);
+        assert_eq!(tea.harvest_year, None);
+        assert!(report.has_issue(TeaField::HarvestYear));
+    }
+
+    #[test]
+    fn test_resolve_scrapers() {
+        assert_eq!(resolve_scrapers(&[]).unwrap().len(), all_scrapers().len());
+        assert_eq!(
+            resolve_scrapers(&["beliyles".to_string()]).unwrap()[0].id(),
+            "beliyles"
+        );
+        assert!(resolve_scrapers(&["unknown".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! Parse diagnostics: typed errors and per-field reports
+
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+/// Tea field extracted from a product page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TeaField {
+    Name,
+    Price,
+    PriceVariants,
+    Stock,
+    VolumeOptions,
+    Images,
+    Description,
+    Composition,
+    FullComposition,
+    SearchTags,
+    StorageInfo,
+    Dimensions,
+    Weight,
+    Series,
+    HarvestYear,
+}
+
+impl TeaField {
+    /// Field name as in `Tea`
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Price => "price",
+            Self::PriceVariants => "price_variants",
+            Self::Stock => "in_stock",
+            Self::VolumeOptions => "volume_options",
+            Self::Images => "images",
+            Self::Description => "description",
+            Self::Composition => "composition",
+            Self::FullComposition => "full_composition",
+            Self::SearchTags => "search_tags",
+            Self::StorageInfo => "storage_info",
+            Self::Dimensions => "dimensions",
+            Self::Weight => "weight",
+            Self::Series => "series",
+            Self::HarvestYear => "harvest_year",
+        }
+    }
+}
+
+impl std::fmt::Display for TeaField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Kind of a field problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// Extraction rule didn't match, field stayed empty
+    Missing,
+    /// Field was extracted (or partly dropped), but the data looks wrong
+    Suspicious,
+}
+
+/// Problem with a single field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseIssue {
+    pub field: TeaField,
+    pub kind: IssueKind,
+    /// Which extraction rule failed and why
+    pub rule: String,
+}
+
+impl std::fmt::Display for ParseIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            IssueKind::Missing => "missing",
+            IssueKind::Suspicious => "suspicious",
+        };
+        write!(f, "{}: {} ({})", self.field, kind, self.rule)
+    }
+}
+
+/// Diagnostics of a successfully parsed page
+#[derive(Debug, Clone, Default)]
+pub struct ParseReport {
+    pub url: String,
+    pub issues: Vec<ParseIssue>,
+}
+
+impl ParseReport {
+    #[must_use]
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// Record a field that an extraction rule couldn't find
+    pub fn missing(&mut self, field: TeaField, rule: impl Into<String>) {
+        self.issues.push(ParseIssue {
+            field,
+            kind: IssueKind::Missing,
+            rule: rule.into(),
+        });
+    }
+
+    /// Record a field with unexpected data
+    pub fn suspicious(&mut self, field: TeaField, rule: impl Into<String>) {
+        self.issues.push(ParseIssue {
+            field,
+            kind: IssueKind::Suspicious,
+            rule: rule.into(),
+        });
+    }
+
+    /// No issues found
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Check if a field has any issue
+    #[must_use]
+    pub fn has_issue(&self, field: TeaField) -> bool {
+        self.issues.iter().any(|issue| issue.field == field)
+    }
+}
+
+/// Fatal parse error (page is skipped)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// No scraper is registered for the URL's host
+    UnsupportedUrl { url: String },
+    /// Page has no embedded product data script
+    NoProductScript { url: String },
+    /// Product data script is present but isn't valid JSON
+    InvalidProductJson { url: String, message: String },
+    /// Neither name nor images could be extracted
+    NoProductData { url: String },
+    /// Sample removed from sale (kept on the site without price)
+    DiscontinuedSample { url: String, name: String },
+}
+
+impl ParseError {
+    /// Short error kind for statistics
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UnsupportedUrl { .. } => "unsupported URL",
+            Self::NoProductScript { .. } => "no product script",
+            Self::InvalidProductJson { .. } => "invalid product JSON",
+            Self::NoProductData { .. } => "no product data",
+            Self::DiscontinuedSample { .. } => "discontinued sample",
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedUrl { url } => write!(f, "No scraper for URL: {}", url),
+            Self::NoProductScript { url } => {
+                write!(f, "Skipping page without product data script: {}", url)
+            }
+            Self::InvalidProductJson { url, message } => {
+                write!(f, "Invalid product JSON on {}: {}", url, message)
+            }
+            Self::NoProductData { url } => write!(f, "Skipping product with no data: {}", url),
+            Self::DiscontinuedSample { name, .. } => {
+                write!(f, "Skipping removed sample (discontinued): {}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Aggregated diagnostics of many parsed pages
+#[derive(Debug, Clone, Default)]
+pub struct ParseSummary {
+    /// Pages parsed successfully
+    pub parsed: usize,
+    /// Pages with at least one issue
+    pub with_issues: usize,
+    /// Number of pages with an issue per field
+    pub field_counts: BTreeMap<TeaField, usize>,
+    /// Number of fatal errors per kind
+    pub error_counts: BTreeMap<&'static str, usize>,
+    reports: Vec<ParseReport>,
+}
+
+impl ParseSummary {
+    /// Record diagnostics of a parsed page
+    pub fn record(&mut self, report: ParseReport) {
+        self.parsed += 1;
+        if report.is_clean() {
+            return;
+        }
+
+        self.with_issues += 1;
+        let mut fields: Vec<TeaField> = report.issues.iter().map(|i| i.field).collect();
+        fields.sort();
+        fields.dedup();
+        for field in fields {
+            *self.field_counts.entry(field).or_default() += 1;
+        }
+        self.reports.push(report);
+    }
+
+    /// Record a fatal parse error
+    pub fn record_error(&mut self, error: &ParseError) {
+        *self.error_counts.entry(error.kind()).or_default() += 1;
+    }
+
+    /// Pages with the most issues
+    #[must_use]
+    pub fn worst(&self, limit: usize) -> Vec<&ParseReport> {
+        let mut reports: Vec<&ParseReport> = self.reports.iter().collect();
+        reports.sort_by(|a, b| b.issues.len().cmp(&a.issues.len()).then(a.url.cmp(&b.url)));
+        reports.truncate(limit);
+        reports
+    }
+
+    /// Log per-field failure counts and the worst pages
+    pub fn log(&self, worst_limit: usize) {
+        info!(
+            "Parse diagnostics: {} pages parsed, {} with issues",
+            self.parsed, self.with_issues
+        );
+
+        let mut fields: Vec<(&TeaField, &usize)> = self.field_counts.iter().collect();
+        fields.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (field, count) in fields {
+            info!("  {:<18} {} pages", field.as_str(), count);
+        }
+
+        for (kind, count) in &self.error_counts {
+            warn!("  x {}: {}", kind, count);
+        }
+
+        let worst = self.worst(worst_limit);
+        if !worst.is_empty() {
+            info!("Worst offenders:");
+            for report in worst {
+                info!("  {} ({} issues)", report.url, report.issues.len());
+                for issue in &report.issues {
+                    info!("    - {}", issue);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_summary() {
+        let mut summary = ParseSummary::default();
+
+        let mut bad = ParseReport::new("https://beliyles.com/tproduct/1");
+        bad.missing(TeaField::Composition, "marker 'Состав:' not found");
+        bad.missing(TeaField::Series, "characteristic 'Серия' not found");
+        bad.suspicious(TeaField::Composition, "empty list");
+        summary.record(bad);
+
+        let mut minor = ParseReport::new("https://beliyles.com/tproduct/2");
+        minor.missing(TeaField::Series, "characteristic 'Серия' not found");
+        summary.record(minor);
+
+        summary.record(ParseReport::new("https://beliyles.com/tproduct/3"));
+        summary.record_error(&ParseError::NoProductData {
+            url: "https://beliyles.com/tproduct/4".to_string(),
+        });
+
+        assert_eq!(summary.parsed, 3);
+        assert_eq!(summary.with_issues, 2);
+        // Counted once per page, even with several issues for the field
+        assert_eq!(summary.field_counts[&TeaField::Composition], 1);
+        assert_eq!(summary.field_counts[&TeaField::Series], 2);
+        assert_eq!(summary.error_counts["no product data"], 1);
+
+        let worst = summary.worst(1);
+        assert_eq!(worst.len(), 1);
+        assert_eq!(worst[0].url, "https://beliyles.com/tproduct/1");
+    }
+}
@@ -0,0 +1,210 @@
+//! Sitemap parsing (urlset + sitemap index) with `<lastmod>` support
+
+use scraper::{Html, Selector};
+use std::sync::LazyLock;
+
+use crate::http::days_from_civil;
+
+static URL_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("url").expect("Invalid url selector"));
+static SITEMAP_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("sitemap").expect("Invalid sitemap selector"));
+static LOC_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("loc").expect("Invalid loc selector"));
+static LASTMOD_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("lastmod").expect("Invalid lastmod selector"));
+
+/// Page listed in a sitemap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SitemapEntry {
+    pub url: String,
+    /// Last modification time (unix timestamp), if the sitemap provides it
+    pub lastmod: Option<i64>,
+}
+
+/// How a sitemap entry relates to the cached copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageChange {
+    /// Not cached yet
+    New,
+    /// Modified after it was cached
+    Changed,
+    /// Cached copy is up to date
+    Unchanged,
+    /// Cached, but the sitemap has no `<lastmod>` to compare with
+    Unknown,
+}
+
+impl SitemapEntry {
+    /// Compare `lastmod` with `fetched_at` of the cached copy
+    #[must_use]
+    pub fn change_since(&self, cached_at: Option<i64>) -> PageChange {
+        match (cached_at, self.lastmod) {
+            (None, _) => PageChange::New,
+            (Some(_), None) => PageChange::Unknown,
+            (Some(fetched_at), Some(lastmod)) if lastmod > fetched_at => PageChange::Changed,
+            (Some(_), Some(_)) => PageChange::Unchanged,
+        }
+    }
+}
+
+/// Parsed sitemap document
+#[derive(Debug, Default)]
+pub struct Sitemap {
+    /// Pages from `<urlset>`
+    pub entries: Vec<SitemapEntry>,
+    /// Child sitemaps from `<sitemapindex>`
+    pub sitemaps: Vec<String>,
+}
+
+/// Parse a sitemap or sitemap index
+pub fn parse_sitemap(xml: &str) -> Sitemap {
+    let document = Html::parse_document(xml);
+
+    let child_text = |element: scraper::ElementRef, selector: &Selector| {
+        element
+            .select(selector)
+            .next()
+            .map(|e| e.text().collect::<String>().trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    let entries = document
+        .select(&URL_SELECTOR)
+        .filter_map(|element| {
+            Some(SitemapEntry {
+                url: child_text(element, &LOC_SELECTOR)?,
+                lastmod: child_text(element, &LASTMOD_SELECTOR)
+                    .and_then(|value| parse_lastmod(&value)),
+            })
+        })
+        .collect();
+
+    let sitemaps = document
+        .select(&SITEMAP_SELECTOR)
+        .filter_map(|element| child_text(element, &LOC_SELECTOR))
+        .collect();
+
+    Sitemap { entries, sitemaps }
+}
+
+/// Parse W3C datetime used in `<lastmod>` into a unix timestamp
+///
+/// Supports `YYYY-MM-DD`, `YYYY-MM-DDThh:mm[:ss[.fff]]` with `Z` or `±hh:mm` offset.
+pub fn parse_lastmod(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let mut date_parts = date.split('-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next().map_or(Some(1), |m| m.parse().ok())?;
+    let day: u32 = date_parts.next().map_or(Some(1), |d| d.parse().ok())?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut timestamp = days_from_civil(year, month, day) * 86_400;
+
+    if let Some(time) = time {
+        // Split off timezone designator
+        let (clock, offset) = if let Some(clock) = time.strip_suffix('Z') {
+            (clock, 0)
+        } else if let Some(pos) = time.rfind(['+', '-']) {
+            let (clock, tz) = time.split_at(pos);
+            let sign = if tz.starts_with('-') { -1 } else { 1 };
+            let (h, m) = tz[1..].split_once(':')?;
+            (
+                clock,
+                sign * (h.parse::<i64>().ok()? * 3600 + m.parse::<i64>().ok()? * 60),
+            )
+        } else {
+            (time, 0)
+        };
+
+        let mut clock_parts = clock.split(':');
+        let hours: i64 = clock_parts.next()?.parse().ok()?;
+        let minutes: i64 = clock_parts.next()?.parse().ok()?;
+        let seconds: i64 = match clock_parts.next() {
+            Some(s) => s.split('.').next()?.parse().ok()?,
+            None => 0,
+        };
+
+        timestamp += hours * 3600 + minutes * 60 + seconds - offset;
+    }
+
+    Some(timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sitemap() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://beliyles.com/tproduct/1-ivan-chai</loc><lastmod>2024-05-01</lastmod></url>
+  <url><loc> https://beliyles.com/tproduct/2-probnik </loc></url>
+</urlset>"#;
+
+        let sitemap = parse_sitemap(xml);
+        assert!(sitemap.sitemaps.is_empty());
+        assert_eq!(
+            sitemap.entries,
+            vec![
+                SitemapEntry {
+                    url: "https://beliyles.com/tproduct/1-ivan-chai".to_string(),
+                    lastmod: Some(1_714_521_600),
+                },
+                SitemapEntry {
+                    url: "https://beliyles.com/tproduct/2-probnik".to_string(),
+                    lastmod: None,
+                },
+            ]
+        );
+
+        let index = r#"<sitemapindex>
+  <sitemap><loc>https://beliyles.com/sitemap-store.xml</loc><lastmod>2024-05-01</lastmod></sitemap>
+</sitemapindex>"#;
+        let sitemap = parse_sitemap(index);
+        assert!(sitemap.entries.is_empty());
+        assert_eq!(
+            sitemap.sitemaps,
+            vec!["https://beliyles.com/sitemap-store.xml"]
+        );
+    }
+
+    #[test]
+    fn test_parse_lastmod() {
+        assert_eq!(parse_lastmod("1970-01-02"), Some(86_400));
+        assert_eq!(parse_lastmod("2024-05-01T00:00:00Z"), Some(1_714_521_600));
+        assert_eq!(parse_lastmod("2024-05-01T03:00+03:00"), Some(1_714_521_600));
+        assert_eq!(
+            parse_lastmod("2024-05-01T00:00:00.123-01:00"),
+            Some(1_714_525_200)
+        );
+        assert_eq!(parse_lastmod("2024-05"), Some(1_714_521_600));
+        assert_eq!(parse_lastmod("yesterday"), None);
+        assert_eq!(parse_lastmod("2024-13-01"), None);
+    }
+
+    #[test]
+    fn test_change_since() {
+        let entry = SitemapEntry {
+            url: "https://beliyles.com/tproduct/1".to_string(),
+            lastmod: Some(1000),
+        };
+        assert_eq!(entry.change_since(None), PageChange::New);
+        assert_eq!(entry.change_since(Some(999)), PageChange::Changed);
+        assert_eq!(entry.change_since(Some(1000)), PageChange::Unchanged);
+
+        let no_lastmod = SitemapEntry {
+            lastmod: None,
+            ..entry
+        };
+        assert_eq!(no_lastmod.change_since(Some(1000)), PageChange::Unknown);
+    }
+}
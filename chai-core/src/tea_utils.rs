@@ -3,7 +3,7 @@
 use anyhow::Result;
 use sha2::{Digest, Sha256};
 
-use crate::models::Tea;
+use crate::models::{Money, Tea, parse_grams};
 
 /// Create text representation of tea for embedding
 #[must_use]
@@ -33,6 +33,17 @@ pub fn tea_to_text(tea: &Tea) -> String {
         parts.push(format!("Серия: {}", series));
     }
 
+    // Series is already included above
+    let characteristics: Vec<String> = tea
+        .characteristics
+        .iter()
+        .filter(|(name, _)| name.trim().to_lowercase() != "серия")
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect();
+    if !characteristics.is_empty() {
+        parts.push(format!("Характеристики: {}", characteristics.join("; ")));
+    }
+
     if !tea.search_tags.is_empty() {
         parts.push(format!("Теги: {}", tea.search_tags.join(", ")));
     }
@@ -52,9 +63,72 @@ pub fn compute_tea_hash(tea: &Tea) -> Result<String> {
     Ok(format!("{:x}", result))
 }
 
+/// Number at the start of a price text ("450.0000", "1 200,50 ₽"), None if there is none
+///
+/// See [`Money::parse`], which keeps the currency and exact kopecks.
+#[must_use]
+pub fn parse_price(text: &str) -> Option<f64> {
+    Money::parse(text).map(|money| money.amount())
+}
+
+/// Lowest price of the tea and its price variants (`teas.min_price`)
+#[must_use]
+pub fn min_price(tea: &Tea) -> Option<f64> {
+    tea.price
+        .iter()
+        .chain(tea.price_variants.iter().map(|v| &v.price))
+        .filter_map(|price| parse_price(price))
+        .min_by(f64::total_cmp)
+}
+
+/// Package weight in grams (`teas.weight_grams`), from "100 g", "0,5 кг" and the like
+#[must_use]
+pub fn weight_grams(tea: &Tea) -> Option<f64> {
+    let weight = tea.weight.as_deref()?;
+    // A bare number is grams too
+    parse_grams(weight).or_else(|| weight.trim().replace(',', ".").parse().ok())
+}
+
+/// Ingredients of the composition in lower case, without duplicates (`tea_ingredients`)
+#[must_use]
+pub fn ingredients(tea: &Tea) -> Vec<String> {
+    let mut ingredients: Vec<String> = Vec::new();
+    for ingredient in &tea.composition {
+        let ingredient = ingredient.trim().to_lowercase();
+        if !ingredient.is_empty() && !ingredients.contains(&ingredient) {
+            ingredients.push(ingredient);
+        }
+    }
+    ingredients
+}
+
+/// Default share of a shop's active teas one sync may discontinue, in percent
+pub const DEFAULT_MAX_REMOVED_PERCENT: f64 = 20.0;
+
+/// Check that a sync doesn't discontinue too much of a shop's catalog
+///
+/// A broken sitemap looks like the products disappeared, so a sync that would
+/// discontinue more than `max_percent` of the `active` teas of a shop fails instead.
+pub fn check_removed(shop: &str, active: usize, removed: usize, max_percent: f64) -> Result<()> {
+    if active == 0 || removed as f64 * 100.0 <= active as f64 * max_percent {
+        return Ok(());
+    }
+
+    anyhow::bail!(
+        "{} of {} active teas of {} are gone from the website ({:.0}%, at most {}% allowed), \
+         check the sitemap or raise --max-removed",
+        removed,
+        active,
+        shop,
+        removed as f64 * 100.0 / active as f64,
+        max_percent
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::PriceVariant;
 
     fn test_tea() -> Tea {
         Tea {
@@ -73,6 +147,12 @@ mod tests {
         tea.description = Some("Tea description".to_string());
         tea.series = Some("Test series".to_string());
         tea.search_tags = vec!["tag1".to_string(), "tag2".to_string()];
+        tea.characteristics
+            .insert("Серия".to_string(), "Test series".to_string());
+        tea.characteristics
+            .insert("Регион".to_string(), "Карелия".to_string());
+        tea.characteristics
+            .insert("Вкус".to_string(), "медовый".to_string());
 
         let text = tea_to_text(&tea);
         assert!(text.contains("Название: Test Tea"));
@@ -80,6 +160,8 @@ mod tests {
         assert!(text.contains("Состав: black tea, bergamot"));
         assert!(text.contains("Серия: Test series"));
         assert!(text.contains("Теги: tag1, tag2"));
+        assert!(text.contains("Характеристики: Вкус: медовый; Регион: Карелия"));
+        assert_eq!(text.matches("Test series").count(), 1);
     }
 
     #[test]
@@ -106,4 +188,59 @@ mod tests {
         // Hash should change
         assert_ne!(hash1, hash2);
     }
+
+    #[test]
+    fn test_typed_fields() {
+        assert_eq!(parse_price("450.0000"), Some(450.0));
+        assert_eq!(parse_price(" 1 200,50 ₽"), Some(1200.5));
+        assert_eq!(parse_price("цена"), None);
+        assert_eq!(parse_price(""), None);
+
+        let mut tea = test_tea();
+        tea.price_variants = vec![
+            PriceVariant {
+                price: "80".to_string(),
+                ..Default::default()
+            },
+            PriceVariant {
+                price: "".to_string(),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(min_price(&tea), Some(80.0));
+        assert_eq!(min_price(&Tea::default()), None);
+
+        for (weight, grams) in [
+            ("100 g", Some(100.0)),
+            ("0,5 кг", Some(500.0)),
+            ("50г", Some(50.0)),
+            ("250", Some(250.0)),
+        ] {
+            tea.weight = Some(weight.to_string());
+            assert_eq!(weight_grams(&tea), grams, "{}", weight);
+        }
+        tea.weight = Some("3 шт".to_string());
+        assert_eq!(weight_grams(&tea), None);
+
+        tea.composition = vec![
+            " Мята ".to_string(),
+            "мята".to_string(),
+            "Чабрец".to_string(),
+        ];
+        assert_eq!(ingredients(&tea), vec!["мята", "чабрец"]);
+    }
+
+    #[test]
+    fn test_check_removed() {
+        assert!(check_removed("beliyles", 100, 20, 20.0).is_ok());
+        assert!(check_removed("beliyles", 0, 0, 20.0).is_ok());
+        assert!(check_removed("beliyles", 100, 0, 0.0).is_ok());
+
+        let err = check_removed("beliyles", 100, 21, 20.0).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("21 of 100 active teas of beliyles")
+        );
+        assert!(check_removed("beliyles", 3, 3, 100.0).is_ok());
+    }
 }
@@ -1,33 +1,68 @@
 //! Unified database module using Turso (embedded Rust database with vector search)
 //!
-//! This module provides:
+//! [`TursoRepository`] implements the [`crate::repository`] traits on a
+//! database file. This module provides:
 //! - Database connection management
 //! - User authentication storage
-//! - HTML cache storage
+//! - HTML cache storage (zstd-compressed, with snapshot history)
+//! - Mirrored image hashes
 //! - Tea storage with vector embeddings for semantic search
+//! - Keyword index and hybrid (keyword + vector) search
+//! - IVF vector index for approximate nearest-neighbour search
+//! - Sample -> main product links
+//! - Consistent backups and restore
 
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::OnceCell;
 use tracing::info;
-use turso::{Builder, Connection, Database};
-
-use crate::models::{SearchResult, Tea, generate_point_id};
-
-/// Global database instance
-static DATABASE: OnceCell<Arc<Database>> = OnceCell::const_new();
+use turso::{Builder, Connection, Database, Value};
+
+use crate::backup::{BackupReader, BackupRecord, BackupSummary, BackupValue, BackupWriter};
+use crate::config::DEFAULT_EMBEDDING_MODEL;
+use crate::facets;
+use crate::filters::SearchFilters;
+use crate::keywords;
+use crate::migrations::{self, Rebuild};
+use crate::models::{
+    CatalogFacets, FacetCount, SearchResult, Tea, TeaLifecycle, TeaStatus, generate_point_id,
+};
+use crate::quantization::{self, VectorStorage};
+use crate::repository::{CacheRepository, TeaRepository, UserRepository};
+use crate::samples::SampleLink;
+use crate::tea_utils;
+use crate::vector_index::{self, VectorSearch};
 
 /// Default vector size for embeddings
 pub const DEFAULT_VECTOR_SIZE: usize = 4096;
 
+/// Default number of HTML snapshots kept per URL
+pub const DEFAULT_CACHE_SNAPSHOTS: usize = 5;
+
+/// zstd level for cached HTML (Tilda markup compresses ~10x)
+const CACHE_ZSTD_LEVEL: i32 = 9;
+
 /// Database configuration
 #[derive(Debug, Clone)]
 pub struct DbConfig {
     /// Path to database file
     pub path: String,
-    /// Vector size for embeddings
+    /// Vector size of embeddings stored before `tea_embeddings` (dimensions are per model now)
     pub vector_size: usize,
+    /// Number of HTML snapshots kept per URL
+    pub cache_snapshots: usize,
+    /// Exact scan or IVF index for vector search
+    pub vector_search: VectorSearch,
+    /// Clusters scanned per ANN query
+    pub vector_probes: usize,
+    /// Precision of stored embeddings
+    pub vector_storage: VectorStorage,
+    /// Embedding model used by sync and search unless another one is requested
+    pub embedding_model: String,
 }
 
 impl DbConfig {
@@ -36,7 +71,12 @@ impl DbConfig {
     /// Environment variables:
     /// - `DATABASE_PATH`: Path to the database file (default: "data/chai.db")
     /// - `SQLITE_DATABASE_PATH`: Legacy alias for DATABASE_PATH (for backward compatibility)
-    /// - `VECTOR_SIZE`: Embedding vector dimension (default: 4096)
+    /// - `VECTOR_SIZE`: Dimension of embeddings stored before multi-model support (default: 4096)
+    /// - `CACHE_SNAPSHOTS`: HTML snapshots kept per URL (default: 5)
+    /// - `VECTOR_SEARCH`: `exact`, `ann` or `binary` (default: ann, exact until the index is built)
+    /// - `VECTOR_INDEX_PROBES`: Clusters scanned per ANN query (default: 4)
+    /// - `VECTOR_STORAGE`: `f32`, `f16` or `int8` (default: f32)
+    /// - `EMBEDDING_MODEL`: Embedding model (default: [`DEFAULT_EMBEDDING_MODEL`])
     pub fn from_env() -> Self {
         // Support both DATABASE_PATH and legacy SQLITE_DATABASE_PATH for backward compatibility
         let path = std::env::var("DATABASE_PATH")
@@ -46,129 +86,138 @@ impl DbConfig {
             .ok()
             .and_then(|s| s.parse().ok())
             .unwrap_or(DEFAULT_VECTOR_SIZE);
-
-        Self { path, vector_size }
+        let cache_snapshots = std::env::var("CACHE_SNAPSHOTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_CACHE_SNAPSHOTS);
+        let vector_search = std::env::var("VECTOR_SEARCH")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default();
+        let vector_probes = std::env::var("VECTOR_INDEX_PROBES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(vector_index::DEFAULT_PROBES);
+        let vector_storage = std::env::var("VECTOR_STORAGE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default();
+        let embedding_model = std::env::var("EMBEDDING_MODEL")
+            .unwrap_or_else(|_| DEFAULT_EMBEDDING_MODEL.to_string());
+
+        Self {
+            path,
+            vector_size,
+            cache_snapshots,
+            vector_search,
+            vector_probes,
+            vector_storage,
+            embedding_model,
+        }
     }
 }
 
-/// Initialize the database and create all tables
-pub async fn init_database(config: &DbConfig) -> Result<()> {
-    // Ensure directory exists
-    if let Some(parent) = std::path::Path::new(&config.path).parent() {
-        std::fs::create_dir_all(parent).context("Failed to create database directory")?;
-    }
-
-    let db = Builder::new_local(&config.path)
-        .build()
-        .await
-        .context("Failed to open database")?;
-
-    let conn = db.connect().context("Failed to connect to database")?;
-
-    // Create users table
-    conn.execute(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            email TEXT NOT NULL UNIQUE,
-            password_hash TEXT NOT NULL,
-            created_at INTEGER NOT NULL
-        )
-        "#,
-        (),
-    )
-    .await
-    .context("Failed to create users table")?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_users_email ON users(email)",
-        (),
-    )
-    .await
-    .context("Failed to create email index")?;
-
-    // Create HTML cache table
-    conn.execute(
-        r#"
-        CREATE TABLE IF NOT EXISTS html_cache (
-            url TEXT PRIMARY KEY,
-            html TEXT NOT NULL,
-            fetched_at INTEGER NOT NULL
-        )
-        "#,
-        (),
-    )
-    .await
-    .context("Failed to create html_cache table")?;
-
-    // Create teas table with vector column
-    // Note: We store tea data as JSON and embedding as F32_BLOB
-    conn.execute(
-        &format!(
-            r#"
-            CREATE TABLE IF NOT EXISTS teas (
-                id TEXT PRIMARY KEY,
-                url TEXT NOT NULL UNIQUE,
-                tea_data TEXT NOT NULL,
-                content_hash TEXT NOT NULL,
-                embedding F32_BLOB({}),
-                in_stock INTEGER NOT NULL DEFAULT 0,
-                is_sample INTEGER NOT NULL DEFAULT 0,
-                is_set INTEGER NOT NULL DEFAULT 0,
-                series TEXT,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )
-            "#,
-            config.vector_size
-        ),
-        (),
-    )
-    .await
-    .context("Failed to create teas table")?;
+/// Turso database with the settings of its [`DbConfig`]
+///
+/// Clones share the database, so one handle is opened per process and passed around.
+#[derive(Clone)]
+pub struct TursoRepository {
+    db: Arc<Database>,
+    /// Path of the database file
+    path: PathBuf,
+    /// Number of HTML snapshots kept per URL
+    cache_snapshots: usize,
+    /// How vector search finds nearest teas
+    vector_search: VectorSearch,
+    /// Clusters scanned per ANN query
+    vector_probes: usize,
+    /// Format of written embeddings
+    vector_storage: VectorStorage,
+}
 
-    // Create indexes for common queries
-    conn.execute("CREATE INDEX IF NOT EXISTS idx_teas_url ON teas(url)", ())
-        .await
-        .context("Failed to create teas url index")?;
+impl TursoRepository {
+    /// Open the database and apply pending migrations
+    ///
+    /// Fails if the schema is newer than this binary knows.
+    pub async fn init(config: &DbConfig) -> Result<Self> {
+        let repo = Self::open(config).await?;
+
+        let applied = repo.migrate(config).await?;
+        if !applied.is_empty() {
+            info!(
+                "Database schema migrated to version {}",
+                migrations::latest_version()
+            );
+        }
 
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_teas_in_stock ON teas(in_stock)",
-        (),
-    )
-    .await
-    .context("Failed to create teas in_stock index")?;
+        info!("Database initialized at {}", config.path);
+        Ok(repo)
+    }
 
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_teas_series ON teas(series)",
-        (),
-    )
-    .await
-    .context("Failed to create teas series index")?;
+    /// Open the database without touching the schema (for `db status` / `db migrate`)
+    pub async fn open(config: &DbConfig) -> Result<Self> {
+        // Ensure directory exists
+        if let Some(parent) = Path::new(&config.path).parent() {
+            std::fs::create_dir_all(parent).context("Failed to create database directory")?;
+        }
 
-    // Store database in global
-    DATABASE
-        .set(Arc::new(db))
-        .map_err(|_| anyhow::anyhow!("Database already initialized"))?;
+        let db = Builder::new_local(&config.path)
+            .build()
+            .await
+            .context("Failed to open database")?;
+
+        Ok(Self {
+            db: Arc::new(db),
+            path: PathBuf::from(&config.path),
+            cache_snapshots: config.cache_snapshots.max(1),
+            vector_search: config.vector_search,
+            vector_probes: config.vector_probes.max(1),
+            vector_storage: config.vector_storage,
+        })
+    }
 
-    info!("Database initialized at {}", config.path);
-    Ok(())
-}
+    /// Apply pending migrations and fill data derived by them
+    ///
+    /// Rebuilds that failed before, even in an earlier run, are retried.
+    /// Returns the applied versions.
+    pub async fn migrate(&self, config: &DbConfig) -> Result<Vec<u32>> {
+        let conn = self.connection()?;
+        let applied = migrations::migrate(&conn, config).await?;
+
+        for rebuild in migrations::pending_rebuilds(&conn).await? {
+            match rebuild {
+                Rebuild::KeywordIndex => {
+                    let indexed = self.rebuild_keyword_index().await?;
+                    info!("Indexed keywords of {} teas", indexed);
+                }
+                Rebuild::EmbeddingStorage => {
+                    let converted = self.convert_embeddings(config.vector_storage).await?;
+                    info!("Converted {} embeddings", converted);
+                }
+                Rebuild::TeaColumns => {
+                    let filled = self.rebuild_tea_columns().await?;
+                    info!("Filled typed columns of {} teas", filled);
+                }
+            }
+            migrations::finish_rebuild(&conn, rebuild).await?;
+        }
 
-/// Get a database connection
-pub fn get_connection() -> Result<Connection> {
-    let db = DATABASE
-        .get()
-        .ok_or_else(|| anyhow::anyhow!("Database not initialized. Call init_database first."))?;
+        Ok(applied)
+    }
 
-    db.connect().context("Failed to get database connection")
-}
+    /// Path of the database file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 
-/// Check if database is initialized
-pub fn is_initialized() -> bool {
-    DATABASE.get().is_some()
+    /// Get a database connection
+    pub fn connection(&self) -> Result<Connection> {
+        self.db
+            .connect()
+            .context("Failed to get database connection")
+    }
 }
-
 // ============================================================================
 // User Operations
 // ============================================================================
@@ -184,74 +233,74 @@ pub struct User {
     pub created_at: i64,
 }
 
-/// Get user by email
-pub async fn get_user_by_email(email: &str) -> Result<Option<User>> {
-    let conn = get_connection()?;
-
-    let mut rows = conn
-        .query(
-            "SELECT id, email, password_hash, created_at FROM users WHERE email = ?",
-            [email],
-        )
-        .await
-        .context("Failed to query user")?;
+#[async_trait]
+impl UserRepository for TursoRepository {
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>> {
+        let conn = self.connection()?;
 
-    if let Some(row) = rows.next().await? {
-        Ok(Some(User {
-            id: row.get::<i64>(0)?,
-            email: row.get::<String>(1)?,
-            password_hash: row.get::<String>(2)?,
-            created_at: row.get::<i64>(3)?,
-        }))
-    } else {
-        Ok(None)
+        let mut rows = conn
+            .query(
+                "SELECT id, email, password_hash, created_at FROM users WHERE email = ?",
+                [email],
+            )
+            .await
+            .context("Failed to query user")?;
+
+        if let Some(row) = rows.next().await? {
+            Ok(Some(User {
+                id: row.get::<i64>(0)?,
+                email: row.get::<String>(1)?,
+                password_hash: row.get::<String>(2)?,
+                created_at: row.get::<i64>(3)?,
+            }))
+        } else {
+            Ok(None)
+        }
     }
-}
-
-/// Get user by ID
-pub async fn get_user_by_id(user_id: i64) -> Result<Option<User>> {
-    let conn = get_connection()?;
 
-    let mut rows = conn
-        .query(
-            "SELECT id, email, password_hash, created_at FROM users WHERE id = ?",
-            [user_id],
-        )
-        .await
-        .context("Failed to query user")?;
+    async fn get_user_by_id(&self, user_id: i64) -> Result<Option<User>> {
+        let conn = self.connection()?;
 
-    if let Some(row) = rows.next().await? {
-        Ok(Some(User {
-            id: row.get::<i64>(0)?,
-            email: row.get::<String>(1)?,
-            password_hash: row.get::<String>(2)?,
-            created_at: row.get::<i64>(3)?,
-        }))
-    } else {
-        Ok(None)
+        let mut rows = conn
+            .query(
+                "SELECT id, email, password_hash, created_at FROM users WHERE id = ?",
+                [user_id],
+            )
+            .await
+            .context("Failed to query user")?;
+
+        if let Some(row) = rows.next().await? {
+            Ok(Some(User {
+                id: row.get::<i64>(0)?,
+                email: row.get::<String>(1)?,
+                password_hash: row.get::<String>(2)?,
+                created_at: row.get::<i64>(3)?,
+            }))
+        } else {
+            Ok(None)
+        }
     }
-}
 
-/// Create a new user
-pub async fn create_user(email: &str, password_hash: &str) -> Result<User> {
-    let conn = get_connection()?;
+    async fn create_user(&self, email: &str, password_hash: &str) -> Result<User> {
+        let conn = self.connection()?;
 
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .context("System time error")?
-        .as_secs() as i64;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
 
-    conn.execute(
-        "INSERT INTO users (email, password_hash, created_at) VALUES (?, ?, ?)",
-        (email, password_hash, now),
-    )
-    .await
-    .context("Failed to create user")?;
+        conn.execute(
+            "INSERT INTO users (email, password_hash, created_at) VALUES (?, ?, ?)",
+            (email, password_hash, now),
+        )
+        .await
+        .context("Failed to create user")?;
 
-    // Get the created user
-    get_user_by_email(email)
-        .await?
-        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created user"))
+        // Get the created user
+        self.get_user_by_email(email)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created user"))
+    }
 }
 
 // ============================================================================
@@ -264,447 +313,1044 @@ pub struct CacheEntry {
     pub url: String,
     pub html: String,
     pub fetched_at: i64,
+    /// `ETag` response header
+    pub etag: Option<String>,
+    /// `Last-Modified` response header
+    pub last_modified: Option<String>,
+    /// HTTP status of the response (None for entries migrated from JSON)
+    pub status: Option<u16>,
 }
 
-/// Get cached HTML for a URL
-pub async fn cache_get(url: &str) -> Result<Option<CacheEntry>> {
-    let conn = get_connection()?;
-
-    let mut rows = conn
-        .query(
-            "SELECT url, html, fetched_at FROM html_cache WHERE url = ?",
-            [url],
-        )
-        .await
-        .context("Failed to query cache")?;
-
-    if let Some(row) = rows.next().await? {
-        Ok(Some(CacheEntry {
-            url: row.get::<String>(0)?,
-            html: row.get::<String>(1)?,
-            fetched_at: row.get::<i64>(2)?,
-        }))
-    } else {
-        Ok(None)
-    }
+/// Response metadata stored with a cache entry
+#[derive(Debug, Clone, Default)]
+pub struct CacheMeta {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub status: Option<u16>,
 }
 
-/// Store HTML in cache
-pub async fn cache_set(url: &str, html: &str) -> Result<()> {
-    let conn = get_connection()?;
-
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .context("System time error")?
-        .as_secs() as i64;
-
-    conn.execute(
-        "INSERT OR REPLACE INTO html_cache (url, html, fetched_at) VALUES (?, ?, ?)",
-        (url, html, now),
-    )
-    .await
-    .context("Failed to store in cache")?;
-
-    Ok(())
+/// Snapshot of a cached page (contents of a URL at some point in time)
+#[derive(Debug, Clone)]
+pub struct CacheSnapshot {
+    pub url: String,
+    /// Hex-encoded SHA256 of the HTML
+    pub content_hash: String,
+    /// When the page switched to this content
+    pub fetched_at: i64,
+    /// Size of the HTML before compression
+    pub size_bytes: usize,
+    /// Size of the stored zstd blob
+    pub compressed_bytes: usize,
 }
 
-/// Check if URL is cached
-pub async fn cache_contains(url: &str) -> Result<bool> {
-    let conn = get_connection()?;
-
-    let mut rows = conn
-        .query("SELECT 1 FROM html_cache WHERE url = ? LIMIT 1", [url])
-        .await
-        .context("Failed to check cache")?;
-
-    Ok(rows.next().await?.is_some())
+/// Hex-encoded SHA256 of page HTML
+#[must_use]
+pub fn html_hash(html: &str) -> String {
+    format!("{:x}", Sha256::digest(html.as_bytes()))
 }
 
-/// Get all cached URLs
-pub async fn cache_list_urls() -> Result<Vec<String>> {
-    let conn = get_connection()?;
-
-    let mut rows = conn
-        .query("SELECT url FROM html_cache", ())
-        .await
-        .context("Failed to list cache URLs")?;
+pub(crate) fn compress_html(html: &str) -> Result<Vec<u8>> {
+    zstd::encode_all(html.as_bytes(), CACHE_ZSTD_LEVEL).context("Failed to compress HTML")
+}
 
-    let mut urls = Vec::new();
-    while let Some(row) = rows.next().await? {
-        urls.push(row.get::<String>(0)?);
-    }
+pub(crate) fn decompress_html(data: &[u8]) -> Result<String> {
+    let bytes = zstd::decode_all(data).context("Failed to decompress cached HTML")?;
+    String::from_utf8(bytes).context("Cached HTML is not valid UTF-8")
+}
 
-    Ok(urls)
+/// Stored item of the cache: a snapshot or an uncompressed legacy entry
+#[derive(Debug, Clone)]
+pub struct CacheItem {
+    pub url: String,
+    /// None for legacy entries
+    pub content_hash: Option<String>,
+    /// Stored (compressed) size
+    pub size_bytes: usize,
+    /// Last fetch of the entry for current content, snapshot time for older ones
+    pub fetched_at: i64,
+    /// Current content of a cached URL
+    pub current: bool,
 }
 
 /// Cache statistics
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     pub entry_count: usize,
+    /// Stored size: compressed snapshots plus uncompressed legacy entries
     pub total_size_bytes: usize,
+    /// Size of all snapshots before compression
+    pub uncompressed_size_bytes: usize,
+    pub snapshot_count: usize,
+    /// Entries stored before compression support
+    pub legacy_count: usize,
     pub oldest_entry: Option<i64>,
     pub newest_entry: Option<i64>,
 }
 
-/// Get cache statistics
-pub async fn cache_stats() -> Result<CacheStats> {
-    let conn = get_connection()?;
+#[async_trait]
+impl CacheRepository for TursoRepository {
+    async fn cache_get(&self, url: &str) -> Result<Option<CacheEntry>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT c.url, c.html, c.fetched_at, c.etag, c.last_modified, c.status, s.html_zstd
+                FROM html_cache c
+                LEFT JOIN html_snapshots s ON s.url = c.url AND s.content_hash = c.content_hash
+                WHERE c.url = ?
+                "#,
+                [url],
+            )
+            .await
+            .context("Failed to query cache")?;
+
+        if let Some(row) = rows.next().await? {
+            // Entries stored before compression keep plain HTML in `html`
+            let html = match row.get::<Option<Vec<u8>>>(6)? {
+                Some(data) => {
+                    decompress_html(&data).with_context(|| format!("Broken cache entry {}", url))?
+                }
+                None => row.get::<String>(1)?,
+            };
+
+            Ok(Some(CacheEntry {
+                url: row.get::<String>(0)?,
+                html,
+                fetched_at: row.get::<i64>(2)?,
+                etag: row.get::<Option<String>>(3)?,
+                last_modified: row.get::<Option<String>>(4)?,
+                status: row.get::<Option<i64>>(5)?.map(|s| s as u16),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
 
-    // Single query for all stats - more efficient than 4 separate queries
-    let mut rows = conn
-        .query(
+    async fn cache_set_at(
+        &self,
+        url: &str,
+        html: &str,
+        fetched_at: i64,
+        meta: &CacheMeta,
+    ) -> Result<()> {
+        let conn = self.connection()?;
+        let hash = html_hash(html);
+
+        let snapshots = self.cache_snapshots(url).await?;
+        if snapshots
+            .first()
+            .is_none_or(|latest| latest.content_hash != hash)
+        {
+            // A page that went back to an older content moves that snapshot to the top
+            conn.execute(
+                r#"
+                INSERT OR REPLACE INTO html_snapshots (url, content_hash, html_zstd, size, fetched_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+                (
+                    url,
+                    hash.as_str(),
+                    compress_html(html)?,
+                    html.len() as i64,
+                    fetched_at,
+                ),
+            )
+            .await
+            .context("Failed to store cache snapshot")?;
+
+            let keep = self.cache_snapshots;
+            for old in snapshots
+                .iter()
+                .filter(|s| s.content_hash != hash)
+                .skip(keep - 1)
+            {
+                conn.execute(
+                    "DELETE FROM html_snapshots WHERE url = ? AND content_hash = ?",
+                    (url, old.content_hash.as_str()),
+                )
+                .await
+                .context("Failed to remove old cache snapshot")?;
+            }
+        }
+
+        conn.execute(
             r#"
-            SELECT
-                COUNT(*) as count,
-                COALESCE(SUM(LENGTH(html)), 0) as total_size,
-                MIN(fetched_at) as oldest,
-                MAX(fetched_at) as newest
-            FROM html_cache
+            INSERT OR REPLACE INTO html_cache
+                (url, html, fetched_at, etag, last_modified, status, content_hash)
+            VALUES (?, '', ?, ?, ?, ?, ?)
             "#,
-            (),
+            (
+                url,
+                fetched_at,
+                meta.etag.as_deref(),
+                meta.last_modified.as_deref(),
+                meta.status.map(i64::from),
+                hash.as_str(),
+            ),
         )
         .await
-        .context("Failed to query cache stats")?;
-
-    if let Some(row) = rows.next().await? {
-        let count: i64 = row.get(0)?;
-        let total_size: i64 = row.get(1)?;
-        let oldest: Option<i64> = row.get::<Option<i64>>(2).ok().flatten();
-        let newest: Option<i64> = row.get::<Option<i64>>(3).ok().flatten();
-
-        Ok(CacheStats {
-            entry_count: count as usize,
-            total_size_bytes: total_size as usize,
-            oldest_entry: oldest.filter(|&t| t > 0),
-            newest_entry: newest.filter(|&t| t > 0),
-        })
-    } else {
-        Ok(CacheStats {
-            entry_count: 0,
-            total_size_bytes: 0,
-            oldest_entry: None,
-            newest_entry: None,
-        })
+        .context("Failed to store in cache")?;
+
+        Ok(())
     }
-}
 
-/// Clear all cache entries
-pub async fn cache_clear() -> Result<usize> {
-    let conn = get_connection()?;
+    async fn cache_snapshots(&self, url: &str) -> Result<Vec<CacheSnapshot>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT url, content_hash, fetched_at, size, LENGTH(html_zstd)
+                FROM html_snapshots WHERE url = ?
+                ORDER BY fetched_at DESC, rowid DESC
+                "#,
+                [url],
+            )
+            .await
+            .context("Failed to query cache snapshots")?;
+
+        let mut snapshots = Vec::new();
+        while let Some(row) = rows.next().await? {
+            snapshots.push(CacheSnapshot {
+                url: row.get::<String>(0)?,
+                content_hash: row.get::<String>(1)?,
+                fetched_at: row.get::<i64>(2)?,
+                size_bytes: row.get::<i64>(3)? as usize,
+                compressed_bytes: row.get::<i64>(4)? as usize,
+            });
+        }
 
-    let result = conn
-        .execute("DELETE FROM html_cache", ())
-        .await
-        .context("Failed to clear cache")?;
+        Ok(snapshots)
+    }
 
-    Ok(result as usize)
-}
+    async fn cache_snapshot_html(&self, url: &str, content_hash: &str) -> Result<Option<String>> {
+        let conn = self.connection()?;
 
-/// Migrate from JSON cache file
-pub async fn cache_migrate_from_json(json_path: &str) -> Result<usize> {
-    let content = std::fs::read_to_string(json_path).context("Failed to read JSON cache file")?;
-    let cache_map: std::collections::HashMap<String, String> =
-        serde_json::from_str(&content).context("Failed to parse JSON cache file")?;
+        let mut rows = conn
+            .query(
+                "SELECT html_zstd FROM html_snapshots WHERE url = ? AND content_hash = ?",
+                (url, content_hash),
+            )
+            .await
+            .context("Failed to query cache snapshot")?;
 
-    let count = cache_map.len();
-    for (url, html) in cache_map {
-        cache_set(&url, &html).await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(decompress_html(&row.get::<Vec<u8>>(0)?)?)),
+            None => Ok(None),
+        }
     }
 
-    info!("Migrated {} entries from JSON cache", count);
-    Ok(count)
-}
+    async fn cache_compress_legacy(&self) -> Result<usize> {
+        let conn = self.connection()?;
 
-// ============================================================================
-// Tea Operations (with Vector Search)
-// ============================================================================
+        let mut rows = conn
+            .query("SELECT url FROM html_cache WHERE content_hash IS NULL", ())
+            .await
+            .context("Failed to query uncompressed cache entries")?;
 
-/// Search filters
-#[derive(Debug, Clone, Default)]
-pub struct SearchFilters {
-    pub exclude_samples: bool,
-    pub exclude_sets: bool,
-    pub only_in_stock: bool,
-    /// Filter by tea series (exact match)
-    pub series: Option<String>,
-}
+        let mut urls = Vec::new();
+        while let Some(row) = rows.next().await? {
+            urls.push(row.get::<String>(0)?);
+        }
 
-/// Database statistics
-#[derive(Debug, Clone)]
-pub struct DatabaseStats {
-    pub total_teas: usize,
-    pub in_stock: usize,
-    pub out_of_stock: usize,
-    pub series_count: usize,
-    pub series_list: Vec<String>,
-}
+        for url in &urls {
+            let Some(entry) = self.cache_get(url).await? else {
+                continue;
+            };
+            let meta = CacheMeta {
+                etag: entry.etag,
+                last_modified: entry.last_modified,
+                status: entry.status,
+            };
+            self.cache_set_at(url, &entry.html, entry.fetched_at, &meta)
+                .await?;
+        }
 
-/// Upsert a tea (insert or update)
-///
-/// If embedding is None, the tea is stored without an embedding (can be added later)
-pub async fn upsert_tea(tea: &Tea, embedding: Option<Vec<f32>>, content_hash: &str) -> Result<()> {
-    let conn = get_connection()?;
+        Ok(urls.len())
+    }
 
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .context("System time error")?
-        .as_secs() as i64;
+    async fn cache_touch(&self, url: &str, meta: &CacheMeta) -> Result<bool> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        let updated = conn
+            .execute(
+                r#"
+                UPDATE html_cache
+                SET fetched_at = ?,
+                    etag = COALESCE(?, etag),
+                    last_modified = COALESCE(?, last_modified)
+                WHERE url = ?
+                "#,
+                (
+                    now,
+                    meta.etag.as_deref(),
+                    meta.last_modified.as_deref(),
+                    url,
+                ),
+            )
+            .await
+            .context("Failed to touch cache entry")?;
 
-    let tea_json = serde_json::to_string(tea).context("Failed to serialize tea")?;
-    let id = generate_point_id(&tea.url);
+        Ok(updated > 0)
+    }
 
-    // Format embedding as vector string for turso
-    let embedding_str = embedding.map(|emb| {
-        let values: Vec<String> = emb.iter().map(|v| v.to_string()).collect();
-        format!("[{}]", values.join(","))
-    });
+    async fn cache_contains(&self, url: &str) -> Result<bool> {
+        let conn = self.connection()?;
 
-    // Handle series - use empty string if None
-    let series_str = tea.series.as_deref().unwrap_or("");
+        let mut rows = conn
+            .query("SELECT 1 FROM html_cache WHERE url = ? LIMIT 1", [url])
+            .await
+            .context("Failed to check cache")?;
 
-    if let Some(ref emb_str) = embedding_str {
-        conn.execute(
-            r#"
-            INSERT INTO teas (id, url, tea_data, content_hash, embedding, in_stock, is_sample, is_set, series, created_at, updated_at)
-            VALUES (?, ?, ?, ?, vector32(?), ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(id) DO UPDATE SET
-                tea_data = excluded.tea_data,
-                content_hash = excluded.content_hash,
-                embedding = excluded.embedding,
-                in_stock = excluded.in_stock,
-                is_sample = excluded.is_sample,
-                is_set = excluded.is_set,
-                series = excluded.series,
-                updated_at = excluded.updated_at
-            "#,
-            (
-                id.as_str(),
-                tea.url.as_str(),
-                tea_json.as_str(),
-                content_hash,
-                emb_str.as_str(),
-                tea.in_stock as i64,
-                tea.is_sample as i64,
-                tea.is_set as i64,
-                series_str,
-                now,
-                now,
-            ),
-        )
-        .await
-        .context("Failed to upsert tea")?;
-    } else {
-        // Insert without embedding
-        conn.execute(
-            r#"
-            INSERT INTO teas (id, url, tea_data, content_hash, embedding, in_stock, is_sample, is_set, series, created_at, updated_at)
-            VALUES (?, ?, ?, ?, NULL, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(id) DO UPDATE SET
-                tea_data = excluded.tea_data,
-                content_hash = excluded.content_hash,
-                in_stock = excluded.in_stock,
-                is_sample = excluded.is_sample,
-                is_set = excluded.is_set,
-                series = excluded.series,
-                updated_at = excluded.updated_at
-            "#,
-            (
-                id.as_str(),
-                tea.url.as_str(),
-                tea_json.as_str(),
-                content_hash,
-                tea.in_stock as i64,
-                tea.is_sample as i64,
-                tea.is_set as i64,
-                series_str,
-                now,
-                now,
-            ),
-        )
-        .await
-        .context("Failed to upsert tea without embedding")?;
+        Ok(rows.next().await?.is_some())
     }
 
-    Ok(())
-}
-
-/// Update embedding for a tea
-pub async fn update_tea_embedding(url: &str, embedding: Vec<f32>) -> Result<()> {
-    let conn = get_connection()?;
+    async fn cache_list_urls(&self) -> Result<Vec<String>> {
+        let conn = self.connection()?;
 
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .context("System time error")?
-        .as_secs() as i64;
+        let mut rows = conn
+            .query("SELECT url FROM html_cache", ())
+            .await
+            .context("Failed to list cache URLs")?;
 
-    let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
-    let embedding_str = format!("[{}]", values.join(","));
+        let mut urls = Vec::new();
+        while let Some(row) = rows.next().await? {
+            urls.push(row.get::<String>(0)?);
+        }
 
-    conn.execute(
-        "UPDATE teas SET embedding = vector32(?), updated_at = ? WHERE url = ?",
-        (embedding_str.as_str(), now, url),
-    )
-    .await
-    .context("Failed to update tea embedding")?;
+        Ok(urls)
+    }
 
-    Ok(())
-}
+    async fn cache_urls_fetched_before(&self, cutoff: i64) -> Result<Vec<String>> {
+        let conn = self.connection()?;
 
-/// Get tea by URL
-pub async fn get_tea_by_url(url: &str) -> Result<Option<Tea>> {
-    let conn = get_connection()?;
+        let mut rows = conn
+            .query(
+                "SELECT url FROM html_cache WHERE fetched_at < ? ORDER BY fetched_at",
+                [cutoff],
+            )
+            .await
+            .context("Failed to query stale cache entries")?;
 
-    let mut rows = conn
-        .query("SELECT tea_data FROM teas WHERE url = ?", [url])
-        .await
-        .context("Failed to query tea by URL")?;
+        let mut urls = Vec::new();
+        while let Some(row) = rows.next().await? {
+            urls.push(row.get::<String>(0)?);
+        }
 
-    if let Some(row) = rows.next().await? {
-        let tea_json: String = row.get(0)?;
-        let tea: Tea = serde_json::from_str(&tea_json).context("Failed to parse tea JSON")?;
-        Ok(Some(tea))
-    } else {
-        Ok(None)
+        Ok(urls)
     }
-}
 
-/// Get tea by ID
-pub async fn get_tea_by_id(id: &str) -> Result<Option<Tea>> {
-    let conn = get_connection()?;
+    async fn cache_items(&self) -> Result<Vec<CacheItem>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT s.url, s.content_hash, LENGTH(s.html_zstd), s.fetched_at,
+                       c.fetched_at, COALESCE(c.content_hash = s.content_hash, 0)
+                FROM html_snapshots s
+                LEFT JOIN html_cache c ON c.url = s.url
+                "#,
+                (),
+            )
+            .await
+            .context("Failed to query cache snapshots")?;
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let current = row.get::<i64>(5)? != 0;
+            items.push(CacheItem {
+                url: row.get::<String>(0)?,
+                content_hash: Some(row.get::<String>(1)?),
+                size_bytes: row.get::<i64>(2)? as usize,
+                fetched_at: if current {
+                    row.get::<i64>(4)?
+                } else {
+                    row.get::<i64>(3)?
+                },
+                current,
+            });
+        }
 
-    let mut rows = conn
-        .query("SELECT tea_data FROM teas WHERE id = ?", [id])
-        .await
-        .context("Failed to query tea by ID")?;
+        let mut rows = conn
+            .query(
+                "SELECT url, LENGTH(html), fetched_at FROM html_cache WHERE content_hash IS NULL",
+                (),
+            )
+            .await
+            .context("Failed to query uncompressed cache entries")?;
+
+        while let Some(row) = rows.next().await? {
+            items.push(CacheItem {
+                url: row.get::<String>(0)?,
+                content_hash: None,
+                size_bytes: row.get::<i64>(1)? as usize,
+                fetched_at: row.get::<i64>(2)?,
+                current: true,
+            });
+        }
 
-    if let Some(row) = rows.next().await? {
-        let tea_json: String = row.get(0)?;
-        let tea: Tea = serde_json::from_str(&tea_json).context("Failed to parse tea JSON")?;
-        Ok(Some(tea))
-    } else {
-        Ok(None)
+        Ok(items)
     }
-}
 
-/// Get tea with content hash by URL
-pub async fn get_tea_with_hash(url: &str) -> Result<Option<(Tea, String)>> {
-    let conn = get_connection()?;
+    async fn cache_delete(&self, url: &str) -> Result<bool> {
+        let conn = self.connection()?;
 
-    let mut rows = conn
-        .query(
-            "SELECT tea_data, content_hash FROM teas WHERE url = ?",
-            [url],
-        )
-        .await
-        .context("Failed to query tea")?;
+        let deleted = conn
+            .execute("DELETE FROM html_cache WHERE url = ?", [url])
+            .await
+            .context("Failed to delete cache entry")?;
 
-    if let Some(row) = rows.next().await? {
-        let tea_json: String = row.get(0)?;
-        let content_hash: String = row.get(1)?;
-        let tea: Tea = serde_json::from_str(&tea_json).context("Failed to parse tea JSON")?;
-        Ok(Some((tea, content_hash)))
-    } else {
-        Ok(None)
+        conn.execute("DELETE FROM html_snapshots WHERE url = ?", [url])
+            .await
+            .context("Failed to delete cache snapshots")?;
+
+        Ok(deleted > 0)
     }
-}
 
-/// Delete tea by URL
-pub async fn delete_tea_by_url(url: &str) -> Result<bool> {
-    let conn = get_connection()?;
+    async fn cache_delete_snapshot(&self, url: &str, content_hash: &str) -> Result<()> {
+        let conn = self.connection()?;
+
+        // The current snapshot stays; Turso doesn't support subqueries in DELETE
+        let mut rows = conn
+            .query("SELECT content_hash FROM html_cache WHERE url = ?", [url])
+            .await?;
+        let current = match rows.next().await? {
+            Some(row) => row.get::<Option<String>>(0)?,
+            None => None,
+        };
+        if current.as_deref() == Some(content_hash) {
+            return Ok(());
+        }
 
-    let result = conn
-        .execute("DELETE FROM teas WHERE url = ?", [url])
+        conn.execute(
+            "DELETE FROM html_snapshots WHERE url = ? AND content_hash = ?",
+            (url, content_hash),
+        )
         .await
-        .context("Failed to delete tea")?;
+        .context("Failed to delete cache snapshot")?;
 
-    Ok(result > 0)
-}
+        Ok(())
+    }
 
-/// Get all tea URLs
-pub async fn get_all_tea_urls() -> Result<Vec<String>> {
-    let conn = get_connection()?;
+    async fn cache_stats(&self) -> Result<CacheStats> {
+        let conn = self.connection()?;
+
+        // Single query for all stats - more efficient than 4 separate queries
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT
+                    COUNT(*) as count,
+                    COALESCE(SUM(LENGTH(html)), 0) as legacy_size,
+                    COALESCE(SUM(content_hash IS NULL), 0) as legacy_count,
+                    MIN(fetched_at) as oldest,
+                    MAX(fetched_at) as newest
+                FROM html_cache
+                "#,
+                (),
+            )
+            .await
+            .context("Failed to query cache stats")?;
 
+        let mut stats = CacheStats {
+            entry_count: 0,
+            total_size_bytes: 0,
+            uncompressed_size_bytes: 0,
+            snapshot_count: 0,
+            legacy_count: 0,
+            oldest_entry: None,
+            newest_entry: None,
+        };
+
+        if let Some(row) = rows.next().await? {
+            let count: i64 = row.get(0)?;
+            let legacy_size: i64 = row.get(1)?;
+            let legacy_count: i64 = row.get(2)?;
+            let oldest: Option<i64> = row.get::<Option<i64>>(3).ok().flatten();
+            let newest: Option<i64> = row.get::<Option<i64>>(4).ok().flatten();
+
+            stats.entry_count = count as usize;
+            stats.total_size_bytes = legacy_size as usize;
+            stats.legacy_count = legacy_count as usize;
+            stats.oldest_entry = oldest.filter(|&t| t > 0);
+            stats.newest_entry = newest.filter(|&t| t > 0);
+        }
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT
+                    COUNT(*),
+                    COALESCE(SUM(LENGTH(html_zstd)), 0),
+                    COALESCE(SUM(size), 0)
+                FROM html_snapshots
+                "#,
+                (),
+            )
+            .await
+            .context("Failed to query cache snapshot stats")?;
+
+        if let Some(row) = rows.next().await? {
+            stats.snapshot_count = row.get::<i64>(0)? as usize;
+            stats.total_size_bytes += row.get::<i64>(1)? as usize;
+            stats.uncompressed_size_bytes = row.get::<i64>(2)? as usize;
+        }
+
+        Ok(stats)
+    }
+
+    async fn cache_clear(&self) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let result = conn
+            .execute("DELETE FROM html_cache", ())
+            .await
+            .context("Failed to clear cache")?;
+
+        conn.execute("DELETE FROM html_snapshots", ())
+            .await
+            .context("Failed to clear cache snapshots")?;
+
+        Ok(result as usize)
+    }
+}
+
+// ============================================================================
+// Tea Operations (with Vector Search)
+// ============================================================================
+
+/// Database statistics
+#[derive(Debug, Clone)]
+pub struct DatabaseStats {
+    pub total_teas: usize,
+    pub in_stock: usize,
+    pub out_of_stock: usize,
+    pub series_count: usize,
+    pub series_list: Vec<String>,
+    /// Tea count per shop ID, sorted by shop ID
+    pub shop_counts: Vec<(String, usize)>,
+    /// Teas gone from the website (not counted above)
+    pub discontinued: usize,
+    /// Teas hidden by hand (not counted above)
+    pub hidden: usize,
+    /// Lowest and highest `min_price` of the teas (None if no tea has a price)
+    pub price_range: Option<(f64, f64)>,
+}
+
+/// Facet values of a `SELECT value, COUNT(*) ... GROUP BY value` query, see [`facets::sorted_counts`]
+async fn facet_counts(conn: &Connection, sql: &str, params: Vec<Value>) -> Result<Vec<FacetCount>> {
     let mut rows = conn
-        .query("SELECT url FROM teas", ())
+        .query(sql, params)
         .await
-        .context("Failed to query tea URLs")?;
-
-    let mut urls = Vec::new();
+        .context("Failed to count facet values")?;
+    let mut counts = Vec::new();
     while let Some(row) = rows.next().await? {
-        urls.push(row.get::<String>(0)?);
+        counts.push((row.get::<String>(0)?, row.get::<i64>(1)? as usize));
+    }
+    Ok(facets::sorted_counts(counts))
+}
+
+/// Stored vector columns of `tea_embeddings` for SELECT, see [`stored_embedding`]
+const EMBEDDING_COLUMNS: &str = "tea_embeddings.vector, tea_embeddings.vector_quantized";
+
+/// Values of `vector`, `vector_quantized` and `vector_bits` for an embedding
+fn embedding_values(storage: VectorStorage, embedding: &[f32]) -> [Value; 3] {
+    let full = match storage {
+        VectorStorage::F32 => Value::Blob(quantization::encode_f32_blob(embedding)),
+        VectorStorage::F16 | VectorStorage::Int8 => Value::Null,
+    };
+    let quantized = quantization::quantize(storage, embedding).map_or(Value::Null, Value::Blob);
+    [
+        full,
+        quantized,
+        Value::Blob(quantization::sign_bits(embedding)),
+    ]
+}
+
+/// Decode an embedding read with [`EMBEDDING_COLUMNS`]
+fn stored_embedding(full: Option<Vec<u8>>, quantized: Option<Vec<u8>>) -> Result<Option<Vec<f32>>> {
+    match (full, quantized) {
+        (Some(data), _) => quantization::decode_f32_blob(&data).map(Some),
+        (None, Some(data)) => quantization::dequantize(&data).map(Some),
+        (None, None) => Ok(None),
     }
+}
+
+/// Embeddings of one model
+#[derive(Debug, Clone)]
+pub struct EmbeddingModelStats {
+    pub model: String,
+    pub dims: usize,
+    /// Teas with an embedding of this model
+    pub teas: usize,
+    /// Embeddings made from older content of their tea
+    pub stale: usize,
+}
 
-    Ok(urls)
+/// Teas considered by a vector scan
+enum Candidates<'a> {
+    All,
+    /// Teas of these IVF clusters, and teas not clustered yet
+    Clusters(&'a [i64]),
+    /// Teas with these IDs
+    Teas(&'a [String]),
 }
 
-/// Search teas by vector similarity (cosine distance)
-pub async fn search_teas(
-    query_embedding: &[f32],
+/// Nearest teas passing the filters among the candidates
+///
+/// `query` is the model and the query embedding. Similarity is computed here
+/// rather than with `vector_distance_cos`: turso can't compare f16/int8 embeddings.
+async fn vector_scan(
+    conn: &Connection,
+    (model, query_embedding): (&str, &[f32]),
     limit: usize,
     filters: &SearchFilters,
+    candidates: Candidates<'_>,
 ) -> Result<Vec<SearchResult>> {
-    let conn = get_connection()?;
+    // Values are bound as parameters, in placeholder order
+    let (filter_sql, filter_params) = filters.to_sql();
+    let mut params = vec![
+        Value::Text(model.to_string()),
+        Value::Integer(query_embedding.len() as i64),
+    ];
+    params.extend(filter_params);
+
+    let candidate_sql = match candidates {
+        Candidates::All => String::new(),
+        // Teas embedded after the index was built have no cluster and are always scanned
+        Candidates::Clusters(clusters) => {
+            params.push(Value::Text(model.to_string()));
+            params.extend(clusters.iter().map(|&c| Value::Integer(c)));
+            params.push(Value::Text(model.to_string()));
+            format!(
+                " AND (teas.id IN (SELECT tea_id FROM tea_vector_clusters WHERE model = ? AND cluster IN ({})) \
+                 OR teas.id NOT IN (SELECT tea_id FROM tea_vector_clusters WHERE model = ?))",
+                vec!["?"; clusters.len()].join(", ")
+            )
+        }
+        Candidates::Teas([]) => return Ok(Vec::new()),
+        Candidates::Teas(ids) => {
+            params.extend(ids.iter().cloned().map(Value::Text));
+            format!(" AND teas.id IN ({})", vec!["?"; ids.len()].join(", "))
+        }
+    };
+
+    let sql = format!(
+        r#"
+        SELECT teas.id, {}
+        FROM tea_embeddings
+        JOIN teas ON teas.id = tea_embeddings.tea_id
+        WHERE tea_embeddings.model = ? AND tea_embeddings.dims = ? AND {}{}
+        "#,
+        EMBEDDING_COLUMNS, filter_sql, candidate_sql
+    );
+
+    let mut rows = conn
+        .query(&sql, params)
+        .await
+        .context("Failed to search teas")?;
+
+    let mut scores = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let id: String = row.get(0)?;
+        if let Some(embedding) = stored_embedding(row.get(1)?, row.get(2)?)? {
+            scores.push((id, quantization::cosine(query_embedding, &embedding)));
+        }
+    }
+
+    // Higher similarity first
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores.truncate(limit);
+
+    let ids: Vec<String> = scores.iter().map(|(id, _)| id.clone()).collect();
+    let mut loaded = load_search_results(conn, &ids, None).await?;
+
+    Ok(scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let mut result = loaded.remove(&id)?;
+            result.score = score;
+            result.vector_score = Some(score);
+            Some(result)
+        })
+        .collect())
+}
+
+/// IVF index statistics
+#[derive(Debug, Clone, Default)]
+pub struct VectorIndexStats {
+    pub clusters: usize,
+    /// Teas assigned to a cluster
+    pub indexed: usize,
+    /// Teas with embeddings but without a cluster (scanned by every query)
+    pub unindexed: usize,
+}
+
+/// Assign a tea to the nearest cluster of the model's IVF index (no-op if it isn't built)
+async fn assign_vector_cluster(
+    conn: &Connection,
+    id: &str,
+    model: &str,
+    embedding: &[f32],
+) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT OR REPLACE INTO tea_vector_clusters (tea_id, model, cluster)
+        SELECT ?, model, cluster FROM vector_centroids
+        WHERE model = ?
+        ORDER BY vector_distance_cos(centroid, ?) ASC
+        LIMIT 1
+        "#,
+        (
+            id,
+            model,
+            Value::Blob(quantization::encode_f32_blob(embedding)),
+        ),
+    )
+    .await
+    .context("Failed to assign tea vector cluster")?;
+
+    Ok(())
+}
+
+async fn clear_vector_index(conn: &Connection, model: &str) -> Result<()> {
+    conn.execute("DELETE FROM tea_vector_clusters WHERE model = ?", [model])
+        .await
+        .context("Failed to clear tea vector clusters")?;
+    conn.execute("DELETE FROM vector_centroids WHERE model = ?", [model])
+        .await
+        .context("Failed to clear vector centroids")?;
+
+    Ok(())
+}
+
+/// Search mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Cosine similarity of embeddings
+    Vector,
+    /// BM25 over the keyword index
+    Keyword,
+    /// Vector and keyword rankings fused with reciprocal rank fusion
+    #[default]
+    Hybrid,
+}
+
+impl SearchMode {
+    /// Load mode from `SEARCH_MODE` (default: hybrid)
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("SEARCH_MODE") {
+            Ok(mode) => mode.parse().context("Invalid SEARCH_MODE"),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+impl std::str::FromStr for SearchMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "vector" => Ok(Self::Vector),
+            "keyword" => Ok(Self::Keyword),
+            "hybrid" => Ok(Self::Hybrid),
+            _ => anyhow::bail!("Unknown search mode '{}' (vector, keyword, hybrid)", s),
+        }
+    }
+}
+
+/// Default reciprocal rank fusion constant (dampens the weight of top ranks)
+pub const DEFAULT_RRF_K: f32 = 60.0;
+
+/// Default number of candidates taken from each ranking before fusion
+pub const DEFAULT_HYBRID_CANDIDATES: usize = 50;
+
+/// Hybrid search configuration
+#[derive(Debug, Clone)]
+pub struct HybridConfig {
+    /// Weight of the vector ranking
+    pub vector_weight: f32,
+    /// Weight of the keyword ranking
+    pub keyword_weight: f32,
+    /// Reciprocal rank fusion constant
+    pub rrf_k: f32,
+    /// Candidates taken from each ranking (at least the requested limit)
+    pub candidates: usize,
+}
+
+impl Default for HybridConfig {
+    fn default() -> Self {
+        Self {
+            vector_weight: 1.0,
+            keyword_weight: 1.0,
+            rrf_k: DEFAULT_RRF_K,
+            candidates: DEFAULT_HYBRID_CANDIDATES,
+        }
+    }
+}
+
+impl HybridConfig {
+    /// Load config from environment variables
+    ///
+    /// Environment variables:
+    /// - `SEARCH_VECTOR_WEIGHT`: Weight of the vector ranking (default: 1.0)
+    /// - `SEARCH_KEYWORD_WEIGHT`: Weight of the keyword ranking (default: 1.0)
+    /// - `SEARCH_RRF_K`: Reciprocal rank fusion constant (default: 60)
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str, default: f32| {
+            std::env::var(name)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            vector_weight: var("SEARCH_VECTOR_WEIGHT", defaults.vector_weight),
+            keyword_weight: var("SEARCH_KEYWORD_WEIGHT", defaults.keyword_weight),
+            rrf_k: var("SEARCH_RRF_K", defaults.rrf_k),
+            candidates: defaults.candidates,
+        }
+    }
+}
+
+/// Replace keyword index entries of a tea
+async fn index_keywords(conn: &Connection, id: &str, tea: &Tea) -> Result<()> {
+    conn.execute("DELETE FROM tea_terms WHERE tea_id = ?", [id])
+        .await
+        .context("Failed to clear tea keywords")?;
+
+    for (term, weight) in keywords::document_terms(tea) {
+        conn.execute(
+            "INSERT INTO tea_terms (tea_id, term, weight) VALUES (?, ?, ?)",
+            (id, term.as_str(), weight),
+        )
+        .await
+        .context("Failed to store tea keyword")?;
+    }
+
+    Ok(())
+}
+
+/// Fill typed columns and ingredients of a tea from its data
+async fn store_tea_columns(conn: &Connection, id: &str, tea: &Tea) -> Result<()> {
+    let name = tea.name.as_deref().map(str::trim);
+
+    conn.execute(
+        "UPDATE teas SET name = ?, min_price = ?, weight_grams = ? WHERE id = ?",
+        (
+            name,
+            tea_utils::min_price(tea),
+            tea_utils::weight_grams(tea),
+            id,
+        ),
+    )
+    .await
+    .context("Failed to store tea columns")?;
+
+    conn.execute("DELETE FROM tea_ingredients WHERE tea_id = ?", [id])
+        .await
+        .context("Failed to clear tea ingredients")?;
+
+    for (position, ingredient) in tea_utils::ingredients(tea).iter().enumerate() {
+        conn.execute(
+            "INSERT INTO tea_ingredients (tea_id, position, ingredient) VALUES (?, ?, ?)",
+            (id, position as i64, ingredient.as_str()),
+        )
+        .await
+        .context("Failed to store tea ingredient")?;
+    }
+
+    Ok(())
+}
+
+/// Rank teas matching the filters by BM25, returns tea IDs with scores, best first
+async fn keyword_ranking(
+    conn: &Connection,
+    query: &str,
+    limit: usize,
+    filters: &SearchFilters,
+) -> Result<Vec<(String, f64)>> {
+    let mut terms = keywords::tokenize(query);
+    terms.sort();
+    terms.dedup();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; terms.len()].join(", ");
+
+    // Corpus statistics
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(DISTINCT tea_id), COALESCE(SUM(weight), 0) FROM tea_terms",
+            (),
+        )
+        .await
+        .context("Failed to query keyword index stats")?;
+    let (docs, total_len) = match rows.next().await? {
+        Some(row) => (row.get::<i64>(0)? as usize, row.get::<f64>(1)?),
+        None => return Ok(Vec::new()),
+    };
+    if docs == 0 {
+        return Ok(Vec::new());
+    }
+    let avg_len = total_len / docs as f64;
+
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT term, COUNT(*) FROM tea_terms WHERE term IN ({}) GROUP BY term",
+                placeholders
+            ),
+            terms.clone(),
+        )
+        .await
+        .context("Failed to query keyword frequencies")?;
+    let mut doc_freq = HashMap::new();
+    while let Some(row) = rows.next().await? {
+        doc_freq.insert(row.get::<String>(0)?, row.get::<i64>(1)? as usize);
+    }
 
-    // Build filter conditions
-    let mut conditions = Vec::new();
-    conditions.push("embedding IS NOT NULL".to_string());
+    // Matching terms of teas passing the filters
+    let (filter_sql, filter_params) = filters.to_sql();
+    let mut params: Vec<Value> = terms.iter().cloned().map(Value::Text).collect();
+    params.extend(filter_params);
+
+    let mut rows = conn
+        .query(
+            &format!(
+                r#"
+                SELECT tea_terms.tea_id, tea_terms.term, tea_terms.weight
+                FROM tea_terms
+                JOIN teas ON teas.id = tea_terms.tea_id
+                WHERE tea_terms.term IN ({}) AND {}
+                "#,
+                placeholders, filter_sql
+            ),
+            params,
+        )
+        .await
+        .context("Failed to search keywords")?;
 
-    if filters.exclude_samples {
-        conditions.push("is_sample = 0".to_string());
+    let mut matches: Vec<(String, String, f64)> = Vec::new();
+    while let Some(row) = rows.next().await? {
+        matches.push((row.get(0)?, row.get(1)?, row.get(2)?));
     }
-    if filters.exclude_sets {
-        conditions.push("is_set = 0".to_string());
+    if matches.is_empty() {
+        return Ok(Vec::new());
     }
-    if filters.only_in_stock {
-        conditions.push("in_stock = 1".to_string());
+
+    // Lengths of matched documents
+    let mut ids: Vec<&str> = matches.iter().map(|(id, ..)| id.as_str()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT tea_id, SUM(weight) FROM tea_terms WHERE tea_id IN ({}) GROUP BY tea_id",
+                vec!["?"; ids.len()].join(", ")
+            ),
+            ids.iter().map(|id| id.to_string()).collect::<Vec<_>>(),
+        )
+        .await
+        .context("Failed to query keyword document lengths")?;
+    let mut doc_len = HashMap::new();
+    while let Some(row) = rows.next().await? {
+        doc_len.insert(row.get::<String>(0)?, row.get::<f64>(1)?);
     }
-    if let Some(ref series) = filters.series {
-        // Escape single quotes in series name to prevent SQL injection
-        let escaped = series.replace('\'', "''");
-        conditions.push(format!("series = '{}'", escaped));
+
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for (id, term, weight) in matches {
+        let df = doc_freq.get(&term).copied().unwrap_or(1);
+        let len = doc_len.get(&id).copied().unwrap_or(avg_len);
+        *scores.entry(id).or_insert(0.0) += keywords::bm25(weight, df, docs, len, avg_len);
     }
 
-    let where_clause = conditions.join(" AND ");
+    let mut ranking: Vec<(String, f64)> = scores.into_iter().collect();
+    ranking.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranking.truncate(limit);
+    Ok(ranking)
+}
+
+/// Load search results for tea IDs, keyed by ID
+///
+/// With a model and query embedding, `vector_score` is filled for teas with
+/// an embedding of that model.
+async fn load_search_results(
+    conn: &Connection,
+    ids: &[String],
+    query: Option<(&str, &[f32])>,
+) -> Result<HashMap<String, SearchResult>> {
+    let mut results = HashMap::new();
+    if ids.is_empty() {
+        return Ok(results);
+    }
 
-    // Format query embedding
-    let values: Vec<String> = query_embedding.iter().map(|v| v.to_string()).collect();
-    let query_vec_str = format!("[{}]", values.join(","));
+    // Embeddings are only read to score them
+    let mut params = Vec::new();
+    let (embedding_sql, embedding_join) = match query {
+        Some((model, embedding)) => {
+            params.push(Value::Text(model.to_string()));
+            params.push(Value::Integer(embedding.len() as i64));
+            (
+                EMBEDDING_COLUMNS,
+                "LEFT JOIN tea_embeddings ON tea_embeddings.tea_id = teas.id \
+                 AND tea_embeddings.model = ? AND tea_embeddings.dims = ?",
+            )
+        }
+        None => ("NULL, NULL", ""),
+    };
+    params.extend(ids.iter().cloned().map(Value::Text));
 
-    // Use cosine distance for similarity search
-    // Lower distance = more similar, so we order ASC
-    // Score = 1 - distance to get similarity score (higher = better)
     let sql = format!(
         r#"
-        SELECT
-            tea_data,
-            1.0 - vector_distance_cos(embedding, vector32(?)) as score
+        SELECT teas.id, teas.tea_data, COALESCE(tea_samples.sample_in_stock, 0), teas.status, {}
         FROM teas
-        WHERE {}
-        ORDER BY vector_distance_cos(embedding, vector32(?)) ASC
-        LIMIT ?
+        LEFT JOIN tea_samples ON tea_samples.tea_id = teas.id
+        {}
+        WHERE teas.id IN ({})
         "#,
-        where_clause
+        embedding_sql,
+        embedding_join,
+        vec!["?"; ids.len()].join(", ")
     );
 
     let mut rows = conn
-        .query(
-            &sql,
-            (query_vec_str.as_str(), query_vec_str.as_str(), limit as i64),
-        )
+        .query(&sql, params)
         .await
-        .context("Failed to search teas")?;
+        .context("Failed to load search results")?;
 
-    let mut results = Vec::new();
     while let Some(row) = rows.next().await? {
-        let tea_json: String = row.get(0)?;
-        let score: f64 = row.get(1)?;
+        let id: String = row.get(0)?;
+        let tea_json: String = row.get(1)?;
+        let sample_in_stock: i64 = row.get(2)?;
+        let status: String = row.get(3)?;
+        let vector_score = match query {
+            Some((_, query)) => stored_embedding(row.get(4)?, row.get(5)?)?
+                .map(|embedding| quantization::cosine(query, &embedding)),
+            None => None,
+        };
 
         match serde_json::from_str::<Tea>(&tea_json) {
             Ok(tea) => {
-                results.push(SearchResult {
-                    tea,
-                    score: score as f32,
-                });
+                results.insert(
+                    id,
+                    SearchResult {
+                        tea,
+                        score: 0.0,
+                        vector_score,
+                        keyword_score: None,
+                        sample_in_stock: sample_in_stock != 0,
+                        status: status.parse().unwrap_or_default(),
+                    },
+                );
             }
             Err(e) => {
                 tracing::warn!("Failed to parse tea from search result: {}", e);
@@ -715,66 +1361,2116 @@ pub async fn search_teas(
     Ok(results)
 }
 
-/// Get database statistics
-pub async fn get_stats() -> Result<DatabaseStats> {
-    let conn = get_connection()?;
+#[async_trait]
+impl TeaRepository for TursoRepository {
+    async fn get_image_hash(&self, url: &str) -> Result<Option<String>> {
+        let conn = self.connection()?;
 
-    // Total count
-    let mut rows = conn.query("SELECT COUNT(*) FROM teas", ()).await?;
-    let total: i64 = rows
-        .next()
-        .await?
-        .map(|r| r.get(0))
-        .transpose()?
-        .unwrap_or(0);
+        let mut rows = conn
+            .query("SELECT hash FROM mirrored_images WHERE url = ?", [url])
+            .await
+            .context("Failed to query mirrored image")?;
 
-    // In stock count
-    let mut rows = conn
-        .query("SELECT COUNT(*) FROM teas WHERE in_stock = 1", ())
-        .await?;
-    let in_stock: i64 = rows
-        .next()
-        .await?
-        .map(|r| r.get(0))
-        .transpose()?
-        .unwrap_or(0);
-
-    // Get unique series
-    let mut rows = conn
-        .query(
-            "SELECT DISTINCT series FROM teas WHERE series IS NOT NULL AND series != ''",
-            (),
+        match rows.next().await? {
+            Some(row) => Ok(Some(row.get::<String>(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_image_hashes(&self, urls: &[String]) -> Result<HashMap<String, String>> {
+        if urls.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                &format!(
+                    "SELECT url, hash FROM mirrored_images WHERE url IN ({})",
+                    vec!["?"; urls.len()].join(", ")
+                ),
+                urls.to_vec(),
+            )
+            .await
+            .context("Failed to query mirrored images")?;
+
+        let mut hashes = HashMap::new();
+        while let Some(row) = rows.next().await? {
+            hashes.insert(row.get::<String>(0)?, row.get::<String>(1)?);
+        }
+        Ok(hashes)
+    }
+
+    async fn set_image_hash(&self, url: &str, hash: &str) -> Result<()> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO mirrored_images (url, hash, fetched_at) VALUES (?, ?, ?)",
+            (url, hash, now),
         )
-        .await?;
+        .await
+        .context("Failed to store mirrored image")?;
 
-    let mut series_list = Vec::new();
-    while let Some(row) = rows.next().await? {
-        if let Ok(series) = row.get::<String>(0) {
-            series_list.push(series);
+        Ok(())
+    }
+
+    async fn upsert_tea(&self, tea: &Tea, content_hash: &str) -> Result<()> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        let tea_json = serde_json::to_string(tea).context("Failed to serialize tea")?;
+        let id = generate_point_id(&tea.url);
+
+        // Handle series - use empty string if None
+        let series_str = tea.series.as_deref().unwrap_or("");
+
+        conn.execute(
+            r#"
+            INSERT INTO teas (id, url, tea_data, content_hash, shop, in_stock, is_sample, is_set, series, created_at, updated_at, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                tea_data = excluded.tea_data,
+                content_hash = excluded.content_hash,
+                shop = excluded.shop,
+                in_stock = excluded.in_stock,
+                is_sample = excluded.is_sample,
+                is_set = excluded.is_set,
+                series = excluded.series,
+                updated_at = excluded.updated_at,
+                last_seen = excluded.last_seen,
+                status = CASE WHEN status = 'discontinued' THEN 'active' ELSE status END,
+                discontinued_at = CASE WHEN status = 'discontinued' THEN NULL ELSE discontinued_at END
+            "#,
+            (
+                id.as_str(),
+                tea.url.as_str(),
+                tea_json.as_str(),
+                content_hash,
+                tea.shop.as_str(),
+                tea.in_stock as i64,
+                tea.is_sample as i64,
+                tea.is_set as i64,
+                series_str,
+                now,
+                now,
+                now,
+                now,
+            ),
+        )
+        .await
+        .context("Failed to upsert tea")?;
+
+        // Replace characteristics
+        conn.execute(
+            "DELETE FROM tea_characteristics WHERE tea_id = ?",
+            [id.as_str()],
+        )
+        .await
+        .context("Failed to clear tea characteristics")?;
+
+        for (name, value) in &tea.characteristics {
+            conn.execute(
+                "INSERT INTO tea_characteristics (tea_id, name, value) VALUES (?, ?, ?)",
+                (id.as_str(), name.as_str(), value.as_str()),
+            )
+            .await
+            .context("Failed to store tea characteristic")?;
         }
+
+        store_tea_columns(&conn, &id, tea).await?;
+        index_keywords(&conn, &id, tea).await?;
+
+        Ok(())
     }
-    series_list.sort();
 
-    Ok(DatabaseStats {
-        total_teas: total as usize,
-        in_stock: in_stock as usize,
-        out_of_stock: (total - in_stock) as usize,
-        series_count: series_list.len(),
-        series_list,
-    })
-}
+    async fn update_tea_embedding(
+        &self,
+        url: &str,
+        model: &str,
+        content_hash: &str,
+        embedding: &[f32],
+    ) -> Result<()> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        let id = generate_point_id(url);
+        let [full, quantized, bits] = embedding_values(self.vector_storage, embedding);
+
+        conn.execute(
+            r#"
+            INSERT OR REPLACE INTO tea_embeddings
+                (tea_id, model, dims, content_hash, vector, vector_quantized, vector_bits, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            (
+                id.as_str(),
+                model,
+                embedding.len() as i64,
+                content_hash,
+                full,
+                quantized,
+                bits,
+                now,
+            ),
+        )
+        .await
+        .context("Failed to store tea embedding")?;
 
-/// Count total teas
-pub async fn count_teas() -> Result<usize> {
-    let conn = get_connection()?;
+        assign_vector_cluster(&conn, &id, model, embedding).await?;
 
-    let mut rows = conn.query("SELECT COUNT(*) FROM teas", ()).await?;
-    let count: i64 = rows
-        .next()
-        .await?
-        .map(|r| r.get(0))
-        .transpose()?
-        .unwrap_or(0);
+        Ok(())
+    }
 
-    Ok(count as usize)
+    async fn teas_needing_embedding(&self, model: &str, force: bool) -> Result<Vec<(Tea, String)>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT teas.tea_data, teas.content_hash
+                FROM teas
+                LEFT JOIN tea_embeddings
+                    ON tea_embeddings.tea_id = teas.id AND tea_embeddings.model = ?
+                WHERE ? = 1 OR tea_embeddings.tea_id IS NULL
+                    OR tea_embeddings.content_hash != teas.content_hash
+                ORDER BY teas.url
+                "#,
+                (model, force as i64),
+            )
+            .await
+            .context("Failed to query teas needing embeddings")?;
+
+        let mut teas = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let tea_json: String = row.get(0)?;
+            let content_hash: String = row.get(1)?;
+            match serde_json::from_str::<Tea>(&tea_json) {
+                Ok(tea) => teas.push((tea, content_hash)),
+                Err(e) => tracing::warn!("Failed to parse stored tea: {}", e),
+            }
+        }
+
+        Ok(teas)
+    }
+
+    async fn get_tea_by_url(&self, url: &str) -> Result<Option<Tea>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query("SELECT tea_data FROM teas WHERE url = ?", [url])
+            .await
+            .context("Failed to query tea by URL")?;
+
+        if let Some(row) = rows.next().await? {
+            let tea_json: String = row.get(0)?;
+            let tea: Tea = serde_json::from_str(&tea_json).context("Failed to parse tea JSON")?;
+            Ok(Some(tea))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_tea_by_id(&self, id: &str) -> Result<Option<Tea>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query("SELECT tea_data FROM teas WHERE id = ?", [id])
+            .await
+            .context("Failed to query tea by ID")?;
+
+        if let Some(row) = rows.next().await? {
+            let tea_json: String = row.get(0)?;
+            let tea: Tea = serde_json::from_str(&tea_json).context("Failed to parse tea JSON")?;
+            Ok(Some(tea))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_tea_with_hash(&self, url: &str) -> Result<Option<(Tea, String)>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                "SELECT tea_data, content_hash FROM teas WHERE url = ?",
+                [url],
+            )
+            .await
+            .context("Failed to query tea")?;
+
+        if let Some(row) = rows.next().await? {
+            let tea_json: String = row.get(0)?;
+            let content_hash: String = row.get(1)?;
+            let tea: Tea = serde_json::from_str(&tea_json).context("Failed to parse tea JSON")?;
+            Ok(Some((tea, content_hash)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn delete_tea_by_url(&self, url: &str) -> Result<bool> {
+        let conn = self.connection()?;
+
+        let result = conn
+            .execute("DELETE FROM teas WHERE url = ?", [url])
+            .await
+            .context("Failed to delete tea")?;
+
+        conn.execute(
+            "DELETE FROM tea_characteristics WHERE tea_id = ?",
+            [generate_point_id(url).as_str()],
+        )
+        .await
+        .context("Failed to delete tea characteristics")?;
+
+        conn.execute(
+            "DELETE FROM tea_ingredients WHERE tea_id = ?",
+            [generate_point_id(url).as_str()],
+        )
+        .await
+        .context("Failed to delete tea ingredients")?;
+
+        conn.execute(
+            "DELETE FROM tea_samples WHERE tea_id = ?",
+            [generate_point_id(url).as_str()],
+        )
+        .await
+        .context("Failed to delete tea sample link")?;
+
+        conn.execute(
+            "DELETE FROM tea_terms WHERE tea_id = ?",
+            [generate_point_id(url).as_str()],
+        )
+        .await
+        .context("Failed to delete tea keywords")?;
+
+        conn.execute(
+            "DELETE FROM tea_embeddings WHERE tea_id = ?",
+            [generate_point_id(url).as_str()],
+        )
+        .await
+        .context("Failed to delete tea embeddings")?;
+
+        conn.execute(
+            "DELETE FROM tea_vector_clusters WHERE tea_id = ?",
+            [generate_point_id(url).as_str()],
+        )
+        .await
+        .context("Failed to delete tea vector clusters")?;
+
+        Ok(result > 0)
+    }
+
+    async fn get_tea_lifecycle(&self, url: &str) -> Result<Option<TeaLifecycle>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                "SELECT status, first_seen, last_seen, discontinued_at FROM teas WHERE url = ?",
+                [url],
+            )
+            .await
+            .context("Failed to query tea lifecycle")?;
+
+        match rows.next().await? {
+            Some(row) => {
+                let status: String = row.get(0)?;
+                Ok(Some(TeaLifecycle {
+                    status: status.parse().map_err(anyhow::Error::msg)?,
+                    first_seen: row.get(1)?,
+                    last_seen: row.get(2)?,
+                    discontinued_at: row.get(3)?,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn mark_teas_seen(&self, urls: &[String]) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        let mut seen = 0;
+        for url in urls {
+            seen += conn
+                .execute(
+                    r#"
+                    UPDATE teas SET
+                        last_seen = ?,
+                        status = CASE WHEN status = 'discontinued' THEN 'active' ELSE status END,
+                        discontinued_at = CASE WHEN status = 'discontinued' THEN NULL ELSE discontinued_at END
+                    WHERE id = ?
+                    "#,
+                    (now, generate_point_id(url).as_str()),
+                )
+                .await
+                .context("Failed to mark tea as seen")? as usize;
+        }
+
+        Ok(seen)
+    }
+
+    async fn discontinue_teas(&self, urls: &[String]) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        let mut discontinued = 0;
+        for url in urls {
+            discontinued += conn
+                .execute(
+                    "UPDATE teas SET status = 'discontinued', discontinued_at = ? WHERE id = ? AND status = 'active'",
+                    (now, generate_point_id(url).as_str()),
+                )
+                .await
+                .context("Failed to discontinue tea")? as usize;
+        }
+
+        Ok(discontinued)
+    }
+
+    async fn set_tea_status(&self, url: &str, status: TeaStatus) -> Result<bool> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        // Active teas have no discontinued_at, hiding keeps it
+        let discontinued_at = match status {
+            TeaStatus::Active => "NULL",
+            TeaStatus::Discontinued => "COALESCE(discontinued_at, ?2)",
+            TeaStatus::Hidden => "discontinued_at",
+        };
+        let sql = format!(
+            "UPDATE teas SET status = ?1, discontinued_at = {} WHERE id = ?3",
+            discontinued_at
+        );
+
+        let changed = conn
+            .execute(
+                &sql,
+                (status.as_str(), now, generate_point_id(url).as_str()),
+            )
+            .await
+            .context("Failed to set tea status")?;
+
+        Ok(changed > 0)
+    }
+
+    async fn replace_sample_links(&self, shop: &str, links: &[SampleLink]) -> Result<()> {
+        let conn = self.connection()?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("System time error")?
+            .as_secs() as i64;
+
+        // Turso doesn't support subqueries in DELETE, so IDs are selected first
+        let mut rows = conn
+            .query("SELECT id FROM teas WHERE shop = ?", [shop])
+            .await?;
+        let mut ids = Vec::new();
+        while let Some(row) = rows.next().await? {
+            ids.push(row.get::<String>(0)?);
+        }
+
+        for id in &ids {
+            conn.execute("DELETE FROM tea_samples WHERE tea_id = ?", [id.as_str()])
+                .await
+                .context("Failed to clear sample links")?;
+        }
+
+        for link in links {
+            conn.execute(
+                r#"
+                INSERT OR REPLACE INTO tea_samples (tea_id, sample_url, sample_in_stock, match_method, updated_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+                (
+                    generate_point_id(&link.main_url).as_str(),
+                    link.sample_url.as_str(),
+                    link.sample_in_stock as i64,
+                    link.method.as_str(),
+                    now,
+                ),
+            )
+            .await
+            .context("Failed to store sample link")?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_sample_link(&self, url: &str) -> Result<Option<(String, bool)>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                "SELECT sample_url, sample_in_stock FROM tea_samples WHERE tea_id = ?",
+                [generate_point_id(url).as_str()],
+            )
+            .await
+            .context("Failed to query sample link")?;
+
+        match rows.next().await? {
+            Some(row) => Ok(Some((row.get::<String>(0)?, row.get::<i64>(1)? != 0))),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_tea_urls(&self, status: TeaStatus) -> Result<Vec<String>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query("SELECT url FROM teas WHERE status = ?", [status.as_str()])
+            .await
+            .context("Failed to query tea URLs")?;
+
+        let mut urls = Vec::new();
+        while let Some(row) = rows.next().await? {
+            urls.push(row.get::<String>(0)?);
+        }
+
+        Ok(urls)
+    }
+
+    async fn search_teas(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        let candidates = filters.candidates(limit);
+        let results = self
+            .vector_ranking(query_embedding, model, candidates, filters)
+            .await?;
+        Ok(filters.order_results(results, limit))
+    }
+
+    async fn search_teas_keyword(
+        &self,
+        query: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        let conn = self.connection()?;
+
+        let ranking = keyword_ranking(&conn, query, filters.candidates(limit), filters).await?;
+        let ids: Vec<String> = ranking.iter().map(|(id, _)| id.clone()).collect();
+        let mut loaded = load_search_results(&conn, &ids, None).await?;
+
+        let results = ranking
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let mut result = loaded.remove(&id)?;
+                result.score = score as f32;
+                result.keyword_score = Some(score as f32);
+                Some(result)
+            })
+            .collect();
+        Ok(filters.order_results(results, limit))
+    }
+
+    async fn search_teas_hybrid(
+        &self,
+        query: &str,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        config: &HybridConfig,
+    ) -> Result<Vec<SearchResult>> {
+        let conn = self.connection()?;
+        let limit_candidates = filters.candidates(limit);
+        let candidates = config.candidates.max(limit_candidates);
+
+        let vector_results = self
+            .vector_ranking(query_embedding, model, candidates, filters)
+            .await?;
+        let keyword_scores = keyword_ranking(&conn, query, candidates, filters).await?;
+
+        let vector_ids: Vec<String> = vector_results
+            .iter()
+            .map(|r| generate_point_id(&r.tea.url))
+            .collect();
+        let mut by_id: HashMap<String, SearchResult> =
+            vector_ids.iter().cloned().zip(vector_results).collect();
+        let keyword_ids: Vec<String> = keyword_scores.iter().map(|(id, _)| id.clone()).collect();
+
+        let fused = keywords::reciprocal_rank_fusion(
+            &[
+                (config.vector_weight, vector_ids),
+                (config.keyword_weight, keyword_ids),
+            ],
+            config.rrf_k,
+        );
+        let fused: Vec<(String, f32)> = fused.into_iter().take(limit_candidates).collect();
+
+        // Keyword-only hits are not loaded yet
+        let missing: Vec<String> = fused
+            .iter()
+            .filter(|(id, _)| !by_id.contains_key(id))
+            .map(|(id, _)| id.clone())
+            .collect();
+        by_id.extend(load_search_results(&conn, &missing, Some((model, query_embedding))).await?);
+
+        let keyword_scores: HashMap<String, f64> = keyword_scores.into_iter().collect();
+
+        let results = fused
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let mut result = by_id.remove(&id)?;
+                result.score = score;
+                result.keyword_score = keyword_scores.get(&id).map(|&s| s as f32);
+                Some(result)
+            })
+            .collect();
+        Ok(filters.order_results(results, limit))
+    }
+
+    async fn get_stats(&self) -> Result<DatabaseStats> {
+        let conn = self.connection()?;
+
+        // Count per status
+        let mut rows = conn
+            .query("SELECT status, COUNT(*) FROM teas GROUP BY status", ())
+            .await?;
+        let mut status_counts = HashMap::new();
+        while let Some(row) = rows.next().await? {
+            status_counts.insert(row.get::<String>(0)?, row.get::<i64>(1)? as usize);
+        }
+        let count = |status: TeaStatus| status_counts.get(status.as_str()).copied().unwrap_or(0);
+        let total = count(TeaStatus::Active);
+
+        // In stock count
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM teas WHERE status = 'active' AND in_stock = 1",
+                (),
+            )
+            .await?;
+        let in_stock: i64 = rows
+            .next()
+            .await?
+            .map(|r| r.get(0))
+            .transpose()?
+            .unwrap_or(0);
+
+        // Get unique series
+        let mut rows = conn
+            .query(
+                "SELECT DISTINCT series FROM teas WHERE status = 'active' AND series IS NOT NULL AND series != ''",
+                (),
+            )
+            .await?;
+
+        let mut series_list = Vec::new();
+        while let Some(row) = rows.next().await? {
+            if let Ok(series) = row.get::<String>(0) {
+                series_list.push(series);
+            }
+        }
+        series_list.sort();
+
+        // Price range
+        let mut rows = conn
+            .query(
+                "SELECT MIN(min_price), MAX(min_price) FROM teas WHERE status = 'active'",
+                (),
+            )
+            .await?;
+        let price_range = match rows.next().await? {
+            Some(row) => row.get::<Option<f64>>(0)?.zip(row.get::<Option<f64>>(1)?),
+            None => None,
+        };
+
+        // Teas per shop
+        let mut rows = conn
+            .query(
+                "SELECT shop, COUNT(*) FROM teas WHERE status = 'active' GROUP BY shop ORDER BY shop",
+                (),
+            )
+            .await?;
+
+        let mut shop_counts = Vec::new();
+        while let Some(row) = rows.next().await? {
+            shop_counts.push((row.get::<String>(0)?, row.get::<i64>(1)? as usize));
+        }
+
+        Ok(DatabaseStats {
+            total_teas: total,
+            in_stock: in_stock as usize,
+            out_of_stock: total - in_stock as usize,
+            series_count: series_list.len(),
+            series_list,
+            shop_counts,
+            discontinued: count(TeaStatus::Discontinued),
+            hidden: count(TeaStatus::Hidden),
+            price_range,
+        })
+    }
+
+    async fn get_facets(&self, filters: &SearchFilters) -> Result<CatalogFacets> {
+        let conn = self.connection()?;
+        let (filter_sql, params) = filters.to_sql();
+
+        // Stock, sample and set state
+        let sql = format!(
+            r#"
+            SELECT COUNT(*), SUM(teas.in_stock), SUM(teas.is_sample), SUM(teas.is_set),
+                SUM(CASE WHEN tea_samples.sample_in_stock = 1 THEN 1 ELSE 0 END)
+            FROM teas
+            LEFT JOIN tea_samples ON tea_samples.tea_id = teas.id
+            WHERE {}
+            "#,
+            filter_sql
+        );
+        let mut rows = conn
+            .query(&sql, params.clone())
+            .await
+            .context("Failed to count facets")?;
+        let mut sums = [0usize; 5];
+        if let Some(row) = rows.next().await? {
+            for (i, sum) in sums.iter_mut().enumerate() {
+                *sum = row.get::<Option<i64>>(i)?.unwrap_or(0) as usize;
+            }
+        }
+        let [total, in_stock, samples, sets, sample_in_stock] = sums;
+
+        // Values per series and per ingredient
+        let sql = format!(
+            r#"
+            SELECT teas.series, COUNT(*) FROM teas
+            WHERE {} AND teas.series IS NOT NULL AND teas.series != ''
+            GROUP BY teas.series
+            "#,
+            filter_sql
+        );
+        let series = facet_counts(&conn, &sql, params.clone()).await?;
+
+        let sql = format!(
+            r#"
+            SELECT tea_ingredients.ingredient, COUNT(*) FROM tea_ingredients
+            JOIN teas ON teas.id = tea_ingredients.tea_id
+            WHERE {}
+            GROUP BY tea_ingredients.ingredient
+            "#,
+            filter_sql
+        );
+        let ingredients = facet_counts(&conn, &sql, params.clone()).await?;
+
+        // Price buckets
+        let sql = format!(
+            r#"
+            SELECT {} AS bucket, COUNT(*) FROM teas
+            WHERE {} AND teas.min_price IS NOT NULL
+            GROUP BY bucket
+            "#,
+            facets::price_bucket_sql("teas.min_price"),
+            filter_sql
+        );
+        let mut rows = conn.query(&sql, params.clone()).await?;
+        let mut prices = HashMap::new();
+        while let Some(row) = rows.next().await? {
+            prices.insert(row.get::<i64>(0)? as usize, row.get::<i64>(1)? as usize);
+        }
+
+        // Values per characteristic
+        let sql = format!(
+            r#"
+            SELECT tea_characteristics.name, tea_characteristics.value, COUNT(*)
+            FROM tea_characteristics
+            JOIN teas ON teas.id = tea_characteristics.tea_id
+            WHERE {}
+            GROUP BY tea_characteristics.name, tea_characteristics.value
+            "#,
+            filter_sql
+        );
+        let mut rows = conn.query(&sql, params).await?;
+        let mut characteristics: HashMap<String, Vec<(String, usize)>> = HashMap::new();
+        while let Some(row) = rows.next().await? {
+            characteristics
+                .entry(row.get(0)?)
+                .or_default()
+                .push((row.get(1)?, row.get::<i64>(2)? as usize));
+        }
+
+        Ok(CatalogFacets {
+            total,
+            in_stock,
+            out_of_stock: total - in_stock,
+            sample_in_stock,
+            samples,
+            sets,
+            series,
+            ingredients,
+            price_buckets: facets::price_buckets(&prices),
+            characteristics: characteristics
+                .into_iter()
+                .map(|(name, values)| (name, facets::sorted_counts(values)))
+                .collect(),
+        })
+    }
+
+    async fn count_teas(&self) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query("SELECT COUNT(*) FROM teas WHERE status = 'active'", ())
+            .await?;
+        let count: i64 = rows
+            .next()
+            .await?
+            .map(|r| r.get(0))
+            .transpose()?
+            .unwrap_or(0);
+
+        Ok(count as usize)
+    }
+}
+
+impl TursoRepository {
+    /// Get stored embedding models, sorted by name
+    pub async fn embedding_models(&self) -> Result<Vec<EmbeddingModelStats>> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT tea_embeddings.model, tea_embeddings.dims, COUNT(*),
+                    SUM(tea_embeddings.content_hash != teas.content_hash)
+                FROM tea_embeddings
+                JOIN teas ON teas.id = tea_embeddings.tea_id
+                GROUP BY tea_embeddings.model, tea_embeddings.dims
+                ORDER BY tea_embeddings.model, tea_embeddings.dims
+                "#,
+                (),
+            )
+            .await
+            .context("Failed to query embedding models")?;
+
+        let mut models = Vec::new();
+        while let Some(row) = rows.next().await? {
+            models.push(EmbeddingModelStats {
+                model: row.get(0)?,
+                dims: row.get::<i64>(1)? as usize,
+                teas: row.get::<i64>(2)? as usize,
+                stale: row.get::<i64>(3)? as usize,
+            });
+        }
+
+        Ok(models)
+    }
+
+    /// Delete all embeddings and the IVF index of a model
+    ///
+    /// Returns the number of deleted embeddings.
+    pub async fn delete_embedding_model(&self, model: &str) -> Result<usize> {
+        let conn = self.connection()?;
+
+        // Counted up front: the changes count of turso includes index entries
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM tea_embeddings WHERE model = ?",
+                [model],
+            )
+            .await
+            .context("Failed to count embeddings")?;
+        let deleted = match rows.next().await? {
+            Some(row) => row.get::<i64>(0)? as usize,
+            None => 0,
+        };
+
+        conn.execute("DELETE FROM tea_embeddings WHERE model = ?", [model])
+            .await
+            .context("Failed to delete embeddings")?;
+        self.drop_vector_index(model).await?;
+
+        Ok(deleted)
+    }
+
+    /// Nearest teas passing the filters, most similar first, with the configured vector search
+    async fn vector_ranking(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        match self.vector_search {
+            VectorSearch::Exact => {
+                self.search_teas_exact(query_embedding, model, limit, filters)
+                    .await
+            }
+            VectorSearch::Ann => {
+                let probes = self.vector_probes;
+                self.search_teas_ann(query_embedding, model, limit, filters, probes)
+                    .await
+            }
+            VectorSearch::Binary => {
+                self.search_teas_binary(query_embedding, model, limit, filters)
+                    .await
+            }
+        }
+    }
+
+    /// Search teas by vector similarity scanning all teas
+    pub async fn search_teas_exact(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        let conn = self.connection()?;
+        let query = (model, query_embedding);
+        vector_scan(&conn, query, limit, filters, Candidates::All).await
+    }
+
+    /// Search teas by vector similarity scanning the `probes` nearest clusters
+    ///
+    /// Filters are applied to teas of these clusters (and teas not clustered yet).
+    /// Falls back to an exact scan if fewer than `limit` teas pass them,
+    /// or if the index of the model isn't built.
+    pub async fn search_teas_ann(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        probes: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let conn = self.connection()?;
+        let query = (model, query_embedding);
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT cluster FROM vector_centroids
+                WHERE model = ?
+                ORDER BY vector_distance_cos(centroid, ?) ASC
+                LIMIT ?
+                "#,
+                (
+                    model,
+                    Value::Blob(quantization::encode_f32_blob(query_embedding)),
+                    probes.max(1) as i64,
+                ),
+            )
+            .await
+            .context("Failed to find nearest clusters")?;
+
+        let mut clusters = Vec::new();
+        while let Some(row) = rows.next().await? {
+            clusters.push(row.get::<i64>(0)?);
+        }
+
+        if !clusters.is_empty() {
+            let candidates = Candidates::Clusters(&clusters);
+            let results = vector_scan(&conn, query, limit, filters, candidates).await?;
+            if results.len() >= limit {
+                return Ok(results);
+            }
+        }
+
+        vector_scan(&conn, query, limit, filters, Candidates::All).await
+    }
+
+    /// Search teas by vector similarity comparing sign bits first
+    ///
+    /// The `limit * RESCORE_FACTOR` teas passing the filters with the fewest
+    /// differing sign bits are rescored with their full (or dequantized) vectors.
+    pub async fn search_teas_binary(
+        &self,
+        query_embedding: &[f32],
+        model: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        let conn = self.connection()?;
+        let query_bits = quantization::sign_bits(query_embedding);
+
+        let (filter_sql, filter_params) = filters.to_sql();
+        let mut params = vec![
+            Value::Text(model.to_string()),
+            Value::Integer(query_embedding.len() as i64),
+        ];
+        params.extend(filter_params);
+
+        let sql = format!(
+            r#"
+            SELECT teas.id, tea_embeddings.vector_bits
+            FROM tea_embeddings
+            JOIN teas ON teas.id = tea_embeddings.tea_id
+            WHERE tea_embeddings.model = ? AND tea_embeddings.dims = ?
+                AND tea_embeddings.vector_bits IS NOT NULL AND {}
+            "#,
+            filter_sql
+        );
+
+        let mut rows = conn
+            .query(&sql, params)
+            .await
+            .context("Failed to scan embedding sign bits")?;
+
+        let mut distances = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let id: String = row.get(0)?;
+            let bits: Vec<u8> = row.get(1)?;
+            distances.push((id, quantization::hamming(&query_bits, &bits)));
+        }
+
+        distances.sort_by_key(|(_, distance)| *distance);
+        distances.truncate(limit.saturating_mul(quantization::RESCORE_FACTOR));
+        let ids: Vec<String> = distances.into_iter().map(|(id, _)| id).collect();
+
+        // Candidates already passed the filters
+        let query = (model, query_embedding);
+        let candidates = Candidates::Teas(&ids);
+        vector_scan(&conn, query, limit, &SearchFilters::new(), candidates).await
+    }
+
+    /// Build the IVF index of a model from its embeddings, replacing the old one
+    ///
+    /// `clusters` defaults to about √n for n embedded teas.
+    pub async fn build_vector_index(
+        &self,
+        model: &str,
+        clusters: Option<usize>,
+    ) -> Result<VectorIndexStats> {
+        let conn = self.connection()?;
+
+        let sql = format!(
+            "SELECT tea_embeddings.tea_id, {} FROM tea_embeddings WHERE tea_embeddings.model = ?",
+            EMBEDDING_COLUMNS
+        );
+        let mut rows = conn
+            .query(&sql, [model])
+            .await
+            .context("Failed to query tea embeddings")?;
+
+        let mut ids = Vec::new();
+        let mut vectors = Vec::new();
+        while let Some(row) = rows.next().await? {
+            if let Some(embedding) = stored_embedding(row.get(1)?, row.get(2)?)? {
+                ids.push(row.get::<String>(0)?);
+                vectors.push(embedding);
+            }
+        }
+
+        let k = clusters.unwrap_or_else(|| vector_index::default_clusters(vectors.len()));
+        let (centroids, assignments) = vector_index::kmeans(&vectors, k);
+
+        conn.execute("BEGIN", ()).await?;
+        let result = async {
+            clear_vector_index(&conn, model).await?;
+
+            for (cluster, centroid) in centroids.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO vector_centroids (model, cluster, centroid) VALUES (?, ?, ?)",
+                    (
+                        model,
+                        cluster as i64,
+                        Value::Blob(quantization::encode_f32_blob(centroid)),
+                    ),
+                )
+                .await
+                .context("Failed to store vector centroid")?;
+            }
+            for (id, cluster) in ids.iter().zip(&assignments) {
+                conn.execute(
+                    "INSERT INTO tea_vector_clusters (tea_id, model, cluster) VALUES (?, ?, ?)",
+                    (id.as_str(), model, *cluster as i64),
+                )
+                .await
+                .context("Failed to store tea vector cluster")?;
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => conn.execute("COMMIT", ()).await?,
+            Err(e) => {
+                conn.execute("ROLLBACK", ()).await.ok();
+                return Err(e);
+            }
+        };
+
+        self.vector_index_stats(model).await
+    }
+
+    /// Remove the IVF index of a model, its vector search falls back to exact scans
+    pub async fn drop_vector_index(&self, model: &str) -> Result<()> {
+        clear_vector_index(&self.connection()?, model).await
+    }
+
+    /// Get IVF index statistics of a model
+    pub async fn vector_index_stats(&self, model: &str) -> Result<VectorIndexStats> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT
+                    (SELECT COUNT(*) FROM vector_centroids WHERE model = ?),
+                    (SELECT COUNT(*) FROM tea_vector_clusters WHERE model = ?),
+                    (SELECT COUNT(*) FROM tea_embeddings WHERE model = ?
+                        AND tea_id NOT IN (SELECT tea_id FROM tea_vector_clusters WHERE model = ?))
+                "#,
+                (model, model, model, model),
+            )
+            .await
+            .context("Failed to query vector index stats")?;
+
+        match rows.next().await? {
+            Some(row) => Ok(VectorIndexStats {
+                clusters: row.get::<i64>(0)? as usize,
+                indexed: row.get::<i64>(1)? as usize,
+                unindexed: row.get::<i64>(2)? as usize,
+            }),
+            None => Ok(VectorIndexStats::default()),
+        }
+    }
+
+    /// Get embeddings of a model for random teas (benchmark queries)
+    pub async fn sample_tea_embeddings(&self, model: &str, count: usize) -> Result<Vec<Vec<f32>>> {
+        let conn = self.connection()?;
+
+        let sql = format!(
+            "SELECT {} FROM tea_embeddings WHERE tea_embeddings.model = ? ORDER BY RANDOM() LIMIT ?",
+            EMBEDDING_COLUMNS
+        );
+        let mut rows = conn
+            .query(&sql, (model, count as i64))
+            .await
+            .context("Failed to sample tea embeddings")?;
+
+        let mut embeddings = Vec::new();
+        while let Some(row) = rows.next().await? {
+            embeddings.extend(stored_embedding(row.get(0)?, row.get(1)?)?);
+        }
+
+        Ok(embeddings)
+    }
+
+    /// Rewrite stored embeddings of all models in the given format and fill their sign bits
+    ///
+    /// Returns the number of rewritten embeddings. Embeddings already stored in
+    /// this format are kept. Converting quantized embeddings back to f32 doesn't
+    /// restore their precision, re-sync embeddings for that.
+    pub async fn convert_embeddings(&self, storage: VectorStorage) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let sql = format!(
+            "SELECT tea_embeddings.tea_id, tea_embeddings.model, {}, tea_embeddings.vector_bits FROM tea_embeddings",
+            EMBEDDING_COLUMNS
+        );
+        let mut rows = conn
+            .query(&sql, ())
+            .await
+            .context("Failed to query tea embeddings")?;
+
+        let mut embeddings = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let full: Option<Vec<u8>> = row.get(2)?;
+            let quantized: Option<Vec<u8>> = row.get(3)?;
+            let has_bits = row.get::<Option<Vec<u8>>>(4)?.is_some();
+
+            let current = match (&full, &quantized) {
+                (Some(_), _) => Some(VectorStorage::F32),
+                (None, Some(data)) => quantization::quantized_storage(data),
+                (None, None) => None,
+            };
+            if current == Some(storage) && has_bits {
+                continue;
+            }
+
+            if let Some(embedding) = stored_embedding(full, quantized)? {
+                embeddings.push((row.get::<String>(0)?, row.get::<String>(1)?, embedding));
+            }
+        }
+
+        conn.execute("BEGIN", ()).await?;
+        let result = async {
+            for (id, model, embedding) in &embeddings {
+                let [full, quantized, bits] = embedding_values(storage, embedding);
+                conn.execute(
+                    r#"
+                    UPDATE tea_embeddings SET vector = ?, vector_quantized = ?, vector_bits = ?
+                    WHERE tea_id = ? AND model = ?
+                    "#,
+                    (full, quantized, bits, id.as_str(), model.as_str()),
+                )
+                .await
+                .context("Failed to store converted embedding")?;
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => conn.execute("COMMIT", ()).await?,
+            Err(e) => {
+                conn.execute("ROLLBACK", ()).await.ok();
+                return Err(e);
+            }
+        };
+
+        Ok(embeddings.len())
+    }
+
+    /// Total size of stored embeddings and sign bits in bytes
+    pub async fn embedding_storage_size(&self) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query(
+                r#"
+                SELECT COALESCE(SUM(
+                    COALESCE(length(vector), 0)
+                    + COALESCE(length(vector_quantized), 0)
+                    + COALESCE(length(vector_bits), 0)
+                ), 0)
+                FROM tea_embeddings
+                "#,
+                (),
+            )
+            .await
+            .context("Failed to query embedding storage size")?;
+
+        match rows.next().await? {
+            Some(row) => Ok(row.get::<i64>(0)? as usize),
+            None => Ok(0),
+        }
+    }
+
+    /// Rebuild the keyword index of all teas
+    ///
+    /// Needed after the index migration or changes to tokenization.
+    /// Returns the number of indexed teas.
+    pub async fn rebuild_keyword_index(&self) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query("SELECT id, tea_data FROM teas", ())
+            .await
+            .context("Failed to query teas")?;
+
+        let mut teas = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let id: String = row.get(0)?;
+            let tea_json: String = row.get(1)?;
+            match serde_json::from_str::<Tea>(&tea_json) {
+                Ok(tea) => teas.push((id, tea)),
+                Err(e) => tracing::warn!("Failed to parse tea {}: {}", id, e),
+            }
+        }
+
+        conn.execute("DELETE FROM tea_terms", ())
+            .await
+            .context("Failed to clear keyword index")?;
+
+        for (id, tea) in &teas {
+            index_keywords(&conn, id, tea).await?;
+        }
+
+        Ok(teas.len())
+    }
+
+    /// Fill typed columns and ingredients of all teas from `tea_data`
+    ///
+    /// Needed after the columns are added and after changes to how they are derived.
+    /// Returns the number of teas.
+    pub async fn rebuild_tea_columns(&self) -> Result<usize> {
+        let conn = self.connection()?;
+
+        let mut rows = conn
+            .query("SELECT id, tea_data FROM teas", ())
+            .await
+            .context("Failed to query teas")?;
+
+        let mut teas = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let id: String = row.get(0)?;
+            let tea_json: String = row.get(1)?;
+            match serde_json::from_str::<Tea>(&tea_json) {
+                Ok(tea) => teas.push((id, tea)),
+                Err(e) => tracing::warn!("Failed to parse tea {}: {}", id, e),
+            }
+        }
+
+        for (id, tea) in &teas {
+            store_tea_columns(&conn, id, tea).await?;
+        }
+
+        Ok(teas.len())
+    }
+}
+
+// ============================================================================
+// Backup Operations
+// ============================================================================
+
+impl From<Value> for BackupValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Integer(v) => Self::Integer(v),
+            Value::Real(v) => Self::Real(v),
+            Value::Text(v) => Self::Text(v),
+            Value::Blob(blob) => Self::Blob { blob },
+        }
+    }
+}
+
+impl From<BackupValue> for Value {
+    fn from(value: BackupValue) -> Self {
+        match value {
+            BackupValue::Null => Self::Null,
+            BackupValue::Integer(v) => Self::Integer(v),
+            BackupValue::Real(v) => Self::Real(v),
+            BackupValue::Text(v) => Self::Text(v),
+            BackupValue::Blob { blob } => Self::Blob(blob),
+        }
+    }
+}
+
+/// Quote an SQL identifier
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+impl TursoRepository {
+    /// Write a consistent backup of all tables (see [`crate::backup`])
+    ///
+    /// Tables are read in one transaction, so writes made meanwhile
+    /// are either fully included or not at all.
+    pub async fn write_backup<W: std::io::Write + Send>(
+        &self,
+        out: W,
+        created_at: i64,
+    ) -> Result<(W, BackupSummary)> {
+        let conn = self.connection()?;
+
+        conn.execute("BEGIN", ())
+            .await
+            .context("Failed to start backup transaction")?;
+        let result = write_backup_tables(&conn, out, created_at).await;
+        // Nothing was written, the transaction only pinned the snapshot
+        conn.execute("ROLLBACK", ())
+            .await
+            .context("Failed to end backup transaction")?;
+
+        result
+    }
+
+    /// Load a backup into this (empty) database
+    ///
+    /// Every table is committed after the reader verified its rows,
+    /// and the restored database must pass `PRAGMA integrity_check`.
+    pub(crate) async fn restore_backup<R: std::io::BufRead>(
+        &self,
+        mut reader: BackupReader<R>,
+    ) -> Result<BackupSummary> {
+        let conn = self.connection()?;
+        let mut insert = String::new();
+        let mut sequence = false;
+
+        for record in reader.by_ref() {
+            match record? {
+                BackupRecord::Table { name, sql, columns } => {
+                    conn.execute("BEGIN", ()).await?;
+                    // AUTOINCREMENT counters, already set by the restored rows
+                    // and only raised here for rows deleted before the backup
+                    sequence = name == "sqlite_sequence";
+                    if !sequence {
+                        conn.execute(&sql, ())
+                            .await
+                            .with_context(|| format!("Failed to create table {}", name))?;
+                    }
+
+                    let placeholders: Vec<String> =
+                        (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+                    let columns: Vec<String> =
+                        columns.iter().map(|c| quote_identifier(c)).collect();
+                    insert = format!(
+                        "INSERT INTO {} ({}) VALUES ({})",
+                        quote_identifier(&name),
+                        columns.join(", "),
+                        placeholders.join(", ")
+                    );
+                }
+                BackupRecord::Row(values) if sequence => {
+                    let [name, seq] = <[BackupValue; 2]>::try_from(values)
+                        .map_err(|_| anyhow::anyhow!("Invalid sqlite_sequence row"))?;
+                    let (name, seq) = (Value::from(name), Value::from(seq));
+                    // Read to the end, a dropped statement ends the transaction
+                    let mut rows = conn
+                        .query(
+                            "SELECT 1 FROM sqlite_sequence WHERE name = ?1",
+                            [name.clone()],
+                        )
+                        .await?;
+                    let mut exists = false;
+                    while rows.next().await?.is_some() {
+                        exists = true;
+                    }
+                    let sql = if exists {
+                        "UPDATE sqlite_sequence SET seq = MAX(seq, ?2) WHERE name = ?1"
+                    } else {
+                        "INSERT INTO sqlite_sequence (name, seq) VALUES (?1, ?2)"
+                    };
+                    conn.execute(sql, [name, seq])
+                        .await
+                        .context("Failed to restore AUTOINCREMENT counter")?;
+                }
+                BackupRecord::Row(values) => {
+                    let params: Vec<Value> = values.into_iter().map(Value::from).collect();
+                    conn.execute(&insert, params)
+                        .await
+                        .context("Failed to restore row")?;
+                }
+                BackupRecord::TableEnd { name, rows, .. } => {
+                    conn.execute("COMMIT", ())
+                        .await
+                        .with_context(|| format!("Failed to restore table {}", name))?;
+
+                    let count: i64 = conn
+                        .query(
+                            &format!("SELECT COUNT(*) FROM {}", quote_identifier(&name)),
+                            (),
+                        )
+                        .await?
+                        .next()
+                        .await?
+                        .map(|row| row.get(0))
+                        .transpose()?
+                        .unwrap_or(0);
+                    if count as u64 != rows {
+                        anyhow::bail!(
+                            "Restored table {} has {} rows, the backup has {}",
+                            name,
+                            count,
+                            rows
+                        );
+                    }
+                }
+                BackupRecord::Index { name, sql } => {
+                    conn.execute(&sql, ())
+                        .await
+                        .with_context(|| format!("Failed to create index {}", name))?;
+                }
+                BackupRecord::End { .. } => {}
+            }
+        }
+
+        let mut rows = conn
+            .query("PRAGMA integrity_check", ())
+            .await
+            .context("Failed to check restored database")?;
+        let mut problems = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let message: String = row.get(0)?;
+            if message != "ok" {
+                problems.push(message);
+            }
+        }
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "Restored database failed the integrity check: {}",
+                problems.join("; ")
+            );
+        }
+
+        Ok(BackupSummary {
+            created_at: reader.header.created_at,
+            schema_version: reader.header.schema_version,
+            tables: reader.tables().to_vec(),
+        })
+    }
+
+    /// Move the write-ahead log into the database file and truncate it
+    pub(crate) async fn checkpoint(&self) -> Result<()> {
+        let conn = self.connection()?;
+        let mut rows = conn
+            .query("PRAGMA wal_checkpoint(TRUNCATE)", ())
+            .await
+            .context("Failed to checkpoint database")?;
+        while rows.next().await?.is_some() {}
+        Ok(())
+    }
+}
+
+/// Write all tables and indexes, inside the backup transaction
+async fn write_backup_tables<W: std::io::Write + Send>(
+    conn: &Connection,
+    out: W,
+    created_at: i64,
+) -> Result<(W, BackupSummary)> {
+    // Turso ends the transaction when a statement is dropped before its
+    // last row, so every query here is read to the end
+    let mut rows = conn
+        .query(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+            (),
+        )
+        .await
+        .context("Failed to read schema version")?;
+    let mut schema_version = 0;
+    while let Some(row) = rows.next().await? {
+        schema_version = row.get::<i64>(0)?;
+    }
+
+    // Schema objects in creation order. SQLite's own tables and automatic
+    // indexes are created with the tables, except for AUTOINCREMENT counters.
+    let mut rows = conn
+        .query("SELECT type, name, sql FROM sqlite_schema", ())
+        .await
+        .context("Failed to read database schema")?;
+    let mut tables = Vec::new();
+    let mut indexes = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let kind: String = row.get(0)?;
+        let name: String = row.get(1)?;
+        let Some(sql) = row.get::<Option<String>>(2)? else {
+            continue;
+        };
+        if name.starts_with("sqlite_") && name != "sqlite_sequence" {
+            continue;
+        }
+        match kind.as_str() {
+            "table" => tables.push((name, sql)),
+            "index" => indexes.push((name, sql)),
+            _ => {}
+        }
+    }
+
+    // Counters are restored after the tables they count
+    tables.sort_by_key(|(name, _)| name == "sqlite_sequence");
+
+    let mut writer = BackupWriter::new(out, created_at, schema_version as u32)?;
+    for (name, sql) in &tables {
+        let mut rows = conn
+            .query(
+                &format!("PRAGMA table_info({})", quote_identifier(name)),
+                (),
+            )
+            .await
+            .with_context(|| format!("Failed to inspect table {}", name))?;
+        let mut columns = Vec::new();
+        while let Some(row) = rows.next().await? {
+            columns.push(row.get::<String>(1)?);
+        }
+
+        writer.begin_table(name, sql, &columns)?;
+        let quoted: Vec<String> = columns.iter().map(|c| quote_identifier(c)).collect();
+        let mut rows = conn
+            .query(
+                &format!(
+                    "SELECT {} FROM {}",
+                    quoted.join(", "),
+                    quote_identifier(name)
+                ),
+                (),
+            )
+            .await
+            .with_context(|| format!("Failed to read table {}", name))?;
+        while let Some(row) = rows.next().await? {
+            let values = (0..columns.len())
+                .map(|i| row.get_value(i).map(BackupValue::from))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            writer.write_row(values)?;
+        }
+    }
+    for (name, sql) in &indexes {
+        writer.write_index(name, sql)?;
+    }
+
+    let (out, tables) = writer.finish()?;
+    Ok((
+        out,
+        BackupSummary {
+            created_at,
+            schema_version: schema_version as u32,
+            tables,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    /// Empty database in memory with the current schema
+    async fn memory_repo() -> TursoRepository {
+        let config = DbConfig {
+            path: ":memory:".to_string(),
+            ..DbConfig::from_env()
+        };
+        TursoRepository::init(&config).await.unwrap()
+    }
+
+    #[test]
+    fn test_image_hashes() {
+        block_on(async {
+            let repo = memory_repo().await;
+            assert!(repo.get_image_hashes(&[]).await.unwrap().is_empty());
+
+            repo.set_image_hash("https://a/1.jpg", "h1").await.unwrap();
+            repo.set_image_hash("https://a/2.jpg", "h2").await.unwrap();
+            let urls = ["https://a/1.jpg", "https://a/2.jpg", "https://a/3.jpg"].map(String::from);
+            let hashes = repo.get_image_hashes(&urls).await.unwrap();
+            assert_eq!(
+                hashes,
+                HashMap::from([
+                    ("https://a/1.jpg".to_string(), "h1".to_string()),
+                    ("https://a/2.jpg".to_string(), "h2".to_string()),
+                ])
+            );
+        });
+    }
+
+    #[test]
+    fn test_unfinished_rebuild_runs_again() {
+        block_on(async {
+            let config = DbConfig {
+                path: ":memory:".to_string(),
+                ..DbConfig::from_env()
+            };
+            let repo = TursoRepository::open(&config).await.unwrap();
+            let conn = repo.connection().unwrap();
+
+            // Rebuilds are recorded with their migrations and cleared once done
+            migrations::migrate(&conn, &config).await.unwrap();
+            assert_eq!(
+                migrations::pending_rebuilds(&conn).await.unwrap(),
+                vec![
+                    Rebuild::KeywordIndex,
+                    Rebuild::EmbeddingStorage,
+                    Rebuild::TeaColumns
+                ]
+            );
+            repo.migrate(&config).await.unwrap();
+            assert!(
+                migrations::pending_rebuilds(&conn)
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+
+            let tea = Tea {
+                name: Some("Иван-чай".to_string()),
+                price: Some("450".to_string()),
+                ..Tea::new("https://a/1")
+            };
+            repo.upsert_tea(&tea, "hash").await.unwrap();
+
+            // Migration 7 applied, but filling its columns failed
+            conn.execute("UPDATE teas SET name = NULL, min_price = NULL", ())
+                .await
+                .unwrap();
+            conn.execute(
+                "INSERT INTO pending_rebuilds (name) VALUES (?)",
+                [Rebuild::TeaColumns.as_str()],
+            )
+            .await
+            .unwrap();
+            let status = migrations::status(&conn).await.unwrap();
+            assert_eq!(status.rebuilds, vec![Rebuild::TeaColumns]);
+
+            let applied = repo.migrate(&config).await.unwrap();
+            assert!(applied.is_empty());
+            assert!(
+                migrations::pending_rebuilds(&conn)
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+            let mut rows = conn
+                .query("SELECT name, min_price FROM teas", ())
+                .await
+                .unwrap();
+            let row = rows.next().await.unwrap().unwrap();
+            assert_eq!(row.get::<String>(0).unwrap(), "Иван-чай");
+            assert_eq!(row.get::<f64>(1).unwrap(), 450.0);
+        });
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let tea = |url: &str| Tea {
+            name: Some("Иван-чай".to_string()),
+            in_stock: true,
+            ..Tea::new(url)
+        };
+        let urls = ["https://a/1", "https://a/2", "https://a/3"].map(String::from);
+
+        block_on(async {
+            let repo = memory_repo().await;
+            for url in &urls {
+                repo.upsert_tea(&tea(url), "hash").await.unwrap();
+                repo.update_tea_embedding(url, "model", "hash", &[1.0, 0.0])
+                    .await
+                    .unwrap();
+            }
+            let repo = &repo;
+            let found = |filters: SearchFilters| async move {
+                let keyword = repo.search_teas_keyword("иван", 10, &filters).await;
+                let vector = repo.search_teas(&[1.0, 0.0], "model", 10, &filters).await;
+                let mut urls: Vec<String> =
+                    keyword.unwrap().into_iter().map(|r| r.tea.url).collect();
+                let mut vector: Vec<String> =
+                    vector.unwrap().into_iter().map(|r| r.tea.url).collect();
+                urls.sort();
+                vector.sort();
+                assert_eq!(urls, vector);
+                urls
+            };
+            let status = |url: &'static str| async move {
+                repo.get_tea_lifecycle(url).await.unwrap().unwrap()
+            };
+            let all = SearchFilters::new();
+
+            // Gone teas are kept, out of search unless asked for
+            assert_eq!(repo.discontinue_teas(&urls[1..]).await.unwrap(), 2);
+            assert_eq!(repo.discontinue_teas(&urls[1..]).await.unwrap(), 0);
+            assert_eq!(found(all.clone()).await, vec![urls[0].clone()]);
+            assert_eq!(found(all.clone().include_discontinued()).await, urls);
+            let lifecycle = status("https://a/2").await;
+            assert_eq!(lifecycle.status, TeaStatus::Discontinued);
+            assert!(lifecycle.discontinued_at.is_some());
+            assert_eq!(repo.count_teas().await.unwrap(), 1);
+            assert_eq!(repo.get_stats().await.unwrap().discontinued, 2);
+            assert_eq!(
+                repo.get_tea_urls(TeaStatus::Discontinued).await.unwrap(),
+                urls[1..].to_vec()
+            );
+
+            // Scraped again: active, whether by upsert or by being seen
+            repo.upsert_tea(&tea(&urls[1]), "changed").await.unwrap();
+            let lifecycle = status("https://a/2").await;
+            assert_eq!(
+                (lifecycle.status, lifecycle.discontinued_at),
+                (TeaStatus::Active, None)
+            );
+            assert_eq!(repo.mark_teas_seen(&urls[2..]).await.unwrap(), 1);
+            assert_eq!(status("https://a/3").await.status, TeaStatus::Active);
+
+            // Hidden teas stay hidden and never match
+            assert!(
+                repo.set_tea_status(&urls[0], TeaStatus::Hidden)
+                    .await
+                    .unwrap()
+            );
+            repo.upsert_tea(&tea(&urls[0]), "changed").await.unwrap();
+            assert_eq!(repo.mark_teas_seen(&urls).await.unwrap(), 3);
+            assert_eq!(repo.discontinue_teas(&urls[..1]).await.unwrap(), 0);
+            assert_eq!(status("https://a/1").await.status, TeaStatus::Hidden);
+            assert_eq!(
+                found(all.clone().include_discontinued()).await,
+                urls[1..].to_vec()
+            );
+
+            // Set by hand
+            assert!(
+                repo.set_tea_status(&urls[1], TeaStatus::Discontinued)
+                    .await
+                    .unwrap()
+            );
+            assert!(status("https://a/2").await.discontinued_at.is_some());
+            assert!(
+                repo.set_tea_status(&urls[0], TeaStatus::Active)
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(found(all).await, vec![urls[0].clone(), urls[2].clone()]);
+            assert!(
+                !repo
+                    .set_tea_status("https://a/4", TeaStatus::Active)
+                    .await
+                    .unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn test_filters_match_sql() {
+        use crate::filters::Filter;
+        use crate::models::PriceVariant;
+        use crate::repository::MemoryRepository;
+        use crate::samples::{MatchMethod, SampleLink};
+
+        let teas = [
+            Tea {
+                name: Some("Иван-чай с малиной".to_string()),
+                series: Some("Ягодные".to_string()),
+                price: Some("450".to_string()),
+                composition: vec!["Иван-чай".to_string(), "Малина".to_string()],
+                characteristics: [("Регион".to_string(), "Карелия".to_string())].into(),
+                in_stock: true,
+                ..Tea::new("https://a/berry")
+            },
+            // No series, price, composition or characteristics
+            Tea::new("https://a/bare"),
+            Tea {
+                name: Some("Пробник".to_string()),
+                series: Some("Ягодные".to_string()),
+                price_variants: vec![
+                    PriceVariant {
+                        price: "1200".to_string(),
+                        ..Default::default()
+                    },
+                    PriceVariant {
+                        price: "300".to_string(),
+                        ..Default::default()
+                    },
+                ],
+                composition: vec!["иван-чай".to_string(), "Мята".to_string()],
+                is_sample: true,
+                ..Tea::new("https://a/sample")
+            },
+            Tea {
+                name: Some("Набор".to_string()),
+                series: Some("Травяные".to_string()),
+                price: Some("2500".to_string()),
+                is_set: true,
+                in_stock: true,
+                shop: "other".to_string(),
+                ..Tea::new("https://a/set")
+            },
+        ];
+        let links = [SampleLink {
+            sample_url: "https://a/sample".to_string(),
+            main_url: "https://a/berry".to_string(),
+            sample_in_stock: true,
+            method: MatchMethod::Slug,
+        }];
+
+        let price = |min, max| Filter::Price { min, max };
+        let series = |v: &[&str]| Filter::Series(v.iter().map(|s| s.to_string()).collect());
+        let filter_sets = vec![
+            SearchFilters::new(),
+            SearchFilters::new().price_range(Some(300.0), Some(500.0)),
+            SearchFilters::new().price_range(None, Some(300.0)),
+            SearchFilters::new().price_range(Some(2500.0), None),
+            SearchFilters::new().matching(price(None, None)),
+            // Negated conditions on missing values (NULL in SQL)
+            SearchFilters::new().matching(price(None, Some(500.0)).negate()),
+            SearchFilters::new().matching(series(&["Ягодные"]).negate()),
+            SearchFilters::new().any_of(vec![price(Some(1000.0), None), Filter::InStock]),
+            SearchFilters::new().any_of(vec![price(Some(1000.0), None).negate(), Filter::Set]),
+            SearchFilters::new().series(["Ягодные", "Травяные"]),
+            SearchFilters::new().shops(["other"]),
+            SearchFilters::new().characteristic("Регион", "Карелия"),
+            SearchFilters::new()
+                .matching(Filter::Characteristic {
+                    name: "Регион".to_string(),
+                    value: "Карелия".to_string(),
+                })
+                .matching(Filter::Not(Box::new(Filter::All(Vec::new())))),
+            SearchFilters::new().with_ingredients(["иван"]),
+            SearchFilters::new().without_ingredients(["мята"]),
+            SearchFilters::new().in_stock_or_sample_in_stock(),
+            SearchFilters::new().exclude_samples().exclude_sets(),
+            SearchFilters::new().only_in_stock(),
+        ];
+
+        block_on(async {
+            let repo = memory_repo().await;
+            let memory = MemoryRepository::new();
+            for tea in &teas {
+                repo.upsert_tea(tea, "hash").await.unwrap();
+                memory.upsert_tea(tea, "hash").await.unwrap();
+            }
+            for shop in ["beliyles", "other"] {
+                repo.replace_sample_links(shop, &links).await.unwrap();
+                memory.replace_sample_links(shop, &links).await.unwrap();
+            }
+            let conn = repo.connection().unwrap();
+
+            for filters in &filter_sets {
+                let (sql, params) = filters.to_sql();
+                let mut rows = conn
+                    .query(
+                        &format!("SELECT url FROM teas WHERE {} ORDER BY url", sql),
+                        params,
+                    )
+                    .await
+                    .unwrap();
+                let mut from_sql = Vec::new();
+                while let Some(row) = rows.next().await.unwrap() {
+                    from_sql.push(row.get::<String>(0).unwrap());
+                }
+
+                let mut evaluated = Vec::new();
+                for tea in &teas {
+                    let link = repo.get_sample_link(&tea.url).await.unwrap();
+                    let sample_in_stock = link.is_some_and(|(_, in_stock)| in_stock);
+                    if filters.matches(tea, sample_in_stock, TeaStatus::Active) {
+                        evaluated.push(tea.url.clone());
+                    }
+                }
+                evaluated.sort();
+                assert_eq!(from_sql, evaluated, "{:?}", filters);
+
+                assert_eq!(
+                    repo.get_facets(filters).await.unwrap(),
+                    memory.get_facets(filters).await.unwrap(),
+                    "{:?}",
+                    filters
+                );
+            }
+
+            let facets = repo.get_facets(&SearchFilters::new()).await.unwrap();
+            assert_eq!((facets.total, facets.sample_in_stock), (4, 1));
+            let prices: Vec<usize> = facets.price_buckets.iter().map(|b| b.count).collect();
+            assert_eq!(prices, vec![0, 2, 0, 0, 1]);
+        });
+    }
+
+    /// Rows of a query over the index tables, as text, sorted
+    async fn table_rows(conn: &Connection, sql: &str) -> Vec<String> {
+        let mut rows = conn.query(sql, ()).await.unwrap();
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            out.push(format!(
+                "{:?}",
+                (0..row.column_count())
+                    .map(|i| row.get_value(i).unwrap())
+                    .collect::<Vec<_>>()
+            ));
+        }
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn test_search_indexes() {
+        use crate::repository::MemoryRepository;
+
+        let tea = |id: u32, name: &str, composition: &[&str]| Tea {
+            name: Some(name.to_string()),
+            composition: composition.iter().map(|s| s.to_string()).collect(),
+            in_stock: true,
+            ..Tea::new(&format!("https://a/{}", id))
+        };
+        let teas = [
+            (
+                tea(1, "Иван-чай с малиной", &["Иван-чай", "Малина"]),
+                [1.0, 0.1, 0.0],
+            ),
+            (
+                tea(2, "Малиновый сбор", &["Малина", "Смородина"]),
+                [0.9, 0.3, 0.1],
+            ),
+            (
+                tea(3, "Иван-чай классический", &["Иван-чай"]),
+                [0.8, 0.0, 0.4],
+            ),
+            (tea(4, "Мятный сбор", &["Мята", "Мелисса"]), [0.0, 1.0, 0.1]),
+            (
+                tea(5, "Травяной чай с мятой", &["Мята", "Чабрец"]),
+                [0.1, 0.9, 0.3],
+            ),
+            (tea(6, "Чабрец", &["Чабрец"]), [0.0, 0.3, 1.0]),
+        ];
+        let config = DbConfig {
+            path: ":memory:".to_string(),
+            vector_search: VectorSearch::Ann,
+            vector_probes: 1,
+            ..DbConfig::from_env()
+        };
+        let urls = |results: Vec<SearchResult>| -> Vec<String> {
+            results.into_iter().map(|r| r.tea.url).collect()
+        };
+        let all = SearchFilters::new();
+        let hybrid = HybridConfig::default();
+        let query = [1.0, 0.2, 0.0];
+
+        block_on(async {
+            let repo = TursoRepository::init(&config).await.unwrap();
+            let memory = MemoryRepository::new();
+            for (tea, embedding) in &teas {
+                for repo in [&repo as &dyn TeaRepository, &memory] {
+                    repo.upsert_tea(tea, "hash").await.unwrap();
+                    repo.update_tea_embedding(&tea.url, "model", "hash", embedding)
+                        .await
+                        .unwrap();
+                }
+            }
+            let conn = repo.connection().unwrap();
+
+            // BM25 over tea_terms ranks like the brute force one
+            let keyword = repo.search_teas_keyword("малина", 10, &all).await.unwrap();
+            assert_eq!(
+                keyword.iter().map(|r| r.keyword_score).collect::<Vec<_>>(),
+                memory
+                    .search_teas_keyword("малина", 10, &all)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|r| r.keyword_score)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(urls(keyword), ["https://a/1", "https://a/2"]);
+
+            // Without the index, ANN scans everything; with it, the nearest cluster
+            let exact = urls(repo.search_teas(&query, "model", 3, &all).await.unwrap());
+            assert_eq!(
+                exact,
+                urls(memory.search_teas(&query, "model", 3, &all).await.unwrap())
+            );
+            assert_eq!(exact, ["https://a/1", "https://a/2", "https://a/3"]);
+
+            let stats = repo.build_vector_index("model", Some(2)).await.unwrap();
+            assert_eq!((stats.clusters, stats.indexed, stats.unindexed), (2, 6, 0));
+            let ann = urls(repo.search_teas(&query, "model", 3, &all).await.unwrap());
+            assert_eq!(ann, exact);
+            let binary = repo
+                .search_teas_binary(&query, "model", 3, &all)
+                .await
+                .unwrap();
+            assert_eq!(urls(binary)[0], "https://a/1");
+
+            let fused = repo
+                .search_teas_hybrid("мята", &query, "model", 4, &all, &hybrid)
+                .await
+                .unwrap();
+            let expected = memory
+                .search_teas_hybrid("мята", &query, "model", 4, &all, &hybrid)
+                .await
+                .unwrap();
+            assert_eq!(urls(fused), urls(expected));
+
+            // Index tables follow upserts and deletes
+            let terms = "SELECT tea_id, term, weight FROM tea_terms";
+            let clusters = "SELECT tea_id, model, cluster FROM tea_vector_clusters";
+            let renamed = tea(4, "Сбор с мелиссой", &["Мелисса"]);
+            repo.upsert_tea(&renamed, "changed").await.unwrap();
+            memory.upsert_tea(&renamed, "changed").await.unwrap();
+            let found = |query: &'static str| {
+                let (repo, all) = (&repo, &all);
+                async move { urls(repo.search_teas_keyword(query, 10, all).await.unwrap()) }
+            };
+            assert_eq!(found("мятный").await, Vec::<String>::new());
+            assert_eq!(found("мелисса").await, ["https://a/4"]);
+
+            let added = tea(7, "Иван-чай с мятой", &["Иван-чай", "Мята"]);
+            repo.upsert_tea(&added, "hash").await.unwrap();
+            repo.update_tea_embedding(&added.url, "model", "hash", &[0.5, 0.8, 0.0])
+                .await
+                .unwrap();
+            assert_eq!(repo.vector_index_stats("model").await.unwrap().indexed, 7);
+
+            assert!(repo.delete_tea_by_url("https://a/1").await.unwrap());
+            let deleted = generate_point_id("https://a/1");
+            for table in ["tea_terms", "tea_vector_clusters"] {
+                let orphans = table_rows(
+                    &conn,
+                    &format!("SELECT tea_id FROM {} WHERE tea_id = '{}'", table, deleted),
+                )
+                .await;
+                assert!(orphans.is_empty(), "{} keeps a deleted tea", table);
+            }
+            assert_eq!(found("малина").await, ["https://a/2"]);
+            let stats = repo.vector_index_stats("model").await.unwrap();
+            assert_eq!((stats.indexed, stats.unindexed), (6, 0));
+
+            // Rebuilding from scratch gives the incrementally maintained tables
+            let before = table_rows(&conn, terms).await;
+            assert_eq!(repo.rebuild_keyword_index().await.unwrap(), 6);
+            assert_eq!(table_rows(&conn, terms).await, before);
+
+            let before = table_rows(&conn, clusters).await;
+            assert_eq!(before.len(), 6);
+            let stats = repo.build_vector_index("model", Some(2)).await.unwrap();
+            assert_eq!((stats.indexed, stats.unindexed), (6, 0));
+            assert_eq!(table_rows(&conn, clusters).await.len(), 6);
+        });
+    }
+
+    #[test]
+    fn test_html_compression() {
+        let html = "<html><body>Иван-чай</body></html>\n".repeat(100);
+
+        let compressed = compress_html(&html).unwrap();
+        assert!(compressed.len() < html.len() / 10);
+        assert_eq!(decompress_html(&compressed).unwrap(), html);
+        assert!(decompress_html(html.as_bytes()).is_err());
+
+        assert_eq!(html_hash(&html), html_hash(&html.clone()));
+        assert_ne!(html_hash(&html), html_hash(""));
+    }
+
+    #[test]
+    fn test_cache_delete_snapshot() {
+        let dir = std::env::temp_dir().join(format!("chai-test-{}", std::process::id()));
+        let config = DbConfig {
+            path: dir.join("chai.db").to_string_lossy().into_owned(),
+            cache_snapshots: 3,
+            ..DbConfig::from_env()
+        };
+        let url = "https://example.com/";
+
+        block_on(async {
+            let repo = TursoRepository::init(&config).await.unwrap();
+            for (html, at) in [("v1", 1), ("v2", 2)] {
+                repo.cache_set_at(url, html, at, &CacheMeta::default())
+                    .await
+                    .unwrap();
+            }
+            let hashes = |snapshots: Vec<CacheSnapshot>| -> Vec<String> {
+                snapshots.into_iter().map(|s| s.content_hash).collect()
+            };
+            assert_eq!(
+                hashes(repo.cache_snapshots(url).await.unwrap()),
+                vec![html_hash("v2"), html_hash("v1")]
+            );
+
+            // The current snapshot can't be deleted on its own
+            repo.cache_delete_snapshot(url, &html_hash("v2"))
+                .await
+                .unwrap();
+            repo.cache_delete_snapshot(url, &html_hash("v1"))
+                .await
+                .unwrap();
+            assert_eq!(
+                hashes(repo.cache_snapshots(url).await.unwrap()),
+                vec![html_hash("v2")]
+            );
+            assert_eq!(
+                repo.cache_get(url).await.unwrap().map(|entry| entry.html),
+                Some("v2".to_string())
+            );
+        });
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
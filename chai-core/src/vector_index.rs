@@ -0,0 +1,221 @@
+//! Approximate nearest-neighbour index (IVF) for vector search
+//!
+//! Turso has no native vector index yet (libSQL's `libsql_vector_idx` and
+//! `vector_top_k` are not available in the embedded engine), so chai keeps
+//! an inverted file index per embedding model in regular tables:
+//! - `vector_centroids`: k-means centroids of tea embeddings
+//! - `tea_vector_clusters`: the cluster of each tea
+//!
+//! A query scans only teas of the `probes` clusters nearest to it, plus teas
+//! embedded after the index was built that have no cluster yet. Filters are
+//! applied to these candidates; when fewer than `limit` teas pass, search
+//! falls back to an exact scan.
+//!
+//! This module has the math (k-means, recall), SQL lives in [`crate::turso`].
+
+use anyhow::Result;
+
+/// Default number of clusters scanned per query
+pub const DEFAULT_PROBES: usize = 4;
+
+/// k-means iterations when building the index
+const KMEANS_ITERATIONS: usize = 25;
+
+/// How vector search finds nearest teas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VectorSearch {
+    /// Scan all teas
+    Exact,
+    /// Scan nearest clusters of the IVF index (exact scan while it isn't built)
+    #[default]
+    Ann,
+    /// Compare sign bits of all teas, rescore the closest with full vectors
+    Binary,
+}
+
+impl std::str::FromStr for VectorSearch {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "exact" => Ok(Self::Exact),
+            "ann" => Ok(Self::Ann),
+            "binary" => Ok(Self::Binary),
+            _ => anyhow::bail!("Unknown vector search '{}' (exact, ann, binary)", s),
+        }
+    }
+}
+
+/// Default cluster count for a catalog: about √n, so a probe scans √n teas
+#[must_use]
+pub fn default_clusters(vectors: usize) -> usize {
+    ((vectors as f64).sqrt().round() as usize).clamp(1, vectors.max(1))
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|v| v / norm).collect()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Index of the centroid most similar to a normalized vector
+fn nearest(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| dot(vector, a).total_cmp(&dot(vector, b)))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Initial centroids: each next one is the vector least similar to those taken
+///
+/// Deterministic, so rebuilding the index of the same catalog gives the same clusters.
+fn farthest_first(vectors: &[Vec<f32>], k: usize) -> Vec<Vec<f32>> {
+    let mut centroids = vec![vectors[0].clone()];
+    let mut similarity: Vec<f32> = vectors.iter().map(|v| dot(v, &vectors[0])).collect();
+
+    while centroids.len() < k {
+        let Some((farthest, &sim)) = similarity
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        else {
+            break;
+        };
+        // Only duplicates left
+        if sim >= 1.0 - 1e-6 {
+            break;
+        }
+
+        let centroid = vectors[farthest].clone();
+        for (s, vector) in similarity.iter_mut().zip(vectors) {
+            *s = s.max(dot(vector, &centroid));
+        }
+        centroids.push(centroid);
+    }
+
+    centroids
+}
+
+/// Spherical k-means (cosine similarity)
+///
+/// Returns unit-length centroids and the cluster of every vector.
+/// Clusters that end up empty are dropped, so there may be fewer than `k`.
+#[must_use]
+pub fn kmeans(vectors: &[Vec<f32>], k: usize) -> (Vec<Vec<f32>>, Vec<usize>) {
+    if vectors.is_empty() || k == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let vectors: Vec<Vec<f32>> = vectors.iter().map(|v| normalize(v)).collect();
+    let mut centroids = farthest_first(&vectors, k);
+    let mut assignments = vec![usize::MAX; vectors.len()];
+
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (vector, assignment) in vectors.iter().zip(assignments.iter_mut()) {
+            let cluster = nearest(vector, &centroids);
+            if *assignment != cluster {
+                *assignment = cluster;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let dims = vectors[0].len();
+        let mut sums = vec![vec![0.0f32; dims]; centroids.len()];
+        for (vector, &cluster) in vectors.iter().zip(&assignments) {
+            for (sum, v) in sums[cluster].iter_mut().zip(vector) {
+                *sum += v;
+            }
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(sums) {
+            // An empty cluster keeps its centroid and is dropped below
+            if sum.iter().any(|&v| v != 0.0) {
+                *centroid = normalize(&sum);
+            }
+        }
+    }
+
+    // Drop empty clusters and renumber the rest
+    let mut remap = vec![None; centroids.len()];
+    let mut kept = Vec::new();
+    for &cluster in &assignments {
+        if remap[cluster].is_none() {
+            remap[cluster] = Some(kept.len());
+            kept.push(centroids[cluster].clone());
+        }
+    }
+    let assignments = assignments
+        .into_iter()
+        .map(|cluster| remap[cluster].unwrap_or(0))
+        .collect();
+
+    (kept, assignments)
+}
+
+/// Share of exact results found by approximate search
+#[must_use]
+pub fn recall(exact: &[String], approximate: &[String]) -> f64 {
+    if exact.is_empty() {
+        return 1.0;
+    }
+    let found = exact.iter().filter(|id| approximate.contains(id)).count();
+    found as f64 / exact.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmeans_separates_groups() {
+        // Two well separated directions with small noise
+        let mut vectors = Vec::new();
+        for i in 0..10 {
+            let noise = i as f32 * 0.01;
+            vectors.push(vec![1.0, noise, 0.0]);
+            vectors.push(vec![0.0, noise, 1.0]);
+        }
+
+        let (centroids, assignments) = kmeans(&vectors, 2);
+        assert_eq!(centroids.len(), 2);
+        assert_eq!(assignments.len(), vectors.len());
+        // Same direction -> same cluster, different direction -> different
+        for pair in assignments.chunks(2) {
+            assert_eq!(pair, &assignments[..2]);
+        }
+        assert_ne!(assignments[0], assignments[1]);
+        for centroid in &centroids {
+            assert!((dot(centroid, centroid) - 1.0).abs() < 1e-4);
+        }
+
+        // Deterministic
+        assert_eq!(kmeans(&vectors, 2), (centroids, assignments));
+
+        // Fewer distinct vectors than clusters
+        assert_eq!(kmeans(&[], 3), (vec![], vec![]));
+        let (centroids, assignments) = kmeans(&[vec![1.0, 0.0], vec![2.0, 0.0]], 3);
+        assert_eq!(centroids.len(), 1);
+        assert_eq!(assignments, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_default_clusters_and_recall() {
+        assert_eq!(default_clusters(0), 1);
+        assert_eq!(default_clusters(1), 1);
+        assert_eq!(default_clusters(1000), 32);
+
+        let ids = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(recall(&ids(&["a", "b"]), &ids(&["b", "c"])), 0.5);
+        assert_eq!(recall(&[], &ids(&["a"])), 1.0);
+    }
+}
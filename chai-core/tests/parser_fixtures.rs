@@ -0,0 +1,137 @@
+//! Golden-file tests for the product page parser
+//!
+//! Each fixture in `tests/fixtures` is a pair of files:
+//! - `<name>.html` - product page as stored in `html_cache`, never edited by hand
+//! - `<name>.json` - expected `Tea` (the page URL is taken from its `url` field)
+//!
+//! After an intended parser or markup change, refresh the fixtures with:
+//! cargo run -p chai-cli --example refresh_fixtures
+//!
+//! See `tests/fixtures/README.md` for how to capture new pages.
+
+use chai_core::{Tea, scraper};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Expected JSON files of all fixtures, sorted by name
+fn fixture_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .expect("Failed to read fixtures dir")
+        .map(|entry| entry.expect("Failed to read fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Describe differences between expected and actual JSON, one line per field
+fn diff_values(path: &str, expected: &Value, actual: &Value, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(exp), Value::Object(act)) => {
+            let mut keys: Vec<&String> = exp.keys().chain(act.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let field = format!("{}.{}", path, key);
+                match (exp.get(key), act.get(key)) {
+                    (Some(e), Some(a)) => diff_values(&field, e, a, out),
+                    (Some(e), None) => {
+                        out.push(format!("{}: expected {}, field missing", field, e))
+                    }
+                    (None, Some(a)) => out.push(format!("{}: unexpected field = {}", field, a)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(exp), Value::Array(act)) if exp.len() == act.len() => {
+            for (i, (e, a)) in exp.iter().zip(act).enumerate() {
+                diff_values(&format!("{}[{}]", path, i), e, a, out);
+            }
+        }
+        _ if expected != actual => {
+            out.push(format!(
+                "{}:\n    expected: {}\n    actual:   {}",
+                path, expected, actual
+            ));
+        }
+        _ => {}
+    }
+}
+
+#[test]
+fn test_parser_fixtures() {
+    let files = fixture_files();
+    assert!(
+        !files.is_empty(),
+        "No fixtures in {}",
+        fixtures_dir().display()
+    );
+
+    let mut failures = Vec::new();
+
+    for json_path in &files {
+        let name = json_path.file_stem().unwrap().to_string_lossy().to_string();
+        let html_path = json_path.with_extension("html");
+
+        let expected: Value = serde_json::from_str(
+            &std::fs::read_to_string(json_path).expect("Failed to read expected JSON"),
+        )
+        .expect("Invalid expected JSON");
+        let expected_tea: Tea =
+            serde_json::from_value(expected.clone()).expect("Expected JSON is not a Tea");
+
+        let html = match std::fs::read_to_string(&html_path) {
+            Ok(html) => html,
+            Err(e) => {
+                failures.push(format!(
+                    "{}: failed to read {}: {}",
+                    name,
+                    html_path.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+
+        let actual = match scraper::parse_tea_from_html(&expected_tea.url, &html) {
+            Ok(tea) => serde_json::to_value(&tea).expect("Failed to serialize Tea"),
+            Err(e) => {
+                failures.push(format!("{}: parse failed: {:#}", name, e));
+                continue;
+            }
+        };
+
+        let mut diffs = Vec::new();
+        diff_values("tea", &expected, &actual, &mut diffs);
+        if !diffs.is_empty() {
+            failures.push(format!("{}:\n  {}", name, diffs.join("\n  ")));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} fixtures differ:\n\n{}\n\nIf the change is intended, run: \
+         cargo run -p chai-cli --example refresh_fixtures -- --keep-html",
+        failures.len(),
+        files.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn test_diff_values() {
+    let expected = serde_json::json!({"name": "A", "tags": ["x", "y"], "in_stock": true});
+    let actual = serde_json::json!({"name": "A", "tags": ["x", "z"], "extra": 1});
+
+    let mut diffs = Vec::new();
+    diff_values("tea", &expected, &actual, &mut diffs);
+
+    assert_eq!(diffs.len(), 3);
+    assert!(diffs[0].starts_with("tea.extra: unexpected field"));
+    assert!(diffs[1].starts_with("tea.in_stock: expected true"));
+    assert!(diffs[2].starts_with("tea.tags[1]:"));
+}
@@ -3,7 +3,7 @@
 //! Run with: cargo test -p chai-core --test prompt_injection -- --ignored --nocapture
 
 use anyhow::Result;
-use chai_core::{Config, ai};
+use chai_core::{Config, DbConfig, TursoRepository, ai};
 
 /// Test case with expected result
 struct TestCase {
@@ -170,13 +170,14 @@ async fn test_injection_detection() -> Result<()> {
     let api_key =
         std::env::var("OPENROUTER_API_KEY").expect("OPENROUTER_API_KEY required for this test");
     let config = Config::from_env()?;
+    let db = TursoRepository::init(&DbConfig::from_env()).await?;
 
     let mut passed = 0;
     let mut failed = 0;
     let mut failures: Vec<String> = Vec::new();
 
     for tc in TEST_CASES {
-        let result = ai::chat_completion(tc.query.to_string(), api_key.clone(), &config).await;
+        let result = ai::chat_completion(tc.query.to_string(), api_key.clone(), &config, &db).await;
         let was_blocked = result.is_err();
 
         if was_blocked == tc.should_block {
@@ -6,11 +6,13 @@ use serde::{Deserialize, Serialize};
 // Server functions - these work on both SSR and hydrate
 #[server]
 pub async fn login_user(email: String, password: String) -> Result<LoginResponse, ServerFnError> {
+    use crate::server::db;
     use chai_core::auth::{self, AuthConfig};
 
     let config = AuthConfig::from_env().map_err(|e| ServerFnError::new(e.to_string()))?;
+    let db = db::repository().map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    match auth::login(&email, &password, &config.jwt_secret).await {
+    match auth::login(&db, &email, &password, &config.jwt_secret).await {
         Ok((user, token)) => {
             // Success is already logged in auth::login
             Ok(LoginResponse {
@@ -32,9 +34,12 @@ pub async fn login_user(email: String, password: String) -> Result<LoginResponse
 
 #[server]
 pub async fn register_user(email: String, password: String) -> Result<(), ServerFnError> {
+    use crate::server::db;
     use chai_core::auth;
 
-    match auth::register(&email, &password).await {
+    let db = db::repository().map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    match auth::register(&db, &email, &password).await {
         Ok(_) => {
             // Success is already logged in auth::register
             Ok(())
@@ -1,7 +1,7 @@
 use crate::components::auth::{UserMenu, use_auth, use_require_auth};
 use crate::components::tea_card::TeaCard;
 use crate::components::theme_toggle::ThemeToggle;
-use crate::models::AIResponse;
+use crate::models::{AIResponse, CatalogFacets, FilterOptions};
 use crate::utils::russian_plural;
 use leptos::prelude::*;
 
@@ -58,6 +58,17 @@ pub async fn get_teas_count() -> Result<usize, ServerFnError> {
         .map_err(|e| ServerFnError::new(e.to_string()))
 }
 
+// Filter options have lists of pairs, which a URL-encoded form can't carry
+#[server(input = leptos::server_fn::codec::Json)]
+pub async fn get_catalog_facets(options: FilterOptions) -> Result<CatalogFacets, ServerFnError> {
+    // Public endpoint like the count: tea counts per filter option
+    use crate::server::db;
+
+    db::catalog_facets(&options)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
 #[component]
 pub fn Home() -> impl IntoView {
     // Auth check - redirects to /login if not authenticated
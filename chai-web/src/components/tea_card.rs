@@ -1,12 +1,20 @@
-use crate::models::TeaCard as TeaCardModel;
+use crate::models::{Money, TeaCard as TeaCardModel};
 use leptos::prelude::*;
 
-/// Format price string: "580.0000" -> "580 ₽"
+/// Format price string: "580.0000" -> "580 ₽", unparsed text is shown as is
 fn format_price(price: &str) -> String {
-    price
-        .parse::<f64>()
-        .map(|p| format!("{} ₽", p.round() as i64))
-        .unwrap_or_else(|_| format!("{} ₽", price))
+    Money::parse(price).map_or_else(|| format!("{} ₽", price), |money| money.to_string())
+}
+
+/// Если локальная миниатюра не загрузилась, показываем оригинал из магазина
+fn show_original_image(event: &web_sys::Event, original: Option<&str>) {
+    let Some(original) = original else {
+        return;
+    };
+    let img = event_target::<web_sys::HtmlImageElement>(event);
+    if img.src() != original {
+        img.set_src(original);
+    }
 }
 
 #[component]
@@ -35,16 +43,24 @@ pub fn TeaCard(card: TeaCardModel) -> impl IntoView {
     // Клонируем данные для использования в замыканиях
     let title = card.title.clone();
     let image_url = card.image_url.clone();
+    // Локальные миниатюры (если изображение скачано при синхронизации), иначе оригинал
+    let card_image = card.thumbnail_url.clone().or_else(|| image_url.clone());
+    let modal_image = card
+        .thumbnail_large_url
+        .clone()
+        .or_else(|| image_url.clone());
     let in_stock = card.in_stock;
     let tags = card.tags.clone();
     let short_description = card.short_description.clone();
     let price = card.price.clone();
+    let price_per_100g = card.price_per_100g;
     let composition = card.composition.clone();
     let url = card.url.clone();
     let sample_url = card.sample_url.clone();
     let sample_in_stock = card.sample_in_stock;
     let description = card.description.clone();
     let series = card.series.clone();
+    let shop_name = (!card.shop_name.is_empty()).then(|| card.shop_name.clone());
     let full_composition = card.full_composition.clone();
     let price_variants = card.price_variants.clone();
 
@@ -73,17 +89,25 @@ pub fn TeaCard(card: TeaCardModel) -> impl IntoView {
                 }
             >
                 // Изображение
-                {image_url.clone().map(|url| view! {
-                    <div class="card-image">
-                        <img src=url.clone() alt=title.clone() loading="lazy"/>
-                        {if !in_stock {
-                            Some(view! {
-                                <div class="out-of-stock-badge">"Нет в наличии"</div>
-                            })
-                        } else {
-                            None
-                        }}
-                    </div>
+                {card_image.clone().map(|src| {
+                    let original = image_url.clone();
+                    view! {
+                        <div class="card-image">
+                            <img
+                                src=src
+                                alt=title.clone()
+                                loading="lazy"
+                                on:error=move |e| show_original_image(&e, original.as_deref())
+                            />
+                            {if !in_stock {
+                                Some(view! {
+                                    <div class="out-of-stock-badge">"Нет в наличии"</div>
+                                })
+                            } else {
+                                None
+                            }}
+                        </div>
+                    }
                 })}
 
                 <div class="card-content">
@@ -129,6 +153,9 @@ pub fn TeaCard(card: TeaCardModel) -> impl IntoView {
                         <div class="card-price">
                             <span class="price-label">"Цена: "</span>
                             <span class="price-value">{price_text}</span>
+                            {price_per_100g.map(|per_100g| view! {
+                                <span class="price-per-100g">{format!(" · {} за 100 г", per_100g)}</span>
+                            })}
                         </div>
                     }
                 })}
@@ -156,20 +183,28 @@ pub fn TeaCard(card: TeaCardModel) -> impl IntoView {
 
                             <div class="modal-body">
                                 // Изображение с бейджем статуса
-                                {image_url.clone().map(|url| view! {
-                                    <div class="modal-image-wrapper">
-                                        <div class="modal-image">
-                                            <img src=url alt=title.clone() loading="lazy"/>
-                                        </div>
-                                        // Статус наличия как overlay
-                                        <div class=move || format!("availability-badge badge-{}", status)>
-                                            {match status {
-                                                "available" => "✅ В наличии",
-                                                "sample_only" => "🔬 Только пробник",
-                                                _ => "❌ Нет в наличии"
-                                            }}
+                                {modal_image.clone().map(|src| {
+                                    let original = image_url.clone();
+                                    view! {
+                                        <div class="modal-image-wrapper">
+                                            <div class="modal-image">
+                                                <img
+                                                    src=src
+                                                    alt=title.clone()
+                                                    loading="lazy"
+                                                    on:error=move |e| show_original_image(&e, original.as_deref())
+                                                />
+                                            </div>
+                                            // Статус наличия как overlay
+                                            <div class=move || format!("availability-badge badge-{}", status)>
+                                                {match status {
+                                                    "available" => "✅ В наличии",
+                                                    "sample_only" => "🔬 Только пробник",
+                                                    _ => "❌ Нет в наличии"
+                                                }}
+                                            </div>
                                         </div>
-                                    </div>
+                                    }
                                 })}
 
                                 // Заголовок и метаинфо
@@ -188,6 +223,14 @@ pub fn TeaCard(card: TeaCardModel) -> impl IntoView {
                                     </div>
                                 })}
 
+                                // Магазин
+                                {shop_name.clone().map(|name| view! {
+                                    <div class="modal-shop">
+                                        <span class="shop-icon">"🏪"</span>
+                                        <span class="shop-name">{name}</span>
+                                    </div>
+                                })}
+
                                 // Теги
                                 {if !tags.is_empty() {
                                     let tags_modal = tags.clone();
@@ -258,6 +301,7 @@ pub fn TeaCard(card: TeaCardModel) -> impl IntoView {
                                                     children=move |variant| {
                                                         let packaging = variant.packaging.clone();
                                                         let price_val = format_price(&variant.price);
+                                                        let per_100g = variant.price_per_100g();
                                                         let quantity = variant.quantity.clone();
                                                         view! {
                                                             <div class="price-variant-item">
@@ -265,6 +309,9 @@ pub fn TeaCard(card: TeaCardModel) -> impl IntoView {
                                                                 <div class="variant-details">
                                                                     <span class="variant-quantity">{quantity}</span>
                                                                     <span class="variant-price">{price_val}</span>
+                                                                    {per_100g.map(|per_100g| view! {
+                                                                        <span class="variant-per-100g">{format!("{} за 100 г", per_100g)}</span>
+                                                                    })}
                                                                 </div>
                                                             </div>
                                                         }
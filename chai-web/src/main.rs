@@ -8,10 +8,11 @@ pub const BUILD_TIME: &str = env!("BUILD_TIME");
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    use axum::response::Json;
-    use axum::{Router, routing::get};
+    use axum::response::{Json, Response};
+    use axum::{Router, middleware, routing::get};
     use axum_governor::GovernorLayer;
-    use chai_core::{DbConfig, turso};
+    use chai_core::images::{IMAGES_URL_PREFIX, ImagesConfig};
+    use chai_core::{DbConfig, TursoRepository};
     use chai_web::app::App;
     use lazy_limit::{Duration, RuleConfig, init_rate_limiter};
     use leptos::prelude::*;
@@ -46,9 +47,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         tracing::warn!("OPENROUTER_API_KEY not set - AI features will not work");
     }
 
-    // Initialize Turso database
+    // Initialize Turso database (applies pending migrations,
+    // refuses a schema newer than this build)
     let db_config = DbConfig::from_env();
-    turso::init_database(&db_config).await?;
+    let db = TursoRepository::init(&db_config).await?;
     tracing::info!("Database initialized at {}", db_config.path);
 
     // Initialize rate limiter: 10 requests per second globally, 2 req/sec for AI endpoint
@@ -76,10 +78,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }))
     }
 
+    // Thumbnails are named by content hash and never change
+    async fn cache_forever(mut response: Response) -> Response {
+        if response.status().is_success() {
+            response.headers_mut().insert(
+                axum::http::header::CACHE_CONTROL,
+                axum::http::HeaderValue::from_static("public, max-age=31536000, immutable"),
+            );
+        }
+        response
+    }
+
+    // Mirrored images (see `chai sync`)
+    let images_config = ImagesConfig::from_env();
+    tracing::info!("Serving images from {}", images_config.dir.display());
+    let images = Router::new()
+        .nest_service(IMAGES_URL_PREFIX, ServeDir::new(&images_config.dir))
+        .layer(middleware::map_response(cache_forever));
+
+    // Admin endpoints check the bearer token themselves (see `server::admin`)
+    let backup_db = db.clone();
+    let backup_handler = move |headers: axum::http::HeaderMap| {
+        chai_web::server::admin::backup(backup_db.clone(), headers)
+    };
+
     // Build Axum router with rate limiting
     let app = Router::new()
         .route("/api/version", get(version_handler))
-        .leptos_routes(&leptos_options, routes, {
+        .route("/api/admin/backup", get(backup_handler))
+        .merge(images)
+        // Server functions get the database from context (see `server::db`)
+        .leptos_routes_with_context(
+            &leptos_options,
+            routes,
+            move || provide_context(db.clone()),
+            {
             let leptos_options = leptos_options.clone();
             move || {
                 use leptos::prelude::*;
@@ -102,7 +135,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     </html>
                 }
             }
-        })
+        },
+        )
         .fallback_service(ServeDir::new(leptos_options.site_root.as_ref()))
         .layer(
             tower::ServiceBuilder::new()
@@ -1,2 +1,2 @@
 // Re-export common types from chai-core
-pub use chai_core::{AIResponse, SearchResult, Tea, TeaCard};
+pub use chai_core::{AIResponse, CatalogFacets, FilterOptions, Money, SearchResult, Tea, TeaCard};
@@ -0,0 +1,88 @@
+//! Admin endpoints, for users listed in `ADMIN_EMAILS`
+//!
+//! Plain Axum handlers (not server functions) so they can be used with curl:
+//! `curl -H "Authorization: Bearer $TOKEN" https://.../api/admin/backup -o chai.jsonl.zst`
+
+use anyhow::{Context, Result};
+use axum::body::Body;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chai_core::auth::{AuthConfig, Claims};
+use chai_core::{TursoRepository, backup};
+use std::path::Path;
+use tokio::sync::Mutex;
+use tokio_util::io::ReaderStream;
+
+use super::auth::validate_token;
+
+/// One backup at a time, they read the whole database
+static BACKUP_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Check the bearer token of a request and that its user is an admin
+fn authorize(headers: &HeaderMap) -> Result<Claims, StatusCode> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let claims = validate_token(token.trim()).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let config = AuthConfig::from_env().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !config.is_admin(&claims.email) {
+        tracing::warn!("Admin request denied for {}", claims.email);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(claims)
+}
+
+/// `GET /api/admin/backup`: stream a compressed backup of the database
+///
+/// Users, cache and catalog are read in one transaction while the server
+/// keeps running. Restore the file with `chai db restore`.
+pub async fn backup(db: TursoRepository, headers: HeaderMap) -> Response {
+    let claims = match authorize(&headers) {
+        Ok(claims) => claims,
+        Err(status) => return status.into_response(),
+    };
+
+    match stream_backup(&db).await {
+        Ok(response) => {
+            tracing::info!("Backup downloaded by {}", claims.email);
+            response
+        }
+        Err(e) => {
+            tracing::error!("Backup failed: {:#}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Backup failed").into_response()
+        }
+    }
+}
+
+async fn stream_backup(db: &TursoRepository) -> Result<Response> {
+    // Written next to the database first: the transaction stays short
+    // and a slow client doesn't hold it open
+    let dir = db.path().parent().unwrap_or(Path::new(""));
+    let path = dir.join(format!(".backup-{}.jsonl.zst", std::process::id()));
+
+    let _lock = BACKUP_LOCK.lock().await;
+    let summary = backup::backup(db, &path, true).await?;
+    let file = tokio::fs::File::open(&path)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let size = file.metadata().await?.len();
+    // The open file stays readable, nothing is left behind if the client disconnects
+    tokio::fs::remove_file(&path)
+        .await
+        .with_context(|| format!("Failed to remove {}", path.display()))?;
+
+    let name = backup::file_name(summary.created_at, true);
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/zstd")
+        .header(header::CONTENT_LENGTH, size)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", name),
+        )
+        .body(Body::from_stream(ReaderStream::new(file)))
+        .context("Failed to build backup response")
+}
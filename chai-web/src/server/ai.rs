@@ -6,5 +6,6 @@ use chai_core::AIResponse;
 /// Прослойка для веб-слоя, вызывает функцию из chai_core
 pub async fn chat_completion(user_query: String, api_key: String) -> Result<AIResponse> {
     let config = super::config::get()?;
-    chai_core::ai::chat_completion(user_query, api_key, config).await
+    let db = super::db::repository()?;
+    chai_core::ai::chat_completion(user_query, api_key, config, &db).await
 }
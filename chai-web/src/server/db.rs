@@ -1,7 +1,20 @@
 use anyhow::Result;
-use chai_core::turso;
+use chai_core::{CatalogFacets, FilterOptions, SearchFilters, TeaRepository, TursoRepository};
+use leptos::prelude::use_context;
+
+/// Database opened by `main`, provided to server functions via context
+pub fn repository() -> Result<TursoRepository> {
+    use_context::<TursoRepository>().ok_or_else(|| anyhow::anyhow!("Database is not provided"))
+}
 
 /// Get count of teas in database
 pub async fn count_teas() -> Result<usize> {
-    turso::count_teas().await
+    repository()?.count_teas().await
+}
+
+/// Count teas passing the filters per facet value
+pub async fn catalog_facets(options: &FilterOptions) -> Result<CatalogFacets> {
+    repository()?
+        .get_facets(&SearchFilters::from(options))
+        .await
 }
@@ -17,6 +17,9 @@ touch src/lib.rs style/main.css
 cargo leptos build --release
 cd ..
 
+# CLI for database maintenance on the server (db restore, db snapshot)
+cargo build --release --package chai-cli
+
 echo ""
 echo "=== Preparing deployment package ==="
 
@@ -30,8 +33,9 @@ fi
 TEMP_DIR=$(mktemp -d)
 trap "rm -rf $TEMP_DIR" EXIT
 
-# Copy binary (built in workspace target/)
+# Copy binaries (built in workspace target/)
 cp target/release/chai-web "$TEMP_DIR/"
+cp target/release/chai "$TEMP_DIR/"
 
 # Copy site directory (CSS, JS, WASM)
 cp -r target/site "$TEMP_DIR/site"
@@ -50,7 +54,7 @@ echo "=== Syncing to $SERVER ==="
 ssh "$SERVER" "mkdir -p $DEPLOY_DIR/data"
 
 # Sync files
-rsync -avz --progress "$TEMP_DIR/chai-web" "$SERVER:$DEPLOY_DIR/"
+rsync -avz --progress "$TEMP_DIR/chai-web" "$TEMP_DIR/chai" "$SERVER:$DEPLOY_DIR/"
 rsync -avz --progress --delete "$TEMP_DIR/site/" "$SERVER:$DEPLOY_DIR/site/"
 
 echo ""